@@ -1,13 +1,105 @@
-use clap::{Parser, Subcommand};
+use clap::builder::{PossibleValue, PossibleValuesParser};
+use clap::{Command, Parser, Subcommand};
+
+use crate::domain::entity::AuditOperation;
+use crate::infra::config::Config;
+
+/// `--format` values, with `md` and `yml` accepted for `markdown` and
+/// `yaml`; listed so that shell completion can offer them.
+fn formats(names: &'static [&'static str]) -> PossibleValuesParser {
+    PossibleValuesParser::new(names.iter().map(|&name| {
+        let value = PossibleValue::new(name);
+        match name {
+            "markdown" => value.alias("md"),
+            "yaml" => value.alias("yml"),
+            _ => value,
+        }
+    }))
+}
+
+/// Commands where `--sender` names the agent running them (rather than
+/// filtering by poster), so the configured sender is their default.
+const SENDER_DEFAULT_COMMANDS: [&[&str]; 7] = [
+    &["message", "read"],
+    &["message", "list"],
+    &["message", "search"],
+    &["message", "mentions"],
+    &["message", "mentions", "dismiss"],
+    &["thread", "watch"],
+    &["thread", "unwatch"],
+];
+
+/// Applies the configured `format` and `sender` as argument defaults.
+pub fn with_config_defaults(cmd: Command, config: &'static Config) -> Command {
+    apply_config_defaults(cmd, config, &mut Vec::new())
+}
+
+fn apply_config_defaults(mut cmd: Command, config: &'static Config, path: &mut Vec<String>) -> Command {
+    if let Some(ref format) = config.format {
+        let supported = cmd
+            .get_arguments()
+            .find(|arg| arg.get_id() == "format")
+            .is_some_and(|arg| arg.get_possible_values().iter().any(|value| value.matches(format, false)));
+        if supported {
+            cmd = cmd.mut_arg("format", |arg| arg.default_value(format.as_str()));
+        }
+    }
+    if let Some(ref sender) = config.sender {
+        if SENDER_DEFAULT_COMMANDS.iter().any(|command| *command == path.as_slice()) {
+            cmd = cmd.mut_arg("sender", |arg| arg.default_value(sender.as_str()).required(false));
+        }
+    }
+    let names: Vec<String> = cmd.get_subcommands().map(|sub| sub.get_name().to_string()).collect();
+    for name in names {
+        path.push(name.clone());
+        cmd = cmd.mut_subcommand(&name, |sub| apply_config_defaults(sub, config, path));
+        path.pop();
+    }
+    cmd
+}
 
 #[derive(Parser)]
 #[command(name = "aiboard", about = "エージェント間通信と会話ログの永続化")]
 pub struct Cli {
+    /// 色付け（auto: 端末かつ NO_COLOR 未設定のとき, always, never）
+    #[arg(long, global = true, default_value = "auto", value_parser = ["auto", "always", "never"])]
+    pub color: String,
+    /// text 出力の日時を「3分前」のような相対表記にする
+    #[arg(long, global = true)]
+    pub relative_time: bool,
+    /// JSON を整形せず 1 行で出力する
+    #[arg(long, global = true)]
+    pub compact: bool,
+    /// 件数・進捗・ヒントなどの案内メッセージを stderr に出さない（エラーと警告は出力する）
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+    /// メッセージの言語（ja, en。省略時は AIBOARD_LANG、なければ ja）
+    #[arg(long, global = true, value_parser = ["ja", "en"])]
+    pub lang: Option<String>,
+    /// 長い出力を端末で $PAGER に通さない
+    #[arg(long, global = true)]
+    pub no_pager: bool,
+    /// データディレクトリ（AIBOARD_DATA_DIR より優先。DB は <PATH>/aiboard.db）
+    #[arg(long, global = true, value_name = "PATH")]
+    pub data_dir: Option<std::path::PathBuf>,
+    /// DB ファイルのパス（--data-dir より優先）
+    #[arg(long, global = true, value_name = "FILE")]
+    pub db: Option<std::path::PathBuf>,
+    /// DB ファイルを開かず、この実行の間だけのメモリ上の空の DB を使う
+    #[arg(long, global = true, conflicts_with = "db")]
+    pub ephemeral: bool,
+    /// 新しく作成する DB で、thread のない message と draft を拒否し thread の削除で一緒に削除する（設定ファイルの strict_integrity）
+    #[arg(long, global = true)]
+    pub strict_integrity: bool,
+    /// DB オープン・マイグレーション確認・クエリ・整形の所要時間を stderr に出力する
+    #[arg(long, global = true)]
+    pub timing: bool,
     #[command(subcommand)]
     pub command: Commands,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 pub enum Commands {
     /// message の管理（投稿・読み取り・検索・更新）
     Message {
@@ -34,11 +126,78 @@ pub enum Commands {
         #[command(subcommand)]
         action: SetupAction,
     },
+    /// メンショングループの管理（@team:<name>）
+    Group {
+        #[command(subcommand)]
+        action: GroupAction,
+    },
+    /// thread の統計情報
+    Stats {
+        #[command(subcommand)]
+        action: StatsAction,
+    },
+    /// thread と message の横断検索
+    Search {
+        #[command(subcommand)]
+        action: SearchAction,
+    },
+    /// 全文検索インデックスの管理
+    Index {
+        #[command(subcommand)]
+        action: IndexAction,
+    },
+    /// データベースファイルの保守
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+    /// DB バックアップ（aiboard.db.bak.*）の作成と管理
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+    /// 共有先（共有ドライブ上の DB ファイルか HTTP エンドポイント）とボードを同期する
+    Sync {
+        #[command(subcommand)]
+        action: SyncAction,
+    },
+    /// 投稿・更新・削除・cleanup・merge の記録（監査ログ）
+    Audit {
+        #[command(subcommand)]
+        action: AuditAction,
+    },
+    /// ボード全体（thread・message・リンク・メンショングループ）を tar.gz アーカイブに書き出す
+    Export {
+        /// 出力先ファイル（例: board.tar.gz）
+        #[arg(long)]
+        output: std::path::PathBuf,
+    },
+    /// aiboard export のアーカイブを取り込む（既にある thread はスキップ）
+    Import {
+        /// アーカイブファイルのパス
+        path: std::path::PathBuf,
+    },
+    /// JSON 出力の JSON Schema を表示する（コマンド省略時は対象コマンドの一覧）
+    Schema {
+        /// 対象コマンド（例: message read）
+        command: Vec<String>,
+    },
     /// ユーティリティコマンド
     Util {
         #[command(subcommand)]
         action: UtilAction,
     },
+    /// シェル補完スクリプトを出力する（bash, zsh, fish, powershell, elvish）
+    Completions {
+        /// 対象のシェル
+        shell: clap_complete::Shell,
+    },
+    /// stdin の各行のコマンドを 1 プロセス・1 トランザクションで実行し、行ごとの結果を JSON で出力する
+    Batch {
+        /// 1 行でも失敗したらすべてをロールバックしてそこで止める（省略時は失敗した行だけを取り消して続ける）
+        #[arg(long)]
+        atomic: bool,
+    },
     /// トースト通知を表示する（Windows専用）
     Notify {
         /// 通知メッセージ
@@ -56,18 +215,18 @@ pub enum MessageAction {
         /// thread ID
         #[arg(long)]
         thread: String,
-        /// message の role（user, assistant, system, tool）
-        #[arg(long, default_value = "user")]
-        role: String,
+        /// message の role（user, assistant, system, tool。省略時は thread の既定値、なければ user）
+        #[arg(long)]
+        role: Option<String>,
         /// message の内容（省略時は stdin から読み取り）
         #[arg(long)]
         content: Option<String>,
         /// session ID
         #[arg(long)]
         session: Option<String>,
-        /// 送信者名（必須）
+        /// 送信者名（thread に既定の送信者がなければ必須）
         #[arg(long)]
-        sender: String,
+        sender: Option<String>,
         /// 親 message の ID
         #[arg(long)]
         parent: Option<String>,
@@ -77,6 +236,12 @@ pub enum MessageAction {
         /// メッセージタイプ（metadata.msg_type に設定される）
         #[arg(long, value_name = "TYPE")]
         r#type: Option<String>,
+        /// 同一 thread・送信者・内容の message が直近にあれば投稿をスキップする
+        #[arg(long)]
+        dedupe: bool,
+        /// --dedupe の判定期間（例: 30s, 10m, 2h, 1d）
+        #[arg(long, default_value = "10m", requires = "dedupe")]
+        window: String,
     },
     /// thread の message を読み取る
     Read {
@@ -86,27 +251,77 @@ pub enum MessageAction {
         /// 返す message の最大件数
         #[arg(long)]
         limit: Option<usize>,
-        /// この日時より前の message のみ（ISO 8601）
+        /// この日時より前の message のみ（ISO 8601、YYYY-MM-DD、yesterday、2d など）
         #[arg(long)]
         before: Option<String>,
-        /// この日時より後の message のみ（ISO 8601）
+        /// この日時より後の message のみ（ISO 8601、YYYY-MM-DD、yesterday、2d など）
         #[arg(long)]
         after: Option<String>,
         /// 内容を省略せず全文表示する
         #[arg(long)]
         full: bool,
-        /// 出力形式（text, json）
-        #[arg(long, default_value = "text")]
+        /// 出力形式（text, json, jsonl, yaml, markdown, transcript, csv, tsv）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json", "jsonl", "yaml", "markdown", "transcript", "csv", "tsv"]))]
         format: String,
+        /// 1 件ごとの出力形式（例: '{{id}} {{sender}}: {{content}}'。{{metadata.key}} も可、\n \t を解釈）
+        #[arg(long)]
+        template: Option<String>,
+        /// 出力する項目（カンマ区切り: id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at）
+        #[arg(long, value_delimiter = ',', value_name = "FIELD")]
+        fields: Vec<String>,
         /// メンション通知対象の送信者名
         #[arg(long)]
         sender: Option<String>,
-        /// メッセージタイプでフィルター
+        /// メッセージタイプでフィルター（--limit がなければ一致する message を新しい順にすべて返す）
         #[arg(long, value_name = "TYPE")]
         r#type: Option<String>,
         /// 最後の checkpoint 以降の message のみ表示
         #[arg(long)]
         since_checkpoint: bool,
+        /// role でフィルター（user, assistant, system, tool）
+        #[arg(long)]
+        role: Option<String>,
+        /// 指定した送信者が投稿した message のみ
+        #[arg(long, value_name = "SENDER")]
+        from: Option<String>,
+        /// 出所タグでフィルター（カンマ区切りで複数指定可: user, system, manual, agent, url-fetch, file-import）
+        #[arg(long, value_delimiter = ',')]
+        source: Vec<String>,
+        /// 指定した出所タグの message を除外する（カンマ区切りで複数指定可）
+        #[arg(long, value_delimiter = ',', value_name = "SOURCE")]
+        exclude_source: Vec<String>,
+        /// metadata のトップレベルキーでフィルター（key=value、複数指定可）
+        #[arg(long, value_name = "KEY=VALUE")]
+        meta: Vec<String>,
+        /// metadata の JSON パスでフィルター（'$.path=value'、複数指定可）
+        #[arg(long, value_name = "PATH=VALUE")]
+        meta_path: Vec<String>,
+        /// 先頭から読み飛ばす message の件数
+        #[arg(long)]
+        offset: Option<usize>,
+        /// 前回出力の next_cursor から続きを読み取る
+        #[arg(long)]
+        cursor: Option<String>,
+        /// 新しい順に表示する（--thread 指定時の既定は古い順）
+        #[arg(long)]
+        desc: bool,
+        /// 指定した message より後に投稿された message のみ（ID は短い prefix でも可）
+        #[arg(long, value_name = "MESSAGE_ID")]
+        since: Option<String>,
+        /// 出力先ファイル（省略時は stdout。書き込みは完了時にまとめて反映される）
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+        /// --output のファイルを上書きせず末尾に追記する
+        #[arg(long, requires = "output")]
+        append: bool,
+    },
+    /// message を 1 件取得する
+    Get {
+        /// message ID（短い prefix でも可）
+        id: String,
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
     },
     /// 最新の message を一覧表示する
     List {
@@ -116,15 +331,48 @@ pub enum MessageAction {
         /// 内容を省略せず全文表示する
         #[arg(long)]
         full: bool,
-        /// 出力形式（text, json）
-        #[arg(long, default_value = "text")]
+        /// 出力形式（text, json, jsonl, yaml, markdown, csv, tsv）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json", "jsonl", "yaml", "markdown", "csv", "tsv"]))]
         format: String,
+        /// 1 件ごとの出力形式（例: '{{id}} {{sender}}: {{content}}'。{{metadata.key}} も可、\n \t を解釈）
+        #[arg(long)]
+        template: Option<String>,
+        /// 出力する項目（カンマ区切り: id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at）
+        #[arg(long, value_delimiter = ',', value_name = "FIELD")]
+        fields: Vec<String>,
         /// メンション通知対象の送信者名
         #[arg(long)]
         sender: Option<String>,
-        /// メッセージタイプでフィルター
+        /// メッセージタイプでフィルター（--limit に関わらず一致する message をすべて返す）
         #[arg(long, value_name = "TYPE")]
         r#type: Option<String>,
+        /// role でフィルター（user, assistant, system, tool）
+        #[arg(long)]
+        role: Option<String>,
+        /// 指定した送信者が投稿した message のみ
+        #[arg(long, value_name = "SENDER")]
+        from: Option<String>,
+        /// 出力先ファイル（省略時は stdout。書き込みは完了時にまとめて反映される）
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+        /// --output のファイルを上書きせず末尾に追記する
+        #[arg(long, requires = "output")]
+        append: bool,
+    },
+    /// message の件数を数える
+    Count {
+        /// thread ID（省略時は全 thread）
+        #[arg(long)]
+        thread: Option<String>,
+        /// 送信者でフィルター
+        #[arg(long)]
+        sender: Option<String>,
+        /// この日時以降の message のみ（ISO 8601、YYYY-MM-DD、yesterday、2d など）
+        #[arg(long)]
+        since: Option<String>,
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
     },
     /// message を検索する
     Search {
@@ -136,26 +384,86 @@ pub enum MessageAction {
         /// 内容を省略せず全文表示する
         #[arg(long)]
         full: bool,
-        /// 出力形式（text, json, markdown）
-        #[arg(long, default_value = "text")]
+        /// 出力形式（text, json, jsonl, yaml, markdown, csv, tsv）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json", "jsonl", "yaml", "markdown", "csv", "tsv"]))]
         format: String,
+        /// 1 件ごとの出力形式（例: '{{id}} {{sender}}: {{content}}'。{{metadata.key}} も可、\n \t を解釈）
+        #[arg(long)]
+        template: Option<String>,
+        /// 出力する項目（カンマ区切り: message の項目と score, matches, fuzzy）
+        #[arg(long, value_delimiter = ',', value_name = "FIELD")]
+        fields: Vec<String>,
         /// メンション通知対象の送信者名
         #[arg(long)]
         sender: Option<String>,
         /// メッセージタイプでフィルター
         #[arg(long, value_name = "TYPE")]
         r#type: Option<String>,
+        /// role でフィルター（user, assistant, system, tool）
+        #[arg(long)]
+        role: Option<String>,
+        /// 指定した送信者が投稿した message のみ
+        #[arg(long, value_name = "SENDER")]
+        from: Option<String>,
+        /// この日時より前の message のみ（ISO 8601、YYYY-MM-DD、yesterday、2d など）
+        #[arg(long)]
+        before: Option<String>,
+        /// この日時より後の message のみ（ISO 8601、YYYY-MM-DD、yesterday、2d など）
+        #[arg(long)]
+        after: Option<String>,
+        /// 検索対象（content: 本文, metadata: metadata の値, all: 両方）
+        #[arg(long = "in", value_name = "FIELD", default_value = "content")]
+        r#in: String,
+        /// 埋め込みベクトルによる意味検索（事前に index embed が必要。AIBOARD_EMBEDDING_URL / AIBOARD_EMBEDDING_MODEL で設定）
+        #[arg(long, conflicts_with = "in")]
+        semantic: bool,
+        /// この語を本文に含む message を除外する（複数指定可）
+        #[arg(long, value_name = "TERM", conflicts_with = "semantic")]
+        exclude: Vec<String>,
+        /// クエリを FTS5 の構文として解釈せず、文字列そのものを検索する（`-` `:` 引用符を含む語など）
+        #[arg(long, conflicts_with_all = ["any", "all_of", "semantic"])]
+        literal: bool,
+        /// 空白区切りの語のいずれかを含む message を検索する
+        #[arg(long, conflicts_with_all = ["all_of", "semantic"])]
+        any: bool,
+        /// 空白区切りの語をすべて含む message を検索する（順序・隣接は問わない）
+        #[arg(long, alias = "allof", conflicts_with = "semantic")]
+        all_of: bool,
+        /// 結果をまとめる単位（thread: thread ごとに見出しと件数を付けて表示）
+        #[arg(long, value_name = "FIELD")]
+        group_by: Option<String>,
+        /// 一致がないとき、綴りの誤りを許容して近い表記を探す（本文のみ）
+        #[arg(long, conflicts_with_all = ["in", "semantic"])]
+        fuzzy: bool,
+        /// 返す結果の最大件数
+        #[arg(long)]
+        limit: Option<usize>,
+        /// 先頭から読み飛ばす結果の件数
+        #[arg(long, default_value = "0")]
+        offset: usize,
+        /// 出力先ファイル（省略時は stdout。書き込みは完了時にまとめて反映される）
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+        /// --output のファイルを上書きせず末尾に追記する
+        #[arg(long, requires = "output")]
+        append: bool,
     },
     /// 自分宛てのメンションを表示する
+    #[command(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
     Mentions {
+        #[command(subcommand)]
+        action: Option<MentionsAction>,
         /// 送信者名（必須）
+        #[arg(long, required = true)]
+        sender: Option<String>,
+        /// 対応済み（dismiss 済み）のメンションを除外する
         #[arg(long)]
-        sender: String,
+        unread: bool,
         /// 内容を省略せず全文表示する
         #[arg(long)]
         full: bool,
         /// 出力形式（text, json）
-        #[arg(long, default_value = "text")]
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
         format: String,
     },
     /// thread の新着 message をリアルタイム監視する
@@ -170,9 +478,48 @@ pub enum MessageAction {
         #[arg(long)]
         full: bool,
         /// 出力形式（text, json）
-        #[arg(long, default_value = "text")]
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
         format: String,
     },
+    /// thread の会話ログをエクスポートする
+    Export {
+        /// thread ID
+        #[arg(long)]
+        thread: String,
+        /// 出力形式（markdown, json, jsonl）
+        #[arg(long, default_value = "markdown", value_parser = formats(&["markdown", "json", "jsonl"]))]
+        format: String,
+        /// 出力先ファイル（省略時は stdout）
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+        /// --output のファイルを上書きせず末尾に追記する
+        #[arg(long, requires = "output")]
+        append: bool,
+    },
+    /// Claude Code の transcript ファイル（JSONL）を thread に取り込む（取り込み済みの発言はスキップ）
+    Import {
+        /// transcript ファイルのパス
+        #[arg(long)]
+        transcript: std::path::PathBuf,
+        /// 取り込み先 thread ID（省略時は transcript の sessionId を使用）
+        #[arg(long)]
+        thread: Option<String>,
+    },
+    /// message 同士を関連付ける（スレッドをまたいでも可）
+    Link {
+        /// リンク元 message ID（短い prefix でも可）
+        id: String,
+        /// リンク先 message ID（短い prefix でも可）
+        other_id: String,
+        /// 関係（blocks, duplicates, relates）
+        #[arg(long, default_value = "relates")]
+        relation: String,
+    },
+    /// 下書きの保存・一覧・投稿
+    Draft {
+        #[command(subcommand)]
+        action: DraftAction,
+    },
     /// message の内容を更新する
     Update {
         /// message ID（短い prefix でも可）
@@ -183,24 +530,229 @@ pub enum MessageAction {
     },
 }
 
+#[derive(Subcommand)]
+pub enum MentionsAction {
+    /// メンションを対応済みにする（以降の通知件数に含めない）
+    Dismiss {
+        /// message ID（短い prefix でも可、複数指定可）
+        #[arg(required_unless_present = "all")]
+        ids: Vec<String>,
+        /// 対応済みにする送信者名
+        #[arg(long)]
+        sender: String,
+        /// 未読のメンションをすべて対応済みにする
+        #[arg(long, conflicts_with = "ids")]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DraftAction {
+    /// 下書きを保存する（--id 指定時は既存の下書きを更新）
+    Save {
+        /// thread ID（新規作成時は必須）
+        #[arg(long, required_unless_present = "id")]
+        thread: Option<String>,
+        /// 送信者名（新規作成時は必須）
+        #[arg(long, required_unless_present = "id")]
+        sender: Option<String>,
+        /// 下書きの内容（省略時は stdin から読み取り）
+        #[arg(long)]
+        content: Option<String>,
+        /// 更新する下書きの ID（短い prefix でも可）
+        #[arg(long)]
+        id: Option<String>,
+        /// 既存の内容を置き換えず末尾に追記する
+        #[arg(long, requires = "id")]
+        append: bool,
+        /// message の role（user, assistant, system, tool。新規作成時のみ）
+        #[arg(long, default_value = "user", conflicts_with = "id")]
+        role: String,
+        /// メッセージタイプ（metadata.msg_type に設定される。新規作成時のみ）
+        #[arg(long, value_name = "TYPE", conflicts_with = "id")]
+        r#type: Option<String>,
+    },
+    /// 下書きを一覧表示する
+    List {
+        /// thread ID でフィルター
+        #[arg(long)]
+        thread: Option<String>,
+        /// 送信者でフィルター
+        #[arg(long)]
+        sender: Option<String>,
+        /// 内容を省略せず全文表示する
+        #[arg(long)]
+        full: bool,
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+    /// 下書きを message として投稿する
+    Post {
+        /// 下書き ID（短い prefix でも可）
+        id: String,
+    },
+    /// 下書きを破棄する
+    Delete {
+        /// 下書き ID（短い prefix でも可）
+        id: String,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum ThreadAction {
     /// 新しい thread を作成する
     Create {
         /// thread のタイトル
         title: String,
+        /// thread の名前（英数字・-・_ のスラッグ、一意）。thread ID の代わりに指定できる
+        #[arg(long)]
+        name: Option<String>,
     },
     /// thread を一覧表示する
     List {
         /// ID を省略せず全文表示する
         #[arg(long)]
         full: bool,
-        /// 出力形式（text, json）
-        #[arg(long, default_value = "text")]
+        /// 出力形式（text, json, jsonl, yaml, markdown, csv, tsv）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json", "jsonl", "yaml", "markdown", "csv", "tsv"]))]
+        format: String,
+        /// 1 件ごとの出力形式（例: '{{id}} {{sender}}: {{content}}'。{{metadata.key}} も可、\n \t を解釈）
+        #[arg(long)]
+        template: Option<String>,
+        /// ステータスでフィルター（open, closed, all）
+        #[arg(long, default_value = "all")]
+        status: String,
+        /// タグでフィルター
+        #[arg(long)]
+        tag: Option<String>,
+        /// フェーズでフィルター（planning, implementing, reviewing, done）
+        #[arg(long, conflicts_with = "no_phase")]
+        phase: Option<String>,
+        /// フェーズ未設定の thread のみ
+        #[arg(long)]
+        no_phase: bool,
+        /// 並び順（created, updated, title, messages）
+        #[arg(long, default_value = "updated")]
+        sort: String,
+        /// 並び順を逆にする
+        #[arg(long)]
+        reverse: bool,
+        /// 出力先ファイル（省略時は stdout。書き込みは完了時にまとめて反映される）
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+        /// --output のファイルを上書きせず末尾に追記する
+        #[arg(long, requires = "output")]
+        append: bool,
+    },
+    /// タイトル・名前・要約から thread を検索する
+    Search {
+        /// 検索クエリ（部分一致、英字の大文字小文字は区別しない）
+        query: String,
+        /// ID を省略せず全文表示する
+        #[arg(long)]
+        full: bool,
+        /// 出力形式（text, json, jsonl, yaml, markdown, csv, tsv）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json", "jsonl", "yaml", "markdown", "csv", "tsv"]))]
         format: String,
+        /// 1 件ごとの出力形式（例: '{{id}} {{sender}}: {{content}}'。{{metadata.key}} も可、\n \t を解釈）
+        #[arg(long)]
+        template: Option<String>,
         /// ステータスでフィルター（open, closed, all）
         #[arg(long, default_value = "all")]
         status: String,
+        /// 出力先ファイル（省略時は stdout。書き込みは完了時にまとめて反映される）
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+        /// --output のファイルを上書きせず末尾に追記する
+        #[arg(long, requires = "output")]
+        append: bool,
+    },
+    /// 指定 message 以降を新しい thread に切り出す
+    Split {
+        /// thread ID
+        id: String,
+        /// 切り出しを開始する message ID（この message を含む）
+        #[arg(long)]
+        from: String,
+        /// 新しい thread のタイトル（省略時は「元のタイトル（分割）」）
+        #[arg(long)]
+        title: Option<String>,
+    },
+    /// 既存 thread のタグ・フェーズ・message を引き継いだ新しい thread を作成する
+    Clone {
+        /// 複製元の thread ID
+        id: String,
+        /// 新しい thread のタイトル（省略時は「元のタイトル（複製）」）
+        #[arg(long)]
+        title: Option<String>,
+        /// 複製する message（none, all, pinned: metadata の pinned が true のもの）
+        #[arg(long, default_value = "none")]
+        messages: String,
+    },
+    /// thread を message・リンクごと JSON バンドルに書き出す
+    Export {
+        /// thread ID
+        id: String,
+        /// 出力先ファイル（省略時は stdout）
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+        /// --output のファイルを上書きせず末尾に追記する
+        #[arg(long, requires = "output")]
+        append: bool,
+    },
+    /// JSON バンドルから thread を復元する
+    Import {
+        /// バンドルファイルのパス
+        path: std::path::PathBuf,
+        /// thread・message に新しい ID を振り直す（既存 thread の複製用）
+        #[arg(long)]
+        new_ids: bool,
+    },
+    /// thread を購読する（他の送信者の投稿時に通知）
+    Watch {
+        /// thread ID
+        id: String,
+        /// 購読する送信者名
+        #[arg(long)]
+        sender: String,
+        /// トースト通知も表示する（Windows専用）
+        #[arg(long)]
+        notify: bool,
+    },
+    /// thread の購読を解除する
+    Unwatch {
+        /// thread ID
+        id: String,
+        /// 購読を解除する送信者名
+        #[arg(long)]
+        sender: String,
+    },
+    /// thread をピン留めする（一覧の先頭に表示）
+    Pin {
+        /// thread ID
+        id: String,
+    },
+    /// thread のピン留めを解除する
+    Unpin {
+        /// thread ID
+        id: String,
+    },
+    /// thread にタグを付ける
+    Tag {
+        /// thread ID
+        id: String,
+        /// タグ（複数指定可）
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// thread からタグを外す
+    Untag {
+        /// thread ID
+        id: String,
+        /// タグ（複数指定可）
+        #[arg(required = true)]
+        tags: Vec<String>,
     },
     /// thread とその message を削除する
     Delete {
@@ -212,11 +764,83 @@ pub enum ThreadAction {
         /// thread ID
         id: String,
     },
+    /// 一定期間 message のない open な thread をクローズする（ピン留めは除く）
+    Autoclose {
+        /// 最終投稿からの経過日数
+        #[arg(long)]
+        inactive_days: i64,
+        /// クローズせず対象の一覧のみ表示する
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// クローズされた thread を再オープンする
     Reopen {
         /// thread ID
         id: String,
     },
+    /// thread のタイトルと名前（スラッグ）を変更する
+    Rename {
+        /// thread ID
+        id: String,
+        /// 新しいタイトル
+        #[arg(required_unless_present = "name")]
+        title: Option<String>,
+        /// thread の名前（英数字・-・_ のスラッグ、一意）
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// thread をロックし、許可した送信者以外の投稿を拒否する
+    Lock {
+        /// thread ID
+        id: String,
+        /// 投稿を許可する送信者（カンマ区切りで複数指定可）
+        #[arg(long, value_delimiter = ',')]
+        allow: Vec<String>,
+    },
+    /// thread のロックを解除する
+    Unlock {
+        /// thread ID
+        id: String,
+    },
+    /// thread の保持ポリシーを設定する（cleanup apply-retention で適用）
+    #[command(group(clap::ArgGroup::new("policy").required(true).args(["days", "keep_last", "none"])))]
+    SetRetention {
+        /// thread ID
+        id: String,
+        /// N 日より古い message を削除する
+        #[arg(long)]
+        days: Option<i64>,
+        /// 直近 N 件の message のみ残す
+        #[arg(long)]
+        keep_last: Option<i64>,
+        /// 保持ポリシーを解除する（無期限に保持）
+        #[arg(long)]
+        none: bool,
+    },
+    /// message post で省略時に使う送信者・role を thread に設定する
+    SetDefault {
+        /// thread ID
+        id: String,
+        /// 既定の送信者名
+        #[arg(long, required_unless_present_any = ["role", "clear"])]
+        sender: Option<String>,
+        /// 既定の role（user, assistant, system, tool）
+        #[arg(long)]
+        role: Option<String>,
+        /// 既定値を解除する（--sender / --role と併用すると解除後に設定）
+        #[arg(long)]
+        clear: bool,
+    },
+    /// thread のメタデータ（JSON オブジェクト）を設定する
+    SetMeta {
+        /// thread ID
+        id: String,
+        /// JSON オブジェクト形式のメタデータ
+        json: String,
+        /// 置き換えずに既存のメタデータへトップレベルのキー単位でマージする（null のキーは削除）
+        #[arg(long)]
+        merge: bool,
+    },
     /// thread のフェーズを設定する
     SetPhase {
         /// thread ID
@@ -224,16 +848,96 @@ pub enum ThreadAction {
         /// フェーズ（planning, implementing, reviewing, done, none）
         phase: String,
     },
+    /// thread の詳細（メタデータ・message 数・参加者）を表示する
+    Show {
+        /// thread ID
+        id: String,
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+    /// thread の統計（件数・送信者別・ロール別・投稿期間・平均投稿間隔）を表示する
+    Stats {
+        /// thread ID
+        id: String,
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+    /// thread の参加者（送信者別の初回・最終投稿日時と件数）を表示する
+    Participants {
+        /// thread ID
+        id: String,
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+    /// thread の要約（冒頭・決定事項・メンション・直近）を生成して保存する
+    Summarize {
+        /// thread ID
+        id: String,
+        /// 要約に含める冒頭の message 数
+        #[arg(long, default_value = "3")]
+        head: usize,
+        /// 要約に含める直近の message 数
+        #[arg(long, default_value = "3")]
+        tail: usize,
+    },
+    /// ローカルの文書ファイル（HTML・Markdown・テキスト）を thread として取り込む
+    ImportFile {
+        /// 取り込むファイルのパス
+        path: std::path::PathBuf,
+        /// ファイル形式（html, md, txt。省略時は拡張子から判定）
+        #[arg(long)]
+        format: Option<String>,
+        /// thread のタイトル（省略時はファイル名）
+        #[arg(long)]
+        title: Option<String>,
+        /// 取り込むコンテンツの送信者名
+        #[arg(long)]
+        sender: Option<String>,
+        /// HTML の会話ページを投稿ごとの message に分割する（thread fetch --split と同じ）
+        #[arg(long)]
+        split: bool,
+    },
     /// URL から会話を取得して保存する
     Fetch {
         /// 取得元 URL
-        url: String,
+        #[arg(required_unless_present_any = ["refresh", "github"], conflicts_with_all = ["refresh", "github"])]
+        url: Option<String>,
+        /// GitHub の issue / PR を REST API で取得する（owner/repo#123、コメントごとに message を作成。トークンは GITHUB_TOKEN または GH_TOKEN）
+        #[arg(long, value_name = "OWNER/REPO#N", conflicts_with_all = ["refresh", "split"])]
+        github: Option<String>,
+        /// 既存 thread の source_url を再取得し、前回までに取得していない差分だけを追加する
+        #[arg(long, value_name = "THREAD_ID", conflicts_with = "title")]
+        refresh: Option<String>,
         /// thread のタイトル（省略時は URL を使用）
         #[arg(long)]
         title: Option<String>,
         /// 取得コンテンツの送信者名
         #[arg(long)]
         sender: Option<String>,
+        /// 会話ページ（ChatGPT 共有ページ・GitHub issue・フォーラム）を投稿ごとの message に分割する
+        #[arg(long, conflicts_with = "refresh")]
+        split: bool,
+        /// 次のページを最大 N ページまでたどり、各ページを別の message として保存する（Link ヘッダーの rel="next" を使用）
+        #[arg(long, value_name = "N", conflicts_with_all = ["refresh", "github"])]
+        follow_next: Option<usize>,
+        /// Link ヘッダーがないページで次ページのリンクを探す CSS セレクター（例: 'a[rel=next]', '.pagination .next'）
+        #[arg(long, value_name = "SELECTOR", requires = "follow_next")]
+        next_selector: Option<String>,
+        /// JSON レスポンスから保存する部分を選ぶ JSONPath（例: $.data.items[0]）
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["refresh", "github", "split"])]
+        json_path: Option<String>,
+        /// 追加のリクエストヘッダー（'Name: value'、複数指定可）
+        #[arg(long, value_name = "HEADER")]
+        header: Vec<String>,
+        /// Bearer トークン（Authorization ヘッダーとして送信）
+        #[arg(long, value_name = "TOKEN", conflicts_with = "bearer_env")]
+        bearer: Option<String>,
+        /// Bearer トークンを読み取る環境変数名（コマンドラインにトークンを残さない）
+        #[arg(long, value_name = "VAR")]
+        bearer_env: Option<String>,
     },
 }
 
@@ -254,8 +958,8 @@ pub enum HookAction {
 pub enum CleanupAction {
     /// N 日より古い message を削除する
     Age {
-        /// 日数
-        days: i64,
+        /// 日数（省略時は設定ファイルの [cleanup] days）
+        days: Option<i64>,
         /// DB バックアップをスキップする
         #[arg(long)]
         no_backup: bool,
@@ -268,6 +972,12 @@ pub enum CleanupAction {
         #[arg(long)]
         no_backup: bool,
     },
+    /// thread ごとの保持ポリシー（thread set-retention）に従って message を削除する
+    ApplyRetention {
+        /// DB バックアップをスキップする
+        #[arg(long)]
+        no_backup: bool,
+    },
     /// session の全 message を削除する
     Session {
         /// session ID
@@ -278,6 +988,47 @@ pub enum CleanupAction {
     },
 }
 
+#[derive(Subcommand)]
+pub enum BackupAction {
+    /// 今の DB のバックアップを作成し、そのパスを表示する
+    Create {
+        /// ファイル名に付けるラベル（英数字・-・_。restore などでも名前の代わりに使える）
+        #[arg(long)]
+        label: Option<String>,
+    },
+    /// バックアップを新しい順に、サイズ・経過時間・thread と message の件数つきで表示する
+    List {
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+    /// バックアップを読み取り専用で開いて整合性チェックを行う（問題があれば終了コード 9）
+    Verify {
+        /// バックアップのファイル名、日時（例: 20240101093000）、ラベルのいずれか
+        name: String,
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+    /// バックアップの内容で DB を置き換える（圧縮されたバックアップは展開し、現在の DB は先にバックアップする）
+    Restore {
+        /// バックアップのファイル名、日時（例: 20240101093000）、ラベルのいずれか
+        name: String,
+    },
+    /// 古いバックアップを削除し、削除したファイルを表示する（省略時は設定ファイルの [backup] keep / older_than_days）
+    Prune {
+        /// 新しい方から N 件は常に残す
+        #[arg(long, value_name = "N")]
+        keep: Option<usize>,
+        /// 残す分を除き、N 日より古いものだけを削除する（省略時は残す分以外すべて）
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<i64>,
+        /// 削除せず対象の一覧のみ表示する
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum SetupAction {
     /// Claude Code 用の hook 設定を生成する
@@ -294,6 +1045,210 @@ pub enum SetupAction {
     },
 }
 
+#[derive(Subcommand)]
+pub enum GroupAction {
+    /// グループにメンバーを追加する
+    Add {
+        /// グループ名（英数字・_・-）
+        name: String,
+        /// 追加する送信者名
+        #[arg(required = true)]
+        members: Vec<String>,
+    },
+    /// グループからメンバーを削除する
+    Remove {
+        /// グループ名
+        name: String,
+        /// 削除する送信者名
+        #[arg(required = true)]
+        members: Vec<String>,
+    },
+    /// グループとメンバーを一覧表示する
+    List {
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum StatsAction {
+    /// thread の推定トークン数とバイト数を表示する
+    Tokens {
+        /// thread ID
+        #[arg(long)]
+        thread: String,
+        /// message ごとの内訳も表示する
+        #[arg(long)]
+        by_message: bool,
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SearchAction {
+    /// タイトル・要約が一致する thread と本文が一致する message を thread ごとにまとめて表示する
+    All {
+        /// 検索クエリ
+        query: String,
+        /// 返す message の最大件数
+        #[arg(long, default_value = "50")]
+        limit: usize,
+        /// 内容を省略せず全文表示する
+        #[arg(long)]
+        full: bool,
+        /// 出力形式（text, json, jsonl, yaml）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json", "jsonl", "yaml"]))]
+        format: String,
+        /// 出力先ファイル（省略時は stdout。書き込みは完了時にまとめて反映される）
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+        /// --output のファイルを上書きせず末尾に追記する
+        #[arg(long, requires = "output")]
+        append: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum IndexAction {
+    /// 既存の全 message から全文検索インデックス（trigram）を再構築する
+    Rebuild,
+    /// インデックスの件数と message テーブルとの整合性を表示する
+    Status {
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+    /// 意味検索用の埋め込みベクトルを作成する（未作成・内容が変わった message のみ）
+    Embed {
+        /// 既存のベクトルを破棄してすべて作り直す
+        #[arg(long)]
+        rebuild: bool,
+        /// 1 リクエストで送る message 数
+        #[arg(long, default_value = "32")]
+        batch_size: usize,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DbAction {
+    /// VACUUM と全文検索インデックスの最適化で DB ファイルを縮小し、削減量を表示する
+    Vacuum {
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+    /// ファイルサイズ・テーブルごとの行数とサイズ・インデックスサイズ・大きい thread と message を表示する
+    Stats {
+        /// 表示する大きい thread と message の数
+        #[arg(long, default_value = "5")]
+        top: usize,
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+    /// DB の整合性・全文検索インデックス・thread のない message・存在しない thread や message を指す行を検査する（問題があれば終了コード 9）
+    Doctor {
+        /// 修復できる問題を修復する（インデックスの再構築、失われた thread の再作成、存在しない thread や message を指す行の削除）
+        #[arg(long)]
+        fix: bool,
+        /// thread のない message と存在しない thread や message を指す行だけを検査する（DB 全体を読む整合性チェックと全文検索の検査を省く）
+        #[arg(long)]
+        orphans: bool,
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+    /// 別の aiboard DB の thread・message・リンクを ID で突き合わせて取り込む
+    Merge {
+        /// 取り込む DB ファイルのパス（読み取りのみで、書き換えない）
+        path: std::path::PathBuf,
+        /// 両方にある thread・message は updated_at が新しい方で上書きする（省略時はこちらを残す）
+        #[arg(long)]
+        prefer_newer: bool,
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+    /// 平文の DB を AIBOARD_DB_KEY か OS のキーリングの鍵で暗号化する（--features encryption でのビルドが必要）
+    Encrypt,
+    /// 暗号化された DB を平文に戻す（--features encryption でのビルドが必要）
+    Decrypt,
+}
+
+#[derive(Subcommand)]
+pub enum SyncAction {
+    /// このボードの thread・message・リンクを共有先に送る（両方にあるものは updated_at が新しい方を残す）
+    Push {
+        /// 共有先: DB ファイルのパス、または GET で DB ファイルを返し PUT で置き換える http(s) URL
+        #[arg(long, value_name = "PATH_OR_URL")]
+        remote: String,
+        /// 追加のリクエストヘッダー（'Name: value'、複数指定可。URL の共有先のみ）
+        #[arg(long, value_name = "HEADER")]
+        header: Vec<String>,
+        /// Bearer トークン（Authorization ヘッダーとして送信）
+        #[arg(long, value_name = "TOKEN", conflicts_with = "bearer_env")]
+        bearer: Option<String>,
+        /// Bearer トークンを読み取る環境変数名（コマンドラインにトークンを残さない）
+        #[arg(long, value_name = "VAR")]
+        bearer_env: Option<String>,
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+    /// 共有先の thread・message・リンクをこのボードに取り込む（両方にあるものは updated_at が新しい方を残す）
+    Pull {
+        /// 共有先: DB ファイルのパス、または GET で DB ファイルを返し PUT で置き換える http(s) URL
+        #[arg(long, value_name = "PATH_OR_URL")]
+        remote: String,
+        /// 追加のリクエストヘッダー（'Name: value'、複数指定可。URL の共有先のみ）
+        #[arg(long, value_name = "HEADER")]
+        header: Vec<String>,
+        /// Bearer トークン（Authorization ヘッダーとして送信）
+        #[arg(long, value_name = "TOKEN", conflicts_with = "bearer_env")]
+        bearer: Option<String>,
+        /// Bearer トークンを読み取る環境変数名（コマンドラインにトークンを残さない）
+        #[arg(long, value_name = "VAR")]
+        bearer_env: Option<String>,
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+    /// 共有先ごとの最後の push と pull を表示する
+    Status {
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AuditAction {
+    /// 監査ログを新しい順に表示する
+    List {
+        /// この操作のみ（message.post, cleanup.age など）
+        #[arg(long, value_parser = PossibleValuesParser::new(AuditOperation::ALL.map(|op| op.as_str())))]
+        operation: Option<String>,
+        /// この実行者（sender）の操作のみ
+        #[arg(long)]
+        actor: Option<String>,
+        /// この ID（前方一致）の message か thread に関わる操作のみ
+        #[arg(long)]
+        target: Option<String>,
+        /// この日時より後の操作のみ（ISO 8601、YYYY-MM-DD、yesterday、2d など）
+        #[arg(long)]
+        after: Option<String>,
+        /// 返す件数の上限
+        #[arg(long, default_value = "50")]
+        limit: usize,
+        /// 出力形式（text, json）
+        #[arg(long, default_value = "text", value_parser = formats(&["text", "json"]))]
+        format: String,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum UtilAction {
     /// リストからランダムに要素を選択する
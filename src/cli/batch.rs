@@ -0,0 +1,126 @@
+//! Input of `aiboard batch`: one command per line, run in a single process.
+//!
+//! A line is either a command line as typed after `aiboard`
+//! (`message post --thread t --content "hi"`, split like a POSIX shell
+//! without any expansion) or JSON: an array of arguments, or an object
+//! `{"args": [...], "stdin": "..."}` that also gives the command its
+//! standard input. Blank lines and lines starting with `#` are skipped.
+
+use std::sync::Mutex;
+
+use anyhow::{anyhow, bail};
+
+use crate::cli::i18n;
+
+/// One command of the batch.
+pub struct Line {
+    pub args: Vec<String>,
+    pub stdin: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonLine {
+    args: Vec<String>,
+    #[serde(default)]
+    stdin: Option<String>,
+}
+
+/// The command on `text`, or `None` for a blank or comment line.
+pub fn parse_line(text: &str) -> anyhow::Result<Option<Line>> {
+    let text = text.trim();
+    if text.is_empty() || text.starts_with('#') {
+        return Ok(None);
+    }
+    let line = if text.starts_with('[') {
+        let args: Vec<String> = serde_json::from_str(text)
+            .map_err(|e| anyhow!(i18n::fill(i18n::tr("JSON の行を解析できません: {}"), &[&e])))?;
+        Line { args, stdin: None }
+    } else if text.starts_with('{') {
+        let line: JsonLine = serde_json::from_str(text)
+            .map_err(|e| anyhow!(i18n::fill(i18n::tr("JSON の行を解析できません: {}"), &[&e])))?;
+        Line { args: line.args, stdin: line.stdin }
+    } else {
+        Line { args: split_words(text)?, stdin: None }
+    };
+    Ok(Some(line))
+}
+
+/// Splits a command line into words: whitespace separates words, single
+/// quotes keep everything literally, double quotes keep everything but
+/// `\"` and `\\`, and a backslash outside quotes escapes the next character.
+fn split_words(text: &str) -> anyhow::Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => bail!(i18n::tr("閉じられていない ' があります")),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => bail!(i18n::tr("閉じられていない \" があります")),
+                        },
+                        Some(c) => word.push(c),
+                        None => bail!(i18n::tr("閉じられていない \" があります")),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                if let Some(c) = chars.next() {
+                    word.push(c);
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Standard input of the command being run by `batch` (whose own stdin
+/// holds the commands).
+static STDIN: Mutex<Option<String>> = Mutex::new(None);
+
+/// Gives the next command `input` as its stdin (`None` outside `batch`).
+pub fn set_stdin(input: Option<String>) {
+    if let Ok(mut slot) = STDIN.lock() {
+        *slot = input;
+    }
+}
+
+/// The stdin given with `set_stdin` when running inside `batch`; reading
+/// it again gives an empty input.
+pub fn take_stdin() -> Option<String> {
+    STDIN.lock().ok().and_then(|mut slot| slot.as_mut().map(std::mem::take))
+}
@@ -1,7 +1,30 @@
-use crate::domain::entity::{Message, Thread};
-use chrono::Local;
+use crate::domain::entity::{
+    AuditEntry, DbCheck, DbStats, Draft, EmbeddingStatus, Group, Message, MessageLink, Participant, Role, SearchGroup, SearchHit,
+    SearchIndexStatus, SyncState, Thread,
+};
+use crate::cli::i18n;
+use crate::cli::output;
+use crate::cli::pager;
+use crate::cli::schema::SCHEMA_VERSION;
+use crate::infra::backup::{BackupSummary, BackupVerification};
+use crate::usecase::merge::MergeReport;
+use crate::usecase::stats::ThreadTokenStats;
+use crate::usecase::sync::SyncReport;
+use crate::usecase::thread::{ThreadBundle, ThreadDetail, ThreadStats};
+use chrono::{DateTime, Local, Utc};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::OnceLock;
 
-const TRUNCATE_LEN: usize = 100;
+/// Characters of content kept by truncated output; `truncate` in the config.
+static TRUNCATE_LEN: AtomicUsize = AtomicUsize::new(100);
+
+pub fn set_truncate_len(len: usize) {
+    TRUNCATE_LEN.store(len, Ordering::Relaxed);
+}
+
+fn truncate_len() -> usize {
+    TRUNCATE_LEN.load(Ordering::Relaxed)
+}
 const SNIPPET_CONTEXT: usize = 50;
 
 fn truncate_content(s: &str, max: usize) -> String {
@@ -12,93 +35,1066 @@ fn truncate_content(s: &str, max: usize) -> String {
     format!("{}…", truncated)
 }
 
-fn snippet_around(s: &str, query: &str, context: usize) -> String {
-    let lower = s.to_lowercase();
-    let q_lower = query.to_lowercase();
-    let Some(byte_pos) = lower.find(&q_lower) else {
-        return truncate_content(s, TRUNCATE_LEN);
+/// ANSI bold red, for highlighting matches on a terminal.
+const HIGHLIGHT_ANSI: (&str, &str) = ("\x1b[1;31m", "\x1b[0m");
+const HIGHLIGHT_MARK: (&str, &str) = ("<mark>", "</mark>");
+
+/// `content` with the `matches` character ranges wrapped in `marker`. Unless
+/// `full`, only a window around the first match is kept (the truncated
+/// content when nothing matched).
+fn highlight_content(content: &str, matches: &[[usize; 2]], marker: Option<(&str, &str)>, full: bool) -> String {
+    let chars: Vec<char> = content.chars().collect();
+    let (start, end) = match matches.first() {
+        _ if full => (0, chars.len()),
+        Some(&[s, e]) => (
+            s.min(chars.len()).saturating_sub(SNIPPET_CONTEXT),
+            (e + SNIPPET_CONTEXT).min(chars.len()),
+        ),
+        None => return truncate_content(content, truncate_len()),
     };
 
-    let chars: Vec<char> = s.chars().collect();
-    let total = chars.len();
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    let mut pos = start;
+    for &[s, e] in matches {
+        let (s, e) = (s.clamp(pos, end), e.min(end));
+        if s >= e {
+            continue;
+        }
+        out.extend(&chars[pos..s]);
+        match marker {
+            Some((open, close)) => {
+                out.push_str(open);
+                out.extend(&chars[s..e]);
+                out.push_str(close);
+            }
+            None => out.extend(&chars[s..e]),
+        }
+        pos = e;
+    }
+    out.extend(&chars[pos..end]);
+    if end < chars.len() {
+        out.push('…');
+    }
+    out
+}
+
+/// `--color always|never`; unset means `auto`.
+static COLOR_CHOICE: OnceLock<Option<bool>> = OnceLock::new();
+
+/// Applies the global `--color` choice; called once before any output.
+pub fn set_color_choice(choice: &str) {
+    let forced = match choice {
+        "always" => Some(true),
+        "never" => Some(false),
+        _ => None,
+    };
+    let _ = COLOR_CHOICE.set(forced);
+}
+
+/// Whether stdout should get ANSI colors: as forced by `--color`, otherwise
+/// a terminal (or the pager on one) with `NO_COLOR` unset and no `--output`.
+pub fn color_enabled() -> bool {
+    use std::io::IsTerminal;
+    match COLOR_CHOICE.get().copied().flatten() {
+        Some(forced) => forced,
+        None => {
+            (std::io::stdout().is_terminal() || pager::active())
+                && !output::redirected()
+                && std::env::var_os("NO_COLOR").is_none()
+        }
+    }
+}
+
+/// Set by the global `--compact` flag.
+static COMPACT_JSON: AtomicBool = AtomicBool::new(false);
+
+pub fn set_compact_json(compact: bool) {
+    COMPACT_JSON.store(compact, Ordering::Relaxed);
+}
+
+/// JSON output: pretty-printed, or on a single line with `--compact`.
+/// Objects get `schema_version` (see `aiboard schema`).
+pub fn to_json<T: serde::Serialize + ?Sized>(value: &T) -> serde_json::Result<String> {
+    let mut value = serde_json::to_value(value)?;
+    if let Some(object) = value.as_object_mut() {
+        object.insert("schema_version".to_string(), SCHEMA_VERSION.into());
+    }
+    json_text(&value)
+}
+
+fn json_text(value: &serde_json::Value) -> serde_json::Result<String> {
+    if COMPACT_JSON.load(Ordering::Relaxed) {
+        serde_json::to_string(value)
+    } else {
+        serde_json::to_string_pretty(value)
+    }
+}
+
+/// `aiboard schema` without a command: one documented command per line.
+pub fn format_schema_commands(commands: &[(&str, &str)]) -> String {
+    commands
+        .iter()
+        .map(|(name, summary)| format!("{:<16}{}", name, summary))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A JSON Schema document, as is.
+pub fn format_schema_json(schema: &serde_json::Value) -> String {
+    json_text(schema).unwrap_or_else(|_| "{}".to_string())
+}
 
-    // Convert byte position to char position
-    let char_pos = s[..byte_pos].chars().count();
-    let q_chars = query.chars().count();
+/// Set by the global `--relative-time` flag.
+static RELATIVE_TIME: AtomicBool = AtomicBool::new(false);
 
-    let start = char_pos.saturating_sub(context);
-    let end = (char_pos + q_chars + context).min(total);
+pub fn set_relative_time(relative: bool) {
+    RELATIVE_TIME.store(relative, Ordering::Relaxed);
+}
 
-    let slice: String = chars[start..end].iter().collect();
+/// A timestamp in text output: local time, or with `--relative-time` the
+/// distance from now (「3分前」「2時間前」).
+fn display_time(t: &DateTime<Utc>) -> String {
+    if RELATIVE_TIME.load(Ordering::Relaxed) {
+        relative_time(t, &Utc::now())
+    } else {
+        t.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+}
+
+fn relative_time(t: &DateTime<Utc>, now: &DateTime<Utc>) -> String {
+    let secs = (*now - *t).num_seconds();
+    let (amount, suffix) = (secs.abs(), if secs < 0 { "後" } else { "前" });
+    match amount {
+        0..=59 => "たった今".to_string(),
+        60..=3_599 => format!("{}分{}", amount / 60, suffix),
+        3_600..=86_399 => format!("{}時間{}", amount / 3_600, suffix),
+        86_400..=2_591_999 => format!("{}日{}", amount / 86_400, suffix),
+        2_592_000..=31_535_999 => format!("{}か月{}", amount / 2_592_000, suffix),
+        _ => format!("{}年{}", amount / 31_536_000, suffix),
+    }
+}
+
+const ANSI_RESET: &str = "\x1b[0m";
+const ANSI_DIM: &str = "\x1b[2m";
+const ANSI_BOLD: &str = "\x1b[1m";
+const ANSI_MENTION: &str = "\x1b[1;34m";
+
+fn paint(text: &str, code: &str, color: bool) -> String {
+    if color {
+        format!("{}{}{}", code, text, ANSI_RESET)
+    } else {
+        text.to_string()
+    }
+}
 
-    let prefix = if start > 0 { "…" } else { "" };
-    let suffix = if end < total { "…" } else { "" };
+fn role_color(role: &Role) -> &'static str {
+    match role {
+        Role::User => "\x1b[32m",
+        Role::Assistant => "\x1b[36m",
+        Role::System => "\x1b[33m",
+        Role::Tool => "\x1b[35m",
+    }
+}
 
-    format!("{}{}{}", prefix, slice, suffix)
+/// `@name`, `@all` and `@team:<group>` mentions wrapped in color. A mention
+/// starts after a non-word character and runs over word characters, `-`, `.`
+/// and `:` (trailing `.` and `:` are left out as punctuation).
+fn paint_mentions(content: &str) -> String {
+    let is_name_char = |c: char| c.is_alphanumeric() || "_-.:".contains(c);
+    let mut out = String::with_capacity(content.len());
+    let mut rest = content;
+    let mut prev: Option<char> = None;
+    while let Some(pos) = rest.find('@') {
+        let before = &rest[..pos];
+        out.push_str(before);
+        let prev_char = before.chars().last().or(prev);
+        let after = &rest[pos + 1..];
+        let name_len = after.find(|c: char| !is_name_char(c)).unwrap_or(after.len());
+        let name = after[..name_len].trim_end_matches(['.', ':']);
+        let at_word_start = !prev_char.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        if at_word_start && name.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+            out.push_str(ANSI_MENTION);
+            out.push('@');
+            out.push_str(name);
+            out.push_str(ANSI_RESET);
+            rest = &after[name.len()..];
+            prev = name.chars().last();
+        } else {
+            out.push('@');
+            rest = after;
+            prev = Some('@');
+        }
+    }
+    out.push_str(rest);
+    out
 }
 
-fn format_message_with_content(msg: &Message, content: &str) -> String {
+/// One message line. With `color`, the timestamp is dimmed, the role colored
+/// by kind and the sender bold; `content` is used as given.
+fn format_message_with_content(msg: &Message, content: &str, color: bool) -> String {
     let id_short = &msg.id[..8.min(msg.id.len())];
     let sender = msg.sender.as_deref().unwrap_or("-");
     let source_tag = match msg.source.as_deref() {
         Some(s) => format!(" [{}]", s),
         None => String::new(),
     };
-    let local_time = msg.created_at.with_timezone(&Local);
     format!(
-        "[{}] {} ({}) {}{}: {}",
-        local_time.format("%Y-%m-%d %H:%M:%S"),
+        "{} {} {} {}{}: {}",
+        paint(&format!("[{}]", display_time(&msg.created_at)), ANSI_DIM, color),
         id_short,
-        msg.role,
-        sender,
+        paint(&format!("({})", msg.role), role_color(&msg.role), color),
+        paint(sender, ANSI_BOLD, color),
         source_tag,
         content,
     )
 }
 
-pub fn format_message_text(msg: &Message) -> String {
-    format_message_with_content(msg, &msg.content)
-}
-
-pub fn format_message_truncated(msg: &Message) -> String {
-    let content = truncate_content(&msg.content, TRUNCATE_LEN);
-    format_message_with_content(msg, &content)
+/// Messages one per line, content truncated unless `full`, colored (mentions
+/// included) when `color`.
+pub fn format_messages_text(messages: &[Message], full: bool, color: bool) -> String {
+    messages
+        .iter()
+        .map(|msg| format_message_line(msg, full, color))
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-pub fn format_message_snippet(msg: &Message, query: &str) -> String {
-    let content = snippet_around(&msg.content, query, SNIPPET_CONTEXT);
-    format_message_with_content(msg, &content)
+/// One line of `format_messages_text`.
+pub fn format_message_line(msg: &Message, full: bool, color: bool) -> String {
+    let content = if full {
+        msg.content.clone()
+    } else {
+        truncate_content(&msg.content, truncate_len())
+    };
+    let content = if color { paint_mentions(&content) } else { content };
+    format_message_with_content(msg, &content, color)
 }
 
-pub fn format_messages_text(messages: &[Message], full: bool) -> String {
-    let fmt = if full { format_message_text } else { format_message_truncated };
+/// Messages as a dialogue: a header line per message (sender, role, time,
+/// short ID) followed by the whole body indented, with a blank line between
+/// messages. Mentions are highlighted when `color`.
+pub fn format_messages_transcript(messages: &[Message], color: bool) -> String {
     messages
         .iter()
-        .map(fmt)
+        .map(|msg| format_transcript_entry(msg, color))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// One message of `format_messages_transcript`.
+pub fn format_transcript_entry(msg: &Message, color: bool) -> String {
+    let mut header = format!(
+        "{} {} {} {}",
+        paint(msg.sender.as_deref().unwrap_or("-"), ANSI_BOLD, color),
+        paint(&format!("({})", msg.role), role_color(&msg.role), color),
+        paint(&display_time(&msg.created_at), ANSI_DIM, color),
+        paint(&msg.id[..8.min(msg.id.len())], ANSI_DIM, color),
+    );
+    if let Some(ref source) = msg.source {
+        header.push_str(&format!(" [{}]", source));
+    }
+    let content = if color { paint_mentions(&msg.content) } else { msg.content.clone() };
+    let body = content
+        .trim_end()
+        .lines()
+        .map(|line| if line.trim().is_empty() { String::new() } else { format!("    {}", line) })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{}\n{}", header, body)
+}
+
+/// Search results as snippets around the first match (whole messages with
+/// `full`), matches in color when `color`. Fuzzy hits are marked with `~`.
+pub fn format_search_hits_text(hits: &[SearchHit], full: bool, color: bool) -> String {
+    let marker = color.then_some(HIGHLIGHT_ANSI);
+    hits.iter()
+        .map(|hit| {
+            let content = highlight_content(&hit.message.content, &hit.matches, marker, full);
+            let text = format_message_with_content(&hit.message, &content, color);
+            match hit.fuzzy {
+                Some(_) => format!("~ {}", text),
+                None => text,
+            }
+        })
         .collect::<Vec<_>>()
         .join("\n")
 }
 
-pub fn format_messages_search(messages: &[Message], query: &str, full: bool) -> String {
-    messages
+/// `search all` results: a header line per thread, its matching messages
+/// indented beneath.
+pub fn format_search_groups_text(groups: &[SearchGroup], full: bool, color: bool) -> String {
+    groups
         .iter()
-        .map(|msg| {
-            if full {
-                format_message_text(msg)
-            } else {
-                format_message_snippet(msg, query)
+        .map(|group| {
+            let thread = &group.thread;
+            let id = if full { &thread.id[..] } else { &thread.id[..8.min(thread.id.len())] };
+            let mut notes = Vec::new();
+            if group.thread_match {
+                notes.push("タイトル・要約に一致".to_string());
+            }
+            if !group.messages.is_empty() {
+                notes.push(format!("message {} 件", group.messages.len()));
             }
+            let mut out = format!("{}\t{}\t({})", id, thread.title, notes.join(", "));
+            for line in format_search_hits_text(&group.messages, full, color).lines() {
+                out.push_str("\n  ");
+                out.push_str(line);
+            }
+            out
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+pub fn format_search_groups_json(groups: &[SearchGroup]) -> String {
+    to_json(groups).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Search results as Markdown, matches wrapped in `<mark>`.
+pub fn format_search_hits_markdown(query: &str, hits: &[SearchHit], full: bool) -> String {
+    let mut out = format!("# Search: {}\n\n- hits: {}\n", query, hits.len());
+    for hit in hits {
+        push_search_hit_markdown(&mut out, hit, "###", full);
+    }
+    out
+}
+
+/// Like `format_search_hits_markdown`, with a section per thread.
+pub fn format_search_groups_markdown(query: &str, groups: &[SearchGroup], full: bool) -> String {
+    let hits: usize = groups.iter().map(|g| g.messages.len()).sum();
+    let mut out = format!("# Search: {}\n\n- hits: {}\n- threads: {}\n", query, hits, groups.len());
+    for group in groups {
+        out.push_str(&format!(
+            "\n## {} (`{}`) — {} 件\n",
+            group.thread.title,
+            group.thread.id,
+            group.messages.len()
+        ));
+        for hit in &group.messages {
+            push_search_hit_markdown(&mut out, hit, "###", full);
+        }
+    }
+    out
+}
+
+fn push_search_hit_markdown(out: &mut String, hit: &SearchHit, heading: &str, full: bool) {
+    let msg = &hit.message;
+    let local_time = msg.created_at.with_timezone(&Local);
+    out.push_str(&format!(
+        "\n---\n\n{} {} ({}) — {}\n\n",
+        heading,
+        msg.sender.as_deref().unwrap_or("-"),
+        msg.role,
+        local_time.format("%Y-%m-%d %H:%M:%S"),
+    ));
+    out.push_str(&format!("- message: `{}`\n- thread: `{}`\n", msg.id, msg.thread_id));
+    if let Some(score) = hit.score {
+        out.push_str(&format!("- score: {:.3}\n", score));
+    }
+    if let Some(ref found) = hit.fuzzy {
+        out.push_str(&format!("- fuzzy: {}\n", found));
+    }
+    out.push('\n');
+    out.push_str(highlight_content(&msg.content, &hit.matches, Some(HIGHLIGHT_MARK), full).trim_end());
+    out.push('\n');
+}
+
+pub fn format_index_status_text(search: &SearchIndexStatus, embeddings: &[EmbeddingStatus]) -> String {
+    let state = if search.consistent && search.indexed == search.messages {
+        "正常"
+    } else {
+        "不整合"
+    };
+    let mut lines = vec![format!(
+        "全文検索: {}（message {} 件 / インデックス {} 件）",
+        state, search.messages, search.indexed
+    )];
+    if embeddings.is_empty() {
+        lines.push("埋め込み: なし".to_string());
+    }
+    for e in embeddings {
+        lines.push(format!(
+            "埋め込み: {}（{} / {} 件、古いもの {} 件）",
+            e.model, e.vectors, search.messages, e.stale
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn format_index_status_json(search: &SearchIndexStatus, embeddings: &[EmbeddingStatus]) -> String {
+    let value = serde_json::json!({ "search": search, "embeddings": embeddings });
+    to_json(&value).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// `bytes` in B, KB, MB or GB (powers of 1024) with one decimal.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+pub fn format_vacuum_text(before: u64, after: u64) -> String {
+    i18n::fill(
+        i18n::tr("DB サイズ: {} → {}（{} 削減）"),
+        &[
+            &format_bytes(before),
+            &format_bytes(after),
+            &format_bytes(before.saturating_sub(after)),
+        ],
+    )
+}
+
+pub fn format_vacuum_json(before: u64, after: u64) -> String {
+    let value = serde_json::json!({
+        "before_bytes": before,
+        "after_bytes": after,
+        "reclaimed_bytes": before.saturating_sub(after),
+    });
+    to_json(&value).unwrap_or_else(|_| "{}".to_string())
+}
+
+pub fn format_db_stats_text(stats: &DbStats) -> String {
+    let mut lines = vec![
+        i18n::fill(
+            i18n::tr("ファイル: {}（WAL {}、空きページ {}）"),
+            &[
+                &format_bytes(stats.file_bytes),
+                &format_bytes(stats.wal_bytes),
+                &format_bytes(stats.free_pages * stats.page_size),
+            ],
+        ),
+        i18n::fill(i18n::tr("スキーマバージョン: {}"), &[&stats.schema_version]),
+        String::new(),
+        i18n::tr("テーブル:").to_string(),
+    ];
+    for t in &stats.tables {
+        lines.push(format!("  {}\t{} rows\t{}", t.name, t.rows, format_bytes(t.bytes)));
+    }
+    lines.push(i18n::tr("インデックス:").to_string());
+    for i in &stats.indexes {
+        lines.push(format!("  {} ({})\t{}", i.name, i.table, format_bytes(i.bytes)));
+    }
+    if !stats.largest_threads.is_empty() {
+        lines.push(i18n::tr("大きい thread:").to_string());
+        for t in &stats.largest_threads {
+            lines.push(format!(
+                "  {}\t{}\t{} messages\t{}",
+                t.id,
+                t.title,
+                t.messages,
+                format_bytes(t.bytes)
+            ));
+        }
+    }
+    if !stats.largest_messages.is_empty() {
+        lines.push(i18n::tr("大きい message:").to_string());
+        for m in &stats.largest_messages {
+            lines.push(format!(
+                "  {}\t{}\t{}\t{}",
+                m.id,
+                m.thread_id,
+                m.sender.as_deref().unwrap_or("-"),
+                format_bytes(m.bytes)
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+pub fn format_db_stats_json(stats: &DbStats) -> String {
+    to_json(stats).unwrap_or_else(|_| "{}".to_string())
+}
+
+pub fn format_db_check_text(check: &DbCheck) -> String {
+    let mut lines = Vec::new();
+    match check.integrity_errors {
+        Some(ref errors) if errors.is_empty() => lines.push(i18n::tr("整合性チェック: 正常").to_string()),
+        Some(ref errors) => {
+            lines.push(i18n::fill(i18n::tr("整合性チェック: 異常（{} 件）"), &[&errors.len()]));
+            lines.extend(errors.iter().map(|e| format!("  {}", e)));
+        }
+        None => {}
+    }
+    if let Some(ref search) = check.search_index {
+        let state = if check.search_index_healthy() { "正常" } else { "不整合" };
+        lines.push(i18n::fill(
+            i18n::tr("全文検索: {}（message {} 件 / インデックス {} 件）"),
+            &[&i18n::tr(state), &search.messages, &search.indexed],
+        ));
+    }
+    if check.orphans.is_empty() {
+        lines.push(i18n::tr("thread のない message: なし").to_string());
+    } else {
+        let total: u64 = check.orphans.iter().map(|o| o.messages).sum();
+        lines.push(i18n::fill(
+            i18n::tr("thread のない message: {} 件（thread {} 件）"),
+            &[&total, &check.orphans.len()],
+        ));
+        for o in &check.orphans {
+            lines.push(format!("  {}\t{} messages", o.thread_id, o.messages));
+        }
+    }
+    if check.dangling.is_empty() {
+        lines.push(i18n::tr("存在しない thread・message を指す行: なし").to_string());
+    } else {
+        let total: u64 = check.dangling.iter().map(|d| d.rows).sum();
+        lines.push(i18n::fill(i18n::tr("存在しない thread・message を指す行: {} 行"), &[&total]));
+        for d in &check.dangling {
+            lines.push(format!("  {}\t{} rows", d.table, d.rows));
+        }
+    }
+    let strict = if check.strict_integrity { "有効" } else { "無効" };
+    lines.push(i18n::fill(i18n::tr("参照整合性の強制: {}"), &[&i18n::tr(strict)]));
+    lines.join("\n")
+}
+
+pub fn format_db_check_json(check: &DbCheck) -> String {
+    let mut value = serde_json::to_value(check).unwrap_or_default();
+    if let Some(object) = value.as_object_mut() {
+        object.insert("healthy".to_string(), check.healthy().into());
+    }
+    to_json(&value).unwrap_or_else(|_| "{}".to_string())
+}
+
+pub fn format_backups_text(backups: &[BackupSummary]) -> String {
+    if backups.is_empty() {
+        return i18n::tr("バックアップはありません").to_string();
+    }
+    let now = Utc::now();
+    backups
+        .iter()
+        .map(|b| {
+            let contents = match (&b.error, b.threads, b.messages) {
+                (None, Some(threads), Some(messages)) => {
+                    i18n::fill(i18n::tr("thread {} 件・message {} 件"), &[&threads, &messages])
+                }
+                (error, ..) => i18n::fill(i18n::tr("読み込めません: {}"), &[&error.as_deref().unwrap_or_default()]),
+            };
+            format!(
+                "{}\t{}\t{}\t{}",
+                b.file.name,
+                format_bytes(b.file.bytes),
+                relative_time(&b.file.created_at, &now),
+                contents
+            )
         })
         .collect::<Vec<_>>()
         .join("\n")
 }
 
+pub fn format_backups_json(backups: &[BackupSummary]) -> String {
+    to_json(backups).unwrap_or_else(|_| "[]".to_string())
+}
+
+pub fn format_backup_verification_text(verification: &BackupVerification) -> String {
+    let summary = &verification.summary;
+    if !verification.healthy() {
+        let mut lines = vec![i18n::fill(
+            i18n::tr("{}: 異常（{} 件）"),
+            &[&summary.file.name, &verification.problems.len()],
+        )];
+        lines.extend(verification.problems.iter().map(|p| format!("  {}", p)));
+        return lines.join("\n");
+    }
+    i18n::fill(
+        i18n::tr("{}: 正常（スキーマ v{}、thread {} 件・message {} 件）"),
+        &[
+            &summary.file.name,
+            &summary.db_schema_version.unwrap_or_default(),
+            &summary.threads.unwrap_or_default(),
+            &summary.messages.unwrap_or_default(),
+        ],
+    )
+}
+
+pub fn format_backup_verification_json(verification: &BackupVerification) -> String {
+    let mut value = serde_json::to_value(verification).unwrap_or_default();
+    if let Some(object) = value.as_object_mut() {
+        object.insert("healthy".to_string(), verification.healthy().into());
+    }
+    to_json(&value).unwrap_or_else(|_| "{}".to_string())
+}
+
+pub fn format_merge_text(report: &MergeReport) -> String {
+    let mut lines = vec![
+        i18n::fill(
+            i18n::tr("thread: {} 件追加、{} 件更新"),
+            &[&report.threads_added, &report.threads_updated],
+        ),
+        i18n::fill(
+            i18n::tr("message: {} 件追加、{} 件更新"),
+            &[&report.messages_added, &report.messages_updated],
+        ),
+        i18n::fill(i18n::tr("リンク: {} 件追加"), &[&report.links_added]),
+        i18n::fill(i18n::tr("内容が異なりこちらを残したもの: {} 件"), &[&report.conflicts_kept]),
+    ];
+    if report.names_dropped > 0 {
+        lines.push(i18n::fill(
+            i18n::tr("名前が使用済みのため名前なしで取り込んだ thread: {} 件"),
+            &[&report.names_dropped],
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn format_merge_json(report: &MergeReport) -> String {
+    to_json(report).unwrap_or_else(|_| "{}".to_string())
+}
+
+pub fn format_sync_text(report: &SyncReport) -> String {
+    if report.up_to_date {
+        return i18n::fill(i18n::tr("{}: {} と同期済みです"), &[&report.direction, &report.remote]);
+    }
+    let merged = &report.merged;
+    let mut lines = vec![
+        format!("{}: {}", report.direction, report.remote),
+        i18n::fill(
+            i18n::tr("thread: {} 件追加、{} 件更新"),
+            &[&merged.threads_added, &merged.threads_updated],
+        ),
+        i18n::fill(
+            i18n::tr("message: {} 件追加、{} 件更新"),
+            &[&merged.messages_added, &merged.messages_updated],
+        ),
+        i18n::fill(i18n::tr("リンク: {} 件追加"), &[&merged.links_added]),
+    ];
+    if merged.conflicts_kept > 0 {
+        lines.push(i18n::fill(
+            i18n::tr("内容が異なり受け取り側が新しいか同時刻のため残したもの: {} 件"),
+            &[&merged.conflicts_kept],
+        ));
+    }
+    if merged.names_dropped > 0 {
+        lines.push(i18n::fill(
+            i18n::tr("名前が使用済みのため名前なしで取り込んだ thread: {} 件"),
+            &[&merged.names_dropped],
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn format_sync_json(report: &SyncReport) -> String {
+    to_json(report).unwrap_or_else(|_| "{}".to_string())
+}
+
+pub fn format_sync_status_text(states: &[SyncState]) -> String {
+    if states.is_empty() {
+        return i18n::tr("同期した共有先はありません").to_string();
+    }
+    let mut lines = Vec::new();
+    for (i, state) in states.iter().enumerate() {
+        if i == 0 || states[i - 1].remote != state.remote {
+            lines.push(state.remote.clone());
+        }
+        lines.push(i18n::fill(
+            i18n::tr("  {}: {}（thread {} 件・message {} 件）"),
+            &[
+                &state.direction,
+                &state.synced_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+                &state.threads,
+                &state.messages,
+            ],
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn format_sync_status_json(states: &[SyncState]) -> String {
+    to_json(&states).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// One line per entry: time, operation, actor, short target IDs and the
+/// detail as `key=value` pairs.
+pub fn format_audit_text(entries: &[AuditEntry]) -> String {
+    if entries.is_empty() {
+        return i18n::tr("記録された操作はありません").to_string();
+    }
+    entries
+        .iter()
+        .map(|entry| {
+            let targets: Vec<&str> = entry.targets.iter().map(|t| &t[..8.min(t.len())]).collect();
+            let detail = match &entry.detail {
+                Some(serde_json::Value::Object(fields)) => fields
+                    .iter()
+                    .map(|(key, value)| match value {
+                        serde_json::Value::String(s) => format!("{}={}", key, s),
+                        other => format!("{}={}", key, other),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                Some(other) => other.to_string(),
+                None => String::new(),
+            };
+            format!(
+                "{}\t{}\t{}\t{}\t{}",
+                display_time(&entry.created_at),
+                entry.operation,
+                entry.actor.as_deref().unwrap_or("-"),
+                if targets.is_empty() { "-".to_string() } else { targets.join(",") },
+                detail
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn format_audit_json(entries: &[AuditEntry]) -> String {
+    to_json(&entries).unwrap_or_else(|_| "[]".to_string())
+}
+
+pub fn is_truncated(content: &str) -> bool {
+    content.chars().count() > truncate_len()
+}
+
+/// Messages as Markdown: a heading per message, the content as a quote block.
+pub fn format_messages_markdown(messages: &[Message], full: bool) -> String {
+    let mut out = String::new();
+    for (i, msg) in messages.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let local_time = msg.created_at.with_timezone(&Local);
+        out.push_str(&format!(
+            "### {} ({}) — {}\n\n",
+            msg.sender.as_deref().unwrap_or("-"),
+            msg.role,
+            local_time.format("%Y-%m-%d %H:%M:%S"),
+        ));
+        let id = if full { &msg.id[..] } else { &msg.id[..8.min(msg.id.len())] };
+        let mut info = format!("`{}`", id);
+        if let Some(ref source) = msg.source {
+            info.push_str(&format!(" · {}", source));
+        }
+        out.push_str(&info);
+        out.push_str("\n\n");
+        let content = if full {
+            msg.content.clone()
+        } else {
+            truncate_content(&msg.content, truncate_len())
+        };
+        for line in content.trim_end().lines() {
+            if line.is_empty() {
+                out.push_str(">\n");
+            } else {
+                out.push_str(&format!("> {}\n", line));
+            }
+        }
+    }
+    out
+}
+
 pub fn any_content_truncated(messages: &[Message]) -> bool {
-    messages.iter().any(|m| m.content.chars().count() > TRUNCATE_LEN)
+    messages.iter().any(|m| is_truncated(&m.content))
 }
 
 pub fn format_messages_json(messages: &[Message]) -> String {
-    serde_json::to_string_pretty(messages).unwrap_or_else(|_| "[]".to_string())
+    to_json(messages).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// One compact JSON object on a single line, for `--format jsonl`.
+pub fn format_json_line<T: serde::Serialize>(item: &T) -> String {
+    serde_json::to_string(item).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// `--format jsonl`: one compact JSON object per line.
+pub fn format_jsonl<T: serde::Serialize>(items: &[T]) -> String {
+    items.iter().map(|item| format_json_line(item) + "\n").collect()
+}
+
+/// `--format yaml`: the same data as the JSON output, as a YAML document.
+pub fn format_yaml<T: serde::Serialize>(item: &T) -> String {
+    let value = serde_json::to_value(item).unwrap_or(serde_json::Value::Null);
+    match value {
+        serde_json::Value::Array(ref items) if !items.is_empty() => yaml_block(&value, 0),
+        serde_json::Value::Object(ref map) if !map.is_empty() => yaml_block(&value, 0),
+        _ => format!("{}\n", yaml_scalar(&value)),
+    }
+}
+
+/// A non-empty sequence or mapping, every line indented by `indent`.
+fn yaml_block(value: &serde_json::Value, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    let mut out = String::new();
+    match value {
+        serde_json::Value::Array(items) => {
+            for item in items {
+                out.push_str(&pad);
+                out.push('-');
+                // A nested collection starts on the dash's line.
+                match yaml_value(item, indent + 2) {
+                    nested if nested.starts_with('\n') => {
+                        out.push(' ');
+                        out.push_str(&nested[indent + 3..]);
+                    }
+                    inline => out.push_str(&inline),
+                }
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for (key, item) in map {
+                out.push_str(&pad);
+                out.push_str(&yaml_string(key));
+                out.push(':');
+                out.push_str(&yaml_value(item, indent + 2));
+            }
+        }
+        _ => out.push_str(&yaml_scalar(value)),
+    }
+    out
+}
+
+/// What follows `key:` or `-`: ` scalar\n`, a literal block for multi-line
+/// text, or a newline and the nested collection.
+fn yaml_value(value: &serde_json::Value, indent: usize) -> String {
+    match value {
+        serde_json::Value::Array(items) if !items.is_empty() => format!("\n{}", yaml_block(value, indent)),
+        serde_json::Value::Object(map) if !map.is_empty() => format!("\n{}", yaml_block(value, indent)),
+        serde_json::Value::String(s) if s.contains('\n') && yaml_literal_safe(s) => {
+            let pad = " ".repeat(indent);
+            let mut out = " |-\n".to_string();
+            for line in s.split('\n') {
+                if !line.is_empty() {
+                    out.push_str(&pad);
+                    out.push_str(line);
+                }
+                out.push('\n');
+            }
+            out
+        }
+        _ => format!(" {}\n", yaml_scalar(value)),
+    }
+}
+
+fn yaml_scalar(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => yaml_string(s),
+        serde_json::Value::Array(_) => "[]".to_string(),
+        serde_json::Value::Object(_) => "{}".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Plain when YAML would read it back as the same string, otherwise
+/// double-quoted (JSON string syntax is valid YAML).
+fn yaml_string(s: &str) -> String {
+    let reserved = matches!(
+        s.to_ascii_lowercase().as_str(),
+        "" | "~" | "null" | "true" | "false" | "yes" | "no" | "on" | "off" | "y" | "n" | ".nan" | ".inf" | "-.inf"
+    );
+    // YAML 1.1 also reads hex, sexagesimal and timestamp forms as non-strings.
+    let numeric_like = s.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c))
+        && s.chars().all(|c| c.is_ascii_hexdigit() || "xXoO_:.+-TZtz ".contains(c));
+    let plain = !reserved
+        && !numeric_like
+        && s.parse::<f64>().is_err()
+        && !s.starts_with(|c: char| c.is_whitespace() || "-?:,[]{}#&*!|>'\"%@`".contains(c))
+        && !s.ends_with(|c: char| c.is_whitespace() || c == ':')
+        && !s.contains(": ")
+        && !s.contains(" #")
+        && !s.chars().any(|c| c.is_control());
+    if plain {
+        s.to_string()
+    } else {
+        serde_json::to_string(s).unwrap_or_default()
+    }
+}
+
+/// Literal blocks cannot express leading spaces on the first line, trailing
+/// newlines or control characters other than tabs.
+fn yaml_literal_safe(s: &str) -> bool {
+    !s.starts_with([' ', '\n'])
+        && !s.ends_with('\n')
+        && !s.chars().any(|c| c.is_control() && c != '\n' && c != '\t')
+}
+
+/// Search results: each message with its `score` (null for substring matches)
+/// and the character offsets of its `matches`.
+pub fn format_search_hits_json(hits: &[SearchHit]) -> String {
+    to_json(hits).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// JSON envelope for paginated reads:
+/// `{"order": "asc"|"desc", "messages": [...], "next_cursor": ...}`.
+pub fn format_messages_page_json<T: serde::Serialize>(
+    messages: &[T],
+    descending: bool,
+    next_cursor: Option<&str>,
+) -> String {
+    to_json(&messages_page_value(messages, descending, next_cursor))
+        .unwrap_or_else(|_| "{}".to_string())
+}
+
+/// The paginated-read envelope of `format_messages_page_json` as YAML.
+pub fn format_messages_page_yaml<T: serde::Serialize>(
+    messages: &[T],
+    descending: bool,
+    next_cursor: Option<&str>,
+) -> String {
+    format_yaml(&messages_page_value(messages, descending, next_cursor))
+}
+
+fn messages_page_value<T: serde::Serialize>(
+    messages: &[T],
+    descending: bool,
+    next_cursor: Option<&str>,
+) -> serde_json::Value {
+    serde_json::json!({
+        "order": if descending { "desc" } else { "asc" },
+        "messages": messages,
+        "next_cursor": next_cursor,
+    })
+}
+
+pub fn format_message_with_links_json(msg: &Message, links: &[MessageLink]) -> String {
+    let mut value = serde_json::to_value(msg).unwrap_or_default();
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert(
+            "links".to_string(),
+            serde_json::to_value(links).unwrap_or_default(),
+        );
+    }
+    to_json(&value).unwrap_or_else(|_| "{}".to_string())
+}
+
+fn format_links(msg_id: &str, links: &[MessageLink]) -> String {
+    links
+        .iter()
+        .map(|l| {
+            if l.from_id == msg_id {
+                format!("\n  {} → {}", l.relation, l.to_id)
+            } else {
+                format!("\n  {} ← {}", l.relation, l.from_id)
+            }
+        })
+        .collect()
+}
+
+pub fn format_message_detail(msg: &Message, links: &[MessageLink]) -> String {
+    let metadata = msg
+        .metadata
+        .as_ref()
+        .map(|m| m.to_string())
+        .unwrap_or_else(|| "-".to_string());
+    let created = display_time(&msg.created_at);
+    let updated = display_time(&msg.updated_at);
+    let links = if links.is_empty() {
+        String::new()
+    } else {
+        format!("\nlinks:{}", format_links(&msg.id, links))
+    };
+    format!(
+        "id:         {}\nthread:     {}\nsession:    {}\nsender:     {}\nrole:       {}\nsource:     {}\nparent:     {}\ncreated_at: {}\nupdated_at: {}\nmetadata:   {}{}\n\n{}",
+        msg.id,
+        msg.thread_id,
+        msg.session_id.as_deref().unwrap_or("-"),
+        msg.sender.as_deref().unwrap_or("-"),
+        msg.role,
+        msg.source.as_deref().unwrap_or("-"),
+        msg.parent_id.as_deref().unwrap_or("-"),
+        created,
+        updated,
+        metadata,
+        links,
+        msg.content,
+    )
+}
+
+pub fn format_count_text(counts: &[(Role, usize)]) -> String {
+    counts.iter().map(|(_, n)| n).sum::<usize>().to_string()
+}
+
+pub fn format_count_json(counts: &[(Role, usize)]) -> String {
+    let total: usize = counts.iter().map(|(_, n)| n).sum();
+    let by_role: serde_json::Map<String, serde_json::Value> = counts
+        .iter()
+        .map(|(role, n)| (role.to_string(), serde_json::Value::from(*n)))
+        .collect();
+    let value = serde_json::json!({
+        "total": total,
+        "by_role": by_role,
+    });
+    to_json(&value).unwrap_or_else(|_| "{}".to_string())
+}
+
+pub fn format_transcript_json(thread: &Thread, messages: &[Message]) -> String {
+    let value = serde_json::json!({
+        "thread": thread,
+        "messages": messages,
+    });
+    to_json(&value).unwrap_or_else(|_| "{}".to_string())
+}
+
+pub fn format_thread_bundle_json(bundle: &ThreadBundle) -> String {
+    to_json(bundle).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Title and summary list opening a Markdown conversation transcript of
+/// `thread`, followed by one `format_transcript_markdown_message` each.
+pub fn format_transcript_markdown_header(thread: &Thread, messages: usize) -> String {
+    let mut out = format!("# {}\n\n", thread.title);
+    out.push_str(&format!("- thread: `{}`\n", thread.id));
+    if let Some(ref url) = thread.source_url {
+        out.push_str(&format!("- source: {}\n", url));
+    }
+    out.push_str(&format!("- messages: {}\n", messages));
+    out
+}
+
+/// One message section of a Markdown conversation transcript, without
+/// the final newline.
+pub fn format_transcript_markdown_message(msg: &Message) -> String {
+    let local_time = msg.created_at.with_timezone(&Local);
+    let mut out = format!(
+        "\n---\n\n### {} ({}) — {}\n\n",
+        msg.sender.as_deref().unwrap_or("-"),
+        msg.role,
+        local_time.format("%Y-%m-%d %H:%M:%S"),
+    );
+    if let Some(ref meta) = msg.metadata {
+        out.push_str(&format!("> metadata: `{}`\n\n", meta));
+    }
+    out.push_str(msg.content.trim_end());
+    out
+}
+
+pub fn format_draft_text(draft: &Draft, full: bool) -> String {
+    let content = if full {
+        draft.content.clone()
+    } else {
+        truncate_content(&draft.content, truncate_len())
+    };
+    format!(
+        "{}\t{}\t{}\t{}\t{}",
+        &draft.id[..8.min(draft.id.len())],
+        &draft.thread_id[..8.min(draft.thread_id.len())],
+        draft.sender.as_deref().unwrap_or("-"),
+        display_time(&draft.updated_at),
+        content,
+    )
+}
+
+pub fn format_drafts_text(drafts: &[Draft], full: bool) -> String {
+    drafts
+        .iter()
+        .map(|d| format_draft_text(d, full))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn format_drafts_json(drafts: &[Draft]) -> String {
+    to_json(drafts).unwrap_or_else(|_| "[]".to_string())
 }
 
 pub fn format_thread_text(thread: &Thread, full: bool) -> String {
@@ -112,15 +1108,22 @@ pub fn format_thread_text(thread: &Thread, full: bool) -> String {
         Some(p) => p.to_string(),
         None => "-".to_string(),
     };
-    let local_time = thread.updated_at.with_timezone(&Local);
+    let tags = if thread.tags.is_empty() {
+        String::new()
+    } else {
+        format!("\t[{}]", thread.tags.join(", "))
+    };
+    let marker = if thread.pinned { "* " } else { "" };
     format!(
-        "{}\t{}\t{}\t{}\t{}\t{}",
+        "{}{}\t{}\t{}\t{}\t{}\t{}{}",
+        marker,
         id,
         thread.status,
         phase_str,
         name,
         thread.title,
-        local_time.format("%Y-%m-%d %H:%M:%S"),
+        display_time(&thread.updated_at),
+        tags,
     )
 }
 
@@ -132,14 +1135,544 @@ pub fn format_threads_text(threads: &[Thread], full: bool) -> String {
         .join("\n")
 }
 
+/// Spreadsheet-friendly output with a header row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimited {
+    /// Comma-separated; fields with commas, quotes or line breaks are quoted (RFC 4180).
+    Csv,
+    /// Tab-separated; tabs, line breaks and backslashes are escaped as `\t`, `\n`, `\\`.
+    Tsv,
+}
+
+impl Delimited {
+    /// `"tsv"` selects TSV, any other format name CSV.
+    pub fn from_format(format: &str) -> Self {
+        if format == "tsv" {
+            Self::Tsv
+        } else {
+            Self::Csv
+        }
+    }
+
+    fn field(self, value: &str) -> String {
+        match self {
+            Self::Csv if value.contains([',', '"', '\n', '\r']) => {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            }
+            Self::Csv => value.to_string(),
+            Self::Tsv => value
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r"),
+        }
+    }
+
+    fn row<S: AsRef<str>>(self, fields: &[S]) -> String {
+        let sep = if self == Self::Csv { "," } else { "\t" };
+        let mut line = fields
+            .iter()
+            .map(|f| self.field(f.as_ref()))
+            .collect::<Vec<_>>()
+            .join(sep);
+        line.push_str(if self == Self::Csv { "\r\n" } else { "\n" });
+        line
+    }
+}
+
+const MESSAGE_COLUMNS: [&str; 9] =
+    ["id", "thread_id", "created_at", "sender", "role", "type", "source", "content", "metadata"];
+
+fn message_fields(msg: &Message) -> Vec<String> {
+    let msg_type = msg
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("msg_type"))
+        .and_then(|t| t.as_str())
+        .unwrap_or_default();
+    vec![
+        msg.id.clone(),
+        msg.thread_id.clone(),
+        msg.created_at.to_rfc3339(),
+        msg.sender.clone().unwrap_or_default(),
+        msg.role.to_string(),
+        msg_type.to_string(),
+        msg.source.clone().unwrap_or_default(),
+        msg.content.clone(),
+        msg.metadata.as_ref().map(|m| m.to_string()).unwrap_or_default(),
+    ]
+}
+
+/// A parsed `--template`.
+pub enum TemplatePart {
+    Text(String),
+    /// A `{{field}}` placeholder; dotted paths reach into objects and arrays
+    /// (`metadata.msg_type`, `tags.0`).
+    Field(String),
+}
+
+/// Splits a `--template` into text and `{{field}}` placeholders. In the
+/// text, `\n`, `\t` and `\\` stand for a newline, a tab and a backslash.
+pub fn parse_template(template: &str) -> Result<Vec<TemplatePart>, String> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while !rest.is_empty() {
+        let (text, field) = match rest.find("{{") {
+            Some(open) => {
+                let close = rest[open..]
+                    .find("}}")
+                    .ok_or_else(|| format!("unclosed placeholder: {}", &rest[open..]))?;
+                let field = rest[open + 2..open + close].trim();
+                if field.is_empty() {
+                    return Err("empty placeholder {{}}".to_string());
+                }
+                let text = &rest[..open];
+                rest = &rest[open + close + 2..];
+                (text, Some(field.to_string()))
+            }
+            None => (std::mem::take(&mut rest), None),
+        };
+        if !text.is_empty() {
+            parts.push(TemplatePart::Text(unescape_template_text(text)));
+        }
+        parts.extend(field.map(TemplatePart::Field));
+    }
+    Ok(parts)
+}
+
+fn unescape_template_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Each record rendered through `template`, one per line. Placeholders
+/// take the record's JSON values: strings as is, missing values and null
+/// empty, anything else as JSON.
+pub fn format_template<T: serde::Serialize>(template: &[TemplatePart], records: &[T]) -> String {
+    records
+        .iter()
+        .map(|record| {
+            let value = serde_json::to_value(record).unwrap_or_default();
+            let mut line: String = template
+                .iter()
+                .map(|part| match part {
+                    TemplatePart::Text(text) => text.clone(),
+                    TemplatePart::Field(path) => path
+                        .split('.')
+                        .try_fold(&value, |v, key| match v {
+                            serde_json::Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+                            _ => v.get(key),
+                        })
+                        .map(field_text)
+                        .unwrap_or_default(),
+                })
+                .collect();
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+/// JSON keys of a thread, for `--template`.
+pub const THREAD_FIELDS: [&str; 18] = [
+    "id", "name", "title", "source_url", "status", "phase", "summary", "tags", "pinned", "metadata",
+    "default_sender", "default_role", "locked", "lock_allow", "retention_days", "retention_keep_last",
+    "created_at", "updated_at",
+];
+
+/// JSON keys of a message that `--fields` can select.
+pub const MESSAGE_FIELDS: [&str; 11] = [
+    "id", "thread_id", "session_id", "sender", "role", "content", "metadata", "parent_id", "source",
+    "created_at", "updated_at",
+];
+/// Keys a search hit has besides those of its message.
+pub const SEARCH_HIT_FIELDS: [&str; 3] = ["score", "matches", "fuzzy"];
+
+/// `--fields`: each record's JSON object reduced to the given keys. Keys a
+/// record lacks come out as null.
+pub fn select_fields<T: serde::Serialize>(records: &[T], fields: &[String]) -> Vec<serde_json::Value> {
+    records
+        .iter()
+        .map(|record| {
+            let value = serde_json::to_value(record).unwrap_or_default();
+            let selected = fields
+                .iter()
+                .map(|f| (f.clone(), value.get(f).cloned().unwrap_or_default()))
+                .collect();
+            serde_json::Value::Object(selected)
+        })
+        .collect()
+}
+
+/// A selected field as plain text: strings as is, null empty, anything
+/// else as JSON.
+fn field_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// `--fields` as text: the selected values of each record separated by
+/// tabs, content truncated unless `full`.
+pub fn format_fields_text(records: &[serde_json::Value], fields: &[String], full: bool) -> String {
+    records
+        .iter()
+        .map(|record| {
+            fields
+                .iter()
+                .map(|f| {
+                    let text = field_text(&record[f.as_str()]);
+                    if f == "content" && !full {
+                        truncate_content(&text, truncate_len())
+                    } else {
+                        text
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\t")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `--fields` as CSV or TSV with the selected fields as columns.
+pub fn format_fields_delimited(records: &[serde_json::Value], fields: &[String], kind: Delimited) -> String {
+    let mut out = kind.row(fields);
+    for record in records {
+        let row: Vec<String> = fields.iter().map(|f| field_text(&record[f.as_str()])).collect();
+        out.push_str(&kind.row(&row));
+    }
+    out
+}
+
+/// Messages as CSV or TSV, full content, timestamps in UTC.
+pub fn format_messages_delimited(messages: &[Message], kind: Delimited) -> String {
+    let mut out = kind.row(&MESSAGE_COLUMNS);
+    for msg in messages {
+        out.push_str(&kind.row(&message_fields(msg)));
+    }
+    out
+}
+
+/// Like `format_messages_delimited`, with a leading `score` column.
+pub fn format_search_hits_delimited(hits: &[SearchHit], kind: Delimited) -> String {
+    let header: Vec<&str> = std::iter::once("score").chain(MESSAGE_COLUMNS).collect();
+    let mut out = kind.row(&header);
+    for hit in hits {
+        let mut fields = vec![hit.score.map(|s| s.to_string()).unwrap_or_default()];
+        fields.extend(message_fields(&hit.message));
+        out.push_str(&kind.row(&fields));
+    }
+    out
+}
+
+/// Threads as CSV or TSV; tags are joined with `;`.
+pub fn format_threads_delimited(threads: &[Thread], kind: Delimited) -> String {
+    let mut out = kind.row(&[
+        "id", "name", "title", "status", "phase", "pinned", "tags", "summary", "created_at", "updated_at",
+    ]);
+    for thread in threads {
+        out.push_str(&kind.row(&[
+            thread.id.clone(),
+            thread.name.clone().unwrap_or_default(),
+            thread.title.clone(),
+            thread.status.to_string(),
+            thread.phase.as_ref().map(|p| p.to_string()).unwrap_or_default(),
+            thread.pinned.to_string(),
+            thread.tags.join(";"),
+            thread.summary.clone().unwrap_or_default(),
+            thread.created_at.to_rfc3339(),
+            thread.updated_at.to_rfc3339(),
+        ]));
+    }
+    out
+}
+
+/// Escapes a value for a Markdown table cell.
+fn markdown_cell(s: &str) -> String {
+    s.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Threads as a Markdown table.
+pub fn format_threads_markdown(threads: &[Thread], full: bool) -> String {
+    let mut out = String::from("| ID | status | phase | name | title | updated | tags |\n");
+    out.push_str("|---|---|---|---|---|---|---|\n");
+    for thread in threads {
+        let id = if full { &thread.id[..] } else { &thread.id[..8.min(thread.id.len())] };
+        let pin = if thread.pinned { " (pinned)" } else { "" };
+        out.push_str(&format!(
+            "| `{}`{} | {} | {} | {} | {} | {} | {} |\n",
+            id,
+            pin,
+            thread.status,
+            thread.phase.as_ref().map(|p| p.to_string()).unwrap_or_else(|| "-".to_string()),
+            markdown_cell(thread.name.as_deref().unwrap_or("-")),
+            markdown_cell(&thread.title),
+            thread.updated_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            markdown_cell(&thread.tags.join(", ")),
+        ));
+    }
+    out
+}
+
+pub fn format_thread_detail(detail: &ThreadDetail) -> String {
+    let thread = &detail.thread;
+    let mut lines = vec![
+        format!("id:         {}", thread.id),
+        format!("title:      {}", thread.title),
+        format!("name:       {}", thread.name.as_deref().unwrap_or("-")),
+        format!("status:     {}", thread.status),
+        format!("pinned:     {}", if thread.pinned { "yes" } else { "no" }),
+        format!(
+            "locked:     {}",
+            match (thread.locked, thread.lock_allow.is_empty()) {
+                (false, _) => "no".to_string(),
+                (true, true) => "yes".to_string(),
+                (true, false) => format!("yes (allow: {})", thread.lock_allow.join(", ")),
+            }
+        ),
+        format!(
+            "phase:      {}",
+            thread.phase.map(|p| p.to_string()).unwrap_or_else(|| "-".to_string())
+        ),
+        format!("source_url: {}", thread.source_url.as_deref().unwrap_or("-")),
+        format!(
+            "tags:       {}",
+            if thread.tags.is_empty() { "-".to_string() } else { thread.tags.join(", ") }
+        ),
+        format!(
+            "retention:  {}",
+            match (thread.retention_days, thread.retention_keep_last) {
+                (Some(d), _) => format!("{} days", d),
+                (None, Some(k)) => format!("last {} messages", k),
+                (None, None) => "-".to_string(),
+            }
+        ),
+        format!(
+            "defaults:   sender={} role={}",
+            thread.default_sender.as_deref().unwrap_or("-"),
+            thread.default_role.as_ref().map(|r| r.to_string()).unwrap_or_else(|| "-".to_string())
+        ),
+        format!(
+            "metadata:   {}",
+            thread.metadata.as_ref().map(|m| m.to_string()).unwrap_or_else(|| "-".to_string())
+        ),
+        format!(
+            "created_at: {}",
+            display_time(&thread.created_at)
+        ),
+        format!(
+            "updated_at: {}",
+            display_time(&thread.updated_at)
+        ),
+        format!("messages:   {}", detail.message_count),
+        format!(
+            "participants: {}",
+            if detail.participants.is_empty() {
+                "-".to_string()
+            } else {
+                detail.participants.join(", ")
+            }
+        ),
+        format!(
+            "last_activity: {}",
+            detail
+                .last_activity
+                .map(|t| display_time(&t))
+                .unwrap_or_else(|| "-".to_string())
+        ),
+    ];
+    if let Some(summary) = &thread.summary {
+        lines.push(String::new());
+        lines.push(summary.clone());
+    }
+    lines.join("\n")
+}
+
+pub fn format_thread_detail_json(detail: &ThreadDetail) -> String {
+    to_json(detail).unwrap_or_else(|_| "{}".to_string())
+}
+
 pub fn format_threads_json(threads: &[Thread]) -> String {
-    serde_json::to_string_pretty(threads).unwrap_or_else(|_| "[]".to_string())
+    to_json(threads).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// A failure as one line of JSON for stderr.
+pub fn format_error_json(error_code: &str, message: &str, exit_code: i32) -> String {
+    serde_json::json!({
+        "error_code": error_code,
+        "message": message,
+        "exit_code": exit_code,
+    })
+    .to_string()
+}
+
+/// Result of one `batch` line: what the command printed, without the final
+/// newline.
+pub fn format_batch_result(line: usize, output: &str) -> String {
+    serde_json::json!({
+        "line": line,
+        "ok": true,
+        "output": output.strip_suffix('\n').unwrap_or(output),
+    })
+    .to_string()
+}
+
+/// A failed `batch` line, with the fields of `format_error_json`.
+pub fn format_batch_error(line: usize, error_code: &str, message: &str, exit_code: i32) -> String {
+    serde_json::json!({
+        "line": line,
+        "ok": false,
+        "error_code": error_code,
+        "message": message,
+        "exit_code": exit_code,
+    })
+    .to_string()
+}
+
+pub fn format_subscriber_notice(thread_id: &str, subscribers: &[&str]) -> String {
+    i18n::fill(
+        i18n::tr("thread {} の購読者: {}"),
+        &[&&thread_id[..8.min(thread_id.len())], &subscribers.join(", ")],
+    )
 }
 
 pub fn format_mention_notification(sender: &str, count: usize) -> String {
-    format!("@{}: {}件のメンションがあります", sender, count)
+    i18n::fill(i18n::tr("@{}: {}件の未読メンションがあります"), &[&sender, &count])
 }
 
 pub fn format_message_posted(msg: &Message) -> String {
     msg.id.to_string()
 }
+
+pub fn format_token_stats_text(stats: &ThreadTokenStats, by_message: bool) -> String {
+    let mut lines = vec![format!(
+        "total\t{} messages\t{} bytes\t~{} tokens",
+        stats.messages, stats.bytes, stats.tokens
+    )];
+    for s in &stats.by_sender {
+        lines.push(format!(
+            "{}\t{} messages\t{} bytes\t~{} tokens",
+            s.sender.as_deref().unwrap_or("-"),
+            s.messages,
+            s.bytes,
+            s.tokens
+        ));
+    }
+    if by_message {
+        lines.push(String::new());
+        for m in &stats.by_message {
+            lines.push(format!(
+                "{}\t{}\t{}\t{} bytes\t~{} tokens",
+                &m.id[..8.min(m.id.len())],
+                m.role,
+                m.sender.as_deref().unwrap_or("-"),
+                m.bytes,
+                m.tokens
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+pub fn format_token_stats_json(stats: &ThreadTokenStats, by_message: bool) -> String {
+    let mut value = serde_json::to_value(stats).unwrap_or_default();
+    if !by_message {
+        if let Some(obj) = value.as_object_mut() {
+            obj.remove("by_message");
+        }
+    }
+    to_json(&value).unwrap_or_else(|_| "{}".to_string())
+}
+
+pub fn format_groups_text(groups: &[Group]) -> String {
+    groups
+        .iter()
+        .map(|g| format!("{}\t{}", g.name, g.members.join(", ")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn format_groups_json(groups: &[Group]) -> String {
+    to_json(groups).unwrap_or_else(|_| "[]".to_string())
+}
+
+pub fn format_participants_text(participants: &[Participant]) -> String {
+    participants
+        .iter()
+        .map(|p| {
+            format!(
+                "{}\t{}\t{}\t{}",
+                p.sender,
+                p.message_count,
+                display_time(&p.first_seen),
+                display_time(&p.last_seen),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn format_participants_json(participants: &[Participant]) -> String {
+    to_json(participants).unwrap_or_else(|_| "[]".to_string())
+}
+
+fn format_interval(secs: i64) -> String {
+    if secs >= 86_400 {
+        format!("{:.1}日", secs as f64 / 86_400.0)
+    } else if secs >= 3_600 {
+        format!("{:.1}時間", secs as f64 / 3_600.0)
+    } else if secs >= 60 {
+        format!("{:.1}分", secs as f64 / 60.0)
+    } else {
+        format!("{}秒", secs)
+    }
+}
+
+pub fn format_thread_stats_text(stats: &ThreadStats) -> String {
+    let fmt_time = |t: Option<DateTime<Utc>>| {
+        t.map(|t| display_time(&t)).unwrap_or_else(|| "-".to_string())
+    };
+    let mut lines = vec![
+        format!("thread:     {} {}", &stats.thread_id[..8.min(stats.thread_id.len())], stats.title),
+        format!("messages:   {}", stats.message_count),
+        format!("first_post: {}", fmt_time(stats.first_post)),
+        format!("last_post:  {}", fmt_time(stats.last_post)),
+        format!(
+            "interval:   {}",
+            stats
+                .average_interval_secs
+                .map(format_interval)
+                .unwrap_or_else(|| "-".to_string())
+        ),
+    ];
+    lines.push(String::new());
+    lines.push("by sender:".to_string());
+    lines.extend(stats.by_sender.iter().map(|e| format!("  {}\t{}", e.key, e.count)));
+    lines.push("by role:".to_string());
+    lines.extend(stats.by_role.iter().map(|e| format!("  {}\t{}", e.key, e.count)));
+    lines.join("\n")
+}
+
+pub fn format_thread_stats_json(stats: &ThreadStats) -> String {
+    to_json(stats).unwrap_or_else(|_| "{}".to_string())
+}
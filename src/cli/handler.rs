@@ -3,57 +3,381 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use anyhow::{bail, Context};
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, Utc};
 
 use crate::cli::args::*;
+use crate::cli::batch;
 use crate::cli::formatter;
-use crate::domain::entity::{Role, ThreadPhase, ThreadStatus};
-use crate::domain::repository::{MessageRepository, ThreadRepository};
+use crate::cli::i18n;
+use crate::cli::output;
+use crate::cli::schema;
+use crate::domain::entity::{
+    AuditOperation, Group, LinkRelation, Message, Role, SyncDirection, ThreadPhase, ThreadStatus,
+};
+use crate::domain::error::DomainError;
+use crate::domain::repository::{
+    AuditFilter, AuditRepository, DraftRepository, EmbeddingProvider, EmbeddingRepository, GroupRepository, MessageCursor,
+    MessageFilter, MessageRepository, Page, QueryMode, SearchQuery, SearchScope, SyncStateRepository,
+    ThreadFilter, ThreadRepository, ThreadSort,
+};
+use crate::infra::archive::{ArchiveReader, ArchiveWriter};
+use crate::infra::backup::{self, PrunePolicy};
+use crate::infra::config;
+use crate::infra::embedding::HttpEmbeddingProvider;
+use crate::infra::github::IssueRef;
+use crate::infra::http;
+use crate::infra::key;
+use crate::infra::logger;
+use crate::infra::sqlite::{BoardCopy, Database, SqliteMessageRepository, SqliteThreadRepository};
+use crate::infra::sync::Remote;
+use crate::usecase::audit::AuditUseCase;
 use crate::usecase::cleanup::CleanupUseCase;
+use crate::usecase::draft::DraftUseCase;
+use crate::usecase::group::GroupUseCase;
 use crate::usecase::hook::HookUseCase;
+use crate::usecase::merge::{MergeReport, MergeUseCase};
 use crate::usecase::message::MessageUseCase;
-use crate::usecase::thread::ThreadUseCase;
+use crate::usecase::semantic::SemanticUseCase;
+use crate::usecase::stats::StatsUseCase;
+use crate::usecase::sync::{SyncReport, SyncUseCase};
+use crate::usecase::thread::{
+    ArchiveManifest, CloneMessages, DocumentFormat, FollowNext, ThreadBundle, ThreadUseCase,
+};
 
 const MAX_CONTENT_SIZE: usize = 1_048_576; // 1MB
 
+/// Set by the global `--quiet` flag.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Informational stderr output (progress, counts, hints), translated by
+/// `--lang` and silenced by `--quiet`. Warnings and prompts use `eprintln!`
+/// directly.
+macro_rules! notice {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            eprintln!("{}", i18n::fill(i18n::tr($fmt), &[$(&$arg as &dyn std::fmt::Display),*]));
+        }
+    };
+}
+
+/// A user-facing message (usually an error) translated by `--lang`, with
+/// `{}` filled like `format!`.
+macro_rules! tr {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        i18n::fill(i18n::tr($fmt), &[$(&$arg as &dyn std::fmt::Display),*])
+    };
+}
+
+/// `print!` / `println!` to the command's output destination (stdout or
+/// `--output`).
+macro_rules! out {
+    ($($arg:tt)*) => {
+        output::print(format_args!($($arg)*))
+    };
+}
+
+macro_rules! outln {
+    () => {
+        output::print(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {
+        output::print(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
 fn read_stdin() -> anyhow::Result<String> {
+    if let Some(input) = batch::take_stdin() {
+        return Ok(input);
+    }
     let mut buf = Vec::new();
     let bytes_read = std::io::stdin()
         .take(MAX_CONTENT_SIZE as u64 + 1)
         .read_to_end(&mut buf)
-        .context("stdin からの読み取りに失敗しました")?;
+        .context(tr!("stdin からの読み取りに失敗しました"))?;
 
     if bytes_read > MAX_CONTENT_SIZE {
-        bail!("入力が 1MB の上限を超えています（{} バイト）", bytes_read);
+        bail!(tr!("入力が 1MB の上限を超えています（{} バイト）", bytes_read));
     }
 
     if buf.contains(&0) {
-        bail!("入力に NUL バイトが含まれています");
+        bail!(tr!("入力に NUL バイトが含まれています"));
     }
 
-    String::from_utf8(buf).context("入力が有効な UTF-8 ではありません")
+    String::from_utf8(buf).context(tr!("入力が有効な UTF-8 ではありません"))
 }
 
 fn validate_content(content: &str) -> anyhow::Result<()> {
     if content.len() > MAX_CONTENT_SIZE {
-        bail!("内容が 1MB の上限を超えています（{} バイト）", content.len());
+        bail!(tr!("内容が 1MB の上限を超えています（{} バイト）", content.len()));
     }
     if content.bytes().any(|b| b == 0) {
-        bail!("内容に NUL バイトが含まれています");
+        bail!(tr!("内容に NUL バイトが含まれています"));
+    }
+    Ok(())
+}
+
+/// Parses the value of a datetime filter flag such as `--after`:
+/// - ISO 8601 (`2024-06-01T12:00:00`, seconds optional; UTC unless an offset is given)
+/// - a date (`2024-06-01`, midnight UTC)
+/// - `now`, `today` or `yesterday` (local midnight)
+/// - a duration ago (`30m`, `2h`, `2d`, `1w`)
+fn parse_datetime_filter(flag: &str, s: &str) -> anyhow::Result<DateTime<Utc>> {
+    let s = s.trim();
+    let local_midnight = |days_ago: i64| {
+        let date = Local::now().date_naive() - Duration::days(days_ago);
+        date.and_hms_opt(0, 0, 0)
+            .and_then(|ndt| ndt.and_local_timezone(Local).earliest())
+            .map(|dt| dt.with_timezone(&Utc))
+    };
+    let parsed = match s {
+        "now" => Some(Utc::now()),
+        "today" => local_midnight(0),
+        "yesterday" => local_midnight(1),
+        _ => DateTime::parse_from_rfc3339(s)
+            .map(|dt| dt.with_timezone(&Utc))
+            .ok()
+            .or_else(|| {
+                ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+                    .iter()
+                    .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+                    .map(|ndt| ndt.and_utc())
+            })
+            .or_else(|| {
+                NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|d| d.and_hms_opt(0, 0, 0))
+                    .map(|ndt| ndt.and_utc())
+            })
+            .or_else(|| {
+                s.starts_with(|c: char| c.is_ascii_digit())
+                    .then(|| parse_duration(s).ok())
+                    .flatten()
+                    .and_then(|d| Utc::now().checked_sub_signed(d))
+            }),
+    };
+    parsed.ok_or_else(|| {
+        anyhow::anyhow!(tr!(
+            "{} の日時形式が不正です: {}（ISO 8601、YYYY-MM-DD、today、yesterday、2d のような相対指定）",
+            flag,
+            s
+        ))
+    })
+}
+
+fn parse_datetime_opt(flag: &str, s: Option<&str>) -> anyhow::Result<Option<DateTime<Utc>>> {
+    s.map(|v| parse_datetime_filter(flag, v)).transpose()
+}
+
+/// Who is recorded in the audit log: the command's sender, otherwise the
+/// configured one.
+fn audit_actor(sender: Option<&str>) -> Option<String> {
+    sender.map(str::to_string).or_else(|| config::get().sender.clone())
+}
+
+/// Tells the poster which subscribers of the thread will see the new message,
+/// and raises a desktop notification for subscriptions that asked for one.
+fn notify_subscribers<T: ThreadRepository, M: MessageRepository>(
+    thread_uc: &ThreadUseCase<T, M>,
+    thread_id: &str,
+    poster: Option<&str>,
+) -> anyhow::Result<()> {
+    let subscribers = thread_uc.subscribers_to_notify(thread_id, poster)?;
+    if subscribers.is_empty() {
+        return Ok(());
+    }
+    let names: Vec<&str> = subscribers.iter().map(|s| s.sender.as_str()).collect();
+    notice!(
+        "{}",
+        formatter::format_subscriber_notice(thread_id, &names)
+    );
+    for sub in subscribers.iter().filter(|s| s.notify) {
+        let message = format!(
+            "@{}: thread {} に新着 message があります",
+            sub.sender,
+            &thread_id[..8.min(thread_id.len())]
+        );
+        // Desktop notifications are best effort (Windows only)
+        let _ = handle_notify(&message, "aiboard");
+    }
+    Ok(())
+}
+
+/// Parses a `--role` value; a typo is invalid input rather than some
+/// other role.
+fn parse_role(role: &str) -> Result<Role, DomainError> {
+    role.parse().map_err(DomainError::InvalidInput)
+}
+
+fn parse_role_filter(role: Option<&str>) -> anyhow::Result<Option<Role>> {
+    role.map(parse_role).transpose().map_err(Into::into)
+}
+
+/// Converts `--meta key=value` and `--meta-path '$.path=value'` arguments
+/// into `(json_path, value)` pairs.
+fn parse_meta_filters(meta: &[String], meta_path: &[String]) -> anyhow::Result<Vec<(String, String)>> {
+    let mut filters = Vec::new();
+    for m in meta {
+        let (key, value) = m
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!(tr!("--meta は key=value 形式で指定してください: {}", m)))?;
+        if key.is_empty() {
+            bail!(tr!("--meta のキーが空です: {}", m));
+        }
+        filters.push((format!("$.\"{}\"", key.replace('"', "\\\"")), value.to_string()));
+    }
+    for m in meta_path {
+        let (path, value) = m
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!(tr!("--meta-path は '$.path=value' 形式で指定してください: {}", m)))?;
+        if !path.starts_with('$') {
+            bail!(tr!("--meta-path の JSON パスは $ で始まる必要があります: {}", path));
+        }
+        filters.push((path.to_string(), value.to_string()));
+    }
+    Ok(filters)
+}
+
+/// Parses a duration such as `30s`, `10m`, `2h`, `1d` or `1w`.
+fn parse_duration(s: &str) -> anyhow::Result<Duration> {
+    let s = s.trim();
+    let unit_pos = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow::anyhow!(tr!("期間には単位（s, m, h, d, w）が必要です: {}", s)))?;
+    let (num, unit) = s.split_at(unit_pos);
+    let n: i64 = num
+        .parse()
+        .map_err(|_| anyhow::anyhow!(tr!("期間の形式が不正です: {}", s)))?;
+    let duration = match unit {
+        "s" => Duration::try_seconds(n),
+        "m" => Duration::try_minutes(n),
+        "h" => Duration::try_hours(n),
+        "d" => Duration::try_days(n),
+        "w" => Duration::try_weeks(n),
+        _ => bail!(tr!("期間の単位が不正です（s, m, h, d, w のいずれか）: {}", s)),
+    };
+    duration.ok_or_else(|| anyhow::anyhow!(tr!("期間が長すぎます: {}", s)))
+}
+
+/// Checks `--fields` against the keys the command outputs; Markdown has no
+/// field-wise form.
+fn validate_fields(fields: &[String], allowed: &[&str], format: &str) -> anyhow::Result<()> {
+    if fields.is_empty() {
+        return Ok(());
+    }
+    if matches!(format, "markdown" | "md" | "transcript") {
+        bail!(tr!("--fields は {} 形式では使えません", format));
+    }
+    if let Some(unknown) = fields.iter().find(|f| !allowed.contains(&f.as_str())) {
+        bail!(tr!("不明な項目です: {}（{}）", unknown, allowed.join(", ")));
     }
     Ok(())
 }
 
-fn parse_datetime_filter(s: &str) -> Option<DateTime<Utc>> {
-    NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S")
-        .ok()
-        .map(|ndt| ndt.and_utc())
+/// Parses `--template`, which replaces `--format` and `--fields`; every
+/// placeholder must start with one of the `allowed` keys.
+fn parse_template_arg(
+    template: Option<&str>,
+    allowed: &[&str],
+    format: &str,
+    fields: &[String],
+) -> anyhow::Result<Option<Vec<formatter::TemplatePart>>> {
+    let Some(template) = template else {
+        return Ok(None);
+    };
+    if format != "text" {
+        bail!(tr!("--template と --format {} は同時に指定できません", format));
+    }
+    if !fields.is_empty() {
+        bail!(tr!("--template と --fields は同時に指定できません"));
+    }
+    let parts = formatter::parse_template(template).map_err(|e| anyhow::anyhow!(tr!("--template が不正です: {}", e)))?;
+    for part in &parts {
+        if let formatter::TemplatePart::Field(path) = part {
+            let key = path.split('.').next().unwrap_or_default();
+            if !allowed.contains(&key) {
+                bail!(tr!("不明な項目です: {}（{}）", key, allowed.join(", ")));
+            }
+        }
+    }
+    Ok(Some(parts))
+}
+
+/// Prints records already reduced by `formatter::select_fields`.
+fn print_selected_fields(records: &[serde_json::Value], fields: &[String], format: &str, full: bool) {
+    match format {
+        "json" => outln!("{}", formatter::to_json(records).unwrap_or_else(|_| "[]".to_string())),
+        "jsonl" => out!("{}", formatter::format_jsonl(records)),
+        "yaml" | "yml" => out!("{}", formatter::format_yaml(&records)),
+        kind @ ("csv" | "tsv") => {
+            out!("{}", formatter::format_fields_delimited(records, fields, formatter::Delimited::from_format(kind)))
+        }
+        _ => outln!("{}", formatter::format_fields_text(records, fields, full)),
+    }
+}
+
+/// Writes each message (reduced to `fields` unless empty) as one JSON line
+/// as soon as `stream` hands it over, returning how many were written and
+/// the last one.
+fn stream_messages_jsonl(
+    fields: &[String],
+    stream: impl FnOnce(&mut dyn FnMut(Message) -> Result<(), DomainError>) -> Result<usize, DomainError>,
+) -> anyhow::Result<(usize, Option<Message>)> {
+    stream_messages(stream, "", |message| {
+        if fields.is_empty() {
+            formatter::format_json_line(message)
+        } else {
+            formatter::format_json_line(&formatter::select_fields(std::slice::from_ref(message), fields)[0])
+        }
+    })
+}
+
+/// Writes each message as `render` formats it, followed by a newline and
+/// preceded by `separator` from the second one on, as soon as `stream`
+/// hands it over, so that long threads start printing at once and are
+/// never held in memory. Returns how many were written and the last one.
+/// A closed pipe (e.g. `| head`) ends the output quietly.
+fn stream_messages(
+    stream: impl FnOnce(&mut dyn FnMut(Message) -> Result<(), DomainError>) -> Result<usize, DomainError>,
+    separator: &str,
+    mut render: impl FnMut(&Message) -> String,
+) -> anyhow::Result<(usize, Option<Message>)> {
+    let mut last = None;
+    let mut written = 0;
+    output::with_writer(|out| {
+        let mut closed = false;
+        let result = stream(&mut |message| {
+            let sep = if written == 0 { "" } else { separator };
+            if let Err(e) = writeln!(out, "{}{}", sep, render(&message)) {
+                closed = e.kind() == std::io::ErrorKind::BrokenPipe;
+                return Err(e.into());
+            }
+            written += 1;
+            last = Some(message);
+            Ok(())
+        });
+        match result {
+            Err(_) if closed => Ok(()),
+            other => {
+                other?;
+                out.flush()?;
+                Ok::<(), anyhow::Error>(())
+            }
+        }
+    })?;
+    Ok((written, last))
 }
 
-pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
+pub fn handle_message<T: ThreadRepository, M: MessageRepository, E: EmbeddingRepository, A: AuditRepository>(
     action: MessageAction,
-    message_uc: &MessageUseCase<M>,
+    message_uc: &MessageUseCase<T, M>,
     thread_uc: &ThreadUseCase<T, M>,
+    hook_uc: &HookUseCase<T, M>,
+    semantic_uc: &SemanticUseCase<E>,
+    audit_uc: &AuditUseCase<A>,
 ) -> anyhow::Result<()> {
     match action {
         MessageAction::Post {
@@ -65,30 +389,45 @@ pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
             parent,
             metadata,
             r#type,
+            dedupe,
+            window,
         } => {
             let full_thread_id = thread_uc.resolve_id(&thread)?;
+            let target = thread_uc.find_by_id(&full_thread_id)?;
 
             // クローズ済みスレッドへの投稿を警告
-            if let Ok(Some(t)) = thread_uc.find_by_id(&full_thread_id) {
+            if let Some(ref t) = target {
                 if t.status == ThreadStatus::Closed {
-                    eprintln!("警告: thread {} はクローズされています", &thread[..8.min(thread.len())]);
+                    eprintln!(
+                        "{}",
+                        i18n::fill(i18n::tr("警告: thread {} はクローズされています"), &[&&thread[..8.min(thread.len())]])
+                    );
                 }
             }
 
+            let sender = match sender
+                .or_else(|| target.as_ref().and_then(|t| t.default_sender.clone()))
+                .or_else(|| config::get().sender.clone())
+            {
+                Some(s) => s,
+                None => bail!(tr!("--sender を指定するか、thread set-default で既定の送信者を設定してください")),
+            };
+
             let body = match content {
                 Some(c) => c,
                 None => read_stdin()?,
             };
             validate_content(&body)?;
 
-            let role: Role = role
-                .parse()
-                .map_err(|e: String| anyhow::anyhow!(e))?;
+            let role: Role = match role {
+                Some(r) => parse_role(&r)?,
+                None => target.and_then(|t| t.default_role).unwrap_or(Role::User),
+            };
 
             let mut metadata_val: Option<serde_json::Value> = match metadata {
                 Some(m) => {
                     let val: serde_json::Value = serde_json::from_str(&m)
-                        .context("--metadata は有効な JSON である必要があります")?;
+                        .context(tr!("--metadata は有効な JSON である必要があります"))?;
                     Some(val)
                 }
                 None => None,
@@ -100,11 +439,11 @@ pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
                     Some(val) => {
                         if let Some(obj) = val.as_object_mut() {
                             if obj.contains_key("msg_type") {
-                                bail!("--metadata に msg_type が既に含まれています。--type と --metadata の msg_type を同時に指定できません");
+                                bail!(tr!("--metadata に msg_type が既に含まれています。--type と --metadata の msg_type を同時に指定できません"));
                             }
                             obj.insert("msg_type".to_string(), serde_json::Value::String(msg_type.clone()));
                         } else {
-                            bail!("--metadata はオブジェクト形式の JSON である必要があります");
+                            bail!(tr!("--metadata はオブジェクト形式の JSON である必要があります"));
                         }
                     }
                     None => {
@@ -113,6 +452,20 @@ pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
                 }
             }
 
+            if dedupe {
+                let window = parse_duration(&window)?;
+                if let Some(existing) =
+                    message_uc.find_duplicate(&full_thread_id, Some(&sender), &body, window)?
+                {
+                    notice!(
+                        "重複する message {} が既に存在するため投稿をスキップしました",
+                        &existing.id[..8.min(existing.id.len())]
+                    );
+                    outln!("{}", formatter::format_message_posted(&existing));
+                    return Ok(());
+                }
+            }
+
             let msg = message_uc.post(
                 &full_thread_id,
                 role,
@@ -122,7 +475,9 @@ pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
                 metadata_val,
                 parent.as_deref(),
             )?;
-            println!("{}", formatter::format_message_posted(&msg));
+            audit_uc.record(AuditOperation::MessagePost, Some(&sender), &[&msg.id, &msg.thread_id], None)?;
+            outln!("{}", formatter::format_message_posted(&msg));
+            notify_subscribers(thread_uc, &msg.thread_id, msg.sender.as_deref())?;
         }
 
         MessageAction::Read {
@@ -135,53 +490,175 @@ pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
             sender,
             r#type,
             since_checkpoint,
+            role,
+            from,
+            source,
+            exclude_source,
+            meta,
+            meta_path,
+            offset,
+            cursor,
+            desc,
+            since,
+            fields,
+            template,
+            output,
+            append,
         } => {
-            let mut messages = if since_checkpoint {
-                let thread_id = thread.as_deref()
-                    .ok_or_else(|| anyhow::anyhow!("--since-checkpoint には --thread が必要です"))?;
-                let full_thread_id = thread_uc.resolve_id(thread_id)?;
-                message_uc.find_since_last_type(&full_thread_id, "checkpoint")?
-            } else if let Some(ref msg_type) = r#type {
-                match thread.as_deref() {
-                    Some(thread_id) => {
-                        let full_thread_id = thread_uc.resolve_id(thread_id)?;
-                        message_uc.find_by_type(Some(&full_thread_id), msg_type)?
+            output::redirect(output.as_deref(), append)?;
+            validate_fields(&fields, &formatter::MESSAGE_FIELDS, &format)?;
+            let template = parse_template_arg(template.as_deref(), &formatter::MESSAGE_FIELDS, &format, &fields)?;
+            let meta = parse_meta_filters(&meta, &meta_path)?;
+            let thread_id = thread
+                .as_deref()
+                .map(|t| thread_uc.resolve_id(t))
+                .transpose()?;
+            let filter = MessageFilter {
+                thread_id: thread_id.clone(),
+                sender: from,
+                role: parse_role_filter(role.as_deref())?,
+                msg_type: r#type,
+                sources: source,
+                exclude_sources: exclude_source,
+                meta,
+                after: parse_datetime_opt("--after", after.as_deref())?,
+                before: parse_datetime_opt("--before", before.as_deref())?,
+                since_message: since
+                    .as_deref()
+                    .map(|id| message_uc.get(id).map(|m| m.id))
+                    .transpose()?,
+                ..Default::default()
+            };
+            let paginated = offset.is_some() || cursor.is_some();
+            // --type returns every match, newest first, with or without --thread.
+            let by_type = filter.msg_type.is_some() && !since_checkpoint;
+            let page_limit = if thread_id.is_some() || by_type { limit } else { Some(limit.unwrap_or(20)) };
+            // Without --thread the most recent messages are returned, newest first.
+            let descending = desc || thread_id.is_none() || by_type;
+
+            let page = Page {
+                limit: page_limit,
+                offset: offset.unwrap_or(0),
+                cursor: cursor.as_deref().map(MessageCursor::decode).transpose()?,
+                descending,
+            };
+            let checkpoint_thread = match (since_checkpoint, thread_id) {
+                (true, None) => bail!(tr!("--since-checkpoint には --thread が必要です")),
+                (true, Some(id)) => Some(id),
+                (false, _) => None,
+            };
+
+            if format == "jsonl" {
+                let (count, last) = stream_messages_jsonl(&fields, |f| match checkpoint_thread {
+                    Some(ref id) => {
+                        let messages = message_uc.find_since_last_type(id, "checkpoint", &filter, &page)?;
+                        let count = messages.len();
+                        messages.into_iter().try_for_each(f)?;
+                        Ok(count)
                     }
-                    None => message_uc.find_by_type(None, msg_type)?,
-                }
-            } else {
-                match thread.as_deref() {
-                    Some(thread_id) => {
-                        let full_thread_id = thread_uc.resolve_id(thread_id)?;
-                        message_uc.read(&full_thread_id)?
+                    None => message_uc.for_each(&filter, &page, f),
+                })?;
+                if let (true, Some(lim), Some(last)) = (paginated, page_limit, last) {
+                    if count == lim {
+                        notice!("(続きを読むには --cursor {} を付けてください)", MessageCursor::after(&last).encode());
                     }
-                    None => {
-                        let recent_limit = limit.unwrap_or(20);
-                        message_uc.list_recent(recent_limit)?
+                }
+            } else if template.is_none()
+                && fields.is_empty()
+                && checkpoint_thread.is_none()
+                && matches!(format.as_str(), "text" | "transcript")
+            {
+                let color = formatter::color_enabled();
+                let mut truncated = false;
+                let (count, last) = if format == "transcript" {
+                    stream_messages(|f| message_uc.for_each(&filter, &page, f), "\n", |m| {
+                        formatter::format_transcript_entry(m, color)
+                    })?
+                } else {
+                    stream_messages(|f| message_uc.for_each(&filter, &page, f), "", |m| {
+                        truncated |= !full && formatter::is_truncated(&m.content);
+                        formatter::format_message_line(m, full, color)
+                    })?
+                };
+                if truncated {
+                    notice!("(全文を表示するには --full を付けてください)");
+                }
+                if let (true, Some(lim), Some(last)) = (paginated, page_limit, last) {
+                    if count == lim {
+                        notice!("(続きを読むには --cursor {} を付けてください)", MessageCursor::after(&last).encode());
                     }
                 }
-            };
-
-            if let Some(dt) = after.as_deref().and_then(parse_datetime_filter) {
-                messages.retain(|m| m.created_at > dt);
-            }
-
-            if let Some(dt) = before.as_deref().and_then(parse_datetime_filter) {
-                messages.retain(|m| m.created_at < dt);
-            }
+            } else {
+                let messages = match checkpoint_thread {
+                    Some(ref id) => message_uc.find_since_last_type(id, "checkpoint", &filter, &page)?,
+                    None => message_uc.find(&filter, &page)?,
+                };
 
-            if thread.is_some() {
-                if let Some(lim) = limit {
-                    messages.truncate(lim);
-                }
-            }
+                // A full page means there may be more rows after the last one.
+                let next_cursor = match (page_limit, messages.last()) {
+                    (Some(lim), Some(last)) if messages.len() == lim => {
+                        Some(MessageCursor::after(last).encode())
+                    }
+                    _ => None,
+                };
 
-            match format.as_str() {
-                "json" => println!("{}", formatter::format_messages_json(&messages)),
-                _ => {
-                    println!("{}", formatter::format_messages_text(&messages, full));
-                    if !full && formatter::any_content_truncated(&messages) {
-                        eprintln!("(全文を表示するには --full を付けてください)");
+                if let Some(ref template) = template {
+                    out!("{}", formatter::format_template(template, &messages));
+                    if let (true, Some(c)) = (paginated, next_cursor.as_deref()) {
+                        notice!("(続きを読むには --cursor {} を付けてください)", c);
+                    }
+                } else if !fields.is_empty() {
+                    let records = formatter::select_fields(&messages, &fields);
+                    match format.as_str() {
+                        "json" if paginated => outln!(
+                            "{}",
+                            formatter::format_messages_page_json(&records, descending, next_cursor.as_deref())
+                        ),
+                        "yaml" | "yml" if paginated => out!(
+                            "{}",
+                            formatter::format_messages_page_yaml(&records, descending, next_cursor.as_deref())
+                        ),
+                        _ => {
+                            print_selected_fields(&records, &fields, &format, full);
+                            if let (true, Some(c)) = (paginated, next_cursor.as_deref()) {
+                                notice!("(続きを読むには --cursor {} を付けてください)", c);
+                            }
+                        }
+                    }
+                } else {
+                    match format.as_str() {
+                        "json" if paginated => outln!(
+                            "{}",
+                            formatter::format_messages_page_json(&messages, descending, next_cursor.as_deref())
+                        ),
+                        "json" => outln!("{}", formatter::format_messages_json(&messages)),
+                        "yaml" | "yml" if paginated => out!(
+                            "{}",
+                            formatter::format_messages_page_yaml(&messages, descending, next_cursor.as_deref())
+                        ),
+                        "yaml" | "yml" => out!("{}", formatter::format_yaml(&messages)),
+                        kind @ ("csv" | "tsv") => out!("{}", formatter::format_messages_delimited(&messages, formatter::Delimited::from_format(kind))),
+                        "markdown" | "md" => {
+                            out!("{}", formatter::format_messages_markdown(&messages, full));
+                            if let (true, Some(c)) = (paginated, next_cursor.as_deref()) {
+                                notice!("(続きを読むには --cursor {} を付けてください)", c);
+                            }
+                        }
+                        "transcript" => {
+                            outln!("{}", formatter::format_messages_transcript(&messages, formatter::color_enabled()));
+                            if let (true, Some(c)) = (paginated, next_cursor.as_deref()) {
+                                notice!("(続きを読むには --cursor {} を付けてください)", c);
+                            }
+                        }
+                        _ => {
+                            outln!("{}", formatter::format_messages_text(&messages, full, formatter::color_enabled()));
+                            if !full && formatter::any_content_truncated(&messages) {
+                                notice!("(全文を表示するには --full を付けてください)");
+                            }
+                            if let (true, Some(c)) = (paginated, next_cursor.as_deref()) {
+                                notice!("(続きを読むには --cursor {} を付けてください)", c);
+                            }
+                        }
                     }
                 }
             }
@@ -189,23 +666,66 @@ pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
             if let Some(ref s) = sender {
                 let count = message_uc.count_mentions(None, s)?;
                 if count > 0 {
-                    eprintln!("{}", formatter::format_mention_notification(s, count));
+                    notice!("{}", formatter::format_mention_notification(s, count));
                 }
             }
         }
 
-        MessageAction::List { limit, full, format, sender, r#type } => {
-            let messages = if let Some(ref msg_type) = r#type {
-                message_uc.find_by_type(None, msg_type)?
-            } else {
-                message_uc.list_recent(limit)?
-            };
+        MessageAction::Get { id, format } => {
+            let msg = message_uc.get(&id)?;
+            let links = message_uc.links(&msg.id)?;
             match format.as_str() {
-                "json" => println!("{}", formatter::format_messages_json(&messages)),
-                _ => {
-                    println!("{}", formatter::format_messages_text(&messages, full));
-                    if !full && formatter::any_content_truncated(&messages) {
-                        eprintln!("(全文を表示するには --full を付けてください)");
+                "json" => outln!("{}", formatter::format_message_with_links_json(&msg, &links)),
+                _ => outln!("{}", formatter::format_message_detail(&msg, &links)),
+            }
+        }
+
+        MessageAction::Link { id, other_id, relation } => {
+            let relation: LinkRelation = relation
+                .parse()
+                .map_err(|e: String| anyhow::anyhow!(e))?;
+            let link = message_uc.link(&id, &other_id, relation)?;
+            notice!(
+                "message {} → {} を {} としてリンクしました",
+                &link.from_id[..8.min(link.from_id.len())],
+                &link.to_id[..8.min(link.to_id.len())],
+                link.relation,
+            );
+        }
+
+        MessageAction::List { limit, full, format, template, fields, sender, r#type, role, from, output, append } => {
+            output::redirect(output.as_deref(), append)?;
+            validate_fields(&fields, &formatter::MESSAGE_FIELDS, &format)?;
+            let template = parse_template_arg(template.as_deref(), &formatter::MESSAGE_FIELDS, &format, &fields)?;
+            let filter = MessageFilter {
+                sender: from,
+                role: parse_role_filter(role.as_deref())?,
+                msg_type: r#type,
+                ..Default::default()
+            };
+            // --type lists every match rather than the most recent --limit.
+            let limit = if filter.msg_type.is_some() { None } else { Some(limit) };
+            let page = Page { limit, descending: true, ..Default::default() };
+            if format == "jsonl" {
+                stream_messages_jsonl(&fields, |f| message_uc.for_each(&filter, &page, f))?;
+            } else {
+                let messages = message_uc.find(&filter, &page)?;
+                match format.as_str() {
+                    _ if template.is_some() => {
+                        out!("{}", formatter::format_template(template.as_deref().unwrap_or_default(), &messages))
+                    }
+                    _ if !fields.is_empty() => {
+                        print_selected_fields(&formatter::select_fields(&messages, &fields), &fields, &format, full)
+                    }
+                    "json" => outln!("{}", formatter::format_messages_json(&messages)),
+                    "yaml" | "yml" => out!("{}", formatter::format_yaml(&messages)),
+                    "markdown" | "md" => out!("{}", formatter::format_messages_markdown(&messages, full)),
+                    kind @ ("csv" | "tsv") => out!("{}", formatter::format_messages_delimited(&messages, formatter::Delimited::from_format(kind))),
+                    _ => {
+                        outln!("{}", formatter::format_messages_text(&messages, full, formatter::color_enabled()));
+                        if !full && formatter::any_content_truncated(&messages) {
+                            notice!("(全文を表示するには --full を付けてください)");
+                        }
                     }
                 }
             }
@@ -213,11 +733,25 @@ pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
             if let Some(ref s) = sender {
                 let count = message_uc.count_mentions(None, s)?;
                 if count > 0 {
-                    eprintln!("{}", formatter::format_mention_notification(s, count));
+                    notice!("{}", formatter::format_mention_notification(s, count));
                 }
             }
         }
 
+        MessageAction::Count { thread, sender, since, format } => {
+            let thread_id = thread
+                .as_deref()
+                .map(|t| thread_uc.resolve_id(t))
+                .transpose()?;
+            let since = parse_datetime_opt("--since", since.as_deref())?;
+            let filter = MessageFilter { thread_id, sender, since, ..Default::default() };
+            let counts = message_uc.count_by_role(&filter)?;
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_count_json(&counts)),
+                _ => outln!("{}", formatter::format_count_text(&counts)),
+            }
+        }
+
         MessageAction::Search {
             query,
             thread,
@@ -225,29 +759,141 @@ pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
             format,
             sender,
             r#type,
+            role,
+            from,
+            before,
+            after,
+            r#in,
+            semantic,
+            fuzzy,
+            exclude,
+            literal,
+            any,
+            all_of,
+            group_by,
+            limit,
+            offset,
+            fields,
+            template,
+            output,
+            append,
         } => {
-            let resolved_thread = thread
-                .as_deref()
-                .map(|t| thread_uc.resolve_id(t))
-                .transpose()?;
-            let mut messages = message_uc.search(&query, resolved_thread.as_deref())?;
-
-            // --type フィルター適用
-            if let Some(ref msg_type) = r#type {
-                messages.retain(|m| {
-                    m.metadata.as_ref()
-                        .and_then(|meta| meta.get("msg_type"))
-                        .and_then(|v| v.as_str())
-                        .map(|t| t == msg_type)
-                        .unwrap_or(false)
-                });
+            output::redirect(output.as_deref(), append)?;
+            let hit_fields: Vec<&str> =
+                formatter::MESSAGE_FIELDS.iter().chain(&formatter::SEARCH_HIT_FIELDS).copied().collect();
+            validate_fields(&fields, &hit_fields, &format)?;
+            let template = parse_template_arg(template.as_deref(), &hit_fields, &format, &fields)?;
+            let scope = match r#in.as_str() {
+                "content" => SearchScope::Content,
+                "metadata" => SearchScope::Metadata,
+                "all" => SearchScope::All,
+                other => bail!(tr!("不明な検索対象です: {}（content, metadata, all）", other)),
+            };
+            let group_by_thread = match group_by.as_deref() {
+                None => false,
+                Some("thread") => true,
+                Some(other) => bail!(tr!("不明なグループ化の単位です: {}（thread）", other)),
+            };
+            if group_by_thread && !fields.is_empty() {
+                bail!(tr!("--fields と --group-by thread は同時に指定できません"));
             }
-            match format.as_str() {
-                "json" => println!("{}", formatter::format_messages_json(&messages)),
-                _ => {
-                    println!("{}", formatter::format_messages_search(&messages, &query, full));
-                    if !full && formatter::any_content_truncated(&messages) {
-                        eprintln!("(全文を表示するには --full を付けてください)");
+            if group_by_thread && template.is_some() {
+                bail!(tr!("--template と --group-by thread は同時に指定できません"));
+            }
+            let filter = MessageFilter {
+                sender: from,
+                thread_id: thread
+                    .as_deref()
+                    .map(|t| thread_uc.resolve_id(t))
+                    .transpose()?,
+                role: parse_role_filter(role.as_deref())?,
+                msg_type: r#type,
+                after: parse_datetime_opt("--after", after.as_deref())?,
+                before: parse_datetime_opt("--before", before.as_deref())?,
+                ..Default::default()
+            };
+            let page = Page {
+                limit,
+                offset,
+                ..Default::default()
+            };
+            let search_query = SearchQuery {
+                text: query.clone(),
+                mode: if any {
+                    QueryMode::Any
+                } else if all_of {
+                    QueryMode::All
+                } else if literal {
+                    QueryMode::Literal
+                } else {
+                    QueryMode::Raw
+                },
+                exclude,
+            };
+            let hits = if semantic {
+                let provider = HttpEmbeddingProvider::from_env()?;
+                let pending = semantic_uc.pending(&provider)?;
+                if pending > 0 {
+                    notice!(
+                        "{} 件の message は埋め込みが未作成のため検索対象外です（aiboard index embed で作成）",
+                        pending
+                    );
+                }
+                semantic_uc.search(&provider, &query, &filter, &page)?
+            } else {
+                message_uc.search(&search_query, scope, &filter, &page, fuzzy)?
+            };
+            let more = limit.is_some_and(|l| l > 0 && hits.len() == l);
+            let shown = hits.len();
+            let truncated = !full && hits.iter().any(|h| formatter::is_truncated(&h.message.content));
+            let mut found: Vec<String> = Vec::new();
+            for term in hits.iter().filter_map(|h| h.fuzzy.as_ref()) {
+                if !found.contains(term) {
+                    found.push(term.clone());
+                }
+            }
+            let groups = if group_by_thread {
+                Some(message_uc.group_by_thread(hits.clone())?)
+            } else {
+                None
+            };
+
+            match (format.as_str(), &groups) {
+                _ if template.is_some() => {
+                    out!("{}", formatter::format_template(template.as_deref().unwrap_or_default(), &hits))
+                }
+                _ if !fields.is_empty() => {
+                    print_selected_fields(&formatter::select_fields(&hits, &fields), &fields, &format, full)
+                }
+                (kind @ ("csv" | "tsv"), _) => out!("{}", formatter::format_search_hits_delimited(&hits, formatter::Delimited::from_format(kind))),
+                ("json", None) => outln!("{}", formatter::format_search_hits_json(&hits)),
+                ("json", Some(g)) => outln!("{}", formatter::format_search_groups_json(g)),
+                ("jsonl", None) => out!("{}", formatter::format_jsonl(&hits)),
+                ("jsonl", Some(g)) => out!("{}", formatter::format_jsonl(g)),
+                ("yaml" | "yml", None) => out!("{}", formatter::format_yaml(&hits)),
+                ("yaml" | "yml", Some(g)) => out!("{}", formatter::format_yaml(g)),
+                ("markdown" | "md", None) => out!("{}", formatter::format_search_hits_markdown(&query, &hits, full)),
+                ("markdown" | "md", Some(g)) => {
+                    out!("{}", formatter::format_search_groups_markdown(&query, g, full))
+                }
+                (_, groups) => {
+                    if !found.is_empty() {
+                        notice!(
+                            "「{}」に一致する message はありません。近い表記の結果を表示します: {}",
+                            query,
+                            found.join(", ")
+                        );
+                    }
+                    let color = formatter::color_enabled();
+                    match groups {
+                        Some(g) => outln!("{}", formatter::format_search_groups_text(g, full, color)),
+                        None => outln!("{}", formatter::format_search_hits_text(&hits, full, color)),
+                    }
+                    if truncated {
+                        notice!("(全文を表示するには --full を付けてください)");
+                    }
+                    if more {
+                        notice!("(続きは --offset {} で表示できます)", offset + shown);
                     }
                 }
             }
@@ -255,19 +901,33 @@ pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
             if let Some(ref s) = sender {
                 let count = message_uc.count_mentions(None, s)?;
                 if count > 0 {
-                    eprintln!("{}", formatter::format_mention_notification(s, count));
+                    notice!("{}", formatter::format_mention_notification(s, count));
                 }
             }
         }
 
-        MessageAction::Mentions { sender, full, format } => {
-            let messages = message_uc.find_mentions(None, &sender)?;
+        MessageAction::Mentions {
+            action: Some(MentionsAction::Dismiss { ids, sender, all }),
+            ..
+        } => {
+            let count = if all {
+                message_uc.dismiss_all_mentions(&sender)?
+            } else {
+                message_uc.dismiss_mentions(&ids, &sender)?
+            };
+            notice!("{} 件のメンションを対応済みにしました", count);
+        }
+
+        MessageAction::Mentions { action: None, sender, unread, full, format } => {
+            // clap enforces --sender when no subcommand is given
+            let sender = sender.unwrap_or_default();
+            let messages = message_uc.find_mentions(None, &sender, unread)?;
             match format.as_str() {
-                "json" => println!("{}", formatter::format_messages_json(&messages)),
+                "json" => outln!("{}", formatter::format_messages_json(&messages)),
                 _ => {
-                    println!("{}", formatter::format_messages_text(&messages, full));
+                    outln!("{}", formatter::format_messages_text(&messages, full, formatter::color_enabled()));
                     if !full && formatter::any_content_truncated(&messages) {
-                        eprintln!("(全文を表示するには --full を付けてください)");
+                        notice!("(全文を表示するには --full を付けてください)");
                     }
                 }
             }
@@ -284,7 +944,7 @@ pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
             ctrlc::set_handler(move || {
                 r.store(false, Ordering::SeqCst);
             })
-            .context("Ctrl-C ハンドラーの設定に失敗しました")?;
+            .context(tr!("Ctrl-C ハンドラーの設定に失敗しました"))?;
 
             match thread {
                 Some(ref thread_id) => {
@@ -302,14 +962,14 @@ pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
 
                     if !initial.is_empty() {
                         match format.as_str() {
-                            "json" => println!("{}", formatter::format_messages_json(&initial)),
-                            _ => println!("{}", formatter::format_messages_text(&initial, full)),
+                            "json" => outln!("{}", formatter::format_messages_json(&initial)),
+                            _ => outln!("{}", formatter::format_messages_text(&initial, full, formatter::color_enabled())),
                         }
                     }
 
                     let mut last_ts = messages.last().map(|m| m.created_at);
 
-                    eprintln!(
+                    notice!(
                         "thread {} を監視中... (Ctrl-C で終了)",
                         &full_thread_id[..8.min(full_thread_id.len())]
                     );
@@ -331,29 +991,29 @@ pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
                                 last_ts = Some(m.created_at);
                             }
                             match format.as_str() {
-                                "json" => println!("{}", formatter::format_messages_json(&new_msgs)),
-                                _ => println!("{}", formatter::format_messages_text(&new_msgs, full)),
+                                "json" => outln!("{}", formatter::format_messages_json(&new_msgs)),
+                                _ => outln!("{}", formatter::format_messages_text(&new_msgs, full, formatter::color_enabled())),
                             }
                         }
                     }
                 }
                 None => {
                     // 全スレッドから監視
-                    let messages = message_uc.list_recent(100)?;
+                    let messages = message_uc.list_recent(&MessageFilter::default(), 100)?;
 
                     // 初回: 最新5件を表示（降順なので先頭5件、逆順にして古い順で表示）
                     let initial = messages.iter().take(5).rev().cloned().collect::<Vec<_>>();
 
                     if !initial.is_empty() {
                         match format.as_str() {
-                            "json" => println!("{}", formatter::format_messages_json(&initial)),
-                            _ => println!("{}", formatter::format_messages_text(&initial, full)),
+                            "json" => outln!("{}", formatter::format_messages_json(&initial)),
+                            _ => outln!("{}", formatter::format_messages_text(&initial, full, formatter::color_enabled())),
                         }
                     }
 
                     let mut last_ts = messages.first().map(|m| m.created_at);
 
-                    eprintln!("全スレッドを監視中... (Ctrl-C で終了)");
+                    notice!("全スレッドを監視中... (Ctrl-C で終了)");
 
                     while running.load(Ordering::SeqCst) {
                         std::thread::sleep(std::time::Duration::from_secs(interval));
@@ -361,7 +1021,7 @@ pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
                             break;
                         }
 
-                        let all = message_uc.list_recent(100)?;
+                        let all = message_uc.list_recent(&MessageFilter::default(), 100)?;
                         let new_msgs: Vec<_> = match last_ts {
                             Some(ts) => all.into_iter().filter(|m| m.created_at > ts).collect(),
                             None => all,
@@ -374,60 +1034,400 @@ pub fn handle_message<T: ThreadRepository, M: MessageRepository>(
                                 last_ts = Some(m.created_at);
                             }
                             match format.as_str() {
-                                "json" => println!("{}", formatter::format_messages_json(&sorted)),
-                                _ => println!("{}", formatter::format_messages_text(&sorted, full)),
+                                "json" => outln!("{}", formatter::format_messages_json(&sorted)),
+                                _ => outln!("{}", formatter::format_messages_text(&sorted, full, formatter::color_enabled())),
                             }
                         }
                     }
                 }
             }
 
-            eprintln!("監視を終了しました");
+            notice!("監視を終了しました");
+        }
+
+        MessageAction::Export { thread, format, output, append } => {
+            let full_thread_id = thread_uc.resolve_id(&thread)?;
+            let t = thread_uc
+                .find_by_id(&full_thread_id)?
+                .ok_or_else(|| DomainError::ThreadNotFound(full_thread_id.clone()))?;
+            let filter = MessageFilter { thread_id: Some(full_thread_id.clone()), ..Default::default() };
+            let page = Page::default();
+
+            output::redirect(output.as_deref(), append)?;
+            // Markdown and JSON Lines are written message by message; the
+            // JSON document is built whole.
+            let count = match format.as_str() {
+                "markdown" | "md" => {
+                    let total = message_uc.count_by_role(&filter)?.iter().map(|(_, n)| n).sum();
+                    out!("{}", formatter::format_transcript_markdown_header(&t, total));
+                    let (count, _) = stream_messages(|f| message_uc.for_each(&filter, &page, f), "", |m| {
+                        formatter::format_transcript_markdown_message(m)
+                    })?;
+                    outln!();
+                    count
+                }
+                "jsonl" => stream_messages_jsonl(&[], |f| message_uc.for_each(&filter, &page, f))?.0,
+                "json" => {
+                    let messages = message_uc.read(&full_thread_id)?;
+                    outln!("{}", formatter::format_transcript_json(&t, &messages));
+                    messages.len()
+                }
+                other => bail!(tr!("未対応の出力形式です: {}（markdown, json, jsonl）", other)),
+            };
+            if let Some(path) = output {
+                output::commit()?;
+                notice!("{} 件の message を {} にエクスポートしました", count, path.display());
+            }
+        }
+
+        MessageAction::Import { transcript, thread } => {
+            let count = hook_uc.import_transcript(thread.as_deref(), &transcript)?;
+            notice!("{} 件の message を取り込みました", count);
+        }
+
+        MessageAction::Draft { .. } => {
+            unreachable!("message draft is dispatched to handle_draft")
         }
 
         MessageAction::Update { id, content } => {
             validate_content(&content)?;
             let full_id = message_uc.update(&id, &content)?;
-            println!("{}", full_id);
+            audit_uc.record(AuditOperation::MessageUpdate, audit_actor(None).as_deref(), &[&full_id], None)?;
+            outln!("{}", full_id);
+        }
+    }
+    Ok(())
+}
+
+pub fn handle_draft<D: DraftRepository, T: ThreadRepository, M: MessageRepository, A: AuditRepository>(
+    action: DraftAction,
+    db: &Database,
+    draft_uc: &DraftUseCase<D>,
+    message_uc: &MessageUseCase<T, M>,
+    thread_uc: &ThreadUseCase<T, M>,
+    audit_uc: &AuditUseCase<A>,
+) -> anyhow::Result<()> {
+    match action {
+        DraftAction::Save {
+            thread,
+            sender,
+            content,
+            id,
+            append,
+            role,
+            r#type,
+        } => {
+            let body = match content {
+                Some(c) => c,
+                None => read_stdin()?,
+            };
+            validate_content(&body)?;
+
+            let draft = match id {
+                Some(id) => {
+                    let mut draft = draft_uc.get(&id)?;
+                    draft.edit_content(&body, append);
+                    validate_content(&draft.content)?;
+                    draft_uc.update(&mut draft)?;
+                    draft
+                }
+                None => {
+                    // clap enforces --thread and --sender when --id is absent
+                    let thread = thread.unwrap_or_default();
+                    let full_thread_id = thread_uc.resolve_id(&thread)?;
+                    let role = parse_role(&role)?;
+                    let metadata = r#type.map(|t| serde_json::json!({"msg_type": t}));
+                    draft_uc.create(&full_thread_id, sender.as_deref(), role, &body, metadata)?
+                }
+            };
+            outln!("{}", draft.id);
+        }
+        DraftAction::List { thread, sender, full, format } => {
+            let thread_id = thread
+                .as_deref()
+                .map(|t| thread_uc.resolve_id(t))
+                .transpose()?;
+            let drafts = draft_uc.list(thread_id.as_deref(), sender.as_deref())?;
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_drafts_json(&drafts)),
+                _ => outln!("{}", formatter::format_drafts_text(&drafts, full)),
+            }
+        }
+        DraftAction::Post { id } => {
+            db.savepoint("draft_post")?;
+            let msg = match draft_uc.post(&id, message_uc) {
+                Ok(msg) => {
+                    db.release("draft_post")?;
+                    msg
+                }
+                Err(e) => {
+                    db.rollback_to("draft_post")?;
+                    return Err(e.into());
+                }
+            };
+            let actor = audit_actor(msg.sender.as_deref());
+            audit_uc.record(AuditOperation::MessagePost, actor.as_deref(), &[&msg.id, &msg.thread_id], None)?;
+            outln!("{}", formatter::format_message_posted(&msg));
+            notify_subscribers(thread_uc, &msg.thread_id, msg.sender.as_deref())?;
+        }
+        DraftAction::Delete { id } => {
+            draft_uc.delete(&id)?;
+            notice!("draft {} を破棄しました", id);
         }
     }
     Ok(())
 }
 
-pub fn handle_thread<T: ThreadRepository, M: MessageRepository>(
+pub fn handle_thread<T: ThreadRepository, M: MessageRepository, A: AuditRepository>(
     action: ThreadAction,
+    db: &Database,
     thread_uc: &ThreadUseCase<T, M>,
+    audit_uc: &AuditUseCase<A>,
 ) -> anyhow::Result<()> {
     match action {
-        ThreadAction::Create { title } => {
-            let thread = thread_uc.create(&title)?;
-            println!("{}", thread.id);
+        ThreadAction::Create { title, name } => {
+            let thread = thread_uc.create(&title, name.as_deref())?;
+            outln!("{}", thread.id);
+        }
+        ThreadAction::List { full, format, template, status, tag, phase, no_phase, sort, reverse, output, append } => {
+            output::redirect(output.as_deref(), append)?;
+            let template = parse_template_arg(template.as_deref(), &formatter::THREAD_FIELDS, &format, &[])?;
+            let status_filter = match status.as_str() {
+                "open" => Some(ThreadStatus::Open),
+                "closed" => Some(ThreadStatus::Closed),
+                _ => None,
+            };
+            let sort = match sort.as_str() {
+                "created" => ThreadSort::Created,
+                "updated" => ThreadSort::Updated,
+                "title" => ThreadSort::Title,
+                "messages" => ThreadSort::Messages,
+                other => bail!(tr!("未対応の並び順です: {}（created, updated, title, messages）", other)),
+            };
+            let phase = match phase {
+                Some(p) => Some(Some(p.parse::<ThreadPhase>().map_err(|e: String| anyhow::anyhow!(e))?)),
+                None if no_phase => Some(None),
+                None => None,
+            };
+            let filter = ThreadFilter { status: status_filter, tag, phase, sort, reverse, ..Default::default() };
+            let threads = thread_uc.list(&filter)?;
+            match format.as_str() {
+                _ if template.is_some() => {
+                    out!("{}", formatter::format_template(template.as_deref().unwrap_or_default(), &threads))
+                }
+                "json" => outln!("{}", formatter::format_threads_json(&threads)),
+                "jsonl" => out!("{}", formatter::format_jsonl(&threads)),
+                "yaml" | "yml" => out!("{}", formatter::format_yaml(&threads)),
+                "markdown" | "md" => out!("{}", formatter::format_threads_markdown(&threads, full)),
+                kind @ ("csv" | "tsv") => out!("{}", formatter::format_threads_delimited(&threads, formatter::Delimited::from_format(kind))),
+                _ => outln!("{}", formatter::format_threads_text(&threads, full)),
+            }
         }
-        ThreadAction::List { full, format, status } => {
+        ThreadAction::Search { query, full, format, template, status, output, append } => {
+            output::redirect(output.as_deref(), append)?;
+            let template = parse_template_arg(template.as_deref(), &formatter::THREAD_FIELDS, &format, &[])?;
             let status_filter = match status.as_str() {
                 "open" => Some(ThreadStatus::Open),
                 "closed" => Some(ThreadStatus::Closed),
                 _ => None,
             };
-            let threads = thread_uc.list_by_status(status_filter)?;
+            let filter = ThreadFilter {
+                status: status_filter,
+                query: Some(query),
+                ..Default::default()
+            };
+            let threads = thread_uc.list(&filter)?;
             match format.as_str() {
-                "json" => println!("{}", formatter::format_threads_json(&threads)),
-                _ => println!("{}", formatter::format_threads_text(&threads, full)),
+                _ if template.is_some() => {
+                    out!("{}", formatter::format_template(template.as_deref().unwrap_or_default(), &threads))
+                }
+                "json" => outln!("{}", formatter::format_threads_json(&threads)),
+                "jsonl" => out!("{}", formatter::format_jsonl(&threads)),
+                "yaml" | "yml" => out!("{}", formatter::format_yaml(&threads)),
+                "markdown" | "md" => out!("{}", formatter::format_threads_markdown(&threads, full)),
+                kind @ ("csv" | "tsv") => out!("{}", formatter::format_threads_delimited(&threads, formatter::Delimited::from_format(kind))),
+                _ => outln!("{}", formatter::format_threads_text(&threads, full)),
             }
         }
         ThreadAction::Delete { id } => {
-            thread_uc.delete(&id)?;
-            eprintln!("thread {} を削除しました", id);
+            let full_id = thread_uc.resolve_id(&id)?;
+            thread_uc.delete(&full_id)?;
+            audit_uc.record(AuditOperation::ThreadDelete, audit_actor(None).as_deref(), &[&full_id], None)?;
+            notice!("thread {} を削除しました", id);
         }
         ThreadAction::Close { id } => {
             thread_uc.close(&id)?;
-            eprintln!("thread {} をクローズしました", id);
+            notice!("thread {} をクローズしました", id);
         }
-        ThreadAction::Reopen { id } => {
-            thread_uc.reopen(&id)?;
-            eprintln!("thread {} を再オープンしました", id);
-        }
-        ThreadAction::SetPhase { id, phase } => {
+        ThreadAction::Autoclose { inactive_days, dry_run } => {
+            if inactive_days < 0 {
+                bail!(tr!("--inactive-days は 0 以上を指定してください"));
+            }
+            let threads = thread_uc.autoclose(inactive_days, dry_run)?;
+            if !threads.is_empty() {
+                outln!("{}", formatter::format_threads_text(&threads, false));
+            }
+            if dry_run {
+                notice!("{} 件の thread がクローズ対象です（dry-run）", threads.len());
+            } else {
+                notice!("{} 件の thread をクローズしました", threads.len());
+            }
+        }
+        ThreadAction::Reopen { id } => {
+            thread_uc.reopen(&id)?;
+            notice!("thread {} を再オープンしました", id);
+        }
+        ThreadAction::Split { id, from, title } => {
+            db.savepoint("split")?;
+            let (thread, moved) = match thread_uc.split(&id, &from, title.as_deref()) {
+                Ok(split) => {
+                    db.release("split")?;
+                    split
+                }
+                Err(e) => {
+                    db.rollback_to("split")?;
+                    return Err(e.into());
+                }
+            };
+            outln!("{}", thread.id);
+            notice!(
+                "{} 件の message を thread {} に移動しました",
+                moved,
+                &thread.id[..8.min(thread.id.len())]
+            );
+        }
+        ThreadAction::Clone { id, title, messages } => {
+            let mode = match messages.as_str() {
+                "none" => CloneMessages::None,
+                "all" => CloneMessages::All,
+                "pinned" => CloneMessages::Pinned,
+                other => bail!(tr!("未対応の --messages です: {}（none, all, pinned）", other)),
+            };
+            db.savepoint("clone")?;
+            let (thread, copied) = match thread_uc.clone_thread(&id, title.as_deref(), mode) {
+                Ok(cloned) => {
+                    db.release("clone")?;
+                    cloned
+                }
+                Err(e) => {
+                    db.rollback_to("clone")?;
+                    return Err(e.into());
+                }
+            };
+            outln!("{}", thread.id);
+            notice!(
+                "thread {} を複製しました（{} 件の message をコピー）",
+                id, copied
+            );
+        }
+        ThreadAction::Export { id, output, append } => {
+            let bundle = thread_uc.export_bundle(&id)?;
+            let rendered = formatter::format_thread_bundle_json(&bundle);
+            output::redirect(output.as_deref(), append)?;
+            outln!("{}", rendered);
+            if let Some(path) = output {
+                output::commit()?;
+                notice!(
+                    "thread {} を {} 件の message とともに {} にエクスポートしました",
+                    &bundle.thread.id[..8.min(bundle.thread.id.len())],
+                    bundle.messages.len(),
+                    path.display()
+                );
+            }
+        }
+        ThreadAction::Import { path, new_ids } => {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| tr!("{} の読み込みに失敗しました", path.display()))?;
+            let bundle: ThreadBundle = serde_json::from_str(&content)
+                .with_context(|| tr!("{} は thread バンドルとして解析できません", path.display()))?;
+            db.savepoint("import")?;
+            let (thread, imported) = match thread_uc.import_bundle(bundle, new_ids) {
+                Ok(imported) => {
+                    db.release("import")?;
+                    imported
+                }
+                Err(e) => {
+                    db.rollback_to("import")?;
+                    return Err(e.into());
+                }
+            };
+            outln!("{}", thread.id);
+            notice!("{} 件の message を取り込みました", imported);
+        }
+        ThreadAction::Watch { id, sender, notify } => {
+            thread_uc.watch(&id, &sender, notify)?;
+            notice!("{} が thread {} を購読しました", sender, id);
+        }
+        ThreadAction::Unwatch { id, sender } => {
+            if thread_uc.unwatch(&id, &sender)? {
+                notice!("{} の thread {} の購読を解除しました", sender, id);
+            } else {
+                notice!("{} は thread {} を購読していません", sender, id);
+            }
+        }
+        ThreadAction::Pin { id } => {
+            thread_uc.set_pinned(&id, true)?;
+            notice!("thread {} をピン留めしました", id);
+        }
+        ThreadAction::Unpin { id } => {
+            thread_uc.set_pinned(&id, false)?;
+            notice!("thread {} のピン留めを解除しました", id);
+        }
+        ThreadAction::Tag { id, tags } => {
+            let added = thread_uc.tag(&id, &tags)?;
+            notice!("thread {} に {} 件のタグを追加しました", id, added);
+        }
+        ThreadAction::Untag { id, tags } => {
+            let removed = thread_uc.untag(&id, &tags)?;
+            notice!("thread {} から {} 件のタグを削除しました", id, removed);
+        }
+        ThreadAction::Rename { id, title, name } => {
+            thread_uc.rename(&id, title.as_deref(), name.as_deref())?;
+            if let Some(t) = &title {
+                notice!("thread {} のタイトルを「{}」に変更しました", id, t);
+            }
+            if let Some(n) = &name {
+                notice!("thread {} の名前を {} に設定しました", id, n);
+            }
+        }
+        ThreadAction::Lock { id, allow } => {
+            thread_uc.lock(&id, &allow)?;
+            if allow.is_empty() {
+                notice!("thread {} をロックしました（投稿不可）", id);
+            } else {
+                notice!("thread {} をロックしました（投稿可: {}）", id, allow.join(", "));
+            }
+        }
+        ThreadAction::Unlock { id } => {
+            thread_uc.unlock(&id)?;
+            notice!("thread {} のロックを解除しました", id);
+        }
+        ThreadAction::SetRetention { id, days, keep_last, .. } => {
+            thread_uc.set_retention(&id, days, keep_last)?;
+            match (days, keep_last) {
+                (Some(d), _) => notice!("thread {} の保持期間を {} 日に設定しました", id, d),
+                (None, Some(k)) => notice!("thread {} を直近 {} 件のみ保持するよう設定しました", id, k),
+                (None, None) => notice!("thread {} の保持ポリシーを解除しました", id),
+            }
+        }
+        ThreadAction::SetDefault { id, sender, role, clear } => {
+            let role = role.as_deref().map(parse_role).transpose()?;
+            let t = thread_uc.set_defaults(&id, sender.as_deref(), role, clear)?;
+            notice!(
+                "thread {} の既定値: sender={} role={}",
+                id,
+                t.default_sender.as_deref().unwrap_or("-"),
+                t.default_role.map(|r| r.to_string()).unwrap_or_else(|| "-".to_string())
+            );
+        }
+        ThreadAction::SetMeta { id, json, merge } => {
+            let value: serde_json::Value =
+                serde_json::from_str(&json).context(tr!("メタデータは有効な JSON である必要があります"))?;
+            let stored = thread_uc.set_metadata(&id, value, merge)?;
+            outln!("{}", stored);
+            notice!("thread {} のメタデータを更新しました", id);
+        }
+        ThreadAction::SetPhase { id, phase } => {
             let phase_value = if phase == "none" {
                 None
             } else {
@@ -438,73 +1438,264 @@ pub fn handle_thread<T: ThreadRepository, M: MessageRepository>(
             };
             thread_uc.set_phase(&id, phase_value)?;
             match phase_value {
-                Some(p) => eprintln!("thread {} のフェーズを {} に設定しました", id, p),
-                None => eprintln!("thread {} のフェーズを解除しました", id),
+                Some(p) => notice!("thread {} のフェーズを {} に設定しました", id, p),
+                None => notice!("thread {} のフェーズを解除しました", id),
+            }
+        }
+        ThreadAction::Show { id, format } => {
+            let detail = thread_uc.show(&id)?;
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_thread_detail_json(&detail)),
+                _ => outln!("{}", formatter::format_thread_detail(&detail)),
+            }
+        }
+        ThreadAction::Stats { id, format } => {
+            let stats = thread_uc.stats(&id)?;
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_thread_stats_json(&stats)),
+                _ => outln!("{}", formatter::format_thread_stats_text(&stats)),
+            }
+        }
+        ThreadAction::Participants { id, format } => {
+            let participants = thread_uc.participants(&id)?;
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_participants_json(&participants)),
+                _ => outln!("{}", formatter::format_participants_text(&participants)),
             }
         }
-        ThreadAction::Fetch { url, title, sender } => {
-            eprintln!("{} を取得中...", url);
-            let thread = thread_uc.fetch(&url, title.as_deref(), sender.as_deref())?;
-            println!("{}", thread.id);
-            eprintln!("取得して thread {} として保存しました", &thread.id[..8.min(thread.id.len())]);
+        ThreadAction::Summarize { id, head, tail } => {
+            let summary = thread_uc.summarize(&id, head, tail)?;
+            outln!("{}", summary);
+        }
+        ThreadAction::ImportFile { path, format, title, sender, split } => {
+            let format = match format.as_deref() {
+                None => None,
+                Some("html" | "htm") => Some(DocumentFormat::Html),
+                Some("md" | "markdown") => Some(DocumentFormat::Markdown),
+                Some("txt" | "text") => Some(DocumentFormat::Text),
+                Some(other) => bail!(tr!("未対応のファイル形式です: {}（html, md, txt）", other)),
+            };
+            let result =
+                thread_uc.import_file(&path, format, title.as_deref(), sender.as_deref(), split)?;
+            let thread = &result.thread;
+            outln!("{}", thread.id);
+            if split && !result.split {
+                notice!("会話構造を認識できなかったため、文書全体を 1 件の message として保存します");
+            }
+            notice!(
+                "{} を thread {} として取り込みました（{} 件の message）",
+                path.display(),
+                &thread.id[..8.min(thread.id.len())],
+                result.messages
+            );
+        }
+        ThreadAction::Fetch { refresh: Some(id), sender, header, bearer, bearer_env, .. } => {
+            let opts = fetch_options(&header, bearer, bearer_env.as_deref())?;
+            notice!("thread {} の取得元を再取得中...", id);
+            let added = thread_uc.refresh(&id, sender.as_deref(), &opts)?;
+            if added.is_empty() {
+                notice!("前回の取得から変更はありません");
+            } else {
+                for msg in &added {
+                    outln!("{}", formatter::format_message_posted(msg));
+                }
+                notice!("差分を {} 件の message として追加しました", added.len());
+            }
+        }
+        ThreadAction::Fetch { github: Some(issue), title, header, bearer, bearer_env, .. } => {
+            let issue: IssueRef = issue.parse()?;
+            let opts = fetch_options(&header, bearer, bearer_env.as_deref())?;
+            notice!("{} を GitHub API から取得中...", issue);
+            let result = thread_uc.fetch_github(&issue, title.as_deref(), &opts)?;
+            let thread = &result.thread;
+            outln!("{}", thread.id);
+            notice!(
+                "取得して thread {} として保存しました（{} 件の message）",
+                &thread.id[..8.min(thread.id.len())],
+                result.messages
+            );
+        }
+        ThreadAction::Fetch {
+            url,
+            title,
+            sender,
+            split,
+            follow_next,
+            next_selector,
+            json_path,
+            header,
+            bearer,
+            bearer_env,
+            ..
+        } => {
+            let url = url.expect("clap requires url without --refresh");
+            let opts = fetch_options(&header, bearer, bearer_env.as_deref())?;
+            let follow = FollowNext {
+                pages: follow_next.unwrap_or(0),
+                selector: next_selector,
+            };
+            notice!("{} を取得中...", url);
+            let result = thread_uc.fetch(
+                &url,
+                title.as_deref(),
+                sender.as_deref(),
+                split,
+                json_path.as_deref(),
+                &follow,
+                &opts,
+            )?;
+            let thread = &result.thread;
+            outln!("{}", thread.id);
+            if split && !result.split {
+                notice!("会話構造を認識できなかったため、ページ全体を 1 件の message として保存します");
+            }
+            notice!(
+                "{} ページを取得して thread {} として保存しました（{} 件の message）",
+                result.pages,
+                &thread.id[..8.min(thread.id.len())],
+                result.messages
+            );
         }
     }
     Ok(())
 }
 
+/// Builds fetch options from `--header`, `--bearer` and `--bearer-env`.
+fn fetch_options(
+    headers: &[String],
+    bearer: Option<String>,
+    bearer_env: Option<&str>,
+) -> anyhow::Result<http::FetchOptions> {
+    let headers = headers
+        .iter()
+        .map(|h| http::FetchOptions::parse_header(h))
+        .collect::<Result<Vec<_>, _>>()?;
+    let bearer = match bearer_env {
+        Some(var) => Some(
+            std::env::var(var)
+                .ok()
+                .filter(|t| !t.is_empty())
+                .with_context(|| tr!("環境変数 {} にトークンが設定されていません", var))?,
+        ),
+        None => bearer,
+    };
+    Ok(http::FetchOptions { headers, bearer })
+}
+
 pub fn handle_hook<T: ThreadRepository, M: MessageRepository>(
     action: HookAction,
     hook_uc: &HookUseCase<T, M>,
+    thread_uc: &ThreadUseCase<T, M>,
+    data_dir: &std::path::Path,
 ) -> anyhow::Result<()> {
     match action {
         HookAction::Ingest { thread, debug } => {
             let input = read_stdin()?;
 
             if debug {
-                let debug_dir = crate::dirs_fallback().join("debug");
+                let debug_dir = data_dir.join("debug");
                 let _ = std::fs::create_dir_all(&debug_dir);
                 let ts = chrono::Utc::now().format("%Y%m%d_%H%M%S_%3f");
                 let path = debug_dir.join(format!("hook_{}.json", ts));
                 let _ = std::fs::write(&path, &input);
-                eprintln!("DEBUG: hook入力を {} に保存", path.display());
+                notice!("DEBUG: hook入力を {} に保存", path.display());
             }
 
-            let count = hook_uc.ingest(thread.as_deref(), &input)?;
-            eprintln!("{} 件の message を取り込みました", count);
+            // A hook must not break the session it records, so a locked
+            // thread only skips the event.
+            let messages = match hook_uc.ingest(thread.as_deref(), &input) {
+                Ok(messages) => messages,
+                Err(DomainError::ThreadLocked(id)) => {
+                    let message = i18n::fill(
+                        i18n::tr("thread {} はロックされているため hook イベントを取り込みませんでした"),
+                        &[&id],
+                    );
+                    logger::log_warning(&message);
+                    notice!("{}", message);
+                    return Ok(());
+                }
+                Err(e) => return Err(e.into()),
+            };
+            notice!("{} 件の message を取り込みました", messages.len());
+            for msg in &messages {
+                notify_subscribers(thread_uc, &msg.thread_id, msg.sender.as_deref())?;
+            }
         }
     }
     Ok(())
 }
 
-pub fn handle_cleanup<T: ThreadRepository, M: MessageRepository>(
+pub fn handle_cleanup<T: ThreadRepository, M: MessageRepository, A: AuditRepository>(
     action: CleanupAction,
     cleanup_uc: &CleanupUseCase<T, M>,
-    db_path: &std::path::Path,
+    audit_uc: &AuditUseCase<A>,
+    db_path: Option<&std::path::Path>,
 ) -> anyhow::Result<()> {
+    // Resolved before the backup so that a missing value fails early.
+    let action = match action {
+        CleanupAction::Age { days: None, no_backup } => match config::get().cleanup.days {
+            Some(days) => CleanupAction::Age { days: Some(days), no_backup },
+            None => bail!(tr!("日数を指定するか、設定ファイルの [cleanup] days を設定してください")),
+        },
+        action => action,
+    };
     let no_backup = match &action {
         CleanupAction::Age { no_backup, .. } => *no_backup,
         CleanupAction::Thread { no_backup, .. } => *no_backup,
+        CleanupAction::ApplyRetention { no_backup } => *no_backup,
         CleanupAction::Session { no_backup, .. } => *no_backup,
     };
 
-    if !no_backup {
-        let backup_path = crate::infra::backup::create_backup(db_path)
-            .context("DB バックアップの作成に失敗しました")?;
-        eprintln!("バックアップを作成しました: {}", backup_path.display());
+    // An ephemeral board has no file to back up.
+    if let (false, Some(db_path)) = (no_backup, db_path) {
+        let backup_path = backup::create_backup(db_path, None)
+            .context(tr!("DB バックアップの作成に失敗しました"))?;
+        notice!("バックアップを作成しました: {}", backup_path.display());
+        auto_prune_backups(db_path)?;
     }
 
+    let actor = audit_actor(None);
     match action {
-        CleanupAction::Age { days, .. } => {
+        CleanupAction::Age { days: Some(days), .. } => {
             let count = cleanup_uc.by_age(days)?;
-            eprintln!("{} 日より古い {} 件の message を削除しました", days, count);
+            if count > 0 {
+                let detail = serde_json::json!({ "days": days, "messages": count });
+                audit_uc.record(AuditOperation::CleanupAge, actor.as_deref(), &[], Some(detail))?;
+            }
+            notice!("{} 日より古い {} 件の message を削除しました", days, count);
         }
+        CleanupAction::Age { days: None, .. } => unreachable!("resolved from the config above"),
         CleanupAction::Thread { id, .. } => {
-            let count = cleanup_uc.by_thread(&id)?;
-            eprintln!("thread {} と {} 件の message を削除しました", id, count);
+            let (full_id, count) = cleanup_uc.by_thread(&id)?;
+            let detail = serde_json::json!({ "messages": count });
+            audit_uc.record(AuditOperation::CleanupThread, actor.as_deref(), &[&full_id], Some(detail))?;
+            notice!("thread {} と {} 件の message を削除しました", id, count);
+        }
+        CleanupAction::ApplyRetention { .. } => {
+            let results = cleanup_uc.apply_retention()?;
+            let total: usize = results.iter().map(|(_, n)| n).sum();
+            if total > 0 {
+                let threads: Vec<&str> = results.iter().map(|(t, _)| t.id.as_str()).collect();
+                let detail = serde_json::json!({ "messages": total });
+                audit_uc.record(AuditOperation::CleanupRetention, actor.as_deref(), &threads, Some(detail))?;
+            }
+            for (thread, count) in &results {
+                notice!(
+                    "thread {} ({}) から {} 件の message を削除しました",
+                    &thread.id[..8.min(thread.id.len())],
+                    thread.title,
+                    count
+                );
+            }
+            notice!("保持ポリシーにより合計 {} 件の message を削除しました", total);
         }
         CleanupAction::Session { id, .. } => {
             let count = cleanup_uc.by_session(&id)?;
-            eprintln!("session {} の {} 件の message を削除しました", id, count);
+            if count > 0 {
+                let detail = serde_json::json!({ "session": id, "messages": count });
+                audit_uc.record(AuditOperation::CleanupSession, actor.as_deref(), &[], Some(detail))?;
+            }
+            notice!("session {} の {} 件の message を削除しました", id, count);
         }
     }
     Ok(())
@@ -519,29 +1710,29 @@ pub fn handle_setup(action: SetupAction) -> anyhow::Result<()> {
                 let settings_path = std::path::Path::new(".claude").join("settings.json");
 
                 eprint!(
-                    "hook 設定を {} に書き込みます。続行しますか？ [y/N] ",
-                    settings_path.display()
+                    "{}",
+                    i18n::fill(i18n::tr("hook 設定を {} に書き込みます。続行しますか？ [y/N] "), &[&settings_path.display()])
                 );
 
                 let mut input = String::new();
                 std::io::stdin()
                     .read_line(&mut input)
-                    .context("確認入力の読み取りに失敗しました")?;
+                    .context(tr!("確認入力の読み取りに失敗しました"))?;
 
                 if !input.trim().eq_ignore_ascii_case("y") {
-                    eprintln!("中止しました");
+                    notice!("中止しました");
                     return Ok(());
                 }
 
                 if let Some(parent) = settings_path.parent() {
                     std::fs::create_dir_all(parent)
-                        .context(".claude ディレクトリの作成に失敗しました")?;
+                        .context(tr!(".claude ディレクトリの作成に失敗しました"))?;
                 }
 
                 // Merge into existing settings if present
                 let mut settings = if settings_path.exists() {
                     let existing = std::fs::read_to_string(&settings_path)
-                        .context("既存の設定ファイルの読み取りに失敗しました")?;
+                        .context(tr!("既存の設定ファイルの読み取りに失敗しました"))?;
                     serde_json::from_str::<serde_json::Value>(&existing)
                         .unwrap_or_else(|_| serde_json::json!({}))
                 } else {
@@ -557,11 +1748,11 @@ pub fn handle_setup(action: SetupAction) -> anyhow::Result<()> {
 
                 let merged = serde_json::to_string_pretty(&settings)?;
                 std::fs::write(&settings_path, &merged)
-                    .context("設定ファイルの書き込みに失敗しました")?;
+                    .context(tr!("設定ファイルの書き込みに失敗しました"))?;
 
-                eprintln!("hook 設定を {} に書き込みました", settings_path.display());
+                notice!("hook 設定を {} に書き込みました", settings_path.display());
             } else {
-                println!("{}", json_str);
+                outln!("{}", json_str);
             }
         }
 
@@ -575,28 +1766,28 @@ pub fn handle_setup(action: SetupAction) -> anyhow::Result<()> {
                 let skill_path = skill_dir.join("SKILL.md");
 
                 eprint!(
-                    "skill ファイルを {} に書き込みます。続行しますか？ [y/N] ",
-                    skill_path.display()
+                    "{}",
+                    i18n::fill(i18n::tr("skill ファイルを {} に書き込みます。続行しますか？ [y/N] "), &[&skill_path.display()])
                 );
 
                 let mut input = String::new();
                 std::io::stdin()
                     .read_line(&mut input)
-                    .context("確認入力の読み取りに失敗しました")?;
+                    .context(tr!("確認入力の読み取りに失敗しました"))?;
 
                 if !input.trim().eq_ignore_ascii_case("y") {
-                    eprintln!("中止しました");
+                    notice!("中止しました");
                     return Ok(());
                 }
 
                 std::fs::create_dir_all(&skill_dir)
-                    .context("skills ディレクトリの作成に失敗しました")?;
+                    .context(tr!("skills ディレクトリの作成に失敗しました"))?;
                 std::fs::write(&skill_path, &content)
-                    .context("skill ファイルの書き込みに失敗しました")?;
+                    .context(tr!("skill ファイルの書き込みに失敗しました"))?;
 
-                eprintln!("skill ファイルを {} に書き込みました", skill_path.display());
+                notice!("skill ファイルを {} に書き込みました", skill_path.display());
             } else {
-                println!("{}", content);
+                outln!("{}", content);
             }
         }
     }
@@ -612,12 +1803,614 @@ pub fn handle_notify(message: &str, title: &str) -> anyhow::Result<()> {
         .text1(message)
         .duration(Duration::Short)
         .show()
-        .map_err(|e| anyhow::anyhow!("通知の表示に失敗しました: {}", e))
+        .map_err(|e| anyhow::anyhow!(tr!("通知の表示に失敗しました: {}", e)))
 }
 
 #[cfg(not(target_os = "windows"))]
 pub fn handle_notify(_message: &str, _title: &str) -> anyhow::Result<()> {
-    anyhow::bail!("notify コマンドは Windows のみ対応しています")
+    anyhow::bail!(tr!("notify コマンドは Windows のみ対応しています"))
+}
+
+pub fn handle_group<G: GroupRepository>(
+    action: GroupAction,
+    group_uc: &GroupUseCase<G>,
+) -> anyhow::Result<()> {
+    match action {
+        GroupAction::Add { name, members } => {
+            let added = group_uc.add(&name, &members)?;
+            notice!("グループ {} に {} 人を追加しました", name, added);
+        }
+        GroupAction::Remove { name, members } => {
+            let removed = group_uc.remove(&name, &members)?;
+            notice!("グループ {} から {} 人を削除しました", name, removed);
+        }
+        GroupAction::List { format } => {
+            let groups = group_uc.list()?;
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_groups_json(&groups)),
+                _ => outln!("{}", formatter::format_groups_text(&groups)),
+            }
+        }
+    }
+    Ok(())
+}
+
+const ARCHIVE_MANIFEST: &str = "manifest.json";
+const ARCHIVE_GROUPS: &str = "groups.json";
+const ARCHIVE_THREADS: &str = "threads/";
+
+pub fn handle_export<T: ThreadRepository, M: MessageRepository, G: GroupRepository>(
+    output: &std::path::Path,
+    db: &Database,
+    thread_uc: &ThreadUseCase<T, M>,
+    group_uc: &GroupUseCase<G>,
+) -> anyhow::Result<()> {
+    let ids = thread_uc.all_thread_ids()?;
+    let manifest = ArchiveManifest::new(db.schema_version()?, ids.len());
+    let mut archive = ArchiveWriter::create(output)?;
+    archive.add(ARCHIVE_MANIFEST, &serde_json::to_vec_pretty(&manifest)?)?;
+    let mut messages = 0;
+    for (index, id) in ids.iter().enumerate() {
+        let bundle = thread_uc.export_board_bundle(id)?;
+        messages += bundle.messages.len();
+        let name = format!("{}{:06}.json", ARCHIVE_THREADS, index + 1);
+        archive.add(&name, &serde_json::to_vec(&bundle)?)?;
+    }
+    archive.add(ARCHIVE_GROUPS, &serde_json::to_vec(&group_uc.list()?)?)?;
+    archive.finish()?;
+    notice!(
+        "{} 件の thread と {} 件の message を {} にエクスポートしました",
+        ids.len(),
+        messages,
+        output.display()
+    );
+    Ok(())
+}
+
+/// Imports an archive of `aiboard export` in one go: on any error nothing is
+/// left behind. Prints the IDs of the imported threads.
+pub fn handle_import<T: ThreadRepository, M: MessageRepository, G: GroupRepository>(
+    path: &std::path::Path,
+    db: &Database,
+    thread_uc: &ThreadUseCase<T, M>,
+    group_uc: &GroupUseCase<G>,
+) -> anyhow::Result<()> {
+    let mut archive = ArchiveReader::open(path)?;
+    let manifest: ArchiveManifest = match archive.next_file()? {
+        Some((name, data)) if name == ARCHIVE_MANIFEST => serde_json::from_slice(&data)
+            .with_context(|| tr!("{} のマニフェストを解析できません", path.display()))?,
+        _ => bail!(tr!("{} は aiboard export のアーカイブではありません", path.display())),
+    };
+    manifest.check()?;
+
+    db.savepoint("import")?;
+    let result = import_archive_files(&mut archive, thread_uc, group_uc);
+    let ArchiveImport { threads, messages, skipped, names_dropped } = match result {
+        Ok(imported) => {
+            db.release("import")?;
+            imported
+        }
+        Err(e) => {
+            db.rollback_to("import")?;
+            return Err(e.context(format!("{} の取り込みに失敗しました", path.display())));
+        }
+    };
+    for id in &threads {
+        outln!("{}", id);
+    }
+    notice!("{} 件の thread と {} 件の message を取り込みました", threads.len(), messages);
+    if skipped > 0 {
+        notice!("既にある {} 件の thread はスキップしました", skipped);
+    }
+    if names_dropped > 0 {
+        notice!("名前が使用済みのため名前なしで取り込んだ thread: {} 件", names_dropped);
+    }
+    Ok(())
+}
+
+/// What `import_archive_files` brought in.
+struct ArchiveImport {
+    /// IDs of the imported threads.
+    threads: Vec<String>,
+    messages: usize,
+    /// Threads skipped because they already exist.
+    skipped: usize,
+    /// Threads imported without their name, which another thread here has.
+    names_dropped: usize,
+}
+
+/// Imports the files after the manifest.
+fn import_archive_files<T: ThreadRepository, M: MessageRepository, G: GroupRepository>(
+    archive: &mut ArchiveReader,
+    thread_uc: &ThreadUseCase<T, M>,
+    group_uc: &GroupUseCase<G>,
+) -> anyhow::Result<ArchiveImport> {
+    let (mut threads, mut messages, mut skipped, mut names_dropped) = (Vec::new(), 0, 0, 0);
+    while let Some((name, data)) = archive.next_file()? {
+        if name == ARCHIVE_GROUPS {
+            let groups: Vec<Group> =
+                serde_json::from_slice(&data).with_context(|| tr!("{} を解析できません", name))?;
+            for group in groups {
+                group_uc.add(&group.name, &group.members)?;
+            }
+        } else if name.starts_with(ARCHIVE_THREADS) {
+            let bundle: ThreadBundle =
+                serde_json::from_slice(&data).with_context(|| tr!("{} を解析できません", name))?;
+            match thread_uc.import_board_bundle(bundle)? {
+                Some((thread, imported, name_dropped)) => {
+                    threads.push(thread.id);
+                    messages += imported;
+                    names_dropped += name_dropped as usize;
+                }
+                None => skipped += 1,
+            }
+        }
+    }
+    Ok(ArchiveImport { threads, messages, skipped, names_dropped })
+}
+
+pub fn handle_stats<T: ThreadRepository, M: MessageRepository>(
+    action: StatsAction,
+    stats_uc: &StatsUseCase<T, M>,
+) -> anyhow::Result<()> {
+    match action {
+        StatsAction::Tokens { thread, by_message, format } => {
+            let stats = stats_uc.tokens(&thread)?;
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_token_stats_json(&stats, by_message)),
+                _ => outln!("{}", formatter::format_token_stats_text(&stats, by_message)),
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn handle_search<T: ThreadRepository, R: MessageRepository>(
+    action: SearchAction,
+    message_uc: &MessageUseCase<T, R>,
+) -> anyhow::Result<()> {
+    match action {
+        SearchAction::All {
+            query,
+            limit,
+            full,
+            format,
+            output,
+            append,
+        } => {
+            output::redirect(output.as_deref(), append)?;
+            let groups = message_uc.search_all(&query, Some(limit))?;
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_search_groups_json(&groups)),
+                "jsonl" => out!("{}", formatter::format_jsonl(&groups)),
+                "yaml" | "yml" => out!("{}", formatter::format_yaml(&groups)),
+                _ => outln!(
+                    "{}",
+                    formatter::format_search_groups_text(&groups, full, formatter::color_enabled())
+                ),
+            }
+            let hits: usize = groups.iter().map(|g| g.messages.len()).sum();
+            if hits == limit {
+                notice!("(message は最大 {} 件まで表示しています。--limit で変更できます)", limit);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn handle_index<T: ThreadRepository, R: MessageRepository, E: EmbeddingRepository>(
+    action: IndexAction,
+    message_uc: &MessageUseCase<T, R>,
+    semantic_uc: &SemanticUseCase<E>,
+) -> anyhow::Result<()> {
+    match action {
+        IndexAction::Rebuild => {
+            notice!("全文検索インデックスを再構築中...");
+            let count = message_uc.rebuild_search_index()?;
+            notice!("{} 件の message のインデックスを再構築しました", count);
+        }
+        IndexAction::Status { format } => {
+            let search = message_uc.search_index_status()?;
+            let embeddings = semantic_uc.status()?;
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_index_status_json(&search, &embeddings)),
+                _ => outln!("{}", formatter::format_index_status_text(&search, &embeddings)),
+            }
+            if !search.consistent || search.indexed != search.messages {
+                eprintln!("{}", i18n::tr("全文検索インデックスが message と一致していません。aiboard index rebuild で再構築してください"));
+            }
+            if embeddings.iter().any(|e| e.stale > 0) {
+                notice!("内容が変わった message の埋め込みは aiboard index embed で作り直せます");
+            }
+        }
+        IndexAction::Embed { rebuild, batch_size } => {
+            let provider = HttpEmbeddingProvider::from_env()?;
+            notice!("モデル {} で埋め込みを作成中...", provider.model());
+            let count = semantic_uc.embed(&provider, rebuild, batch_size, |done, total| {
+                notice!("  {}/{}", done, total);
+            })?;
+            notice!("{} 件の message の埋め込みを作成しました", count);
+        }
+    }
+    Ok(())
+}
+
+pub fn handle_db<T: ThreadRepository, R: MessageRepository, A: AuditRepository>(
+    action: DbAction,
+    db: &Database,
+    db_path: Option<&std::path::Path>,
+    message_uc: &MessageUseCase<T, R>,
+    merge_uc: &MergeUseCase<T, R>,
+    audit_uc: &AuditUseCase<A>,
+) -> anyhow::Result<()> {
+    match action {
+        DbAction::Vacuum { format } => {
+            let before = db.disk_size();
+            notice!("データベースを最適化中...");
+            db.vacuum()?;
+            let after = db.disk_size();
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_vacuum_json(before, after)),
+                _ => outln!("{}", formatter::format_vacuum_text(before, after)),
+            }
+        }
+        DbAction::Stats { top, format } => {
+            let stats = db.stats(top)?;
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_db_stats_json(&stats)),
+                _ => outln!("{}", formatter::format_db_stats_text(&stats)),
+            }
+        }
+        DbAction::Doctor { fix, orphans, format } => {
+            let mut check = db.check(orphans)?;
+            if fix && !check.healthy() {
+                if !check.search_index_healthy() {
+                    notice!("全文検索インデックスを再構築中...");
+                    message_uc.rebuild_search_index()?;
+                }
+                if !check.orphans.is_empty() {
+                    let count = db.adopt_orphans()?;
+                    notice!("thread のない message のために {} 件の thread を再作成しました", count);
+                }
+                if !check.dangling.is_empty() {
+                    let count = db.remove_dangling()?;
+                    notice!("存在しない thread・message を指す {} 行を削除しました", count);
+                }
+                check = db.check(orphans)?;
+            }
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_db_check_json(&check)),
+                _ => outln!("{}", formatter::format_db_check_text(&check)),
+            }
+            if check.corrupt() {
+                eprintln!("{}", i18n::tr("DB ファイルが破損しています。aiboard db doctor では修復できないため、バックアップから復元してください"));
+            } else if !check.healthy() {
+                notice!("aiboard db doctor --fix で修復できます");
+            }
+            if !check.healthy() {
+                return Err(DomainError::Database("db doctor found problems".to_string()).into());
+            }
+        }
+        DbAction::Merge { path, prefer_newer, format } => {
+            let same = db_path.is_some_and(|own| {
+                matches!((own.canonicalize(), path.canonicalize()), (Ok(a), Ok(b)) if a == b)
+            });
+            if same {
+                bail!(tr!("取り込み元がこの DB 自身です: {}", path.display()));
+            }
+            notice!("{} を読み込み中...", path.display());
+            let other = BoardCopy::open(&path)?;
+            let conn = other.database().connection();
+            db.savepoint("merge")?;
+            let result = merge_uc
+                .merge_from(&SqliteThreadRepository::new(conn), &SqliteMessageRepository::new(conn), prefer_newer)
+                .and_then(|report| {
+                    if report.changed() {
+                        let mut detail = serde_json::json!(report);
+                        detail["source"] = path.display().to_string().into();
+                        audit_uc.record(AuditOperation::DbMerge, audit_actor(None).as_deref(), &[], Some(detail))?;
+                    }
+                    Ok(report)
+                });
+            let report = match result {
+                Ok(report) => {
+                    db.release("merge")?;
+                    report
+                }
+                Err(e) => {
+                    db.rollback_to("merge")?;
+                    return Err(e.into());
+                }
+            };
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_merge_json(&report)),
+                _ => outln!("{}", formatter::format_merge_text(&report)),
+            }
+            if report.conflicts_kept > 0 && !prefer_newer {
+                notice!("内容の異なる {} 件はこちらを残しました（--prefer-newer で新しい方を採用）", report.conflicts_kept);
+            }
+        }
+        DbAction::Encrypt => {
+            let path = rewritable_db_path(db_path)?;
+            if db.is_encrypted() {
+                bail!(tr!("DB は既に暗号化されています: {}", path.display()));
+            }
+            let key = key::db_key().ok_or_else(|| {
+                DomainError::InvalidInput(i18n::fill(
+                    i18n::tr("鍵を {} か OS のキーリング（service aiboard, account db-key）に設定してください"),
+                    &[&key::KEY_ENV],
+                ))
+            })?;
+            notice!("DB を暗号化中...");
+            db.rewrite(path, Some(&key))?;
+            outln!("{}", i18n::fill(i18n::tr("DB を暗号化しました: {}"), &[&path.display()]));
+            notice!("以前に作成したバックアップ（*.bak.*）は平文のままです。不要なら削除してください");
+        }
+        DbAction::Decrypt => {
+            let path = rewritable_db_path(db_path)?;
+            if !db.is_encrypted() {
+                bail!(tr!("DB は暗号化されていません: {}", path.display()));
+            }
+            notice!("DB を復号中...");
+            db.rewrite(path, None)?;
+            outln!("{}", i18n::fill(i18n::tr("DB を平文に戻しました: {}"), &[&path.display()]));
+        }
+    }
+    Ok(())
+}
+
+/// The board file for `db encrypt` and `db decrypt`, which need both a
+/// file and SQLCipher.
+fn rewritable_db_path(db_path: Option<&std::path::Path>) -> anyhow::Result<&std::path::Path> {
+    if !key::ENCRYPTION {
+        return Err(DomainError::InvalidInput(
+            i18n::tr("暗号化には --features encryption でビルドした aiboard が必要です").to_string(),
+        )
+        .into());
+    }
+    db_path.ok_or_else(|| {
+        DomainError::InvalidInput(i18n::tr("このコマンドは --ephemeral では使えません").to_string()).into()
+    })
+}
+
+pub fn handle_audit<A: AuditRepository>(action: AuditAction, audit_uc: &AuditUseCase<A>) -> anyhow::Result<()> {
+    match action {
+        AuditAction::List { operation, actor, target, after, limit, format } => {
+            let filter = AuditFilter {
+                operation: operation.map(|o| o.parse().map_err(|e: String| anyhow::anyhow!(e))).transpose()?,
+                actor,
+                target,
+                after: parse_datetime_opt("--after", after.as_deref())?,
+            };
+            let entries = audit_uc.list(&filter, limit)?;
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_audit_json(&entries)),
+                _ => outln!("{}", formatter::format_audit_text(&entries)),
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn handle_backup(action: BackupAction, db_path: Option<&std::path::Path>) -> anyhow::Result<()> {
+    let db_path = db_path.ok_or_else(|| {
+        DomainError::InvalidInput(i18n::tr("このコマンドは --ephemeral では使えません").to_string())
+    })?;
+    match action {
+        BackupAction::List { format } => {
+            let summaries: Vec<_> = backup::list_backups(db_path)?.into_iter().map(backup::summarize).collect();
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_backups_json(&summaries)),
+                _ => outln!("{}", formatter::format_backups_text(&summaries)),
+            }
+        }
+        BackupAction::Verify { name, format } => {
+            let verification = backup::verify(find_backup(db_path, &name)?);
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_backup_verification_json(&verification)),
+                _ => outln!("{}", formatter::format_backup_verification_text(&verification)),
+            }
+            if !verification.healthy() {
+                return Err(DomainError::Database("backup verify found problems".to_string()).into());
+            }
+        }
+        BackupAction::Create { label } => {
+            if let Some(label) = label.as_deref().filter(|l| !backup::is_valid_label(l)) {
+                return Err(DomainError::InvalidInput(tr!("ラベルには英数字・-・_ を 64 文字まで使えます: {}", label)).into());
+            }
+            let backup_path = backup::create_backup(db_path, label.as_deref())?;
+            outln!("{}", backup_path.display());
+        }
+        BackupAction::Restore { name } => {
+            let file = find_backup(db_path, &name)?;
+            // Take the backup out first: the safety backup below must not
+            // touch what is being restored.
+            let prepared = backup::prepare_restore(&file)?;
+            // Keep the board being replaced, so that the restore can be undone.
+            if db_path.exists() {
+                let backup_path = backup::create_backup(db_path, None)
+                    .context(tr!("DB バックアップの作成に失敗しました"))?;
+                notice!("バックアップを作成しました: {}", backup_path.display());
+            }
+            prepared.apply(db_path)?;
+            notice!("{} から復元しました", file.name);
+        }
+        BackupAction::Prune { keep, older_than, dry_run } => {
+            let policy = match (keep, older_than) {
+                (None, None) => configured_prune_policy().ok_or_else(|| {
+                    DomainError::InvalidInput(
+                        i18n::tr("--keep か --older-than を指定するか、設定ファイルの [backup] keep / older_than_days を設定してください")
+                            .to_string(),
+                    )
+                })?,
+                _ => PrunePolicy { keep, older_than_days: older_than },
+            };
+            if policy.older_than_days.is_some_and(|days| days < 0) {
+                bail!(tr!("--older-than は 0 以上を指定してください"));
+            }
+            let pruned = backup::prune_backups(db_path, &policy, dry_run)?;
+            for file in &pruned {
+                outln!("{}", file.path.display());
+            }
+            let bytes = formatter::format_bytes(pruned.iter().map(|f| f.bytes).sum());
+            if dry_run {
+                notice!("{} 件のバックアップが削除対象です（{}、dry-run）", pruned.len(), bytes);
+            } else {
+                notice!("{} 件のバックアップを削除しました（{} 解放）", pruned.len(), bytes);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The backup `name` refers to (see `backup::find_backup`).
+fn find_backup(db_path: &std::path::Path, name: &str) -> Result<backup::BackupFile, DomainError> {
+    backup::find_backup(db_path, name)?
+        .ok_or_else(|| DomainError::InvalidInput(tr!("バックアップが見つかりません: {}", name)))
+}
+
+/// The `[backup]` settings, if any are set.
+fn configured_prune_policy() -> Option<PrunePolicy> {
+    let settings = &config::get().backup;
+    let policy = PrunePolicy { keep: settings.keep, older_than_days: settings.older_than_days };
+    (!policy.is_empty()).then_some(policy)
+}
+
+/// Prunes by the `[backup]` settings after a backup was made. The backup
+/// just made is always kept.
+fn auto_prune_backups(db_path: &std::path::Path) -> anyhow::Result<()> {
+    let Some(mut policy) = configured_prune_policy() else {
+        return Ok(());
+    };
+    policy.keep = Some(policy.keep.unwrap_or(0).max(1));
+    let pruned = backup::prune_backups(db_path, &policy, false).context(tr!("古いバックアップの削除に失敗しました"))?;
+    if !pruned.is_empty() {
+        notice!("古いバックアップを {} 件削除しました", pruned.len());
+    }
+    Ok(())
+}
+
+pub fn handle_sync<T: ThreadRepository, M: MessageRepository, S: SyncStateRepository, A: AuditRepository>(
+    action: SyncAction,
+    db: &Database,
+    db_path: Option<&std::path::Path>,
+    merge_uc: &MergeUseCase<T, M>,
+    sync_uc: &SyncUseCase<S>,
+    audit_uc: &AuditUseCase<A>,
+) -> anyhow::Result<()> {
+    let (report, format) = match action {
+        SyncAction::Push { remote, header, bearer, bearer_env, format } => {
+            let remote = sync_remote(&remote, &header, bearer, bearer_env.as_deref(), db_path)?;
+            let name = remote.name();
+            notice!("{} を取得中...", name);
+            let mut snapshot = remote.fetch()?;
+            let merged = {
+                let conn = snapshot.copy.database().connection();
+                MergeUseCase::new(SqliteThreadRepository::new(conn), SqliteMessageRepository::new(conn))
+                    .merge_from(&merge_uc.thread_repo, &merge_uc.message_repo, true)?
+            };
+            if merged.changed() {
+                notice!("{} に送信中...", name);
+                let version = remote.store(&mut snapshot)?;
+                sync_uc.record(&name, SyncDirection::Push, version, &merged)?;
+            }
+            let report = SyncReport { remote: name, direction: SyncDirection::Push, up_to_date: !merged.changed(), merged };
+            (report, format)
+        }
+        SyncAction::Pull { remote, header, bearer, bearer_env, format } => {
+            let remote = sync_remote(&remote, &header, bearer, bearer_env.as_deref(), db_path)?;
+            let name = remote.name();
+            let merged = if sync_uc.pulled_already(&name, remote.version()?.as_deref())? {
+                MergeReport::default()
+            } else {
+                notice!("{} を取得中...", name);
+                let snapshot = remote.fetch()?;
+                let conn = snapshot.copy.database().connection();
+                db.savepoint("sync")?;
+                let result = merge_uc
+                    .merge_from(&SqliteThreadRepository::new(conn), &SqliteMessageRepository::new(conn), true)
+                    .and_then(|merged| {
+                        let version = snapshot.version().map(str::to_string);
+                        sync_uc.record(&name, SyncDirection::Pull, version, &merged)?;
+                        if merged.changed() {
+                            let mut detail = serde_json::json!(merged);
+                            detail["remote"] = name.clone().into();
+                            audit_uc.record(AuditOperation::SyncPull, audit_actor(None).as_deref(), &[], Some(detail))?;
+                        }
+                        Ok(merged)
+                    });
+                match result {
+                    Ok(merged) => {
+                        db.release("sync")?;
+                        merged
+                    }
+                    Err(e) => {
+                        db.rollback_to("sync")?;
+                        return Err(e.into());
+                    }
+                }
+            };
+            let report = SyncReport { remote: name, direction: SyncDirection::Pull, up_to_date: !merged.changed(), merged };
+            (report, format)
+        }
+        SyncAction::Status { format } => {
+            let states = sync_uc.status()?;
+            match format.as_str() {
+                "json" => outln!("{}", formatter::format_sync_status_json(&states)),
+                _ => outln!("{}", formatter::format_sync_status_text(&states)),
+            }
+            return Ok(());
+        }
+    };
+    match format.as_str() {
+        "json" => outln!("{}", formatter::format_sync_json(&report)),
+        _ => outln!("{}", formatter::format_sync_text(&report)),
+    }
+    Ok(())
+}
+
+/// The remote of `sync push` and `sync pull`, which must not be this
+/// board's own file.
+fn sync_remote(
+    raw: &str,
+    headers: &[String],
+    bearer: Option<String>,
+    bearer_env: Option<&str>,
+    db_path: Option<&std::path::Path>,
+) -> anyhow::Result<Remote> {
+    let remote = Remote::parse(raw, fetch_options(headers, bearer, bearer_env)?);
+    if let Remote::File(path) = &remote {
+        let same = db_path.is_some_and(|own| {
+            matches!((own.canonicalize(), path.canonicalize()), (Ok(a), Ok(b)) if a == b)
+        });
+        if same {
+            bail!(tr!("共有先がこの DB 自身です: {}", path.display()));
+        }
+    }
+    Ok(remote)
+}
+
+pub fn handle_schema(command: &[String]) -> anyhow::Result<()> {
+    if command.is_empty() {
+        outln!("{}", formatter::format_schema_commands(&schema::COMMANDS));
+        return Ok(());
+    }
+    let name = command.join(" ");
+    let document = schema::schema_for(&name).ok_or_else(|| {
+        anyhow::anyhow!(tr!("JSON スキーマが定義されていないコマンドです: {}（aiboard schema で一覧を表示）", name))
+    })?;
+    outln!("{}", formatter::format_schema_json(&document));
+    Ok(())
+}
+
+pub fn handle_completions(shell: clap_complete::Shell) -> anyhow::Result<()> {
+    use clap::CommandFactory;
+
+    let mut cmd = i18n::localize_command(Cli::command());
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut cmd, "aiboard", &mut script);
+    out!("{}", String::from_utf8_lossy(&script));
+    Ok(())
 }
 
 pub fn handle_util(action: UtilAction) -> anyhow::Result<()> {
@@ -627,19 +2420,19 @@ pub fn handle_util(action: UtilAction) -> anyhow::Result<()> {
             use rand::thread_rng;
 
             if items.is_empty() {
-                anyhow::bail!("選択対象が指定されていません");
+                anyhow::bail!(tr!("選択対象が指定されていません"));
             }
 
             if count == 0 {
-                anyhow::bail!("選択数は1以上を指定してください");
+                anyhow::bail!(tr!("選択数は1以上を指定してください"));
             }
 
             if count > items.len() {
-                anyhow::bail!(
+                anyhow::bail!(tr!(
                     "選択数（{}）が要素数（{}）を超えています",
                     count,
                     items.len()
-                );
+                ));
             }
 
             let mut rng = thread_rng();
@@ -649,7 +2442,7 @@ pub fn handle_util(action: UtilAction) -> anyhow::Result<()> {
                 .collect();
 
             for item in selected {
-                println!("{}", item);
+                outln!("{}", item);
             }
         }
     }
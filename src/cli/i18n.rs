@@ -0,0 +1,767 @@
+//! Language of help text, stderr notices and error messages.
+//!
+//! Japanese is the source language: messages are written in Japanese in the
+//! code and looked up in `CATALOG` by that text. A message without an
+//! English entry is shown in Japanese, so a missing translation never hides
+//! output.
+
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use clap::Command;
+
+use crate::domain::error::DomainError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    Ja,
+    En,
+}
+
+impl Lang {
+    /// Accepts `ja`/`en` and locale-style values such as `en_US.UTF-8`.
+    pub fn parse(value: &str) -> Option<Lang> {
+        let value = value.trim().to_ascii_lowercase();
+        let code = value.split(['_', '-', '.']).next().unwrap_or("");
+        match code {
+            "ja" => Some(Lang::Ja),
+            "en" => Some(Lang::En),
+            _ => None,
+        }
+    }
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+pub fn set_lang(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+pub fn lang() -> Lang {
+    LANG.get().copied().unwrap_or(Lang::Ja)
+}
+
+/// Picks the language before clap runs, so that `--help` is translated too:
+/// `--lang` on the command line wins over `AIBOARD_LANG`, then Japanese.
+pub fn detect(args: &[OsString]) -> Lang {
+    let mut iter = args.iter().skip(1).map(|a| a.to_string_lossy());
+    let mut from_args = None;
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--lang" {
+            from_args = iter.next().and_then(|v| Lang::parse(&v));
+        } else if let Some(value) = arg.strip_prefix("--lang=") {
+            from_args = Lang::parse(value);
+        }
+    }
+    from_args
+        .or_else(|| std::env::var("AIBOARD_LANG").ok().and_then(|v| Lang::parse(&v)))
+        .unwrap_or(Lang::Ja)
+}
+
+/// The message `ja` in the current language.
+pub fn tr(ja: &'static str) -> &'static str {
+    if lang() == Lang::Ja {
+        return ja;
+    }
+    CATALOG
+        .iter()
+        .find(|(source, _)| *source == ja)
+        .map(|(_, en)| *en)
+        .unwrap_or(ja)
+}
+
+/// Replaces each `{}` in `template` with the next of `args`; `{N}` takes the
+/// N-th argument, for translations that reorder them.
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut next = 0;
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let index = after.find('}').and_then(|end| {
+            let key = &after[..end];
+            let index = if key.is_empty() {
+                next += 1;
+                Some(next - 1)
+            } else {
+                key.parse::<usize>().ok()
+            };
+            index.map(|i| (i, end))
+        });
+        match index.and_then(|(i, end)| args.get(i).map(|arg| (arg, end))) {
+            Some((arg, end)) => {
+                out.push_str(&arg.to_string());
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Translates the about and help texts of `cmd` and its subcommands.
+pub fn localize_command(cmd: Command) -> Command {
+    if lang() == Lang::Ja {
+        return cmd;
+    }
+    let cmd = match cmd.get_about().map(|s| s.to_string()) {
+        Some(about) => cmd.about(translate_owned(about)),
+        None => cmd,
+    };
+    let cmd = cmd.mut_args(|arg| match arg.get_help().map(|s| s.to_string()) {
+        Some(help) => arg.help(translate_owned(help)),
+        None => arg,
+    });
+    cmd.mut_subcommands(localize_command)
+}
+
+fn translate_owned(text: String) -> String {
+    CATALOG
+        .iter()
+        .find(|(source, _)| *source == text)
+        .map(|(_, en)| en.to_string())
+        .unwrap_or(text)
+}
+
+/// `e` as shown after the `エラー:` prefix.
+pub fn domain_error(e: &DomainError) -> String {
+    if lang() == Lang::Ja {
+        return e.to_string();
+    }
+    match e {
+        DomainError::ThreadNotFound(id) => format!("thread not found: {}", id),
+        DomainError::MessageNotFound(id) => format!("message not found: {}", id),
+        DomainError::DraftNotFound(id) => format!("draft not found: {}", id),
+        DomainError::ThreadLocked(id) => {
+            format!("thread {} is locked (only allowed senders can post)", id)
+        }
+        DomainError::AmbiguousShortId(id, n) => {
+            format!("short ID '{}' is ambiguous: matches {} records", id, n)
+        }
+        DomainError::Database(m) => format!("database error: {}", m),
+        DomainError::Busy(m) => format!("database is locked (another process is writing): {}", m),
+        DomainError::Conflict(m) => {
+            format!("the remote changed after it was read (run sync pull, then push again): {}", m)
+        }
+        DomainError::InvalidInput(m) => format!("invalid input: {}", m),
+        DomainError::Network(m) => format!("network error: {}", m),
+        DomainError::Parse(m) => format!("parse error: {}", m),
+        DomainError::Io(m) => format!("I/O error: {}", m),
+    }
+}
+
+/// Japanese source text and its English translation.
+const CATALOG: &[(&str, &str)] = &[
+    // Errors and warnings
+    ("エラー: {}", "Error: {}"),
+    ("警告: thread {} はクローズされています", "warning: thread {} is closed"),
+    (
+        "全文検索インデックスが message と一致していません。aiboard index rebuild で再構築してください",
+        "The full-text index does not match the messages. Rebuild it with aiboard index rebuild",
+    ),
+    ("hook 設定を {} に書き込みます。続行しますか？ [y/N] ", "Write hook settings to {}? [y/N] "),
+    ("skill ファイルを {} に書き込みます。続行しますか？ [y/N] ", "Write the skill file to {}? [y/N] "),
+    ("{} はファイルのパスではありません", "{} is not a file path"),
+    ("{} への書き込みに失敗しました", "Failed to write to {}"),
+    ("{} の読み込みに失敗しました", "Failed to read {}"),
+    ("JSON の行を解析できません: {}", "Cannot parse the JSON line: {}"),
+    ("閉じられていない ' があります", "Unclosed '"),
+    ("閉じられていない \" があります", "Unclosed \""),
+    ("stdin からの読み取りに失敗しました", "Failed to read from stdin"),
+    ("入力が 1MB の上限を超えています（{} バイト）", "The input exceeds the 1MB limit ({} bytes)"),
+    ("入力に NUL バイトが含まれています", "The input contains a NUL byte"),
+    ("入力が有効な UTF-8 ではありません", "The input is not valid UTF-8"),
+    ("内容が 1MB の上限を超えています（{} バイト）", "The content exceeds the 1MB limit ({} bytes)"),
+    ("内容に NUL バイトが含まれています", "The content contains a NUL byte"),
+    (
+        "{} の日時形式が不正です: {}（ISO 8601、YYYY-MM-DD、today、yesterday、2d のような相対指定）",
+        "Invalid date/time for {}: {} (ISO 8601, YYYY-MM-DD, today, yesterday, or relative like 2d)",
+    ),
+    ("--meta は key=value 形式で指定してください: {}", "--meta must be key=value: {}"),
+    ("--meta のキーが空です: {}", "--meta has an empty key: {}"),
+    ("--meta-path は '$.path=value' 形式で指定してください: {}", "--meta-path must be '$.path=value': {}"),
+    ("--meta-path の JSON パスは $ で始まる必要があります: {}", "The JSON path of --meta-path must start with $: {}"),
+    ("期間には単位（s, m, h, d, w）が必要です: {}", "A duration needs a unit (s, m, h, d, w): {}"),
+    ("期間の形式が不正です: {}", "Invalid duration: {}"),
+    ("期間の単位が不正です（s, m, h, d, w のいずれか）: {}", "Invalid duration unit (one of s, m, h, d, w): {}"),
+    ("期間が長すぎます: {}", "Duration too long: {}"),
+    ("--fields は {} 形式では使えません", "--fields cannot be used with the {} format"),
+    ("不明な項目です: {}（{}）", "Unknown field: {} ({})"),
+    ("--template と --format {} は同時に指定できません", "--template cannot be combined with --format {}"),
+    ("--template と --fields は同時に指定できません", "--template cannot be combined with --fields"),
+    ("--template が不正です: {}", "Invalid --template: {}"),
+    (
+        "--sender を指定するか、thread set-default で既定の送信者を設定してください",
+        "Pass --sender or set a default sender with thread set-default",
+    ),
+    ("--metadata は有効な JSON である必要があります", "--metadata must be valid JSON"),
+    (
+        "--metadata に msg_type が既に含まれています。--type と --metadata の msg_type を同時に指定できません",
+        "--metadata already has a msg_type; --type and a msg_type in --metadata cannot be combined",
+    ),
+    ("--metadata はオブジェクト形式の JSON である必要があります", "--metadata must be a JSON object"),
+    ("--since-checkpoint には --thread が必要です", "--since-checkpoint needs --thread"),
+    ("不明な検索対象です: {}（content, metadata, all）", "Unknown search target: {} (content, metadata, all)"),
+    ("不明なグループ化の単位です: {}（thread）", "Unknown grouping: {} (thread)"),
+    ("--fields と --group-by thread は同時に指定できません", "--fields cannot be combined with --group-by thread"),
+    ("--template と --group-by thread は同時に指定できません", "--template cannot be combined with --group-by thread"),
+    ("Ctrl-C ハンドラーの設定に失敗しました", "Failed to set the Ctrl-C handler"),
+    ("未対応の出力形式です: {}（markdown, json, jsonl）", "Unsupported output format: {} (markdown, json, jsonl)"),
+    (
+        "未対応の並び順です: {}（created, updated, title, messages）",
+        "Unsupported sort order: {} (created, updated, title, messages)",
+    ),
+    ("--inactive-days は 0 以上を指定してください", "--inactive-days must be 0 or more"),
+    ("未対応の --messages です: {}（none, all, pinned）", "Unsupported --messages: {} (none, all, pinned)"),
+    ("{} は thread バンドルとして解析できません", "{} cannot be parsed as a thread bundle"),
+    ("メタデータは有効な JSON である必要があります", "The metadata must be valid JSON"),
+    ("未対応のファイル形式です: {}（html, md, txt）", "Unsupported file format: {} (html, md, txt)"),
+    ("環境変数 {} にトークンが設定されていません", "No token is set in the environment variable {}"),
+    ("日数を指定するか、設定ファイルの [cleanup] days を設定してください", "Pass the number of days or set [cleanup] days in the config file"),
+    ("DB バックアップの作成に失敗しました", "Failed to back up the database"),
+    ("確認入力の読み取りに失敗しました", "Failed to read the confirmation"),
+    (".claude ディレクトリの作成に失敗しました", "Failed to create the .claude directory"),
+    ("既存の設定ファイルの読み取りに失敗しました", "Failed to read the existing settings file"),
+    ("設定ファイルの書き込みに失敗しました", "Failed to write the settings file"),
+    ("skills ディレクトリの作成に失敗しました", "Failed to create the skills directory"),
+    ("skill ファイルの書き込みに失敗しました", "Failed to write the skill file"),
+    ("通知の表示に失敗しました: {}", "Failed to show the notification: {}"),
+    ("notify コマンドは Windows のみ対応しています", "The notify command is only supported on Windows"),
+    ("{} のマニフェストを解析できません", "Cannot parse the manifest of {}"),
+    ("{} は aiboard export のアーカイブではありません", "{} is not an archive written by aiboard export"),
+    ("{} を解析できません", "Cannot parse {}"),
+    ("取り込み元がこの DB 自身です: {}", "The source is this database itself: {}"),
+    ("DB は既に暗号化されています: {}", "The database is already encrypted: {}"),
+    ("DB は暗号化されていません: {}", "The database is not encrypted: {}"),
+    ("ラベルには英数字・-・_ を 64 文字まで使えます: {}", "A label may have up to 64 letters, digits, - and _: {}"),
+    ("--older-than は 0 以上を指定してください", "--older-than must be 0 or more"),
+    ("バックアップが見つかりません: {}", "Backup not found: {}"),
+    ("古いバックアップの削除に失敗しました", "Failed to delete old backups"),
+    ("共有先がこの DB 自身です: {}", "The remote is this database itself: {}"),
+    (
+        "JSON スキーマが定義されていないコマンドです: {}（aiboard schema で一覧を表示）",
+        "No JSON Schema is defined for the command: {} (list them with aiboard schema)",
+    ),
+    ("選択対象が指定されていません", "No items to choose from"),
+    ("選択数は1以上を指定してください", "The count must be 1 or more"),
+    ("選択数（{}）が要素数（{}）を超えています", "The count ({}) exceeds the number of items ({})"),
+    // Notices
+    ("(全文を表示するには --full を付けてください)", "(add --full to show the full content)"),
+    ("(続きは --offset {} で表示できます)", "(show more with --offset {})"),
+    ("(続きを読むには --cursor {} を付けてください)", "(read more with --cursor {})"),
+    (
+        "(message は最大 {} 件まで表示しています。--limit で変更できます)",
+        "(showing up to {} messages; change with --limit)",
+    ),
+    ("@{}: {}件の未読メンションがあります", "@{}: {} unread mention(s)"),
+    ("thread {} の購読者: {}", "subscribers of thread {}: {}"),
+    ("DEBUG: hook入力を {} に保存", "DEBUG: saved hook input to {}"),
+    ("draft {} を破棄しました", "Discarded draft {}"),
+    ("hook 設定を {} に書き込みました", "Wrote hook settings to {}"),
+    ("skill ファイルを {} に書き込みました", "Wrote the skill file to {}"),
+    ("session {} の {} 件の message を削除しました", "Deleted {1} message(s) of session {0}"),
+    ("thread {} から {} 件のタグを削除しました", "Removed {1} tag(s) from thread {0}"),
+    ("thread {} と {} 件の message を削除しました", "Deleted thread {} and {} message(s)"),
+    ("thread {} に {} 件のタグを追加しました", "Added {1} tag(s) to thread {0}"),
+    ("thread {} のタイトルを「{}」に変更しました", "Renamed thread {} to \"{}\""),
+    ("thread {} のピン留めを解除しました", "Unpinned thread {}"),
+    ("thread {} のフェーズを {} に設定しました", "Set the phase of thread {} to {}"),
+    ("thread {} のフェーズを解除しました", "Cleared the phase of thread {}"),
+    ("thread {} のメタデータを更新しました", "Updated the metadata of thread {}"),
+    ("thread {} のロックを解除しました", "Unlocked thread {}"),
+    ("thread {} の保持ポリシーを解除しました", "Cleared the retention policy of thread {}"),
+    ("thread {} の保持期間を {} 日に設定しました", "Set the retention of thread {} to {} days"),
+    ("thread {} の取得元を再取得中...", "Refetching the source of thread {}..."),
+    ("thread {} の名前を {} に設定しました", "Named thread {} {}"),
+    ("thread {} の既定値: sender={} role={}", "Defaults of thread {}: sender={} role={}"),
+    ("thread {} をクローズしました", "Closed thread {}"),
+    ("thread {} をピン留めしました", "Pinned thread {}"),
+    ("thread {} をロックしました（投稿不可）", "Locked thread {} (no posting)"),
+    ("thread {} をロックしました（投稿可: {}）", "Locked thread {} (allowed: {})"),
+    ("thread {} を再オープンしました", "Reopened thread {}"),
+    ("thread {} を削除しました", "Deleted thread {}"),
+    ("thread {} を直近 {} 件のみ保持するよう設定しました", "Thread {} now keeps only the last {} messages"),
+    ("thread {} を監視中... (Ctrl-C で終了)", "Watching thread {}... (Ctrl-C to stop)"),
+    (
+        "thread {} を {} 件の message とともに {} にエクスポートしました",
+        "Exported thread {} with {} message(s) to {}",
+    ),
+    (
+        "thread {} を複製しました（{} 件の message をコピー）",
+        "Cloned thread {} ({} message(s) copied)",
+    ),
+    ("thread {} ({}) から {} 件の message を削除しました", "Deleted {2} message(s) from thread {0} ({1})"),
+    ("message {} → {} を {} としてリンクしました", "Linked message {} → {} as {}"),
+    ("{} が thread {} を購読しました", "{} subscribed to thread {}"),
+    ("{} の thread {} の購読を解除しました", "{} unsubscribed from thread {}"),
+    ("{} は thread {} を購読していません", "{} is not subscribed to thread {}"),
+    ("{} を GitHub API から取得中...", "Fetching {} from the GitHub API..."),
+    ("{} を取得中...", "Fetching {}..."),
+    (
+        "{} を thread {} として取り込みました（{} 件の message）",
+        "Imported {} as thread {} ({} message(s))",
+    ),
+    (
+        "{} ページを取得して thread {} として保存しました（{} 件の message）",
+        "Fetched {} page(s) and saved them as thread {} ({} message(s))",
+    ),
+    (
+        "取得して thread {} として保存しました（{} 件の message）",
+        "Fetched and saved as thread {} ({} message(s))",
+    ),
+    ("{} 件の message を thread {} に移動しました", "Moved {} message(s) to thread {}"),
+    ("{} 件の message を {} にエクスポートしました", "Exported {} message(s) to {}"),
+    ("{} 件の message を取り込みました", "Imported {} message(s)"),
+    (
+        "thread {} はロックされているため hook イベントを取り込みませんでした",
+        "Skipped the hook event: thread {} is locked",
+    ),
+    ("{} 件の message のインデックスを再構築しました", "Rebuilt the index of {} message(s)"),
+    ("{} 件の message の埋め込みを作成しました", "Created embeddings for {} message(s)"),
+    (
+        "{} 件の message は埋め込みが未作成のため検索対象外です（aiboard index embed で作成）",
+        "{} message(s) have no embedding and were not searched (create them with aiboard index embed)",
+    ),
+    ("{} 件の thread がクローズ対象です（dry-run）", "{} thread(s) would be closed (dry-run)"),
+    ("{} 件の thread をクローズしました", "Closed {} thread(s)"),
+    ("{} 件のメンションを対応済みにしました", "Marked {} mention(s) as handled"),
+    ("{} 日より古い {} 件の message を削除しました", "Deleted {1} message(s) older than {0} days"),
+    (
+        "「{}」に一致する message はありません。近い表記の結果を表示します: {}",
+        "No message matches \"{}\". Showing results for similar terms: {}",
+    ),
+    (
+        "重複する message {} が既に存在するため投稿をスキップしました",
+        "Skipped posting because duplicate message {} already exists",
+    ),
+    ("グループ {} から {} 人を削除しました", "Removed {1} member(s) from group {0}"),
+    ("グループ {} に {} 人を追加しました", "Added {1} member(s) to group {0}"),
+    ("バックアップを作成しました: {}", "Created a backup: {}"),
+    ("古いバックアップを {} 件削除しました", "Removed {} old backup(s)"),
+    ("{} から復元しました", "Restored from {}"),
+    ("{} 件のバックアップが削除対象です（{}、dry-run）", "{} backup(s) would be removed ({}, dry run)"),
+    ("{} 件のバックアップを削除しました（{} 解放）", "Removed {} backup(s) ({} freed)"),
+    (
+        "--keep か --older-than を指定するか、設定ファイルの [backup] keep / older_than_days を設定してください",
+        "Give --keep or --older-than, or set [backup] keep / older_than_days in the config file",
+    ),
+    ("モデル {} で埋め込みを作成中...", "Creating embeddings with model {}..."),
+    ("中止しました", "Aborted"),
+    (
+        "会話構造を認識できなかったため、ページ全体を 1 件の message として保存します",
+        "No conversation structure found; saving the whole page as one message",
+    ),
+    (
+        "会話構造を認識できなかったため、文書全体を 1 件の message として保存します",
+        "No conversation structure found; saving the whole document as one message",
+    ),
+    ("保持ポリシーにより合計 {} 件の message を削除しました", "Deleted {} message(s) in total by retention policies"),
+    ("全スレッドを監視中... (Ctrl-C で終了)", "Watching all threads... (Ctrl-C to stop)"),
+    ("全文検索インデックスを再構築中...", "Rebuilding the full-text index..."),
+    ("データベースを最適化中...", "Compacting the database..."),
+    ("{} 行のコマンドが失敗しました", "{} command line(s) failed"),
+    ("DB オープン", "DB open"),
+    ("マイグレーション確認", "Migration check"),
+    ("クエリ（{} 件）", "Queries ({})"),
+    ("整形・出力", "Formatting and output"),
+    ("合計", "Total"),
+    (
+        "batch の各行では --data-dir・--db・--ephemeral は指定できません（batch 自体に指定してください）",
+        "--data-dir, --db and --ephemeral cannot be given on a batch line (give them to batch itself)",
+    ),
+    ("このコマンドは batch では実行できません", "This command cannot be run in batch"),
+    ("DB サイズ: {} → {}（{} 削減）", "Database size: {} -> {} ({} reclaimed)"),
+    ("ファイル: {}（WAL {}、空きページ {}）", "File: {} (WAL {}, free pages {})"),
+    ("スキーマバージョン: {}", "Schema version: {}"),
+    ("テーブル:", "Tables:"),
+    ("インデックス:", "Indexes:"),
+    ("大きい thread:", "Largest threads:"),
+    ("大きい message:", "Largest messages:"),
+    ("整合性チェック: 正常", "Integrity check: ok"),
+    ("整合性チェック: 異常（{} 件）", "Integrity check: {} problem(s)"),
+    ("全文検索: {}（message {} 件 / インデックス {} 件）", "Full-text index: {} ({} messages / {} indexed)"),
+    ("正常", "ok"),
+    ("不整合", "inconsistent"),
+    ("thread のない message: なし", "Messages without a thread: none"),
+    (
+        "thread のない message: {} 件（thread {} 件）",
+        "Messages without a thread: {} (in {} missing thread(s))",
+    ),
+    (
+        "thread のない message のために {} 件の thread を再作成しました",
+        "Recreated {} thread(s) for messages without a thread",
+    ),
+    ("存在しない thread・message を指す行: なし", "Rows pointing at a missing thread or message: none"),
+    (
+        "存在しない thread・message を指す行: {} 行",
+        "Rows pointing at a missing thread or message: {}",
+    ),
+    (
+        "存在しない thread・message を指す {} 行を削除しました",
+        "Removed {} row(s) pointing at a missing thread or message",
+    ),
+    ("参照整合性の強制: {}", "Strict integrity: {}"),
+    ("有効", "on"),
+    ("無効", "off"),
+    ("aiboard db doctor --fix で修復できます", "Run aiboard db doctor --fix to repair"),
+    (
+        "{} 件の thread と {} 件の message を {} にエクスポートしました",
+        "Exported {} thread(s) and {} message(s) to {}",
+    ),
+    ("{} 件の thread と {} 件の message を取り込みました", "Imported {} thread(s) and {} message(s)"),
+    ("既にある {} 件の thread はスキップしました", "Skipped {} thread(s) that already exist"),
+    ("{} を読み込み中...", "Reading {}..."),
+    (
+        "内容の異なる {} 件はこちらを残しました（--prefer-newer で新しい方を採用）",
+        "Kept this board's copy of {} differing item(s) (use --prefer-newer to take the newer one)",
+    ),
+    ("thread: {} 件追加、{} 件更新", "Threads: {} added, {} updated"),
+    ("message: {} 件追加、{} 件更新", "Messages: {} added, {} updated"),
+    ("内容が異なりこちらを残したもの: {} 件", "Differing, kept this board's copy: {}"),
+    (
+        "名前が使用済みのため名前なしで取り込んだ thread: {} 件",
+        "Threads imported without their name (already in use): {}",
+    ),
+    ("リンク: {} 件追加", "Links: {} added"),
+    ("{} に送信中...", "Sending to {}..."),
+    ("{}: {} と同期済みです", "{}: already in sync with {}"),
+    (
+        "内容が異なり受け取り側が新しいか同時刻のため残したもの: {} 件",
+        "Differing, kept the receiving side's newer or same-age copy: {}",
+    ),
+    ("同期した共有先はありません", "No remotes synced yet"),
+    ("記録された操作はありません", "No operations recorded"),
+    ("  {}: {}（thread {} 件・message {} 件）", "  {}: {} ({} threads, {} messages)"),
+    ("バックアップはありません", "No backups"),
+    ("thread {} 件・message {} 件", "{} threads, {} messages"),
+    ("読み込めません: {}", "Unreadable: {}"),
+    ("{}: 異常（{} 件）", "{}: damaged ({} problem(s))"),
+    (
+        "{}: 正常（スキーマ v{}、thread {} 件・message {} 件）",
+        "{}: healthy (schema v{}, {} threads, {} messages)",
+    ),
+    ("DB を暗号化中...", "Encrypting the database..."),
+    ("DB を復号中...", "Decrypting the database..."),
+    ("DB を暗号化しました: {}", "Encrypted the database: {}"),
+    ("DB を平文に戻しました: {}", "Decrypted the database: {}"),
+    (
+        "以前に作成したバックアップ（*.bak.*）は平文のままです。不要なら削除してください",
+        "Backups made earlier (*.bak.*) are still plaintext; delete them if no longer needed",
+    ),
+    (
+        "鍵を {} か OS のキーリング（service aiboard, account db-key）に設定してください",
+        "Set the key in {} or the OS keyring (service aiboard, account db-key)",
+    ),
+    (
+        "暗号化には --features encryption でビルドした aiboard が必要です",
+        "Encryption needs aiboard built with --features encryption",
+    ),
+    ("このコマンドは --ephemeral では使えません", "This command cannot be used with --ephemeral"),
+    (
+        "DB ファイルが破損しています。aiboard db doctor では修復できないため、バックアップから復元してください",
+        "The database file is corrupt. aiboard db doctor cannot repair it; restore a backup",
+    ),
+    (
+        "内容が変わった message の埋め込みは aiboard index embed で作り直せます",
+        "Recreate embeddings of edited messages with aiboard index embed",
+    ),
+    ("前回の取得から変更はありません", "No changes since the last fetch"),
+    ("差分を {} 件の message として追加しました", "Added the changes as {} message(s)"),
+    ("監視を終了しました", "Stopped watching"),
+    // Help
+    ("エージェント間通信と会話ログの永続化", "Inter-agent messaging and conversation log storage"),
+    ("message の管理（投稿・読み取り・検索・更新）", "Manage messages (post, read, search, update)"),
+    ("thread の管理（作成・一覧・削除・取得）", "Manage threads (create, list, delete, fetch)"),
+    ("hook イベントから会話ログを取り込む", "Import conversation logs from hook events"),
+    ("古いデータのクリーンアップ", "Clean up old data"),
+    ("hook と skill の設定", "Configure hooks and the skill"),
+    ("メンショングループの管理（@team:<name>）", "Manage mention groups (@team:<name>)"),
+    ("thread の統計情報", "Thread statistics"),
+    ("thread と message の横断検索", "Search across threads and messages"),
+    ("全文検索インデックスの管理", "Manage the full-text index"),
+    ("データベースファイルの保守", "Maintain the database file"),
+    (
+        "VACUUM と全文検索インデックスの最適化で DB ファイルを縮小し、削減量を表示する",
+        "Shrink the database file with VACUUM and full-text index optimization, and show the space reclaimed",
+    ),
+    (
+        "ファイルサイズ・テーブルごとの行数とサイズ・インデックスサイズ・大きい thread と message を表示する",
+        "Show the file size, rows and size per table, index sizes, and the largest threads and messages",
+    ),
+    ("表示する大きい thread と message の数", "Number of largest threads and messages to show"),
+    (
+        "DB の整合性・全文検索インデックス・thread のない message・存在しない thread や message を指す行を検査する（問題があれば終了コード 9）",
+        "Check database integrity, the full-text index, messages without a thread and rows pointing at a missing thread or message (exit code 9 on problems)",
+    ),
+    (
+        "修復できる問題を修復する（インデックスの再構築、失われた thread の再作成、存在しない thread や message を指す行の削除）",
+        "Repair what can be repaired (rebuild the index, recreate missing threads, remove rows pointing at a missing thread or message)",
+    ),
+    (
+        "thread のない message と存在しない thread や message を指す行だけを検査する（DB 全体を読む整合性チェックと全文検索の検査を省く）",
+        "Only check for messages without a thread and rows pointing at a missing thread or message (skip the integrity and full-text checks, which read the whole file)",
+    ),
+    (
+        "別の aiboard DB の thread・message・リンクを ID で突き合わせて取り込む",
+        "Import threads, messages and links from another aiboard database, matched by ID",
+    ),
+    (
+        "取り込む DB ファイルのパス（読み取りのみで、書き換えない）",
+        "Path of the database file to import from (only read, never written)",
+    ),
+    (
+        "両方にある thread・message は updated_at が新しい方で上書きする（省略時はこちらを残す）",
+        "Overwrite threads and messages found on both boards with the one updated last (default: keep this board's)",
+    ),
+    (
+        "DB バックアップ（aiboard.db.bak.*）の作成と管理",
+        "Create and manage database backups (aiboard.db.bak.*)",
+    ),
+    ("今の DB のバックアップを作成し、そのパスを表示する", "Back up the database now and print the backup's path"),
+    (
+        "ファイル名に付けるラベル（英数字・-・_。restore などでも名前の代わりに使える）",
+        "Label added to the file name (letters, digits, - and _; usable in place of the name in restore and others)",
+    ),
+    (
+        "古いバックアップを削除し、削除したファイルを表示する（省略時は設定ファイルの [backup] keep / older_than_days）",
+        "Remove old backups and print the removed files (defaults: [backup] keep / older_than_days in the config file)",
+    ),
+    (
+        "バックアップを新しい順に、サイズ・経過時間・thread と message の件数つきで表示する",
+        "List backups newest first, with size, age and thread and message counts",
+    ),
+    (
+        "バックアップを読み取り専用で開いて整合性チェックを行う（問題があれば終了コード 9）",
+        "Check the integrity of a backup without modifying it (exit code 9 on problems)",
+    ),
+    (
+        "バックアップの内容で DB を置き換える（圧縮されたバックアップは展開し、現在の DB は先にバックアップする）",
+        "Replace the database with a backup (compressed backups are unpacked; the current database is backed up first)",
+    ),
+    (
+        "バックアップのファイル名、日時（例: 20240101093000）、ラベルのいずれか",
+        "File name, timestamp (e.g. 20240101093000) or label of the backup",
+    ),
+    ("新しい方から N 件は常に残す", "Always keep the N newest"),
+    (
+        "残す分を除き、N 日より古いものだけを削除する（省略時は残す分以外すべて）",
+        "Of the rest, remove only those older than N days (default: all of the rest)",
+    ),
+    (
+        "共有先（共有ドライブ上の DB ファイルか HTTP エンドポイント）とボードを同期する",
+        "Sync the board with a remote (a DB file on a shared drive or an HTTP endpoint)",
+    ),
+    (
+        "このボードの thread・message・リンクを共有先に送る（両方にあるものは updated_at が新しい方を残す）",
+        "Send this board's threads, messages and links to the remote (where both have one, the one updated last wins)",
+    ),
+    (
+        "共有先の thread・message・リンクをこのボードに取り込む（両方にあるものは updated_at が新しい方を残す）",
+        "Bring the remote's threads, messages and links into this board (where both have one, the one updated last wins)",
+    ),
+    ("共有先ごとの最後の push と pull を表示する", "Show the last push and pull of each remote"),
+    (
+        "投稿・更新・削除・cleanup・merge の記録（監査ログ）",
+        "Record of posts, updates, deletions, cleanups and merges (audit log)",
+    ),
+    ("監査ログを新しい順に表示する", "Show the audit log, newest first"),
+    (
+        "この操作のみ（message.post, cleanup.age など）",
+        "Only this operation (message.post, cleanup.age, ...)",
+    ),
+    ("この実行者（sender）の操作のみ", "Only operations by this actor (sender)"),
+    (
+        "この ID（前方一致）の message か thread に関わる操作のみ",
+        "Only operations affecting the message or thread with this ID (prefix match)",
+    ),
+    (
+        "この日時より後の操作のみ（ISO 8601、YYYY-MM-DD、yesterday、2d など）",
+        "Only operations after this time (ISO 8601, YYYY-MM-DD, yesterday, 2d, ...)",
+    ),
+    ("返す件数の上限", "Maximum number of entries to return"),
+    (
+        "共有先: DB ファイルのパス、または GET で DB ファイルを返し PUT で置き換える http(s) URL",
+        "Remote: path of a DB file, or an http(s) URL that returns the DB file on GET and replaces it on PUT",
+    ),
+    (
+        "追加のリクエストヘッダー（'Name: value'、複数指定可。URL の共有先のみ）",
+        "Additional request header ('Name: value', repeatable; URL remotes only)",
+    ),
+    (
+        "平文の DB を AIBOARD_DB_KEY か OS のキーリングの鍵で暗号化する（--features encryption でのビルドが必要）",
+        "Encrypt a plaintext database with the key from AIBOARD_DB_KEY or the OS keyring (needs a build with --features encryption)",
+    ),
+    (
+        "暗号化された DB を平文に戻す（--features encryption でのビルドが必要）",
+        "Decrypt an encrypted database back to plaintext (needs a build with --features encryption)",
+    ),
+    (
+        "ボード全体（thread・message・リンク・メンショングループ）を tar.gz アーカイブに書き出す",
+        "Write the whole board (threads, messages, links, mention groups) to a tar.gz archive",
+    ),
+    ("出力先ファイル（例: board.tar.gz）", "Output file (e.g. board.tar.gz)"),
+    (
+        "aiboard export のアーカイブを取り込む（既にある thread はスキップ）",
+        "Import an archive written by aiboard export (threads that already exist are skipped)",
+    ),
+    ("アーカイブファイルのパス", "Path of the archive file"),
+    (
+        "JSON 出力の JSON Schema を表示する（コマンド省略時は対象コマンドの一覧）",
+        "Print the JSON Schema of a JSON output (lists the commands when omitted)",
+    ),
+    ("ユーティリティコマンド", "Utility commands"),
+    (
+        "シェル補完スクリプトを出力する（bash, zsh, fish, powershell, elvish）",
+        "Print a shell completion script (bash, zsh, fish, powershell, elvish)",
+    ),
+    (
+        "stdin の各行のコマンドを 1 プロセス・1 トランザクションで実行し、行ごとの結果を JSON で出力する",
+        "Run the commands on stdin, one per line, in one process and one transaction, printing a JSON result per line",
+    ),
+    (
+        "1 行でも失敗したらすべてをロールバックしてそこで止める（省略時は失敗した行だけを取り消して続ける）",
+        "Roll everything back and stop at the first failing line (by default only the failing line is undone)",
+    ),
+    ("トースト通知を表示する（Windows専用）", "Show a toast notification (Windows only)"),
+    (
+        "色付け（auto: 端末かつ NO_COLOR 未設定のとき, always, never）",
+        "Colorize output (auto: on a terminal without NO_COLOR, always, never)",
+    ),
+    ("text 出力の日時を「3分前」のような相対表記にする", "Show text output times relatively, like \"3 minutes ago\""),
+    ("JSON を整形せず 1 行で出力する", "Print JSON on a single line"),
+    (
+        "件数・進捗・ヒントなどの案内メッセージを stderr に出さない（エラーと警告は出力する）",
+        "Suppress informational stderr messages such as counts, progress and hints (errors and warnings are still shown)",
+    ),
+    (
+        "出力先ファイル（省略時は stdout。書き込みは完了時にまとめて反映される）",
+        "Write to this file instead of stdout (replaced only when the command succeeds)",
+    ),
+    ("--output のファイルを上書きせず末尾に追記する", "Append to the --output file instead of replacing it"),
+    ("出力先ファイル（省略時は stdout）", "Output file (stdout when omitted)"),
+    (
+        "データディレクトリ（AIBOARD_DATA_DIR より優先。DB は <PATH>/aiboard.db）",
+        "Data directory (overrides AIBOARD_DATA_DIR; the DB is <PATH>/aiboard.db)",
+    ),
+    ("DB ファイルのパス（--data-dir より優先）", "Path of the DB file (overrides --data-dir)"),
+    (
+        "DB ファイルを開かず、この実行の間だけのメモリ上の空の DB を使う",
+        "Use an empty in-memory database for this run instead of opening the database file",
+    ),
+    ("長い出力を端末で $PAGER に通さない", "Do not pipe long output on a terminal through $PAGER"),
+    (
+        "新しく作成する DB で、thread のない message と draft を拒否し thread の削除で一緒に削除する（設定ファイルの strict_integrity）",
+        "On a newly created board, refuse messages and drafts without a thread and delete them with their thread (strict_integrity in the config file)",
+    ),
+    (
+        "DB オープン・マイグレーション確認・クエリ・整形の所要時間を stderr に出力する",
+        "Print the time spent opening the DB, checking migrations, querying and formatting to stderr",
+    ),
+    (
+        "メッセージの言語（ja, en。省略時は AIBOARD_LANG、なければ ja）",
+        "Language of messages (ja, en; defaults to AIBOARD_LANG, then ja)",
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// The Rust string literal starting at `src[start]` (the opening quote),
+    /// unescaped.
+    fn string_literal(src: &str, start: usize) -> String {
+        let mut out = String::new();
+        let mut chars = src[start + 1..].chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => match chars.next() {
+                    Some('n') => out.push('\n'),
+                    Some('t') => out.push('\t'),
+                    // A line continuation skips the newline and indentation.
+                    Some('\n') => {
+                        let rest = chars.as_str().trim_start();
+                        chars = rest.chars();
+                    }
+                    Some(other) => out.push(other),
+                    None => break,
+                },
+                c => out.push(c),
+            }
+        }
+        out
+    }
+
+    /// Literals passed to `notice!`, `tr!` and `tr` in the Rust files under
+    /// `dir`.
+    fn translated_literals(dir: &Path, found: &mut Vec<(String, String)>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                translated_literals(&path, found);
+                continue;
+            }
+            if path.extension().is_none_or(|e| e != "rs") {
+                continue;
+            }
+            let src = std::fs::read_to_string(&path).unwrap();
+            for marker in ["notice!(", "tr!(", "tr("] {
+                for (at, _) in src.match_indices(marker) {
+                    // Skips `str(`, `attr(` and the markers above.
+                    let skip = src[..at]
+                        .chars()
+                        .next_back()
+                        .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '"');
+                    let args = &src[at + marker.len()..];
+                    let quote = at + marker.len() + (args.len() - args.trim_start().len());
+                    if !skip && src[quote..].starts_with('"') {
+                        found.push((path.display().to_string(), string_literal(&src, quote)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_translated_literal_has_an_english_entry() {
+        let mut found = Vec::new();
+        translated_literals(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src"), &mut found);
+        assert!(found.len() > 100, "the scan found too few literals: {}", found.len());
+        let missing: Vec<_> = found
+            .iter()
+            .filter(|(_, ja)| {
+                // Templates of placeholders and punctuation need no entry.
+                let mut in_placeholder = false;
+                ja.chars().any(|c| {
+                    match c {
+                        '{' => in_placeholder = true,
+                        '}' => in_placeholder = false,
+                        _ => {}
+                    }
+                    !in_placeholder && c.is_alphabetic()
+                })
+            })
+            .filter(|(_, ja)| !CATALOG.iter().any(|(source, _)| source == ja))
+            .collect();
+        assert!(missing.is_empty(), "no English entry in CATALOG for {:#?}", missing);
+    }
+
+    #[test]
+    fn catalog_sources_are_unique() {
+        let mut sources: Vec<&str> = CATALOG.iter().map(|(source, _)| *source).collect();
+        sources.sort_unstable();
+        let before = sources.len();
+        sources.dedup();
+        assert_eq!(sources.len(), before);
+    }
+}
@@ -1,3 +1,8 @@
 pub mod args;
+pub mod batch;
 pub mod handler;
 pub mod formatter;
+pub mod i18n;
+pub mod output;
+pub mod pager;
+pub mod schema;
@@ -0,0 +1,136 @@
+//! Destination of command output: stdout, or the file given by `--output`.
+//!
+//! A file is written through a temporary file in the same directory and
+//! renamed into place when the command succeeds, so a failed or interrupted
+//! command never leaves a partial report behind. `--append` copies the
+//! current contents into the temporary file first.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Context;
+
+use crate::cli::i18n;
+
+struct Sink {
+    file: BufWriter<File>,
+    tmp: PathBuf,
+    dest: PathBuf,
+    error: Option<std::io::Error>,
+}
+
+static SINK: Mutex<Option<Sink>> = Mutex::new(None);
+
+/// Output of the command being run by `batch`, collected instead of being
+/// written to stdout.
+static CAPTURE: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+
+/// Sends the output of the rest of the command to `path` (stdout when
+/// `None`).
+pub fn redirect(path: Option<&Path>, append: bool) -> anyhow::Result<()> {
+    let Some(dest) = path else {
+        return Ok(());
+    };
+    let file_name = dest
+        .file_name()
+        .with_context(|| i18n::fill(i18n::tr("{} はファイルのパスではありません"), &[&dest.display()]))?;
+    let tmp = dest.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    let mut file = File::create(&tmp)
+        .with_context(|| i18n::fill(i18n::tr("{} への書き込みに失敗しました"), &[&dest.display()]))?;
+    if append && dest.exists() {
+        let copied = File::open(dest).and_then(|mut current| std::io::copy(&mut current, &mut file));
+        if let Err(e) = copied {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e).with_context(|| i18n::fill(i18n::tr("{} の読み込みに失敗しました"), &[&dest.display()]));
+        }
+    }
+    if let Ok(mut slot) = SINK.lock() {
+        *slot = Some(Sink {
+            file: BufWriter::new(file),
+            tmp,
+            dest: dest.to_path_buf(),
+            error: None,
+        });
+    }
+    Ok(())
+}
+
+/// Whether output currently goes to a file.
+pub fn redirected() -> bool {
+    SINK.lock().map(|slot| slot.is_some()).unwrap_or(false)
+}
+
+/// Runs `f` with the current destination. Write errors on a file are kept
+/// and reported by `commit`.
+pub fn with_writer<R>(f: impl FnOnce(&mut dyn Write) -> R) -> R {
+    let mut slot = SINK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(sink) = slot.as_mut() {
+        return f(&mut sink.file);
+    }
+    let mut captured = CAPTURE.lock().unwrap_or_else(|e| e.into_inner());
+    match captured.as_mut() {
+        Some(buf) => f(buf),
+        None => f(&mut std::io::stdout().lock()),
+    }
+}
+
+/// Writes `args` like `print!`.
+pub fn print(args: std::fmt::Arguments) {
+    let mut slot = SINK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(sink) = slot.as_mut() {
+        if sink.error.is_none() {
+            sink.error = sink.file.write_fmt(args).err();
+        }
+        return;
+    }
+    let mut captured = CAPTURE.lock().unwrap_or_else(|e| e.into_inner());
+    match captured.as_mut() {
+        Some(buf) => {
+            let _ = buf.write_fmt(args);
+        }
+        None => std::io::stdout().write_fmt(args).expect("failed printing to stdout"),
+    }
+}
+
+/// Collects stdout output until `take_captured`.
+pub fn capture() {
+    if let Ok(mut captured) = CAPTURE.lock() {
+        *captured = Some(Vec::new());
+    }
+}
+
+/// Ends `capture`, returning what was written.
+pub fn take_captured() -> String {
+    let buf = CAPTURE.lock().ok().and_then(|mut captured| captured.take()).unwrap_or_default();
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
+/// Moves the written file into place.
+pub fn commit() -> anyhow::Result<()> {
+    let Some(mut sink) = SINK.lock().ok().and_then(|mut slot| slot.take()) else {
+        return Ok(());
+    };
+    let written = match sink.error.take() {
+        Some(e) => Err(e),
+        None => sink.file.flush().and_then(|()| sink.file.get_ref().sync_all()),
+    };
+    let result = written.and_then(|()| std::fs::rename(&sink.tmp, &sink.dest));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&sink.tmp);
+    }
+    result.with_context(|| i18n::fill(i18n::tr("{} への書き込みに失敗しました"), &[&sink.dest.display()]))
+}
+
+/// Drops what was written, leaving the destination untouched.
+pub fn discard() {
+    if let Some(sink) = SINK.lock().ok().and_then(|mut slot| slot.take()) {
+        drop(sink.file);
+        let _ = std::fs::remove_file(&sink.tmp);
+    }
+}
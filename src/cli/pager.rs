@@ -0,0 +1,126 @@
+//! Pages long stdout output through `$PAGER`, the way git does.
+//!
+//! The pager gets stdout only when it is a terminal. `less` is started with
+//! `LESS=FRX` unless `LESS` is set, so output that fits on one screen is
+//! printed as is and colors pass through.
+
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::cli::args::{Cli, Commands, MessageAction, SearchAction, ThreadAction};
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static PAGER: Mutex<Option<Child>> = Mutex::new(None);
+
+/// Whether stdout currently goes to a pager (which is on a terminal).
+pub fn active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Commands whose output can run long; watch modes and commands that prompt
+/// write to the terminal directly.
+pub fn wanted(cli: &Cli) -> bool {
+    if cli.no_pager {
+        return false;
+    }
+    match &cli.command {
+        Commands::Message { action } => matches!(
+            action,
+            MessageAction::Read { output: None, .. }
+                | MessageAction::Get { .. }
+                | MessageAction::List { output: None, .. }
+                | MessageAction::Search { output: None, .. }
+                | MessageAction::Mentions { action: None, .. }
+        ),
+        Commands::Thread { action } => matches!(
+            action,
+            ThreadAction::List { output: None, .. }
+                | ThreadAction::Search { output: None, .. }
+                | ThreadAction::Show { .. }
+        ),
+        Commands::Search { action } => matches!(action, SearchAction::All { output: None, .. }),
+        _ => false,
+    }
+}
+
+/// `$AIBOARD_PAGER`, then `$PAGER`, then `less`; empty or `cat` disables
+/// paging.
+fn pager_command() -> Option<String> {
+    let command = std::env::var("AIBOARD_PAGER")
+        .or_else(|_| std::env::var("PAGER"))
+        .unwrap_or_else(|_| "less".to_string());
+    let command = command.trim();
+    if command.is_empty() || command == "cat" {
+        return None;
+    }
+    Some(command.to_string())
+}
+
+/// Redirects stdout into the pager when stdout is a terminal. Failing to
+/// start the pager leaves stdout untouched.
+#[cfg(unix)]
+pub fn start() {
+    use std::io::IsTerminal;
+    use std::os::unix::io::AsRawFd;
+    use std::process::{Command, Stdio};
+
+    if !std::io::stdout().is_terminal() {
+        return;
+    }
+    let Some(command) = pager_command() else {
+        return;
+    };
+    let mut pager = Command::new("sh");
+    pager.arg("-c").arg(&command).stdin(Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        pager.env("LESS", "FRX");
+    }
+    if std::env::var_os("LV").is_none() {
+        pager.env("LV", "-c");
+    }
+    let Ok(mut child) = pager.spawn() else {
+        return;
+    };
+    let Some(stdin) = child.stdin.take() else {
+        return;
+    };
+    // SAFETY: dup2 onto fd 1 only swaps what stdout refers to; `stdin` stays
+    // owned here and is closed when it drops.
+    let redirected = unsafe { libc::dup2(stdin.as_raw_fd(), libc::STDOUT_FILENO) } >= 0;
+    drop(stdin);
+    if !redirected {
+        let _ = child.kill();
+        return;
+    }
+    // Quitting the pager early ends the process quietly instead of failing
+    // the next write to stdout.
+    // SAFETY: restores the default disposition of SIGPIPE.
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+    ACTIVE.store(true, Ordering::Relaxed);
+    if let Ok(mut slot) = PAGER.lock() {
+        *slot = Some(child);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn start() {}
+
+/// Closes stdout and waits for the user to leave the pager.
+pub fn finish() {
+    use std::io::Write;
+
+    let Some(mut child) = PAGER.lock().ok().and_then(|mut slot| slot.take()) else {
+        return;
+    };
+    let _ = std::io::stdout().flush();
+    #[cfg(unix)]
+    // SAFETY: fd 1 is the pipe to the pager, closed so that it sees EOF.
+    unsafe {
+        libc::close(libc::STDOUT_FILENO);
+    }
+    let _ = child.wait();
+    ACTIVE.store(false, Ordering::Relaxed);
+}
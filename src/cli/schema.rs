@@ -0,0 +1,411 @@
+//! JSON Schemas of the `--format json` outputs, printed by `aiboard schema`.
+//!
+//! Object outputs carry `schema_version`; top-level arrays stay bare so
+//! existing consumers keep working, and their version is the one stated in
+//! the schema document. Bump `SCHEMA_VERSION` when a field is removed or
+//! changes meaning; adding fields is compatible.
+
+use serde_json::{json, Value};
+
+use crate::domain::entity::AuditOperation;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Commands with a documented JSON output and a summary of what they return.
+pub const COMMANDS: [(&str, &str); 19] = [
+    ("message read", "message の配列（--offset / --cursor 指定時は order・messages・next_cursor の封筒）"),
+    ("message list", "message の配列"),
+    ("message get", "message とそのリンク"),
+    ("message search", "検索結果の配列（--group-by thread 指定時は thread ごとのグループ）"),
+    ("thread list", "thread の配列"),
+    ("thread search", "thread の配列"),
+    ("thread show", "thread と message 数・参加者・最終活動日時"),
+    ("search all", "thread ごとにまとめた検索結果"),
+    ("index status", "全文検索インデックスと埋め込みの状態"),
+    ("db vacuum", "VACUUM 前後の DB ファイルサイズと削減量"),
+    ("db stats", "DB ファイル・テーブル・インデックスのサイズと大きい thread・message"),
+    ("db doctor", "整合性チェック・全文検索インデックス・thread のない message・存在しない thread や message を指す行の検査結果"),
+    ("db merge", "別の DB から追加・更新した thread と message の件数"),
+    ("backup list", "バックアップと中身の件数の配列（新しい順）"),
+    ("backup verify", "バックアップの整合性チェックの結果"),
+    ("sync push", "共有先に追加・更新した thread と message の件数"),
+    ("sync pull", "共有先から追加・更新した thread と message の件数"),
+    ("sync status", "共有先ごとの最後の push と pull"),
+    ("audit list", "監査ログの配列（新しい順）"),
+];
+
+/// The JSON Schema (draft 2020-12) of `command`'s JSON output.
+pub fn schema_for(command: &str) -> Option<Value> {
+    let reference = |name: &str| json!({ "$ref": format!("#/$defs/{}", name) });
+    let array_of = |name: &str| json!({ "type": "array", "items": reference(name) });
+    let root = match command {
+        "message read" => json!({ "oneOf": [array_of("message"), reference("message_page")] }),
+        "message list" => array_of("message"),
+        "message get" => versioned(json!({
+            "allOf": [reference("message")],
+            "properties": {
+                "links": { "type": "array", "items": reference("link") },
+            },
+            "required": ["links"],
+        })),
+        "message search" => json!({ "oneOf": [array_of("search_hit"), array_of("search_group")] }),
+        "thread list" | "thread search" => array_of("thread"),
+        "thread show" => versioned(json!({
+            "allOf": [reference("thread")],
+            "properties": {
+                "message_count": { "type": "integer", "minimum": 0 },
+                "participants": { "type": "array", "items": { "type": "string" } },
+                "last_activity": { "type": ["string", "null"], "format": "date-time" },
+            },
+            "required": ["message_count", "participants", "last_activity"],
+        })),
+        "search all" => array_of("search_group"),
+        "index status" => versioned(json!({
+            "type": "object",
+            "properties": {
+                "search": {
+                    "type": "object",
+                    "properties": {
+                        "messages": { "type": "integer", "minimum": 0 },
+                        "indexed": { "type": "integer", "minimum": 0, "description": "全文検索インデックス内の文書数" },
+                        "consistent": { "type": "boolean", "description": "FTS5 の整合性チェックに通ったか" },
+                    },
+                    "required": ["messages", "indexed", "consistent"],
+                },
+                "embeddings": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "model": { "type": "string" },
+                            "vectors": { "type": "integer", "minimum": 0 },
+                            "stale": { "type": "integer", "minimum": 0, "description": "作成後に本文が編集されたベクトルの数" },
+                        },
+                        "required": ["model", "vectors", "stale"],
+                    },
+                },
+            },
+            "required": ["search", "embeddings"],
+        })),
+        "db vacuum" => versioned(json!({
+            "type": "object",
+            "properties": {
+                "before_bytes": { "type": "integer", "minimum": 0, "description": "WAL を含む VACUUM 前のサイズ" },
+                "after_bytes": { "type": "integer", "minimum": 0 },
+                "reclaimed_bytes": { "type": "integer", "minimum": 0 },
+            },
+            "required": ["before_bytes", "after_bytes", "reclaimed_bytes"],
+        })),
+        "db stats" => {
+            let count = json!({ "type": "integer", "minimum": 0 });
+            let object = |properties: Value| {
+                let required: Vec<&String> = properties.as_object().map(|p| p.keys().collect()).unwrap_or_default();
+                json!({ "type": "object", "properties": properties, "required": required })
+            };
+            versioned(json!({
+                "type": "object",
+                "properties": {
+                    "file_bytes": count,
+                    "wal_bytes": count,
+                    "schema_version": { "type": "integer" },
+                    "page_size": count,
+                    "pages": count,
+                    "free_pages": { "type": "integer", "minimum": 0, "description": "データを持たないページ数（db vacuum で回収）" },
+                    "tables": { "type": "array", "items": object(json!({
+                        "name": { "type": "string" }, "rows": count, "bytes": count,
+                    })) },
+                    "indexes": { "type": "array", "items": object(json!({
+                        "name": { "type": "string" }, "table": { "type": "string" }, "bytes": count,
+                    })) },
+                    "largest_threads": { "type": "array", "items": object(json!({
+                        "id": { "type": "string" }, "title": { "type": "string" }, "messages": count, "bytes": count,
+                    })) },
+                    "largest_messages": { "type": "array", "items": object(json!({
+                        "id": { "type": "string" }, "thread_id": { "type": "string" },
+                        "sender": { "type": ["string", "null"] }, "bytes": count,
+                    })) },
+                },
+                "required": [
+                    "file_bytes", "wal_bytes", "schema_version", "page_size", "pages", "free_pages",
+                    "tables", "indexes", "largest_threads", "largest_messages",
+                ],
+            }))
+        }
+        "db merge" => {
+            let count = json!({ "type": "integer", "minimum": 0 });
+            versioned(json!({
+                "type": "object",
+                "properties": {
+                    "threads_added": count,
+                    "threads_updated": count,
+                    "messages_added": count,
+                    "messages_updated": count,
+                    "links_added": count,
+                    "conflicts_kept": { "type": "integer", "minimum": 0, "description": "両方にあり内容が異なるため、こちらを残した thread・message の数" },
+                    "names_dropped": { "type": "integer", "minimum": 0, "description": "名前がこちらの別の thread と重なるため名前なしで取り込んだ thread の数" },
+                },
+                "required": ["threads_added", "threads_updated", "messages_added", "messages_updated", "links_added", "conflicts_kept", "names_dropped"],
+            }))
+        }
+        "backup list" => json!({ "type": "array", "items": backup_summary() }),
+        "backup verify" => {
+            let mut schema = backup_summary();
+            schema["properties"]["healthy"] = json!({ "type": "boolean" });
+            schema["properties"]["problems"] = json!({
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "PRAGMA integrity_check の報告、または開けなかった理由（正常なら空）",
+            });
+            if let Some(required) = schema["required"].as_array_mut() {
+                required.extend([json!("healthy"), json!("problems")]);
+            }
+            versioned(schema)
+        }
+        "sync push" | "sync pull" => {
+            let count = json!({ "type": "integer", "minimum": 0 });
+            versioned(json!({
+                "type": "object",
+                "properties": {
+                    "remote": { "type": "string", "description": "共有先の URL か DB ファイルの絶対パス" },
+                    "direction": { "enum": ["push", "pull"] },
+                    "up_to_date": { "type": "boolean", "description": "送るもの・取り込むものがなかったか" },
+                    "threads_added": count,
+                    "threads_updated": count,
+                    "messages_added": count,
+                    "messages_updated": count,
+                    "links_added": count,
+                    "conflicts_kept": { "type": "integer", "minimum": 0, "description": "両方にあり内容が異なるが、受け取り側が新しいか同時刻のため残した thread・message の数" },
+                    "names_dropped": { "type": "integer", "minimum": 0, "description": "名前が受け取り側の別の thread と重なるため名前なしで取り込んだ thread の数" },
+                },
+                "required": ["remote", "direction", "up_to_date", "threads_added", "threads_updated", "messages_added", "messages_updated", "links_added", "conflicts_kept", "names_dropped"],
+            }))
+        }
+        "sync status" => json!({
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "remote": { "type": "string" },
+                    "direction": { "enum": ["push", "pull"] },
+                    "remote_version": { "type": ["string", "null"], "description": "交換後の共有先の ETag、またはファイルの更新時刻とサイズ" },
+                    "synced_at": { "type": "string", "format": "date-time" },
+                    "threads": { "type": "integer", "minimum": 0, "description": "受け取り側で追加・更新した thread の数" },
+                    "messages": { "type": "integer", "minimum": 0, "description": "受け取り側で追加・更新した message の数" },
+                },
+                "required": ["remote", "direction", "remote_version", "synced_at", "threads", "messages"],
+            },
+        }),
+        "audit list" => json!({
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "id": { "type": "integer" },
+                    "operation": { "enum": AuditOperation::ALL.map(|op| op.as_str()) },
+                    "actor": { "type": ["string", "null"], "description": "操作したコマンドの sender（なければ設定ファイルの sender）" },
+                    "targets": { "type": "array", "items": { "type": "string" }, "description": "影響を受けた message・thread の ID" },
+                    "detail": { "type": ["object", "null"], "description": "件数や条件（cleanup age の日数など）" },
+                    "created_at": { "type": "string", "format": "date-time" },
+                },
+                "required": ["id", "operation", "actor", "targets", "detail", "created_at"],
+            },
+        }),
+        "db doctor" => versioned(json!({
+            "type": "object",
+            "properties": {
+                "healthy": { "type": "boolean" },
+                "integrity_errors": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "PRAGMA integrity_check の報告（正常なら空。--orphans では省略）",
+                },
+                "search_index": {
+                    "type": "object",
+                    "description": "--orphans では省略",
+                    "properties": {
+                        "messages": { "type": "integer", "minimum": 0 },
+                        "indexed": { "type": "integer", "minimum": 0 },
+                        "consistent": { "type": "boolean" },
+                    },
+                    "required": ["messages", "indexed", "consistent"],
+                },
+                "orphans": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "thread_id": { "type": "string", "description": "存在しない thread の ID" },
+                            "messages": { "type": "integer", "minimum": 1 },
+                        },
+                        "required": ["thread_id", "messages"],
+                    },
+                },
+                "dangling": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "table": { "type": "string", "description": "存在しない thread・message を指す行のあるテーブル" },
+                            "rows": { "type": "integer", "minimum": 1 },
+                        },
+                        "required": ["table", "rows"],
+                    },
+                },
+                "strict_integrity": { "type": "boolean", "description": "--strict-integrity で作成された DB か" },
+            },
+            "required": ["healthy", "orphans", "dangling", "strict_integrity"],
+        })),
+        _ => return None,
+    };
+
+    let mut schema = json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": format!("aiboard {}", command),
+        "x-schema-version": SCHEMA_VERSION,
+    });
+    let object = schema.as_object_mut()?;
+    object.extend(root.as_object()?.clone());
+    object.insert("$defs".to_string(), defs());
+    Some(schema)
+}
+
+/// Adds the `schema_version` property every object output carries.
+/// A backup file with what it holds; the counts are null when it could
+/// not be opened.
+fn backup_summary() -> Value {
+    let nullable_count = json!({ "type": ["integer", "null"], "minimum": 0 });
+    json!({
+        "type": "object",
+        "properties": {
+            "name": { "type": "string" },
+            "path": { "type": "string" },
+            "created_at": { "type": "string", "format": "date-time" },
+            "bytes": { "type": "integer", "minimum": 0 },
+            "label": { "type": ["string", "null"], "description": "backup create --label で付けたラベル" },
+            "compressed": { "type": "boolean", "description": "gzip 圧縮されているか（以前のバックアップは圧縮なし）" },
+            "db_schema_version": { "type": ["integer", "null"] },
+            "threads": nullable_count,
+            "messages": nullable_count,
+            "error": { "type": ["string", "null"], "description": "開けなかった理由" },
+        },
+        "required": [
+            "name", "path", "created_at", "bytes", "label", "compressed", "db_schema_version", "threads", "messages", "error",
+        ],
+    })
+}
+
+fn versioned(mut schema: Value) -> Value {
+    if let Some(object) = schema.as_object_mut() {
+        let properties = object.entry("properties").or_insert_with(|| json!({}));
+        if let Some(properties) = properties.as_object_mut() {
+            properties.insert("schema_version".to_string(), json!({ "const": SCHEMA_VERSION }));
+        }
+        let required = object.entry("required").or_insert_with(|| json!([]));
+        if let Some(list) = required.as_array_mut() {
+            list.push(json!("schema_version"));
+        }
+    }
+    schema
+}
+
+fn defs() -> Value {
+    let nullable_string = json!({ "type": ["string", "null"] });
+    let timestamp = json!({ "type": "string", "format": "date-time", "description": "UTC の RFC 3339" });
+    let role = json!({ "enum": ["user", "assistant", "system", "tool"] });
+    json!({
+        "message": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "thread_id": { "type": "string" },
+                "session_id": nullable_string,
+                "sender": nullable_string,
+                "role": role,
+                "content": { "type": "string" },
+                "metadata": { "description": "任意の JSON（--type の値は msg_type キー）。なければ null" },
+                "parent_id": nullable_string,
+                "source": { "type": ["string", "null"], "description": "出所タグ（user, system, manual, agent, url-fetch, file-import など）" },
+                "created_at": timestamp,
+                "updated_at": timestamp,
+            },
+            "required": [
+                "id", "thread_id", "session_id", "sender", "role", "content", "metadata", "parent_id",
+                "source", "created_at", "updated_at",
+            ],
+        },
+        "message_page": versioned(json!({
+            "type": "object",
+            "properties": {
+                "order": { "enum": ["asc", "desc"] },
+                "messages": { "type": "array", "items": { "$ref": "#/$defs/message" } },
+                "next_cursor": { "type": ["string", "null"], "description": "--cursor に渡すと続きを読める。最後のページでは null" },
+            },
+            "required": ["order", "messages", "next_cursor"],
+        })),
+        "search_hit": {
+            "allOf": [{ "$ref": "#/$defs/message" }],
+            "properties": {
+                "score": { "type": ["number", "null"], "description": "大きいほど関連度が高い。部分一致・fuzzy の結果では null" },
+                "matches": {
+                    "type": "array",
+                    "description": "本文中の一致箇所の [開始, 終了) 文字オフセット",
+                    "items": {
+                        "type": "array",
+                        "items": { "type": "integer", "minimum": 0 },
+                        "minItems": 2,
+                        "maxItems": 2,
+                    },
+                },
+                "fuzzy": { "type": "string", "description": "--fuzzy で見つかった表記。完全一致の結果にはない" },
+            },
+            "required": ["score", "matches"],
+        },
+        "search_group": {
+            "type": "object",
+            "properties": {
+                "thread": { "$ref": "#/$defs/thread" },
+                "thread_match": { "type": "boolean", "description": "クエリが thread のタイトル・名前・要約に一致したか" },
+                "messages": { "type": "array", "items": { "$ref": "#/$defs/search_hit" } },
+            },
+            "required": ["thread", "thread_match", "messages"],
+        },
+        "thread": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "name": nullable_string,
+                "title": { "type": "string" },
+                "source_url": nullable_string,
+                "status": { "enum": ["open", "closed"] },
+                "phase": { "enum": ["planning", "implementing", "reviewing", "done", null] },
+                "summary": nullable_string,
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "pinned": { "type": "boolean" },
+                "metadata": { "description": "任意の JSON。なければ null" },
+                "default_sender": nullable_string,
+                "default_role": { "enum": ["user", "assistant", "system", "tool", null] },
+                "locked": { "type": "boolean" },
+                "lock_allow": { "type": "array", "items": { "type": "string" } },
+                "retention_days": { "type": ["integer", "null"] },
+                "retention_keep_last": { "type": ["integer", "null"] },
+                "created_at": timestamp,
+                "updated_at": timestamp,
+            },
+            "required": [
+                "id", "name", "title", "source_url", "status", "phase", "summary", "tags", "pinned",
+                "metadata", "default_sender", "default_role", "locked", "lock_allow", "retention_days",
+                "retention_keep_last", "created_at", "updated_at",
+            ],
+        },
+        "link": {
+            "type": "object",
+            "properties": {
+                "from_id": { "type": "string" },
+                "to_id": { "type": "string" },
+                "relation": { "enum": ["blocks", "duplicates", "relates"] },
+                "created_at": timestamp,
+            },
+            "required": ["from_id", "to_id", "relation", "created_at"],
+        },
+    })
+}
@@ -65,7 +65,7 @@ impl std::str::FromStr for ThreadPhase {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Thread {
     pub id: String,
     pub name: Option<String>,
@@ -73,11 +73,44 @@ pub struct Thread {
     pub source_url: Option<String>,
     pub status: ThreadStatus,
     pub phase: Option<ThreadPhase>,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+    /// Sender used by `message post` when `--sender` is omitted.
+    #[serde(default)]
+    pub default_sender: Option<String>,
+    /// Role used by `message post` when `--role` is omitted.
+    #[serde(default)]
+    pub default_role: Option<Role>,
+    #[serde(default)]
+    pub locked: bool,
+    /// Senders that may still post while the thread is locked.
+    #[serde(default)]
+    pub lock_allow: Vec<String>,
+    /// Retention policy: delete messages older than this many days.
+    #[serde(default)]
+    pub retention_days: Option<i64>,
+    /// Retention policy: keep only this many most recent messages.
+    #[serde(default)]
+    pub retention_keep_last: Option<i64>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Thread {
+    /// Whether a post by `sender` is accepted. Unlocked threads accept
+    /// anyone; locked threads only the senders in `lock_allow`.
+    pub fn accepts_post_from(&self, sender: Option<&str>) -> bool {
+        !self.locked || sender.is_some_and(|s| self.lock_allow.iter().any(|a| a == s))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Message {
     pub id: String,
     pub thread_id: String,
@@ -92,6 +125,369 @@ pub struct Message {
     pub updated_at: DateTime<Utc>,
 }
 
+/// A `message search` result with its relevance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub message: Message,
+    /// Higher means more relevant: the negated FTS5 bm25 rank for keyword
+    /// search, cosine similarity for semantic search. `None` for hits from
+    /// the substring and fuzzy fallbacks, which have no ranking.
+    pub score: Option<f64>,
+    /// The spelling actually found when the hit comes from the typo-tolerant
+    /// fallback (`message search --fuzzy`) rather than an exact match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuzzy: Option<String>,
+    /// Matched spans of `content` as `[start, end)` character (not byte)
+    /// offsets, in order. Empty for semantic and metadata-only hits.
+    #[serde(default)]
+    pub matches: Vec<[usize; 2]>,
+}
+
+/// State of the full-text index compared with the messages table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchIndexStatus {
+    pub messages: usize,
+    /// Documents in the FTS index.
+    pub indexed: usize,
+    /// Whether FTS5's integrity check against the messages table passed.
+    pub consistent: bool,
+}
+
+/// Stored embedding vectors of one model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingStatus {
+    pub model: String,
+    pub vectors: usize,
+    /// Vectors computed from content that has since been edited.
+    pub stale: usize,
+}
+
+/// Size and contents of the database file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbStats {
+    pub file_bytes: u64,
+    /// Pages in the write-ahead log not yet checkpointed into the file.
+    pub wal_bytes: u64,
+    pub schema_version: i64,
+    pub page_size: u64,
+    pub pages: u64,
+    /// Pages kept by the file but holding no data (reclaimed by `db vacuum`).
+    pub free_pages: u64,
+    pub tables: Vec<TableSize>,
+    pub indexes: Vec<IndexSize>,
+    pub largest_threads: Vec<ThreadSize>,
+    pub largest_messages: Vec<MessageSize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableSize {
+    pub name: String,
+    pub rows: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexSize {
+    pub name: String,
+    pub table: String,
+    pub bytes: u64,
+}
+
+/// Total content of one thread's messages.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadSize {
+    pub id: String,
+    pub title: String,
+    pub messages: u64,
+    /// UTF-8 bytes of content.
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageSize {
+    pub id: String,
+    pub thread_id: String,
+    pub sender: Option<String>,
+    /// UTF-8 bytes of content.
+    pub bytes: u64,
+}
+
+/// Problems found by `db doctor`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbCheck {
+    /// Lines reported by `PRAGMA integrity_check`; empty when the file is
+    /// sound. Not checked with `--orphans`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrity_errors: Option<Vec<String>>,
+    /// Not checked with `--orphans`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_index: Option<SearchIndexStatus>,
+    /// Messages whose thread no longer exists, grouped by thread ID.
+    pub orphans: Vec<OrphanThread>,
+    /// Rows of other tables that refer to a missing thread or message.
+    pub dangling: Vec<DanglingRows>,
+    /// Whether the board was created with `--strict-integrity`.
+    pub strict_integrity: bool,
+}
+
+impl DbCheck {
+    pub fn healthy(&self) -> bool {
+        !self.corrupt() && self.search_index_healthy() && self.orphans.is_empty() && self.dangling.is_empty()
+    }
+
+    /// Whether the integrity check found the file damaged.
+    pub fn corrupt(&self) -> bool {
+        self.integrity_errors.as_ref().is_some_and(|errors| !errors.is_empty())
+    }
+
+    pub fn search_index_healthy(&self) -> bool {
+        self.search_index
+            .as_ref()
+            .is_none_or(|index| index.consistent && index.indexed == index.messages)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrphanThread {
+    pub thread_id: String,
+    pub messages: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DanglingRows {
+    pub table: String,
+    pub rows: u64,
+}
+
+/// Search results belonging to one thread.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchGroup {
+    pub thread: Thread,
+    /// Whether the query matched the thread's own title, name or summary.
+    pub thread_match: bool,
+    pub messages: Vec<SearchHit>,
+}
+
+/// A sender's activity in one thread.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Participant {
+    pub thread_id: String,
+    pub sender: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub message_count: usize,
+}
+
+/// A sender watching a thread for activity by others.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    pub thread_id: String,
+    pub sender: String,
+    /// Also raise a desktop notification (Windows only).
+    pub notify: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A named set of senders reachable with `@team:<name>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Group {
+    pub name: String,
+    pub members: Vec<String>,
+}
+
+/// Which way a `sync` exchange copied the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncDirection {
+    Push,
+    Pull,
+}
+
+impl std::fmt::Display for SyncDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncDirection::Push => write!(f, "push"),
+            SyncDirection::Pull => write!(f, "pull"),
+        }
+    }
+}
+
+impl std::str::FromStr for SyncDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "push" => Ok(SyncDirection::Push),
+            "pull" => Ok(SyncDirection::Pull),
+            other => Err(format!("unknown sync direction: {}", other)),
+        }
+    }
+}
+
+/// The last exchange with a sync remote in one direction.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncState {
+    pub remote: String,
+    pub direction: SyncDirection,
+    /// What the remote reported after the exchange: its ETag, or the
+    /// modification time and size of a file.
+    pub remote_version: Option<String>,
+    pub synced_at: DateTime<Utc>,
+    /// Threads and messages the exchange added or updated on the receiving side.
+    pub threads: usize,
+    pub messages: usize,
+}
+
+/// A kind of operation recorded in the audit log, named after its command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditOperation {
+    #[serde(rename = "message.post")]
+    MessagePost,
+    #[serde(rename = "message.update")]
+    MessageUpdate,
+    #[serde(rename = "thread.delete")]
+    ThreadDelete,
+    #[serde(rename = "cleanup.age")]
+    CleanupAge,
+    #[serde(rename = "cleanup.thread")]
+    CleanupThread,
+    #[serde(rename = "cleanup.apply-retention")]
+    CleanupRetention,
+    #[serde(rename = "cleanup.session")]
+    CleanupSession,
+    #[serde(rename = "db.merge")]
+    DbMerge,
+    #[serde(rename = "sync.pull")]
+    SyncPull,
+}
+
+impl AuditOperation {
+    pub const ALL: [AuditOperation; 9] = [
+        AuditOperation::MessagePost,
+        AuditOperation::MessageUpdate,
+        AuditOperation::ThreadDelete,
+        AuditOperation::CleanupAge,
+        AuditOperation::CleanupThread,
+        AuditOperation::CleanupRetention,
+        AuditOperation::CleanupSession,
+        AuditOperation::DbMerge,
+        AuditOperation::SyncPull,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditOperation::MessagePost => "message.post",
+            AuditOperation::MessageUpdate => "message.update",
+            AuditOperation::ThreadDelete => "thread.delete",
+            AuditOperation::CleanupAge => "cleanup.age",
+            AuditOperation::CleanupThread => "cleanup.thread",
+            AuditOperation::CleanupRetention => "cleanup.apply-retention",
+            AuditOperation::CleanupSession => "cleanup.session",
+            AuditOperation::DbMerge => "db.merge",
+            AuditOperation::SyncPull => "sync.pull",
+        }
+    }
+}
+
+impl std::fmt::Display for AuditOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for AuditOperation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AuditOperation::ALL
+            .into_iter()
+            .find(|op| op.as_str() == s)
+            .ok_or_else(|| format!("unknown audit operation: {}", s))
+    }
+}
+
+/// One entry of the append-only audit log.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub operation: AuditOperation,
+    /// Who did it: the command's sender, or the configured one.
+    pub actor: Option<String>,
+    /// IDs of the messages and threads the operation affected.
+    pub targets: Vec<String>,
+    /// Counts and parameters of the operation, such as the days of `cleanup age`.
+    pub detail: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An unposted message kept in the `drafts` table until it is posted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Draft {
+    pub id: String,
+    pub thread_id: String,
+    pub sender: Option<String>,
+    pub role: Role,
+    pub content: String,
+    pub metadata: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Draft {
+    /// Replaces the content, or with `append` adds `content` as a new line.
+    pub fn edit_content(&mut self, content: &str, append: bool) {
+        if append {
+            if !self.content.is_empty() {
+                self.content.push('\n');
+            }
+            self.content.push_str(content);
+        } else {
+            self.content = content.to_string();
+        }
+    }
+}
+
+/// Relation of a cross-reference link, read as "`from` <relation> `to`".
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkRelation {
+    Blocks,
+    Duplicates,
+    Relates,
+}
+
+impl std::fmt::Display for LinkRelation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkRelation::Blocks => write!(f, "blocks"),
+            LinkRelation::Duplicates => write!(f, "duplicates"),
+            LinkRelation::Relates => write!(f, "relates"),
+        }
+    }
+}
+
+impl std::str::FromStr for LinkRelation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "blocks" => Ok(LinkRelation::Blocks),
+            "duplicates" => Ok(LinkRelation::Duplicates),
+            "relates" => Ok(LinkRelation::Relates),
+            other => Err(format!("unknown relation: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageLink {
+    pub from_id: String,
+    pub to_id: String,
+    pub relation: LinkRelation,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum Role {
@@ -8,12 +8,24 @@ pub enum DomainError {
     #[error("message が見つかりません: {0}")]
     MessageNotFound(String),
 
+    #[error("draft が見つかりません: {0}")]
+    DraftNotFound(String),
+
+    #[error("thread {0} はロックされています（許可された送信者のみ投稿できます）")]
+    ThreadLocked(String),
+
     #[error("短縮 ID '{0}' が曖昧です: {1} 件のレコードに一致")]
     AmbiguousShortId(String, usize),
 
     #[error("データベースエラー: {0}")]
     Database(String),
 
+    #[error("データベースがロックされています（他のプロセスが書き込み中）: {0}")]
+    Busy(String),
+
+    #[error("共有先が取得後に更新されました（sync pull で取り込んでから push し直してください）: {0}")]
+    Conflict(String),
+
     #[error("入力が不正です: {0}")]
     InvalidInput(String),
 
@@ -28,15 +40,43 @@ pub enum DomainError {
 }
 
 impl DomainError {
-    /// Returns the appropriate exit code for this error.
-    /// 0 = success, 1 = general error, 2 = input error.
+    /// Returns the exit code for this error: 1 is left for errors outside
+    /// `DomainError` and 2 for invalid input (as with usage errors); every
+    /// other variant has its own code.
     pub fn exit_code(&self) -> i32 {
         match self {
-            DomainError::InvalidInput(_) | DomainError::Parse(_) => 2,
-            _ => 1,
+            DomainError::InvalidInput(_) => 2,
+            DomainError::Parse(_) => 3,
+            DomainError::ThreadNotFound(_) => 4,
+            DomainError::MessageNotFound(_) => 5,
+            DomainError::DraftNotFound(_) => 6,
+            DomainError::ThreadLocked(_) => 7,
+            DomainError::AmbiguousShortId(..) => 8,
+            DomainError::Database(_) => 9,
+            DomainError::Network(_) => 10,
+            DomainError::Io(_) => 11,
+            DomainError::Busy(_) => 12,
+            DomainError::Conflict(_) => 13,
         }
     }
 
+    /// Stable identifier of the error kind for machine-readable output.
+    pub fn code(&self) -> &'static str {
+        match self {
+            DomainError::ThreadNotFound(_) => "THREAD_NOT_FOUND",
+            DomainError::MessageNotFound(_) => "MESSAGE_NOT_FOUND",
+            DomainError::DraftNotFound(_) => "DRAFT_NOT_FOUND",
+            DomainError::ThreadLocked(_) => "THREAD_LOCKED",
+            DomainError::AmbiguousShortId(..) => "AMBIGUOUS_SHORT_ID",
+            DomainError::Database(_) => "DATABASE",
+            DomainError::Busy(_) => "BUSY",
+            DomainError::Conflict(_) => "CONFLICT",
+            DomainError::InvalidInput(_) => "INVALID_INPUT",
+            DomainError::Network(_) => "NETWORK",
+            DomainError::Parse(_) => "PARSE",
+            DomainError::Io(_) => "IO",
+        }
+    }
 }
 
 impl From<std::io::Error> for DomainError {
@@ -47,6 +87,11 @@ impl From<std::io::Error> for DomainError {
 
 impl From<rusqlite::Error> for DomainError {
     fn from(e: rusqlite::Error) -> Self {
-        DomainError::Database(e.to_string())
+        match e.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked) => {
+                DomainError::Busy(e.to_string())
+            }
+            _ => DomainError::Database(e.to_string()),
+        }
     }
 }
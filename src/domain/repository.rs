@@ -1,33 +1,342 @@
-use super::entity::{Message, Thread, ThreadPhase, ThreadStatus};
+use chrono::{DateTime, Utc};
+
+use super::entity::{
+    AuditEntry, AuditOperation, Draft, EmbeddingStatus, Group, Message, MessageLink, Participant, Role, SearchHit,
+    SearchIndexStatus, Subscription, SyncDirection, SyncState, Thread, ThreadPhase, ThreadStatus,
+};
 use super::error::DomainError;
 
+/// Sort key for thread listings. Pinned threads always come first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ThreadSort {
+    Created,
+    #[default]
+    Updated,
+    Title,
+    Messages,
+}
+
+/// Which part of a message `search` matches against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchScope {
+    #[default]
+    Content,
+    /// Scalar values anywhere in the metadata JSON (keys are not matched).
+    Metadata,
+    All,
+}
+
+/// How the words of a search query are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueryMode {
+    /// The text is handed to the full-text engine as typed (FTS5 syntax).
+    #[default]
+    Raw,
+    /// The exact text, operators and quotes included.
+    Literal,
+    /// Every whitespace-separated word must appear.
+    All,
+    /// At least one of the words must appear.
+    Any,
+}
+
+/// What `search` looks for.
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    pub text: String,
+    pub mode: QueryMode,
+    /// Messages whose content contains any of these are left out.
+    pub exclude: Vec<String>,
+}
+
+impl SearchQuery {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// The strings to match: the words for `All` / `Any`, otherwise (or when
+    /// there are no words) the whole text.
+    pub fn terms(&self) -> Vec<&str> {
+        let words: Vec<&str> = match self.mode {
+            QueryMode::All | QueryMode::Any => self.text.split_whitespace().collect(),
+            QueryMode::Raw | QueryMode::Literal => Vec::new(),
+        };
+        if words.is_empty() {
+            vec![self.text.as_str()]
+        } else {
+            words
+        }
+    }
+}
+
+/// Conditions for narrowing thread listings. Unset fields do not filter.
+#[derive(Debug, Clone, Default)]
+pub struct ThreadFilter {
+    pub status: Option<ThreadStatus>,
+    pub tag: Option<String>,
+    /// `Some(None)` matches threads without a phase.
+    pub phase: Option<Option<ThreadPhase>>,
+    /// Substring matched against title, name and summary.
+    pub query: Option<String>,
+    pub sort: ThreadSort,
+    /// Flip the sort's natural direction (newest / largest first, titles A-Z).
+    pub reverse: bool,
+}
+
+/// Conditions for narrowing message queries. Unset fields do not filter.
+#[derive(Debug, Clone, Default)]
+pub struct MessageFilter {
+    pub thread_id: Option<String>,
+    pub sender: Option<String>,
+    pub role: Option<Role>,
+    pub msg_type: Option<String>,
+    pub sources: Vec<String>,
+    pub exclude_sources: Vec<String>,
+    /// `(json_path, value)` pairs matched against `metadata`.
+    pub meta: Vec<(String, String)>,
+    /// Inclusive lower bound on `created_at`.
+    pub since: Option<DateTime<Utc>>,
+    /// Exclusive lower bound on `created_at`.
+    pub after: Option<DateTime<Utc>>,
+    /// Exclusive upper bound on `created_at`.
+    pub before: Option<DateTime<Utc>>,
+    /// Only messages inserted after the message with this (full) ID.
+    pub since_message: Option<String>,
+}
+
+/// Window of a message query: ordering, cursor position, offset and limit.
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    pub limit: Option<usize>,
+    pub offset: usize,
+    pub cursor: Option<MessageCursor>,
+    pub descending: bool,
+}
+
+/// Keyset position after a given message, ordered by `(created_at, rowid)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageCursor {
+    pub created_at: DateTime<Utc>,
+    pub id: String,
+}
+
+impl MessageCursor {
+    pub fn after(message: &Message) -> Self {
+        Self {
+            created_at: message.created_at,
+            id: message.id.clone(),
+        }
+    }
+
+    /// Encodes the cursor as an opaque hex token.
+    pub fn encode(&self) -> String {
+        format!("{}|{}", self.created_at.timestamp(), self.id)
+            .bytes()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    pub fn decode(token: &str) -> Result<Self, DomainError> {
+        let invalid = || DomainError::InvalidInput(format!("invalid cursor: {}", token));
+
+        if !token.len().is_multiple_of(2) {
+            return Err(invalid());
+        }
+        let bytes = (0..token.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(token.get(i..i + 2).ok_or_else(invalid)?, 16).map_err(|_| invalid()))
+            .collect::<Result<Vec<u8>, _>>()?;
+        let raw = String::from_utf8(bytes).map_err(|_| invalid())?;
+
+        let (ts, id) = raw.split_once('|').ok_or_else(invalid)?;
+        let secs: i64 = ts.parse().map_err(|_| invalid())?;
+        let created_at = DateTime::from_timestamp(secs, 0).ok_or_else(invalid)?;
+        Ok(Self {
+            created_at,
+            id: id.to_string(),
+        })
+    }
+}
+
 pub trait ThreadRepository {
     fn create(&self, thread: &Thread) -> Result<(), DomainError>;
     fn upsert(&self, thread: &Thread) -> Result<(), DomainError>;
+    /// Overwrites every column and the tags of an existing thread with
+    /// `thread`, timestamps included.
+    fn replace(&self, thread: &Thread) -> Result<(), DomainError>;
     fn find_by_id(&self, id: &str) -> Result<Option<Thread>, DomainError>;
+    fn find_id_by_name(&self, name: &str) -> Result<Option<String>, DomainError>;
+    /// Resolves a thread name, or failing that a unique ID prefix, to a full ID.
     fn resolve_short_id(&self, short_id: &str) -> Result<String, DomainError>;
-    fn list(&self) -> Result<Vec<Thread>, DomainError>;
-    fn list_by_status(&self, status: Option<ThreadStatus>) -> Result<Vec<Thread>, DomainError>;
+    fn find(&self, filter: &ThreadFilter) -> Result<Vec<Thread>, DomainError>;
+    /// Open, unpinned threads whose latest message (or creation, if empty)
+    /// is older than `before`.
+    fn find_inactive(&self, before: &DateTime<Utc>) -> Result<Vec<Thread>, DomainError>;
+    /// Senders active in the thread, in order of first post. Maintained by
+    /// triggers on `messages`.
+    fn participants(&self, id: &str) -> Result<Vec<Participant>, DomainError>;
+    /// Creates the subscription, or updates its notify flag if it exists.
+    fn subscribe(&self, subscription: &Subscription) -> Result<(), DomainError>;
+    /// Returns false when there was no such subscription.
+    fn unsubscribe(&self, id: &str, sender: &str) -> Result<bool, DomainError>;
+    fn subscriptions(&self, id: &str) -> Result<Vec<Subscription>, DomainError>;
+    /// Pinning does not touch `updated_at`.
+    fn set_pinned(&self, id: &str, pinned: bool) -> Result<(), DomainError>;
+    /// Returns false when the thread already had the tag.
+    fn add_tag(&self, id: &str, tag: &str) -> Result<bool, DomainError>;
+    /// Returns false when the thread did not have the tag.
+    fn remove_tag(&self, id: &str, tag: &str) -> Result<bool, DomainError>;
     fn update_status(&self, id: &str, status: ThreadStatus) -> Result<(), DomainError>;
     fn update_phase(&self, id: &str, phase: Option<ThreadPhase>) -> Result<(), DomainError>;
+    fn update_summary(&self, id: &str, summary: Option<&str>) -> Result<(), DomainError>;
+    fn update_metadata(&self, id: &str, metadata: Option<&serde_json::Value>) -> Result<(), DomainError>;
+    fn update_defaults(&self, id: &str, sender: Option<&str>, role: Option<&Role>) -> Result<(), DomainError>;
+    /// Locks (or unlocks) a thread; `allow` lists the senders still allowed to post.
+    fn set_lock(&self, id: &str, locked: bool, allow: &[String]) -> Result<(), DomainError>;
+    fn set_retention(&self, id: &str, days: Option<i64>, keep_last: Option<i64>) -> Result<(), DomainError>;
+    /// Updates the title and/or name; `None` leaves the field unchanged.
+    fn rename(&self, id: &str, title: Option<&str>, name: Option<&str>) -> Result<(), DomainError>;
     fn delete(&self, id: &str) -> Result<(), DomainError>;
 }
 
 pub trait MessageRepository {
     fn insert(&self, message: &Message) -> Result<(), DomainError>;
     fn insert_batch(&self, messages: &[Message]) -> Result<usize, DomainError>;
-    #[allow(dead_code)]
+    /// Overwrites an existing message with `message`, timestamps included.
+    fn replace(&self, message: &Message) -> Result<(), DomainError>;
     fn find_by_id(&self, id: &str) -> Result<Option<Message>, DomainError>;
     fn resolve_short_id(&self, short_id: &str) -> Result<String, DomainError>;
     fn find_by_thread(&self, thread_id: &str) -> Result<Vec<Message>, DomainError>;
-    fn list_recent(&self, limit: usize) -> Result<Vec<Message>, DomainError>;
-    fn search(&self, query: &str, thread_id: Option<&str>) -> Result<Vec<Message>, DomainError>;
+    fn find(&self, filter: &MessageFilter, page: &Page) -> Result<Vec<Message>, DomainError>;
+    /// Like `find`, but hands each row to `f` as it is read instead of
+    /// collecting them; returns the number of rows visited.
+    fn for_each(
+        &self,
+        filter: &MessageFilter,
+        page: &Page,
+        f: &mut dyn FnMut(Message) -> Result<(), DomainError>,
+    ) -> Result<usize, DomainError>;
+    fn list_recent(&self, filter: &MessageFilter, limit: usize) -> Result<Vec<Message>, DomainError>;
+    /// Full-text search, most relevant first (newest first among equal scores).
+    /// Only `limit` and `offset` of `page` apply.
+    fn search(
+        &self,
+        query: &SearchQuery,
+        scope: SearchScope,
+        filter: &MessageFilter,
+        page: &Page,
+    ) -> Result<Vec<SearchHit>, DomainError>;
+    /// Messages sharing at least one trigram with `query`, those sharing the
+    /// most first; candidates for typo-tolerant matching. Empty for queries
+    /// shorter than three characters.
+    fn find_fuzzy_candidates(
+        &self,
+        query: &str,
+        filter: &MessageFilter,
+        limit: usize,
+    ) -> Result<Vec<Message>, DomainError>;
     fn update_content(&self, id: &str, content: &str) -> Result<(), DomainError>;
+    /// Moves `from` and every later message of its thread (in `created_at`,
+    /// insertion order) to another thread.
+    fn move_from(&self, from: &Message, to_thread_id: &str) -> Result<usize, DomainError>;
     fn delete_by_thread(&self, thread_id: &str) -> Result<usize, DomainError>;
     fn delete_by_session(&self, session_id: &str) -> Result<usize, DomainError>;
+    fn find_duplicate(&self, thread_id: &str, sender: Option<&str>, content: &str, since: &chrono::DateTime<chrono::Utc>) -> Result<Option<Message>, DomainError>;
     fn delete_older_than(&self, before: &chrono::DateTime<chrono::Utc>) -> Result<usize, DomainError>;
-    fn find_mentions(&self, thread_id: Option<&str>, mention_target: &str) -> Result<Vec<Message>, DomainError>;
+    fn delete_in_thread_older_than(&self, thread_id: &str, before: &chrono::DateTime<chrono::Utc>) -> Result<usize, DomainError>;
+    /// Deletes all but the `keep` most recent messages of a thread.
+    fn delete_in_thread_except_last(&self, thread_id: &str, keep: usize) -> Result<usize, DomainError>;
+    fn count_by_role(&self, filter: &MessageFilter) -> Result<Vec<(Role, usize)>, DomainError>;
+    /// Messages mentioning `mention_target` directly, via `@all`, or via
+    /// `@team:<group>` for groups the target belongs to. With `unread_only`,
+    /// mentions the target has dismissed are skipped.
+    fn find_mentions(&self, thread_id: Option<&str>, mention_target: &str, unread_only: bool) -> Result<Vec<Message>, DomainError>;
+    /// Counts unread (not dismissed) mentions.
     fn count_mentions(&self, thread_id: Option<&str>, mention_target: &str) -> Result<usize, DomainError>;
-    fn find_by_type(&self, thread_id: Option<&str>, msg_type: &str) -> Result<Vec<Message>, DomainError>;
-    fn find_since_last_type(&self, thread_id: &str, msg_type: &str) -> Result<Vec<Message>, DomainError>;
+    /// Returns false when the mention was already dismissed.
+    fn dismiss_mention(&self, message_id: &str, sender: &str) -> Result<bool, DomainError>;
+    /// Messages of the thread posted after its latest message of `msg_type`
+    /// (all of them if there is none), windowed like `find`.
+    fn find_since_last_type(
+        &self,
+        thread_id: &str,
+        msg_type: &str,
+        filter: &MessageFilter,
+        page: &Page,
+    ) -> Result<Vec<Message>, DomainError>;
+    fn insert_link(&self, link: &MessageLink) -> Result<(), DomainError>;
+    /// Links where the message is either end, oldest first.
+    fn find_links(&self, message_id: &str) -> Result<Vec<MessageLink>, DomainError>;
+    /// Rebuilds the full-text index from the messages table; returns the number of indexed messages.
+    fn rebuild_search_index(&self) -> Result<usize, DomainError>;
+    fn search_index_status(&self) -> Result<SearchIndexStatus, DomainError>;
+}
+
+pub trait DraftRepository {
+    fn save(&self, draft: &Draft) -> Result<(), DomainError>;
+    fn find_by_id(&self, id: &str) -> Result<Option<Draft>, DomainError>;
+    fn resolve_short_id(&self, short_id: &str) -> Result<String, DomainError>;
+    fn list(&self, thread_id: Option<&str>, sender: Option<&str>) -> Result<Vec<Draft>, DomainError>;
+    fn delete(&self, id: &str) -> Result<(), DomainError>;
+}
+
+pub trait GroupRepository {
+    /// Returns false when the member was already in the group.
+    fn add_member(&self, name: &str, member: &str) -> Result<bool, DomainError>;
+    /// Returns false when the member was not in the group.
+    fn remove_member(&self, name: &str, member: &str) -> Result<bool, DomainError>;
+    fn list(&self) -> Result<Vec<Group>, DomainError>;
+}
+
+/// Bookkeeping of `sync` exchanges, one row per remote and direction.
+pub trait SyncStateRepository {
+    fn find(&self, remote: &str, direction: SyncDirection) -> Result<Option<SyncState>, DomainError>;
+    /// Replaces the row of the state's remote and direction.
+    fn save(&self, state: &SyncState) -> Result<(), DomainError>;
+    /// All rows, by remote and direction.
+    fn list(&self) -> Result<Vec<SyncState>, DomainError>;
+}
+
+/// Conditions of an audit log query; unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub operation: Option<AuditOperation>,
+    pub actor: Option<String>,
+    /// Prefix of an ID among the entry's targets.
+    pub target: Option<String>,
+    pub after: Option<DateTime<Utc>>,
+}
+
+/// The append-only audit log. Entries are never changed or removed.
+pub trait AuditRepository {
+    /// Appends the entry and returns the ID it was given (`entry.id` is ignored).
+    fn append(&self, entry: &AuditEntry) -> Result<i64, DomainError>;
+    /// Matching entries, newest first.
+    fn find(&self, filter: &AuditFilter, limit: usize) -> Result<Vec<AuditEntry>, DomainError>;
+}
+
+/// Sidecar storage of message embeddings, keyed by message and model.
+pub trait EmbeddingRepository {
+    /// Non-empty messages without an up-to-date vector for `model`, oldest first.
+    fn find_unembedded(&self, model: &str, limit: usize) -> Result<Vec<Message>, DomainError>;
+    fn count_unembedded(&self, model: &str) -> Result<usize, DomainError>;
+    /// Stores the vector for the message's current content.
+    fn save(&self, message_id: &str, model: &str, vector: &[f32]) -> Result<(), DomainError>;
+    /// Messages matching `filter` that have a vector for `model`.
+    fn find_vectors(&self, model: &str, filter: &MessageFilter) -> Result<Vec<(Message, Vec<f32>)>, DomainError>;
+    /// Deletes all vectors of `model`; returns how many were removed.
+    fn clear(&self, model: &str) -> Result<usize, DomainError>;
+    /// Vector counts per stored model, by model name.
+    fn status(&self) -> Result<Vec<EmbeddingStatus>, DomainError>;
+}
+
+/// Turns text into embedding vectors. Implementations decide where the
+/// model runs; vectors from different models must not be compared.
+pub trait EmbeddingProvider {
+    fn model(&self) -> &str;
+    /// One vector per input text, in input order.
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, DomainError>;
 }
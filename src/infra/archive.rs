@@ -0,0 +1,282 @@
+//! Gzip-compressed tar archives of plain files, as written by `aiboard export`.
+//!
+//! Only what the board archive needs is supported: regular files with names
+//! of up to 100 bytes. Other entry types are skipped when reading.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::domain::error::DomainError;
+
+const BLOCK: usize = 512;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Largest entry read from an archive. The size in a header is not trusted
+/// for allocation: a corrupt one can claim up to 8 GiB.
+const MAX_ENTRY_BYTES: u64 = 1 << 30;
+
+fn io_error(context: &str, path: &Path, e: std::io::Error) -> DomainError {
+    DomainError::Io(format!("{} {}: {}", context, path.display(), e))
+}
+
+pub struct ArchiveWriter {
+    path: PathBuf,
+    /// Where the archive is written until `finish` renames it to `path`.
+    tmp: PathBuf,
+    out: Option<GzEncoder<BufWriter<File>>>,
+    mtime: u64,
+}
+
+impl ArchiveWriter {
+    /// Starts an archive that `finish` puts at `path`, replacing any file
+    /// there. Until then it is written to a temporary file next to `path`,
+    /// which is removed again unless `finish` is called, so a failed export
+    /// leaves an existing archive alone.
+    pub fn create(path: &Path) -> Result<Self, DomainError> {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| DomainError::InvalidInput(format!("not a file path: {}", path.display())))?;
+        let tmp = path.with_file_name(format!(
+            ".{}.{}.tmp",
+            file_name.to_string_lossy(),
+            std::process::id()
+        ));
+        let file = File::create(&tmp).map_err(|e| io_error("failed to create", path, e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            tmp,
+            out: Some(GzEncoder::new(BufWriter::new(file), Compression::default())),
+            mtime: chrono::Utc::now().timestamp().max(0) as u64,
+        })
+    }
+
+    /// Appends a file named `name` holding `data`.
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<(), DomainError> {
+        let header = header(name, data.len() as u64, self.mtime)?;
+        let padding = (BLOCK - data.len() % BLOCK) % BLOCK;
+        self.write(&header)?;
+        self.write(data)?;
+        self.write(&[0u8; BLOCK][..padding])
+    }
+
+    /// Writes the end-of-archive marker, flushes the file and moves it to
+    /// its path.
+    pub fn finish(mut self) -> Result<(), DomainError> {
+        self.write(&[0u8; BLOCK * 2])?;
+        let out = self.out.take().expect("finish is called once");
+        let written = out
+            .finish()
+            .and_then(|mut file| file.flush())
+            .and_then(|_| std::fs::rename(&self.tmp, &self.path));
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&self.tmp);
+            return Err(io_error("failed to write", &self.path, e));
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), DomainError> {
+        let out = self.out.as_mut().expect("not finished");
+        out.write_all(bytes).map_err(|e| io_error("failed to write", &self.path, e))
+    }
+}
+
+impl Drop for ArchiveWriter {
+    fn drop(&mut self) {
+        if self.out.take().is_some() {
+            let _ = std::fs::remove_file(&self.tmp);
+        }
+    }
+}
+
+/// A ustar header for a regular file.
+fn header(name: &str, size: u64, mtime: u64) -> Result<[u8; BLOCK], DomainError> {
+    if name.len() > 100 {
+        return Err(DomainError::InvalidInput(format!("archive entry name too long: {}", name)));
+    }
+    let mut header = [0u8; BLOCK];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    octal(&mut header[100..108], 0o644);
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], size);
+    octal(&mut header[136..148], mtime);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[148..156].fill(b' ');
+    let sum: u64 = header.iter().map(|&b| b as u64).sum();
+    octal(&mut header[148..155], sum);
+    Ok(header)
+}
+
+/// Writes `value` as zero-padded octal digits followed by a NUL.
+fn octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}", value, width = field.len() - 1);
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[digits.len()] = 0;
+}
+
+fn parse_octal(field: &[u8]) -> Option<u64> {
+    let text = std::str::from_utf8(field).ok()?;
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(text, 8).ok()
+}
+
+pub struct ArchiveReader {
+    path: PathBuf,
+    input: Box<dyn Read>,
+}
+
+impl ArchiveReader {
+    /// Opens the archive at `path`, gzip-compressed or not.
+    pub fn open(path: &Path) -> Result<Self, DomainError> {
+        let mut file = BufReader::new(File::open(path).map_err(|e| io_error("failed to open", path, e))?);
+        let mut magic = [0u8; 2];
+        let compressed = file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC;
+        file.rewind().map_err(|e| io_error("failed to read", path, e))?;
+        let input: Box<dyn Read> = if compressed { Box::new(GzDecoder::new(file)) } else { Box::new(file) };
+        Ok(Self { path: path.to_path_buf(), input })
+    }
+
+    /// The next regular file as its name and contents, or `None` at the end.
+    pub fn next_file(&mut self) -> Result<Option<(String, Vec<u8>)>, DomainError> {
+        loop {
+            let mut header = [0u8; BLOCK];
+            self.read(&mut header)?;
+            if header.iter().all(|&b| b == 0) {
+                return Ok(None);
+            }
+            let invalid = || DomainError::Parse(format!("{} is not a valid archive", self.path.display()));
+            let checksum = parse_octal(&header[148..156]).ok_or_else(invalid)?;
+            let mut blank = header;
+            blank[148..156].fill(b' ');
+            if blank.iter().map(|&b| b as u64).sum::<u64>() != checksum {
+                return Err(invalid());
+            }
+            let size = parse_octal(&header[124..136]).ok_or_else(invalid)?;
+            if size > MAX_ENTRY_BYTES {
+                return Err(DomainError::Parse(format!(
+                    "{} has an entry of {} bytes (at most {} are read)",
+                    self.path.display(),
+                    size,
+                    MAX_ENTRY_BYTES
+                )));
+            }
+            let data = self.read_entry(size)?;
+            let size = size as usize;
+            let mut padding = [0u8; BLOCK];
+            self.read(&mut padding[..(BLOCK - size % BLOCK) % BLOCK])?;
+            if matches!(header[156], b'0' | 0) {
+                let end = header[..100].iter().position(|&b| b == 0).unwrap_or(100);
+                let name = String::from_utf8_lossy(&header[..end]).into_owned();
+                return Ok(Some((name, data)));
+            }
+        }
+    }
+
+    /// Reads `size` bytes, growing the buffer as they arrive rather than
+    /// allocating `size` up front.
+    fn read_entry(&mut self, size: u64) -> Result<Vec<u8>, DomainError> {
+        let mut data = Vec::new();
+        (&mut self.input)
+            .take(size)
+            .read_to_end(&mut data)
+            .map_err(|e| io_error("failed to read", &self.path, e))?;
+        if (data.len() as u64) < size {
+            return Err(DomainError::Parse(format!("{} ends unexpectedly", self.path.display())));
+        }
+        Ok(data)
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), DomainError> {
+        self.input.read_exact(buf).map_err(|e| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => {
+                DomainError::Parse(format!("{} ends unexpectedly", self.path.display()))
+            }
+            _ => io_error("failed to read", &self.path, e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_round_trip_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("board.tar.gz");
+        let mut writer = ArchiveWriter::create(&path).unwrap();
+        writer.add("manifest.json", b"{}").unwrap();
+        writer.add("threads/000001.json", &[b'x'; BLOCK]).unwrap();
+        writer.add("empty", b"").unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = ArchiveReader::open(&path).unwrap();
+        assert_eq!(reader.next_file().unwrap(), Some(("manifest.json".to_string(), b"{}".to_vec())));
+        assert_eq!(reader.next_file().unwrap(), Some(("threads/000001.json".to_string(), vec![b'x'; BLOCK])));
+        assert_eq!(reader.next_file().unwrap(), Some(("empty".to_string(), Vec::new())));
+        assert_eq!(reader.next_file().unwrap(), None);
+    }
+
+    #[test]
+    fn unfinished_archive_is_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("board.tar.gz");
+        let mut writer = ArchiveWriter::create(&path).unwrap();
+        writer.add("manifest.json", b"{}").unwrap();
+        drop(writer);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn failed_export_keeps_the_previous_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("board.tar.gz");
+        std::fs::write(&path, b"previous").unwrap();
+        let mut writer = ArchiveWriter::create(&path).unwrap();
+        writer.add("manifest.json", b"{}").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"previous");
+        drop(writer);
+        assert_eq!(std::fs::read(&path).unwrap(), b"previous");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let mut writer = ArchiveWriter::create(&path).unwrap();
+        writer.add("manifest.json", b"{}").unwrap();
+        writer.finish().unwrap();
+        let mut reader = ArchiveReader::open(&path).unwrap();
+        assert_eq!(reader.next_file().unwrap(), Some(("manifest.json".to_string(), b"{}".to_vec())));
+    }
+
+    #[test]
+    fn oversize_entry_is_rejected_without_allocating_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("board.tar");
+        std::fs::write(&path, header("huge", 0o77777777777, 0).unwrap()).unwrap();
+        assert!(matches!(ArchiveReader::open(&path).unwrap().next_file(), Err(DomainError::Parse(_))));
+
+        // A size under the cap but past the end of the file fails as a
+        // truncated archive.
+        std::fs::write(&path, header("short", 1 << 20, 0).unwrap()).unwrap();
+        assert!(matches!(ArchiveReader::open(&path).unwrap().next_file(), Err(DomainError::Parse(_))));
+    }
+
+    #[test]
+    fn corrupt_header_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("board.tar");
+        let mut block = header("a", 0, 0).unwrap();
+        block[0] = b'b';
+        std::fs::write(&path, block).unwrap();
+        assert!(matches!(ArchiveReader::open(&path).unwrap().next_file(), Err(DomainError::Parse(_))));
+    }
+}
@@ -1,11 +1,67 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind};
 use std::path::{Path, PathBuf};
 
-use chrono::Utc;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
 
 use crate::domain::error::DomainError;
+use crate::infra::sqlite::{BoardCopy, Database};
 
-/// DB ファイルのバックアップを作成し、バックアップ先のパスを返す。
-pub fn create_backup(db_path: &Path) -> Result<PathBuf, DomainError> {
+const TIMESTAMP_FORMAT: &str = "%Y%m%d%H%M%S";
+
+/// gzip 圧縮したバックアップの拡張子。
+const COMPRESSED_SUFFIX: &str = ".gz";
+
+/// DB ファイルの横にある `<DB ファイル名>.bak.<日時>[-<連番>][.<ラベル>].gz` の
+/// バックアップ（以前のバージョンが作成した圧縮なしの `<DB ファイル名>.bak.<日時>`
+/// も含む）。連番は同じ秒に作られた 2 つ目以降に付く。
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupFile {
+    /// ファイル名（`backup verify` などで指定する名前）。
+    pub name: String,
+    pub path: PathBuf,
+    /// ファイル名の日時（UTC）。
+    pub created_at: DateTime<Utc>,
+    /// `backup create --label` で付けたラベル。
+    pub label: Option<String>,
+    pub bytes: u64,
+    /// gzip 圧縮されているか。
+    pub compressed: bool,
+    /// 同じ秒のバックアップの中での順番（連番がなければ 1）。
+    #[serde(skip)]
+    seq: u32,
+}
+
+/// `prune_backups` で削除するバックアップの条件。新しい方から `keep` 件は
+/// 常に残し、残りのうち `older_than_days` 日より古いものを削除する
+/// （`older_than_days` がなければ残り全部）。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrunePolicy {
+    pub keep: Option<usize>,
+    pub older_than_days: Option<i64>,
+}
+
+impl PrunePolicy {
+    /// 条件が何も指定されていない（何も削除しない）か。
+    pub fn is_empty(&self) -> bool {
+        self.keep.is_none() && self.older_than_days.is_none()
+    }
+}
+
+/// DB ファイルを gzip 圧縮したバックアップを作成し、バックアップ先のパスを返す。
+/// ファイルをそのまま複製するのではなく `VACUUM INTO` で取った複製を圧縮する
+/// ので、別のプロセスが書き込み中でも WAL の内容まで含めた一貫した状態になる。
+/// `label` はファイル名の日時の後ろに付く。
+pub fn create_backup(db_path: &Path, label: Option<&str>) -> Result<PathBuf, DomainError> {
+    // 利用者向けのメッセージは呼び出し側が出す。ここではファイル名が
+    // DB の横から外れないことだけを保証する。
+    if let Some(label) = label.filter(|l| !is_valid_label(l)) {
+        return Err(DomainError::InvalidInput(format!("invalid backup label: {}", label)));
+    }
     if !db_path.exists() {
         return Err(DomainError::Io(format!(
             "バックアップ対象のファイルが見つかりません: {}",
@@ -13,29 +69,308 @@ pub fn create_backup(db_path: &Path) -> Result<PathBuf, DomainError> {
         )));
     }
 
-    let timestamp = Utc::now().format("%Y%m%d%H%M%S");
-    let file_name = format!(
-        "{}.bak.{}",
-        db_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("aiboard.db"),
-        timestamp
+    let timestamp = Utc::now().format(TIMESTAMP_FORMAT).to_string();
+    let db_name = db_path.file_name().and_then(|n| n.to_str()).unwrap_or("aiboard.db");
+    let dir = db_path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut snapshot = BoardCopy::open(db_path)?;
+    let source = snapshot.close()?;
+    // 同じ秒に作られたバックアップがあれば `-2`, `-3`, ... を付けて別名にする。
+    // 既存のファイルは決して上書きしない。
+    for seq in 1.. {
+        let stamp = if seq == 1 { timestamp.clone() } else { format!("{}-{}", timestamp, seq) };
+        let file_name = format!(
+            "{}.bak.{}{}{}",
+            db_name,
+            stamp,
+            label.map(|l| format!(".{}", l)).unwrap_or_default(),
+            COMPRESSED_SUFFIX
+        );
+        let backup_path = dir.join(file_name);
+        let file = match OpenOptions::new().write(true).create_new(true).open(&backup_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(DomainError::Io(format!("バックアップの作成に失敗しました: {}", e)));
+            }
+        };
+        write_compressed(source, file).map_err(|e| {
+            // 書きかけのバックアップは残さない。
+            let _ = std::fs::remove_file(&backup_path);
+            DomainError::Io(format!(
+                "バックアップの作成に失敗しました: {}",
+                e
+            ))
+        })?;
+        return Ok(backup_path);
+    }
+    unreachable!("backup sequence numbers exhausted")
+}
+
+/// ラベルはファイル名に入るため、英数字・`-`・`_` の 64 文字までに限る。
+pub fn is_valid_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= 64
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn write_compressed(source: &Path, target: File) -> std::io::Result<()> {
+    let mut input = File::open(source)?;
+    let mut encoder = GzEncoder::new(BufWriter::new(target), Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.into_inner().map_err(|e| e.into_error())?.sync_all()
+}
+
+/// DB ファイルのバックアップを新しい順に返す。
+pub fn list_backups(db_path: &Path) -> Result<Vec<BackupFile>, DomainError> {
+    let dir = match db_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let prefix = format!(
+        "{}.bak.",
+        db_path.file_name().and_then(|n| n.to_str()).unwrap_or("aiboard.db")
     );
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(DomainError::Io(format!(
+                "バックアップの一覧を取得できません: {}: {}",
+                dir.display(),
+                e
+            )))
+        }
+    };
+
+    let mut backups = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        let Some(rest) = name.strip_prefix(&prefix) else {
+            continue;
+        };
+        let (rest, compressed) = match rest.strip_suffix(COMPRESSED_SUFFIX) {
+            Some(rest) => (rest, true),
+            None => (rest, false),
+        };
+        let (timestamp, label) = match rest.split_once('.') {
+            Some((timestamp, label)) if is_valid_label(label) => (timestamp, Some(label.to_string())),
+            Some(_) => continue,
+            None => (rest, None),
+        };
+        let (timestamp, seq) = match timestamp.split_once('-') {
+            Some((timestamp, seq)) => match seq.parse::<u32>() {
+                Ok(seq) if seq >= 2 => (timestamp, seq),
+                _ => continue,
+            },
+            None => (timestamp, 1),
+        };
+        let Ok(created) = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT) else {
+            continue;
+        };
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if meta.is_file() {
+            backups.push(BackupFile {
+                name: name.to_string(),
+                path: entry.path(),
+                created_at: created.and_utc(),
+                label,
+                bytes: meta.len(),
+                compressed,
+                seq,
+            });
+        }
+    }
+    backups.sort_by_key(|b| std::cmp::Reverse((b.created_at, b.seq)));
+    Ok(backups)
+}
+
+/// ファイル名、日時（`20240101093000`）、ラベルのいずれかで指定された
+/// バックアップ。同じラベルのものが複数あれば最も新しいもの。
+pub fn find_backup(db_path: &Path, name: &str) -> Result<Option<BackupFile>, DomainError> {
+    Ok(list_backups(db_path)?.into_iter().find(|b| {
+        b.name == name
+            || b.created_at.format(TIMESTAMP_FORMAT).to_string() == name
+            || b.label.as_deref() == Some(name)
+    }))
+}
+
+/// `backup list` の 1 件。開けなかったバックアップは件数の代わりに `error` を持つ。
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupSummary {
+    #[serde(flatten)]
+    pub file: BackupFile,
+    /// バックアップ時点の DB のスキーマバージョン。
+    pub db_schema_version: Option<i64>,
+    pub threads: Option<u64>,
+    pub messages: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// バックアップを開き、スキーマバージョンと件数を調べる。
+pub fn summarize(file: BackupFile) -> BackupSummary {
+    let contents = if file.compressed {
+        extract(&file).and_then(|scratch| contents(Database::open_immutable(&scratch.0)?))
+    } else {
+        Database::open_immutable(&file.path).and_then(contents)
+    };
+    summary_of(file, contents)
+}
+
+fn contents(db: Database) -> Result<(i64, (u64, u64)), DomainError> {
+    Ok((db.schema_version()?, db.counts()?))
+}
+
+fn summary_of(file: BackupFile, contents: Result<(i64, (u64, u64)), DomainError>) -> BackupSummary {
+    match contents {
+        Ok((version, (threads, messages))) => BackupSummary {
+            file,
+            db_schema_version: Some(version),
+            threads: Some(threads),
+            messages: Some(messages),
+            error: None,
+        },
+        Err(e) => BackupSummary {
+            file,
+            db_schema_version: None,
+            threads: None,
+            messages: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// `backup verify` の結果。`problems` が空なら復元に使える。
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupVerification {
+    #[serde(flatten)]
+    pub summary: BackupSummary,
+    /// `PRAGMA integrity_check` の報告、または開けなかった理由。
+    pub problems: Vec<String>,
+}
+
+impl BackupVerification {
+    pub fn healthy(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// バックアップの整合性チェックを行う。全文検索インデックスのチェックは
+/// 書き込みを伴うため、バックアップ自体ではなく取り出したコピーを検査する。
+pub fn verify(file: BackupFile) -> BackupVerification {
+    let checked = extract(&file).and_then(|scratch| {
+        let db = Database::open_unmigrated(&scratch.0)?;
+        let problems = db.integrity_errors()?;
+        Ok((contents(db)?, problems))
+    });
+    match checked {
+        Ok((contents, problems)) => BackupVerification { summary: summary_of(file, Ok(contents)), problems },
+        Err(e) => {
+            let problems = vec![e.to_string()];
+            BackupVerification { summary: summary_of(file, Err(e)), problems }
+        }
+    }
+}
+
+/// 復元の準備ができたバックアップ。`prepare_restore` で取り出して検査し、
+/// `apply` で DB を置き換える。取り出しを先に済ませておけば、その後で
+/// 退避用のバックアップを作っても復元するものには影響しない。
+pub struct PreparedRestore {
+    // `scratch` より先に閉じる。
+    source: Database,
+    _scratch: Scratch,
+}
 
-    let backup_path = db_path
-        .parent()
-        .unwrap_or_else(|| Path::new("."))
-        .join(file_name);
+/// バックアップを一時ファイルに取り出して検査する。壊れたバックアップは
+/// ここで拒否する。
+pub fn prepare_restore(file: &BackupFile) -> Result<PreparedRestore, DomainError> {
+    let scratch = extract(file)?;
+    let source = Database::open_unmigrated(&scratch.0)?;
+    if !source.integrity_errors()?.is_empty() {
+        return Err(DomainError::Database(format!(
+            "{} は壊れているため復元できません（backup verify で詳細を確認できます）",
+            file.name
+        )));
+    }
+    Ok(PreparedRestore { source, _scratch: scratch })
+}
+
+impl PreparedRestore {
+    /// バックアップの内容で DB を置き換える。SQLite のバックアップ API で
+    /// 書き込むため、他のプロセスが DB を開いていても壊れない。
+    pub fn apply(self, db_path: &Path) -> Result<(), DomainError> {
+        let mut target = Database::open(db_path)?;
+        target.replace_with(&self.source)?;
+        drop(target);
+        // 古いスキーマのバックアップを現在のスキーマに移行する。
+        Database::open(db_path)?;
+        Ok(())
+    }
+}
 
-    std::fs::copy(db_path, &backup_path).map_err(|e| {
-        DomainError::Io(format!(
-            "バックアップの作成に失敗しました: {}",
-            e
-        ))
+/// バックアップの中身を一時ファイルに取り出す。圧縮されたものは展開する。
+fn extract(file: &BackupFile) -> Result<Scratch, DomainError> {
+    let scratch = Scratch(std::env::temp_dir().join(format!("aiboard-backup-{}.db", uuid::Uuid::new_v4())));
+    let copied = if file.compressed {
+        File::open(&file.path).and_then(|input| {
+            let mut decoder = GzDecoder::new(BufReader::new(input));
+            std::io::copy(&mut decoder, &mut File::create(&scratch.0)?)
+        })
+    } else {
+        std::fs::copy(&file.path, &scratch.0)
+    };
+    copied.map_err(|e| {
+        DomainError::Io(format!("バックアップを読み込めません: {}: {}", file.path.display(), e))
     })?;
+    Ok(scratch)
+}
+
+/// 取り出したバックアップの一時ファイル。drop で削除する。
+struct Scratch(PathBuf);
 
-    Ok(backup_path)
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        for suffix in ["", "-journal", "-wal", "-shm"] {
+            let mut path = self.0.as_os_str().to_owned();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// `policy` に当てはまるバックアップを削除し、削除したものを返す。
+/// `dry_run` なら削除せずに対象だけを返す。
+pub fn prune_backups(
+    db_path: &Path,
+    policy: &PrunePolicy,
+    dry_run: bool,
+) -> Result<Vec<BackupFile>, DomainError> {
+    if policy.is_empty() {
+        return Ok(Vec::new());
+    }
+    let cutoff = policy.older_than_days.map(|days| Utc::now() - Duration::days(days));
+    let targets: Vec<BackupFile> = list_backups(db_path)?
+        .into_iter()
+        .skip(policy.keep.unwrap_or(0))
+        .filter(|b| cutoff.is_none_or(|cutoff| b.created_at < cutoff))
+        .collect();
+    if !dry_run {
+        for backup in &targets {
+            std::fs::remove_file(&backup.path).map_err(|e| {
+                DomainError::Io(format!(
+                    "バックアップを削除できません: {}: {}",
+                    backup.path.display(),
+                    e
+                ))
+            })?;
+        }
+    }
+    Ok(targets)
 }
 
 #[cfg(test)]
@@ -44,18 +379,105 @@ mod tests {
     use std::fs;
 
     #[test]
-    fn create_backup_copies_file() {
+    fn create_backup_includes_writes_still_in_the_wal() {
         let dir = tempfile::tempdir().unwrap();
         let db_path = dir.path().join("aiboard.db");
-        fs::write(&db_path, b"test data").unwrap();
-
-        let backup_path = create_backup(&db_path).unwrap();
+        // The open connection keeps the insert in aiboard.db-wal, not in
+        // aiboard.db itself.
+        let db = Database::open(&db_path).unwrap();
+        db.connection()
+            .execute("INSERT INTO threads (id, title) VALUES ('t1', 'in the wal')", [])
+            .unwrap();
 
-        assert!(backup_path.exists());
-        assert_eq!(fs::read(&backup_path).unwrap(), b"test data");
+        let backup_path = create_backup(&db_path, None).unwrap();
 
         let name = backup_path.file_name().unwrap().to_str().unwrap();
         assert!(name.starts_with("aiboard.db.bak."));
+        assert!(name.ends_with(".gz"));
+        let backups = list_backups(&db_path).unwrap();
+        let summary = summarize(backups.into_iter().next().unwrap());
+        assert_eq!(summary.error, None);
+        assert_eq!(summary.threads, Some(1));
+        drop(db);
+    }
+
+    #[test]
+    fn backups_in_the_same_second_get_their_own_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("aiboard.db");
+        drop(Database::open(&db_path).unwrap());
+        // Pretend a backup was already made this second.
+        let taken = dir.path().join(format!("aiboard.db.bak.{}.gz", Utc::now().format(TIMESTAMP_FORMAT)));
+        fs::write(&taken, b"earlier backup").unwrap();
+
+        let path = create_backup(&db_path, None).unwrap();
+        assert_ne!(path, taken);
+        assert_eq!(fs::read(&taken).unwrap(), b"earlier backup");
+        let backups = list_backups(&db_path).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].path, path);
+    }
+
+    fn backup_at(dir: &Path, days_ago: i64) -> PathBuf {
+        let timestamp = (Utc::now() - Duration::days(days_ago)).format(TIMESTAMP_FORMAT);
+        let path = dir.join(format!("aiboard.db.bak.{}", timestamp));
+        fs::write(&path, b"backup").unwrap();
+        path
+    }
+
+    #[test]
+    fn list_backups_newest_first_and_ignores_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("aiboard.db");
+        let old = backup_at(dir.path(), 3);
+        let new = backup_at(dir.path(), 1);
+        let compressed = dir.path().join(format!(
+            "aiboard.db.bak.{}.gz",
+            (Utc::now() - Duration::days(2)).format(TIMESTAMP_FORMAT)
+        ));
+        fs::write(&compressed, b"").unwrap();
+        fs::write(dir.path().join("aiboard.db.bak.notes"), b"").unwrap();
+        fs::write(dir.path().join("other.db.bak.20240101000000"), b"").unwrap();
+
+        let paths: Vec<PathBuf> = list_backups(&db_path).unwrap().into_iter().map(|b| b.path).collect();
+        assert_eq!(paths, [new, compressed, old]);
+    }
+
+    #[test]
+    fn labelled_backups_are_found_by_label() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("aiboard.db");
+        drop(Database::open(&db_path).unwrap());
+        let path = create_backup(&db_path, Some("before-import")).unwrap();
+        assert!(path.to_string_lossy().ends_with(".before-import.gz"));
+
+        let found = find_backup(&db_path, "before-import").unwrap().unwrap();
+        assert_eq!(found.path, path);
+        assert_eq!(found.label.as_deref(), Some("before-import"));
+        assert!(create_backup(&db_path, Some("../escape")).is_err());
+    }
+
+    #[test]
+    fn prune_keeps_newest_and_removes_old() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("aiboard.db");
+        let newest = backup_at(dir.path(), 0);
+        let recent = backup_at(dir.path(), 2);
+        let old = backup_at(dir.path(), 10);
+        let oldest = backup_at(dir.path(), 20);
+
+        let policy = PrunePolicy { keep: Some(1), older_than_days: Some(7) };
+        let planned = prune_backups(&db_path, &policy, true).unwrap();
+        assert_eq!(planned.len(), 2);
+        assert!(old.exists());
+
+        prune_backups(&db_path, &policy, false).unwrap();
+        assert!(newest.exists() && recent.exists());
+        assert!(!old.exists() && !oldest.exists());
+
+        let removed = prune_backups(&db_path, &PrunePolicy { keep: Some(1), older_than_days: None }, false).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert!(newest.exists() && !recent.exists());
     }
 
     #[test]
@@ -63,7 +485,7 @@ mod tests {
         let dir = tempfile::tempdir().unwrap();
         let db_path = dir.path().join("nonexistent.db");
 
-        let result = create_backup(&db_path);
+        let result = create_backup(&db_path, None);
         assert!(result.is_err());
     }
 }
@@ -0,0 +1,173 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use crate::domain::error::DomainError;
+
+/// Values accepted for `format`; a command uses it as its `--format` default
+/// when it supports the value.
+const FORMATS: [&str; 10] = ["text", "json", "jsonl", "yaml", "yml", "markdown", "md", "transcript", "csv", "tsv"];
+
+/// Defaults read from `~/.aiboard/config.toml` and the nearest
+/// `.aiboard.toml` (project settings win). Command-line flags and
+/// environment variables win over both.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Default `--sender` (who the agent is).
+    pub sender: Option<String>,
+    /// Default `--format`.
+    pub format: Option<String>,
+    /// Data directory, below `--data-dir` and `AIBOARD_DATA_DIR`.
+    pub data_dir: Option<PathBuf>,
+    /// Characters of content shown when output is truncated.
+    pub truncate: Option<usize>,
+    /// `--strict-integrity` for every board created.
+    pub strict_integrity: Option<bool>,
+    pub cleanup: CleanupConfig,
+    pub backup: BackupConfig,
+    pub fetch: FetchConfig,
+    pub log: LogConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CleanupConfig {
+    /// Default `<DAYS>` of `cleanup age`.
+    pub days: Option<i64>,
+}
+
+/// Defaults of `backup prune`. When either is set, the same pruning also
+/// runs after every backup made by `cleanup`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupConfig {
+    /// Newest backups that are never pruned.
+    pub keep: Option<usize>,
+    /// Backups beyond `keep` older than this many days are pruned.
+    pub older_than_days: Option<i64>,
+}
+
+/// Rotation of `~/.aiboard/error.log`. The log is also rotated when the
+/// first error of a new day is written.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// Size in bytes at which error.log is rotated.
+    pub max_bytes: Option<u64>,
+    /// Rotated files (error.log.1, error.log.2, ...) that are kept.
+    pub keep: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FetchConfig {
+    /// Hosts refused by `thread fetch`, on top of the built-in list.
+    pub blocked_hosts: Vec<String>,
+}
+
+impl Config {
+    /// Fields set in `other` replace those in `self`; blocked hosts add up.
+    fn merge(mut self, other: Config) -> Config {
+        self.sender = other.sender.or(self.sender);
+        self.format = other.format.or(self.format);
+        self.data_dir = other.data_dir.or(self.data_dir);
+        self.truncate = other.truncate.or(self.truncate);
+        self.strict_integrity = other.strict_integrity.or(self.strict_integrity);
+        self.cleanup.days = other.cleanup.days.or(self.cleanup.days);
+        self.backup.keep = other.backup.keep.or(self.backup.keep);
+        self.backup.older_than_days = other.backup.older_than_days.or(self.backup.older_than_days);
+        self.log.max_bytes = other.log.max_bytes.or(self.log.max_bytes);
+        self.log.keep = other.log.keep.or(self.log.keep);
+        self.fetch.blocked_hosts.extend(other.fetch.blocked_hosts);
+        self
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+pub fn set(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// The loaded configuration (empty before `set`).
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Reads the user and project configuration files; missing files are empty.
+pub fn load() -> Result<Config, DomainError> {
+    let mut config = Config::default();
+    if let Some(home) = home_dir() {
+        config = config.merge(read(&home.join(".aiboard").join("config.toml"))?);
+    }
+    if let Some(project) = std::env::current_dir().ok().and_then(|dir| find_project_file(&dir)) {
+        config = config.merge(read(&project)?);
+    }
+    Ok(config)
+}
+
+/// `.aiboard.toml` in `dir` or its nearest ancestor.
+fn find_project_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(".aiboard.toml"))
+        .find(|path| path.is_file())
+}
+
+fn read(path: &Path) -> Result<Config, DomainError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(DomainError::Io(format!("{}: {}", path.display(), e))),
+    };
+    let mut config: Config =
+        toml::from_str(&text).map_err(|e| DomainError::Parse(format!("{}: {}", path.display(), e)))?;
+    if let Some(ref format) = config.format {
+        if !FORMATS.contains(&format.as_str()) {
+            return Err(DomainError::Parse(format!(
+                "{}: unknown format '{}' (expected one of {})",
+                path.display(),
+                format,
+                FORMATS.join(", ")
+            )));
+        }
+    }
+    // A relative data_dir is relative to the file that sets it.
+    if let (Some(dir), Some(base)) = (config.data_dir.as_ref(), path.parent()) {
+        if dir.is_relative() {
+            config.data_dir = Some(base.join(dir));
+        }
+    }
+    Ok(config)
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("USERPROFILE")
+        .or_else(|| std::env::var_os("HOME"))
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_values_override_user_values() {
+        let user: Config = toml::from_str(
+            "sender = \"alice\"\nformat = \"json\"\n[fetch]\nblocked_hosts = [\"a.example\"]\n",
+        )
+        .unwrap();
+        let project: Config =
+            toml::from_str("sender = \"bob\"\n[fetch]\nblocked_hosts = [\"b.example\"]\n").unwrap();
+        let merged = user.merge(project);
+        assert_eq!(merged.sender.as_deref(), Some("bob"));
+        assert_eq!(merged.format.as_deref(), Some("json"));
+        assert_eq!(merged.fetch.blocked_hosts, ["a.example", "b.example"]);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<Config>("sendr = \"alice\"\n").is_err());
+    }
+}
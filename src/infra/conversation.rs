@@ -0,0 +1,375 @@
+//! Extraction of individual posts from known conversation page layouts
+//! (ChatGPT share pages, GitHub issue/PR timelines, Discourse-style forums).
+
+use chrono::{DateTime, Utc};
+use html5ever::tendril::TendrilSink;
+use markup5ever_rcdom::{Handle, NodeData, RcDom, SerializableHandle};
+
+use crate::domain::entity::Role;
+use crate::domain::error::DomainError;
+use crate::infra::http::html_to_markdown;
+
+/// One post recovered from a conversation page.
+#[derive(Debug, Clone)]
+pub struct ExtractedPost {
+    pub sender: Option<String>,
+    pub role: Role,
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Post body converted to Markdown.
+    pub content: String,
+}
+
+/// Splits a page into posts when its layout is recognized. Returns `None`
+/// when no known conversation structure is found, so callers can fall back
+/// to storing the whole page.
+pub fn extract_posts(html: &str) -> Option<Vec<ExtractedPost>> {
+    let dom = html5ever::parse_document(RcDom::default(), Default::default())
+        .from_utf8()
+        .read_from(&mut html.as_bytes())
+        .ok()?;
+    let root = dom.document;
+
+    [chatgpt_posts, github_posts, forum_posts]
+        .iter()
+        .map(|extract| extract(&root))
+        .find(|posts| !posts.is_empty())
+}
+
+/// ChatGPT share pages mark each turn with `data-message-author-role`.
+fn chatgpt_posts(root: &Handle) -> Vec<ExtractedPost> {
+    find_all(root, &|n| attr(n, "data-message-author-role").is_some())
+        .into_iter()
+        .filter_map(|node| {
+            let role = match attr(&node, "data-message-author-role")?.as_str() {
+                "user" => Role::User,
+                "assistant" => Role::Assistant,
+                _ => Role::System,
+            };
+            let sender = (role == Role::Assistant).then(|| "chatgpt".to_string());
+            post(sender, role, None, &node)
+        })
+        .collect()
+}
+
+/// GitHub issue and pull request pages: one `.timeline-comment` per comment,
+/// with the login in `.author` and the time in `<relative-time datetime>`.
+fn github_posts(root: &Handle) -> Vec<ExtractedPost> {
+    find_all(root, &|n| has_class(n, "timeline-comment"))
+        .into_iter()
+        .filter_map(|node| {
+            let sender = find_first(&node, &|n| has_class(n, "author")).map(|a| text_content(&a));
+            let timestamp = find_first(&node, &|n| tag_name(n) == Some("relative-time"))
+                .and_then(|t| attr(&t, "datetime"))
+                .and_then(|s| parse_timestamp(&s));
+            let body = find_first(&node, &|n| has_class(n, "comment-body"))?;
+            post(sender, Role::User, timestamp, &body)
+        })
+        .collect()
+}
+
+/// Discourse-style forums: one `.topic-post` per post, the author in
+/// `.username` and the body in `.cooked`.
+fn forum_posts(root: &Handle) -> Vec<ExtractedPost> {
+    find_all(root, &|n| has_class(n, "topic-post"))
+        .into_iter()
+        .filter_map(|node| {
+            let sender = find_first(&node, &|n| has_class(n, "username")).map(|a| text_content(&a));
+            let timestamp = find_first(&node, &|n| {
+                tag_name(n) == Some("time") && attr(n, "datetime").is_some()
+            })
+            .and_then(|t| attr(&t, "datetime"))
+            .and_then(|s| parse_timestamp(&s));
+            let body = find_first(&node, &|n| has_class(n, "cooked"))?;
+            post(sender, Role::User, timestamp, &body)
+        })
+        .collect()
+}
+
+/// A small CSS selector subset used to locate pagination links: compound
+/// selectors (`a`, `.next`, `#more`, `[rel=next]`, `a.next[rel]`) joined by
+/// descendant combinators, e.g. `nav.pagination a[rel=next]`.
+#[derive(Debug, Clone)]
+pub struct Selector(Vec<Compound>);
+
+#[derive(Debug, Clone, Default)]
+struct Compound {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+    attrs: Vec<(String, Option<String>)>,
+}
+
+impl Selector {
+    pub fn parse(selector: &str) -> Result<Self, DomainError> {
+        let compounds = selector
+            .split_whitespace()
+            .map(|part| parse_compound(part).ok_or_else(|| {
+                DomainError::InvalidInput(format!("unsupported selector: '{}'", selector))
+            }))
+            .collect::<Result<Vec<_>, _>>()?;
+        if compounds.is_empty() {
+            return Err(DomainError::InvalidInput("selector is empty".to_string()));
+        }
+        Ok(Selector(compounds))
+    }
+}
+
+impl Compound {
+    fn matches(&self, node: &Handle) -> bool {
+        let Some(tag) = tag_name(node) else {
+            return false;
+        };
+        self.tag.as_deref().is_none_or(|t| t.eq_ignore_ascii_case(tag))
+            && self.id.as_deref().is_none_or(|id| attr(node, "id").as_deref() == Some(id))
+            && self.classes.iter().all(|c| has_class(node, c))
+            && self.attrs.iter().all(|(key, value)| match (attr(node, key), value) {
+                (Some(_), None) => true,
+                (Some(actual), Some(expected)) => actual == *expected,
+                (None, _) => false,
+            })
+    }
+}
+
+fn parse_compound(part: &str) -> Option<Compound> {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    let take_ident = |s: &str| -> Option<(String, usize)> {
+        let end = s.find(|c: char| !is_ident(c)).unwrap_or(s.len());
+        (end > 0).then(|| (s[..end].to_string(), end))
+    };
+
+    let mut compound = Compound::default();
+    let mut rest = part;
+    if let Some((tag, len)) = take_ident(rest) {
+        compound.tag = Some(tag);
+        rest = &rest[len..];
+    } else if let Some(after) = rest.strip_prefix('*') {
+        rest = after;
+    }
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let (class, len) = take_ident(after)?;
+            compound.classes.push(class);
+            rest = &after[len..];
+        } else if let Some(after) = rest.strip_prefix('#') {
+            let (id, len) = take_ident(after)?;
+            compound.id = Some(id);
+            rest = &after[len..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']')?;
+            let (key, value) = match after[..end].split_once('=') {
+                Some((key, value)) => {
+                    let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+                    (key.trim(), Some(value.to_string()))
+                }
+                None => (after[..end].trim(), None),
+            };
+            if key.is_empty() || !key.chars().all(is_ident) {
+                return None;
+            }
+            compound.attrs.push((key.to_string(), value));
+            rest = &after[end + 1..];
+        } else {
+            return None;
+        }
+    }
+    Some(compound)
+}
+
+/// The `href` of the first element matching `selector`, or of the first
+/// link inside it (so `.pagination .next` works on a wrapping `<li>`).
+pub fn find_href(html: &str, selector: &Selector) -> Option<String> {
+    let dom = html5ever::parse_document(RcDom::default(), Default::default())
+        .from_utf8()
+        .read_from(&mut html.as_bytes())
+        .ok()?;
+    let found = find_matching(&dom.document, &selector.0)?;
+    attr(&found, "href").or_else(|| {
+        find_first(&found, &|n| tag_name(n) == Some("a") && attr(n, "href").is_some())
+            .and_then(|a| attr(&a, "href"))
+    })
+}
+
+/// First element in document order whose ancestors match the leading
+/// compounds of `chain` and which itself matches the last one.
+fn find_matching(node: &Handle, chain: &[Compound]) -> Option<Handle> {
+    let (first, rest) = chain.split_first()?;
+    for child in node.children.borrow().iter() {
+        if first.matches(child) {
+            if rest.is_empty() {
+                return Some(child.clone());
+            }
+            if let Some(found) = find_matching(child, rest) {
+                return Some(found);
+            }
+        }
+        if let Some(found) = find_matching(child, chain) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+fn post(
+    sender: Option<String>,
+    role: Role,
+    timestamp: Option<DateTime<Utc>>,
+    body: &Handle,
+) -> Option<ExtractedPost> {
+    let content = html_to_markdown(&inner_html(body));
+    if content.trim().is_empty() {
+        return None;
+    }
+    Some(ExtractedPost {
+        sender: sender.filter(|s| !s.is_empty()),
+        role,
+        timestamp,
+        content,
+    })
+}
+
+fn parse_timestamp(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+fn tag_name(node: &Handle) -> Option<&str> {
+    match node.data {
+        NodeData::Element { ref name, .. } => Some(&name.local),
+        _ => None,
+    }
+}
+
+fn attr(node: &Handle, key: &str) -> Option<String> {
+    match node.data {
+        NodeData::Element { ref attrs, .. } => attrs
+            .borrow()
+            .iter()
+            .find(|a| &*a.name.local == key)
+            .map(|a| a.value.to_string()),
+        _ => None,
+    }
+}
+
+fn has_class(node: &Handle, class: &str) -> bool {
+    attr(node, "class").is_some_and(|c| c.split_whitespace().any(|c| c == class))
+}
+
+/// Descendants matching `pred`, in document order. Matches are not searched
+/// further, so nested layouts yield only their outermost elements.
+fn find_all(node: &Handle, pred: &dyn Fn(&Handle) -> bool) -> Vec<Handle> {
+    let mut found = Vec::new();
+    for child in node.children.borrow().iter() {
+        if pred(child) {
+            found.push(child.clone());
+        } else {
+            found.extend(find_all(child, pred));
+        }
+    }
+    found
+}
+
+fn find_first(node: &Handle, pred: &dyn Fn(&Handle) -> bool) -> Option<Handle> {
+    for child in node.children.borrow().iter() {
+        if pred(child) {
+            return Some(child.clone());
+        }
+        if let Some(found) = find_first(child, pred) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+fn text_content(node: &Handle) -> String {
+    fn collect(node: &Handle, out: &mut String) {
+        if let NodeData::Text { ref contents } = node.data {
+            out.push_str(&contents.borrow());
+        }
+        for child in node.children.borrow().iter() {
+            collect(child, out);
+        }
+    }
+    let mut out = String::new();
+    collect(node, &mut out);
+    out.trim().to_string()
+}
+
+fn inner_html(node: &Handle) -> String {
+    let mut buf = Vec::new();
+    let handle = SerializableHandle::from(node.clone());
+    if html5ever::serialize(&mut buf, &handle, Default::default()).is_err() {
+        return String::new();
+    }
+    String::from_utf8(buf).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_chatgpt_turns() {
+        let html = r#"<html><body>
+            <div data-message-author-role="user"><p>How do I sort?</p></div>
+            <div data-message-author-role="assistant"><p>Use <code>sort()</code>.</p></div>
+        </body></html>"#;
+
+        let posts = extract_posts(html).unwrap();
+        assert_eq!(posts.len(), 2);
+        assert_eq!(posts[0].role, Role::User);
+        assert_eq!(posts[0].sender, None);
+        assert_eq!(posts[0].content, "How do I sort?");
+        assert_eq!(posts[1].role, Role::Assistant);
+        assert_eq!(posts[1].sender.as_deref(), Some("chatgpt"));
+        assert_eq!(posts[1].content, "Use `sort()`.");
+    }
+
+    #[test]
+    fn extracts_github_comments() {
+        let html = r#"<html><body>
+            <div class="timeline-comment">
+              <a class="author">octocat</a>
+              <relative-time datetime="2024-05-01T12:00:00Z">May 1</relative-time>
+              <div class="comment-body"><p>First!</p></div>
+            </div>
+            <div class="timeline-comment">
+              <a class="author">hubot</a>
+              <div class="comment-body"><p>Second</p></div>
+            </div>
+        </body></html>"#;
+
+        let posts = extract_posts(html).unwrap();
+        assert_eq!(posts.len(), 2);
+        assert_eq!(posts[0].sender.as_deref(), Some("octocat"));
+        assert_eq!(
+            posts[0].timestamp.unwrap().to_rfc3339(),
+            "2024-05-01T12:00:00+00:00"
+        );
+        assert_eq!(posts[1].content, "Second");
+        assert!(posts[1].timestamp.is_none());
+    }
+
+    #[test]
+    fn finds_next_link_by_selector() {
+        let html = r#"<html><body>
+            <a href="/about">About</a>
+            <nav class="pagination">
+              <a href="?page=1" rel="prev">Prev</a>
+              <li class="next"><a href="?page=3">Next</a></li>
+            </nav>
+        </body></html>"#;
+
+        let href = |s: &str| find_href(html, &Selector::parse(s).unwrap());
+        assert_eq!(href("nav.pagination .next").as_deref(), Some("?page=3"));
+        assert_eq!(href("a[rel=prev]").as_deref(), Some("?page=1"));
+        assert_eq!(href("a[rel='next']"), None);
+        assert!(Selector::parse("a > b").is_err());
+        assert!(Selector::parse("  ").is_err());
+    }
+
+    #[test]
+    fn unrecognized_page_is_not_split() {
+        assert!(extract_posts("<html><body><p>plain article</p></body></html>").is_none());
+    }
+}
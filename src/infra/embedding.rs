@@ -0,0 +1,135 @@
+//! Embedding provider for any server speaking the OpenAI-compatible
+//! `POST /embeddings` API (OpenAI, Ollama, LM Studio, llama.cpp, ...).
+//!
+//! Configured through the environment:
+//! - `AIBOARD_EMBEDDING_URL`: full endpoint URL, e.g. `http://localhost:11434/v1/embeddings`
+//! - `AIBOARD_EMBEDDING_MODEL`: model name sent with each request
+//! - `AIBOARD_EMBEDDING_API_KEY`: optional, sent as a bearer token
+//!
+//! Unlike `thread fetch`, the endpoint is chosen by the user rather than by
+//! stored content, so local addresses are allowed.
+
+use serde_json::Value;
+
+use crate::domain::error::DomainError;
+use crate::domain::repository::EmbeddingProvider;
+
+const TIMEOUT_SECS: u64 = 120;
+
+pub struct HttpEmbeddingProvider {
+    url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl HttpEmbeddingProvider {
+    pub fn from_env() -> Result<Self, DomainError> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let url = var("AIBOARD_EMBEDDING_URL").ok_or_else(|| {
+            DomainError::InvalidInput(
+                "AIBOARD_EMBEDDING_URL is not set (an OpenAI-compatible /embeddings endpoint)".to_string(),
+            )
+        })?;
+        let parsed = url::Url::parse(&url)
+            .map_err(|e| DomainError::InvalidInput(format!("invalid AIBOARD_EMBEDDING_URL: {}", e)))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(DomainError::InvalidInput(format!(
+                "unsupported AIBOARD_EMBEDDING_URL scheme: {}",
+                parsed.scheme()
+            )));
+        }
+        let model = var("AIBOARD_EMBEDDING_MODEL")
+            .ok_or_else(|| DomainError::InvalidInput("AIBOARD_EMBEDDING_MODEL is not set".to_string()))?;
+        Ok(Self {
+            url,
+            model,
+            api_key: var("AIBOARD_EMBEDDING_API_KEY"),
+        })
+    }
+}
+
+impl EmbeddingProvider for HttpEmbeddingProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, DomainError> {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(std::time::Duration::from_secs(TIMEOUT_SECS))
+            .build();
+        let mut request = agent.post(&self.url).set("Content-Type", "application/json");
+        if let Some(ref key) = self.api_key {
+            request = request.set("Authorization", &format!("Bearer {}", key));
+        }
+        let body = serde_json::json!({ "model": self.model, "input": texts });
+        let response = request.send_string(&body.to_string()).map_err(|e| match e {
+            ureq::Error::Status(status, resp) => {
+                let detail = resp.into_string().unwrap_or_default();
+                DomainError::Network(format!(
+                    "embedding request failed: HTTP {} {}",
+                    status,
+                    detail.chars().take(200).collect::<String>()
+                ))
+            }
+            other => DomainError::Network(format!("embedding request failed: {}", other)),
+        })?;
+        let text = response
+            .into_string()
+            .map_err(|e| DomainError::Network(format!("failed to read embedding response: {}", e)))?;
+        let json: Value = serde_json::from_str(&text)
+            .map_err(|e| DomainError::Parse(format!("invalid embedding response: {}", e)))?;
+
+        let vectors = parse_response(&json)?;
+        if vectors.len() != texts.len() {
+            return Err(DomainError::Parse(format!(
+                "embedding response has {} vectors for {} inputs",
+                vectors.len(),
+                texts.len()
+            )));
+        }
+        Ok(vectors)
+    }
+}
+
+/// Reads `data[].embedding`, ordered by `data[].index` when present.
+fn parse_response(json: &Value) -> Result<Vec<Vec<f32>>, DomainError> {
+    let invalid = || DomainError::Parse("embedding response has no data[].embedding".to_string());
+    let data = json["data"].as_array().ok_or_else(invalid)?;
+    let mut items = data
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let index = item["index"].as_u64().map(|n| n as usize).unwrap_or(i);
+            let vector = item["embedding"]
+                .as_array()
+                .ok_or_else(invalid)?
+                .iter()
+                .map(|v| v.as_f64().map(|f| f as f32).ok_or_else(invalid))
+                .collect::<Result<Vec<f32>, _>>()?;
+            Ok((index, vector))
+        })
+        .collect::<Result<Vec<_>, DomainError>>()?;
+    items.sort_by_key(|(index, _)| *index);
+    Ok(items.into_iter().map(|(_, v)| v).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_vectors_in_index_order() {
+        let json = serde_json::json!({
+            "object": "list",
+            "data": [
+                { "index": 1, "embedding": [0.5, 0.25] },
+                { "index": 0, "embedding": [1.0, -1.0] }
+            ]
+        });
+        let vectors = parse_response(&json).unwrap();
+        assert_eq!(vectors, vec![vec![1.0, -1.0], vec![0.5, 0.25]]);
+
+        assert!(parse_response(&serde_json::json!({ "error": "boom" })).is_err());
+        assert!(parse_response(&serde_json::json!({ "data": [{ "embedding": ["x"] }] })).is_err());
+    }
+}
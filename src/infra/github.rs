@@ -0,0 +1,208 @@
+//! GitHub REST API client for issue and pull request conversations.
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use crate::domain::error::DomainError;
+use crate::infra::http::{self, FetchOptions};
+
+const API_BASE: &str = "https://api.github.com";
+const PER_PAGE: usize = 100;
+/// Upper bound on comment pages, so a runaway issue cannot loop forever.
+const MAX_PAGES: usize = 50;
+
+/// An issue or pull request, written `owner/repo#123`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueRef {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+}
+
+impl std::fmt::Display for IssueRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}#{}", self.owner, self.repo, self.number)
+    }
+}
+
+impl std::str::FromStr for IssueRef {
+    type Err = DomainError;
+
+    /// Accepts `owner/repo#123` as well as issue and pull request URLs on github.com.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            DomainError::InvalidInput(format!(
+                "invalid GitHub issue reference: '{}' (expected owner/repo#123)",
+                s
+            ))
+        };
+        let s = s.trim();
+        let (owner, repo, number) = if let Some(path) = s
+            .strip_prefix("https://github.com/")
+            .or_else(|| s.strip_prefix("http://github.com/"))
+        {
+            let parts: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+            match parts.as_slice() {
+                [owner, repo, "issues" | "pull", number, ..] => (*owner, *repo, *number),
+                _ => return Err(invalid()),
+            }
+        } else {
+            let (path, number) = s.split_once('#').ok_or_else(invalid)?;
+            let (owner, repo) = path.split_once('/').ok_or_else(invalid)?;
+            (owner, repo, number)
+        };
+
+        let valid_name =
+            |n: &str| !n.is_empty() && n.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+        if !valid_name(owner) || !valid_name(repo) {
+            return Err(invalid());
+        }
+        let number = number.parse().map_err(|_| invalid())?;
+        Ok(IssueRef {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            number,
+        })
+    }
+}
+
+/// The opening post or one comment of an issue.
+#[derive(Debug, Clone)]
+pub struct GitHubPost {
+    /// GitHub's numeric ID, used to skip already stored comments on refresh.
+    pub id: u64,
+    pub author: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    /// Markdown body as written on GitHub.
+    pub body: String,
+}
+
+/// An issue or pull request with its conversation in order.
+#[derive(Debug, Clone)]
+pub struct GitHubIssue {
+    pub title: String,
+    pub html_url: String,
+    /// The description first, then the comments. Empty bodies are skipped.
+    pub posts: Vec<GitHubPost>,
+}
+
+/// Reads a token from `GITHUB_TOKEN`, falling back to `GH_TOKEN`.
+pub fn token_from_env() -> Option<String> {
+    ["GITHUB_TOKEN", "GH_TOKEN"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|t| !t.is_empty())
+}
+
+/// Fetches an issue (or pull request) and its comments through the REST API.
+pub fn fetch_issue(issue: &IssueRef, token: Option<&str>) -> Result<GitHubIssue, DomainError> {
+    let opts = FetchOptions {
+        headers: vec![
+            ("Accept".to_string(), "application/vnd.github+json".to_string()),
+            ("X-GitHub-Api-Version".to_string(), "2022-11-28".to_string()),
+        ],
+        bearer: token.map(|t| t.to_string()),
+    };
+    let base = format!("{}/repos/{}/{}/issues/{}", API_BASE, issue.owner, issue.repo, issue.number);
+
+    let json = get_json(&base, &opts)?;
+    let mut result = parse_issue(&json)?;
+
+    for page in 1..=MAX_PAGES {
+        let url = format!("{}/comments?per_page={}&page={}", base, PER_PAGE, page);
+        let comments = get_json(&url, &opts)?;
+        let comments = comments
+            .as_array()
+            .ok_or_else(|| DomainError::Parse("unexpected GitHub comments response".to_string()))?;
+        result.posts.extend(comments.iter().filter_map(parse_post));
+        if comments.len() < PER_PAGE {
+            break;
+        }
+    }
+    Ok(result)
+}
+
+fn get_json(url: &str, opts: &FetchOptions) -> Result<Value, DomainError> {
+    let body = http::fetch_url(url, opts)?;
+    serde_json::from_str(&body)
+        .map_err(|e| DomainError::Parse(format!("invalid GitHub API response: {}", e)))
+}
+
+fn parse_issue(json: &Value) -> Result<GitHubIssue, DomainError> {
+    let field = |key: &str| {
+        json[key]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| DomainError::Parse(format!("GitHub issue response has no '{}'", key)))
+    };
+    Ok(GitHubIssue {
+        title: field("title")?,
+        html_url: field("html_url")?,
+        posts: parse_post(json).into_iter().collect(),
+    })
+}
+
+fn parse_post(json: &Value) -> Option<GitHubPost> {
+    let body = json["body"].as_str()?.trim();
+    if body.is_empty() {
+        return None;
+    }
+    Some(GitHubPost {
+        id: json["id"].as_u64()?,
+        author: json["user"]["login"].as_str().map(|s| s.to_string()),
+        created_at: json["created_at"]
+            .as_str()
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|dt| dt.with_timezone(&Utc)),
+        body: body.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_issue_references() {
+        let expected = IssueRef {
+            owner: "rust-lang".to_string(),
+            repo: "rust".to_string(),
+            number: 123,
+        };
+        assert_eq!("rust-lang/rust#123".parse::<IssueRef>().unwrap(), expected);
+        assert_eq!(
+            "https://github.com/rust-lang/rust/pull/123/files".parse::<IssueRef>().unwrap(),
+            expected
+        );
+        assert_eq!(expected.to_string(), "rust-lang/rust#123");
+
+        assert!("rust-lang/rust".parse::<IssueRef>().is_err());
+        assert!("rust-lang#12".parse::<IssueRef>().is_err());
+        assert!("../etc/passwd#1".parse::<IssueRef>().is_err());
+        assert!("https://github.com/rust-lang/rust".parse::<IssueRef>().is_err());
+    }
+
+    #[test]
+    fn parses_issue_and_comments() {
+        let issue = serde_json::json!({
+            "id": 1,
+            "title": "Crash on start",
+            "html_url": "https://github.com/o/r/issues/7",
+            "user": { "login": "octocat" },
+            "created_at": "2024-05-01T12:00:00Z",
+            "body": "It crashes.\n"
+        });
+        let parsed = parse_issue(&issue).unwrap();
+        assert_eq!(parsed.title, "Crash on start");
+        assert_eq!(parsed.posts.len(), 1);
+        assert_eq!(parsed.posts[0].author.as_deref(), Some("octocat"));
+        assert_eq!(parsed.posts[0].body, "It crashes.");
+        assert_eq!(
+            parsed.posts[0].created_at.unwrap().to_rfc3339(),
+            "2024-05-01T12:00:00+00:00"
+        );
+
+        let empty = serde_json::json!({ "id": 2, "user": { "login": "hubot" }, "body": null });
+        assert!(parse_post(&empty).is_none());
+    }
+}
@@ -7,11 +7,70 @@ const MAX_RESPONSE_SIZE: usize = 10 * 1024 * 1024; // 10MB
 const TIMEOUT_SECS: u64 = 30;
 const MAX_REDIRECTS: u32 = 5;
 
-pub fn fetch_url(url: &str) -> Result<String, DomainError> {
+/// Extra request settings for authenticated fetches.
+#[derive(Debug, Clone, Default)]
+pub struct FetchOptions {
+    /// Additional request headers as (name, value).
+    pub headers: Vec<(String, String)>,
+    /// Sent as `Authorization: Bearer <token>`.
+    pub bearer: Option<String>,
+}
+
+impl FetchOptions {
+    /// Parses a `Name: value` header argument.
+    pub fn parse_header(raw: &str) -> Result<(String, String), DomainError> {
+        let (name, value) = raw.split_once(':').ok_or_else(|| {
+            DomainError::InvalidInput(format!("invalid header '{}' (expected 'Name: value')", raw))
+        })?;
+        let name = name.trim();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+        {
+            return Err(DomainError::InvalidInput(format!("invalid header name: '{}'", name)));
+        }
+        if name.eq_ignore_ascii_case("host") {
+            return Err(DomainError::InvalidInput("the Host header cannot be overridden".to_string()));
+        }
+        Ok((name.to_string(), value.trim().to_string()))
+    }
+}
+
+/// A successful response body with the headers callers act on.
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    /// Final URL after redirects.
+    pub url: String,
+    pub body: String,
+    /// Media type without parameters, e.g. `application/json`.
+    pub content_type: Option<String>,
+    /// Absolute `rel="next"` target from the `Link` header.
+    pub next: Option<String>,
+}
+
+impl FetchedPage {
+    /// `application/json` and `+json` types such as `application/vnd.github+json`.
+    pub fn is_json(&self) -> bool {
+        self.content_type
+            .as_deref()
+            .is_some_and(|t| t == "application/json" || t.ends_with("+json"))
+    }
+}
+
+/// Fetches a URL and returns only the body.
+pub fn fetch_url(url: &str, opts: &FetchOptions) -> Result<String, DomainError> {
+    fetch_page(url, opts).map(|page| page.body)
+}
+
+/// Fetches a URL with optional extra headers and credentials. These are only
+/// sent to the origin of `url`; they are dropped when a redirect leaves it.
+pub fn fetch_page(url: &str, opts: &FetchOptions) -> Result<FetchedPage, DomainError> {
     let parsed = url::Url::parse(url)
         .map_err(|e| DomainError::InvalidInput(format!("invalid URL: {}", e)))?;
 
     validate_url(&parsed)?;
+    let origin = parsed.origin();
 
     let agent = ureq::AgentBuilder::new()
         .timeout_connect(std::time::Duration::from_secs(TIMEOUT_SECS))
@@ -23,8 +82,17 @@ pub fn fetch_url(url: &str) -> Result<String, DomainError> {
     let mut redirects = 0u32;
 
     loop {
-        let response = agent
-            .get(&current_url)
+        let mut request = agent.get(&current_url);
+        let same_origin = url::Url::parse(&current_url).is_ok_and(|u| u.origin() == origin);
+        if same_origin {
+            for (name, value) in &opts.headers {
+                request = request.set(name, value);
+            }
+            if let Some(ref token) = opts.bearer {
+                request = request.set("Authorization", &format!("Bearer {}", token));
+            }
+        }
+        let response = request
             .call()
             .map_err(|e| match e {
                 ureq::Error::Status(status, resp) => {
@@ -40,7 +108,21 @@ pub fn fetch_url(url: &str) -> Result<String, DomainError> {
 
         match response {
             Ok(resp) => {
-                return read_response_body(resp);
+                let content_type = resp.header("Content-Type").and_then(|v| {
+                    let media = v.split(';').next()?.trim().to_ascii_lowercase();
+                    (!media.is_empty()).then_some(media)
+                });
+                let next = resp
+                    .header("Link")
+                    .and_then(link_next)
+                    .and_then(|target| resolve_redirect(&current_url, &target).ok());
+                let body = read_response_body(resp)?;
+                return Ok(FetchedPage {
+                    url: current_url,
+                    body,
+                    content_type,
+                    next,
+                });
             }
             Err(DomainError::Network(msg)) if msg.starts_with("redirect:") => {
                 redirects += 1;
@@ -64,6 +146,133 @@ pub fn fetch_url(url: &str) -> Result<String, DomainError> {
     }
 }
 
+/// Precondition of `upload`, so that a file replaced since it was read is
+/// not overwritten.
+#[derive(Debug, Clone, Copy)]
+pub enum IfMatch<'a> {
+    /// The file must not exist yet (`If-None-Match: *`).
+    Absent,
+    /// The file must still have this entity tag (`If-Match`).
+    Tag(&'a str),
+    /// The server sent no entity tag, so nothing can be checked.
+    Any,
+}
+
+/// Streams the body of `url` into `out` and returns its entity tag;
+/// `None` when the server answers 404. There is no size limit, and unlike
+/// `fetch_page` private and loopback hosts are allowed: a sync remote is
+/// chosen by the user rather than taken from content, and usually lives on
+/// the local network.
+pub fn download(
+    url: &str,
+    opts: &FetchOptions,
+    out: &mut dyn std::io::Write,
+) -> Result<Option<Option<String>>, DomainError> {
+    validate_scheme(url)?;
+    let response = match authorize(transfer_agent().get(url), opts).call() {
+        Err(ureq::Error::Status(404, _)) => return Ok(None),
+        response => response.map_err(transfer_error)?,
+    };
+    let etag = response.header("ETag").map(str::to_string);
+    std::io::copy(&mut response.into_reader(), out)
+        .map_err(|e| DomainError::Network(format!("failed to read response: {}", e)))?;
+    Ok(Some(etag))
+}
+
+/// The entity tag of `url` from a HEAD request; `None` when the file is
+/// missing or the server sends no tag.
+pub fn etag(url: &str, opts: &FetchOptions) -> Result<Option<String>, DomainError> {
+    validate_scheme(url)?;
+    match authorize(transfer_agent().head(url), opts).call() {
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        response => Ok(response.map_err(transfer_error)?.header("ETag").map(str::to_string)),
+    }
+}
+
+/// Replaces the file at `url` with the `len` bytes of `body` using PUT and
+/// returns the new entity tag if the server sends one. The length is sent
+/// up front, as endpoints such as presigned object-store URLs refuse
+/// chunked uploads. A failed precondition becomes `DomainError::Conflict`,
+/// since someone else uploaded in the meantime.
+pub fn upload(
+    url: &str,
+    opts: &FetchOptions,
+    body: impl Read,
+    len: u64,
+    precondition: IfMatch,
+) -> Result<Option<String>, DomainError> {
+    validate_scheme(url)?;
+    let request = authorize(transfer_agent().put(url), opts)
+        .set("Content-Type", "application/vnd.sqlite3")
+        .set("Content-Length", &len.to_string());
+    let request = match precondition {
+        IfMatch::Absent => request.set("If-None-Match", "*"),
+        IfMatch::Tag(tag) => request.set("If-Match", tag),
+        IfMatch::Any => request,
+    };
+    match request.send(body) {
+        Err(ureq::Error::Status(412, _)) => Err(DomainError::Conflict(format!(
+            "{} was replaced after it was read",
+            url
+        ))),
+        response => Ok(response.map_err(transfer_error)?.header("ETag").map(str::to_string)),
+    }
+}
+
+/// Agent for `download` and `upload`. Redirects are not followed, so that
+/// credentials are only ever sent to the configured URL.
+fn transfer_agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout_connect(std::time::Duration::from_secs(TIMEOUT_SECS))
+        .timeout_read(std::time::Duration::from_secs(TIMEOUT_SECS))
+        .redirects(0)
+        .build()
+}
+
+fn authorize(mut request: ureq::Request, opts: &FetchOptions) -> ureq::Request {
+    for (name, value) in &opts.headers {
+        request = request.set(name, value);
+    }
+    if let Some(ref token) = opts.bearer {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+    request
+}
+
+fn transfer_error(e: ureq::Error) -> DomainError {
+    match e {
+        ureq::Error::Status(status, _) => DomainError::Network(format!("HTTP {} error", status)),
+        other => DomainError::Network(format!("HTTP request failed: {}", other)),
+    }
+}
+
+fn validate_scheme(url: &str) -> Result<(), DomainError> {
+    let parsed = url::Url::parse(url)
+        .map_err(|e| DomainError::InvalidInput(format!("invalid URL: {}", e)))?;
+    check_scheme(&parsed)
+}
+
+/// Extracts the `rel="next"` target from a `Link` header such as
+/// `<https://api.example.com/items?page=2>; rel="next", <...>; rel="last"`.
+fn link_next(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let (target, params) = link.split_once(';')?;
+        let target = target.trim().strip_prefix('<')?.strip_suffix('>')?;
+        params
+            .split(';')
+            .filter_map(|p| p.split_once('='))
+            .any(|(key, value)| {
+                key.trim().eq_ignore_ascii_case("rel")
+                    && value
+                        .trim()
+                        .trim_matches('"')
+                        .split_whitespace()
+                        .any(|rel| rel.eq_ignore_ascii_case("next"))
+            })
+            .then(|| target.to_string())
+    })
+}
+
 fn resolve_redirect(base: &str, location: &str) -> Result<String, DomainError> {
     let base_url = url::Url::parse(base)
         .map_err(|e| DomainError::InvalidInput(format!("invalid base URL: {}", e)))?;
@@ -74,16 +283,18 @@ fn resolve_redirect(base: &str, location: &str) -> Result<String, DomainError> {
 }
 
 fn validate_url(parsed: &url::Url) -> Result<(), DomainError> {
+    check_scheme(parsed)?;
+    validate_host(parsed)
+}
+
+fn check_scheme(parsed: &url::Url) -> Result<(), DomainError> {
     match parsed.scheme() {
-        "http" | "https" => {}
-        scheme => {
-            return Err(DomainError::InvalidInput(format!(
-                "unsupported URL scheme: {} (only http/https allowed)",
-                scheme
-            )));
-        }
+        "http" | "https" => Ok(()),
+        scheme => Err(DomainError::InvalidInput(format!(
+            "unsupported URL scheme: {} (only http/https allowed)",
+            scheme
+        ))),
     }
-    validate_host(parsed)
 }
 
 fn read_response_body(response: ureq::Response) -> Result<String, DomainError> {
@@ -185,9 +396,10 @@ fn validate_host(parsed: &url::Url) -> Result<(), DomainError> {
         "metadata.google",
     ];
 
+    let configured = &crate::infra::config::get().fetch.blocked_hosts;
     let host_lower = host.to_lowercase();
-    for blocked in &blocked_hosts {
-        if host_lower == *blocked {
+    for blocked in blocked_hosts.iter().copied().chain(configured.iter().map(String::as_str)) {
+        if host_lower == blocked.to_lowercase() {
             return Err(DomainError::InvalidInput(format!(
                 "access to {} is not allowed",
                 host
@@ -253,3 +465,19 @@ fn is_blocked_ipv4(v4: &std::net::Ipv4Addr) -> bool {
         || v4.is_unspecified()   // 0.0.0.0
         || v4.is_broadcast()     // 255.255.255.255
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_next_link() {
+        let header = r#"<https://api.example.com/items?page=1>; rel="prev", <https://api.example.com/items?page=3>; rel="next", <https://api.example.com/items?page=9>; rel="last""#;
+        assert_eq!(
+            link_next(header).as_deref(),
+            Some("https://api.example.com/items?page=3")
+        );
+        assert_eq!(link_next("</page/2>; rel=next").as_deref(), Some("/page/2"));
+        assert!(link_next(r#"<https://example.com/>; rel="last""#).is_none());
+    }
+}
@@ -0,0 +1,99 @@
+//! A small JSONPath subset for picking part of a fetched JSON document:
+//! `$`, `.key`, `["key"]` and `[index]`, e.g. `$.data.items[0].title`.
+
+use serde_json::Value;
+
+use crate::domain::error::DomainError;
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Returns the value at `path`, or `None` when nothing matches.
+pub fn select<'a>(value: &'a Value, path: &str) -> Result<Option<&'a Value>, DomainError> {
+    let mut current = value;
+    for segment in parse(path)? {
+        let next = match segment {
+            Segment::Key(key) => current.get(&key),
+            Segment::Index(i) => current.get(i),
+        };
+        match next {
+            Some(v) => current = v,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(current))
+}
+
+/// Checks the syntax of `path` without evaluating it.
+pub fn validate(path: &str) -> Result<(), DomainError> {
+    parse(path).map(|_| ())
+}
+
+fn parse(path: &str) -> Result<Vec<Segment>, DomainError> {
+    let invalid = |reason: &str| DomainError::InvalidInput(format!("invalid JSON path '{}': {}", path, reason));
+    let trimmed = path.trim();
+    // A bare leading key (`data.items`) reads as `$.data.items`.
+    let normalized = match trimmed.strip_prefix('$') {
+        Some(rest) => rest.to_string(),
+        None if trimmed.starts_with(['.', '[']) => trimmed.to_string(),
+        None => format!(".{}", trimmed),
+    };
+    let mut rest = normalized.as_str();
+
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if end == 0 {
+                return Err(invalid("empty key"));
+            }
+            segments.push(Segment::Key(after[..end].to_string()));
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or_else(|| invalid("unclosed '['"))?;
+            let inner = after[..end].trim();
+            let quoted = inner
+                .strip_prefix('"')
+                .and_then(|s| s.strip_suffix('"'))
+                .or_else(|| inner.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')));
+            segments.push(match quoted {
+                Some(key) => Segment::Key(key.to_string()),
+                None => Segment::Index(inner.parse().map_err(|_| invalid("expected an index or quoted key"))?),
+            });
+            rest = &after[end + 1..];
+        } else {
+            return Err(invalid("expected '.' or '['"));
+        }
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn selects_nested_values() {
+        let doc = json!({ "data": { "items": [{ "title": "a" }, { "title": "b" }], "odd key": 1 } });
+
+        assert_eq!(select(&doc, "$").unwrap(), Some(&doc));
+        assert_eq!(select(&doc, "$.data.items[1].title").unwrap(), Some(&json!("b")));
+        assert_eq!(select(&doc, "data.items[0]").unwrap(), Some(&json!({ "title": "a" })));
+        assert_eq!(select(&doc, "$.data['odd key']").unwrap(), Some(&json!(1)));
+        assert_eq!(select(&doc, "$.data.items[5]").unwrap(), None);
+        assert_eq!(select(&doc, "$.missing.key").unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        let doc = json!({});
+        assert!(select(&doc, "$.a[").is_err());
+        assert!(select(&doc, "$..a").is_err());
+        assert!(select(&doc, "$.a[x]").is_err());
+        assert!(select(&doc, "$a").is_err());
+    }
+}
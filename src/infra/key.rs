@@ -0,0 +1,44 @@
+//! Key of an encrypted board (builds with `--features encryption`).
+//!
+//! Taken from `AIBOARD_DB_KEY`, falling back to the OS keyring entry with
+//! service `aiboard` and account `db-key`:
+//! - macOS: `security add-generic-password -s aiboard -a db-key -w`
+//! - Linux (Secret Service): `secret-tool store --label aiboard service aiboard account db-key`
+
+use std::process::{Command, Stdio};
+
+pub const KEY_ENV: &str = "AIBOARD_DB_KEY";
+const KEYRING_SERVICE: &str = "aiboard";
+const KEYRING_ACCOUNT: &str = "db-key";
+
+/// Whether this build can open and write encrypted boards.
+pub const ENCRYPTION: bool = cfg!(feature = "encryption");
+
+/// The board key from the environment or the keyring, if one is set.
+pub fn db_key() -> Option<String> {
+    std::env::var(KEY_ENV)
+        .ok()
+        .filter(|key| !key.is_empty())
+        .or_else(keyring_key)
+}
+
+fn keyring_key() -> Option<String> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("security");
+        command.args(["find-generic-password", "-s", KEYRING_SERVICE, "-a", KEYRING_ACCOUNT, "-w"]);
+        command
+    } else if cfg!(unix) {
+        let mut command = Command::new("secret-tool");
+        command.args(["lookup", "service", KEYRING_SERVICE, "account", KEYRING_ACCOUNT]);
+        command
+    } else {
+        return None;
+    };
+    let output = command.stdin(Stdio::null()).stderr(Stdio::null()).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let key = String::from_utf8(output.stdout).ok()?;
+    let key = key.trim_end_matches(['\r', '\n']);
+    (!key.is_empty()).then(|| key.to_string())
+}
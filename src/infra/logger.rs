@@ -1,7 +1,13 @@
-use chrono::Local;
+use chrono::{DateTime, Local, SecondsFormat};
+use std::ffi::OsString;
 use std::fs::{self, OpenOptions};
-use std::io::Write;
-use std::path::PathBuf;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::infra::config;
+use crate::infra::hash::stable_hash;
 
 /// Returns the path to the aiboard data directory (~/.aiboard/).
 /// Creates the directory if it does not exist.
@@ -14,24 +20,163 @@ pub fn data_dir() -> Result<PathBuf, std::io::Error> {
     Ok(dir)
 }
 
+/// error.log is rotated once it would grow past this many bytes, unless
+/// `[log] max_bytes` says otherwise.
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Rotated files kept when `[log] keep` is not set.
+const DEFAULT_KEEP: usize = 5;
+
+/// The running invocation, described by JSON log entries.
+struct Invocation {
+    started: Instant,
+    args_hash: String,
+    command: OnceLock<String>,
+}
+
+static INVOCATION: OnceLock<Invocation> = OnceLock::new();
+
+/// Records the start of the invocation with `args` (without the program
+/// name). Only a hash of the arguments is logged, as they may hold message
+/// content.
+pub fn start(args: &[OsString]) {
+    let joined: Vec<String> = args.iter().map(|arg| arg.to_string_lossy().into_owned()).collect();
+    let _ = INVOCATION.set(Invocation {
+        started: Instant::now(),
+        args_hash: stable_hash(&joined.join("\0")),
+        command: OnceLock::new(),
+    });
+}
+
+/// Records the subcommand being run (e.g. `message post`).
+pub fn set_command(command: String) {
+    if let Some(invocation) = INVOCATION.get() {
+        let _ = invocation.command.set(command);
+    }
+}
+
+/// `AIBOARD_LOG_FORMAT=json` writes one JSON event per line instead of text.
+fn json_format() -> bool {
+    std::env::var("AIBOARD_LOG_FORMAT").is_ok_and(|v| v.eq_ignore_ascii_case("json"))
+}
+
 /// Logs an error message to ~/.aiboard/error.log with a timestamp.
-pub fn log_error(message: &str) {
-    if let Err(e) = try_log_error(message) {
+/// The log is rotated to error.log.1, error.log.2, ... by size and date.
+pub fn log_error(message: &str, error_code: &str, exit_code: i32) {
+    let fields = serde_json::json!({ "error_code": error_code, "exit_code": exit_code });
+    write("error", message, fields);
+}
+
+/// Logs something that was skipped rather than failed, such as a hook
+/// event for a locked thread.
+pub fn log_warning(message: &str) {
+    write("warn", message, serde_json::json!({}));
+}
+
+/// Logs a statement that took longer than `timing::SLOW_QUERY`.
+pub fn log_slow_query(sql: &str, elapsed: Duration) {
+    let sql = sql.split_whitespace().collect::<Vec<_>>().join(" ");
+    let ms = elapsed.as_millis() as u64;
+    let message = format!("遅いクエリ（{} ms）: {}", ms, sql);
+    write("warn", &message, serde_json::json!({ "query_ms": ms, "sql": sql }));
+}
+
+fn write(level: &str, message: &str, fields: serde_json::Value) {
+    if let Err(e) = try_write(level, message, fields) {
         eprintln!("警告: error.log への書き込みに失敗しました: {}", e);
     }
 }
 
-fn try_log_error(message: &str) -> Result<(), std::io::Error> {
+fn try_write(level: &str, message: &str, fields: serde_json::Value) -> Result<(), std::io::Error> {
     let dir = data_dir()?;
-    let log_path = dir.join("error.log");
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(log_path)?;
+    let log = &config::get().log;
+    let rotation = Rotation {
+        max_bytes: log.max_bytes.unwrap_or(DEFAULT_MAX_BYTES),
+        keep: log.keep.unwrap_or(DEFAULT_KEEP),
+    };
+    let now = Local::now();
+    let line = if json_format() {
+        format!("{}\n", json_event(now, level, message, fields))
+    } else {
+        format!("[{}] {}\n", now.format("%Y-%m-%d %H:%M:%S%.3f"), message)
+    };
+    append(&dir.join("error.log"), &line, &rotation, now)
+}
+
+/// The JSON form of an entry: the invocation, then `fields`.
+fn json_event(now: DateTime<Local>, level: &str, message: &str, fields: serde_json::Value) -> serde_json::Value {
+    let invocation = INVOCATION.get();
+    let mut event = serde_json::json!({
+        "timestamp": now.to_rfc3339_opts(SecondsFormat::Millis, false),
+        "level": level,
+        "command": invocation.and_then(|i| i.command.get()),
+        "args_hash": invocation.map(|i| &i.args_hash),
+        "duration_ms": invocation.map(|i| i.started.elapsed().as_millis() as u64),
+        "message": message,
+    });
+    if let (Some(event), serde_json::Value::Object(fields)) = (event.as_object_mut(), fields) {
+        event.extend(fields);
+    }
+    event
+}
 
-    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
-    writeln!(file, "[{}] {}", timestamp, message)?;
-    Ok(())
+struct Rotation {
+    max_bytes: u64,
+    keep: usize,
+}
+
+/// Appends `line` to the log at `path`, rotating it first when the line would
+/// take it past `max_bytes` or the log was last written before today.
+fn append(path: &Path, line: &str, rotation: &Rotation, now: DateTime<Local>) -> Result<(), std::io::Error> {
+    if needs_rotation(path, line.len() as u64, rotation.max_bytes, now)? {
+        rotate(path, rotation.keep)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())
+}
+
+fn needs_rotation(path: &Path, incoming: u64, max_bytes: u64, now: DateTime<Local>) -> Result<bool, std::io::Error> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    if metadata.len() == 0 {
+        return Ok(false);
+    }
+    if metadata.len() + incoming > max_bytes {
+        return Ok(true);
+    }
+    let modified: DateTime<Local> = metadata.modified()?.into();
+    Ok(modified.date_naive() < now.date_naive())
+}
+
+/// Shifts error.log.N to error.log.N+1 and the log itself to error.log.1,
+/// dropping whatever would land beyond `keep`.
+fn rotate(path: &Path, keep: usize) -> Result<(), std::io::Error> {
+    if keep == 0 {
+        return remove_if_exists(path);
+    }
+    let numbered = |n: usize| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    };
+    remove_if_exists(&numbered(keep))?;
+    for n in (1..keep).rev() {
+        let from = numbered(n);
+        if from.exists() {
+            fs::rename(&from, numbered(n + 1))?;
+        }
+    }
+    fs::rename(path, numbered(1))
+}
+
+fn remove_if_exists(path: &Path) -> Result<(), std::io::Error> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
 }
 
 fn dirs_home() -> PathBuf {
@@ -46,3 +191,50 @@ fn dirs_home() -> PathBuf {
     }
     PathBuf::from(".")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn rotates_when_the_size_cap_is_reached() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("error.log");
+        let rotation = Rotation { max_bytes: 10, keep: 2 };
+        let now = Local::now();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            append(&log, line, &rotation, now).unwrap();
+        }
+        assert_eq!(read(&log), "fourth\n");
+        assert_eq!(read(&dir.path().join("error.log.1")), "third\n");
+        assert_eq!(read(&dir.path().join("error.log.2")), "second\n");
+        assert!(!dir.path().join("error.log.3").exists());
+    }
+
+    #[test]
+    fn rotates_on_the_first_write_of_a_new_day() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("error.log");
+        let rotation = Rotation { max_bytes: DEFAULT_MAX_BYTES, keep: 1 };
+        append(&log, "yesterday\n", &rotation, Local::now()).unwrap();
+        append(&log, "today\n", &rotation, Local::now() + Duration::days(1)).unwrap();
+        assert_eq!(read(&log), "today\n");
+        assert_eq!(read(&dir.path().join("error.log.1")), "yesterday\n");
+    }
+
+    #[test]
+    fn keep_zero_discards_the_old_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("error.log");
+        let rotation = Rotation { max_bytes: 1, keep: 0 };
+        append(&log, "old\n", &rotation, Local::now()).unwrap();
+        append(&log, "new\n", &rotation, Local::now()).unwrap();
+        assert_eq!(read(&log), "new\n");
+        assert!(!dir.path().join("error.log.1").exists());
+    }
+}
@@ -0,0 +1,8 @@
+-- Schema v5: Add content_hash column to messages (duplicate suppression)
+-- Existing rows keep NULL; only messages inserted after this migration are hashed.
+
+ALTER TABLE messages ADD COLUMN content_hash TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_messages_thread_hash ON messages(thread_id, content_hash);
+
+INSERT INTO schema_version (version) VALUES (5);
@@ -0,0 +1,16 @@
+-- Schema v6: Add drafts table (unposted messages assembled incrementally)
+
+CREATE TABLE IF NOT EXISTS drafts (
+    id TEXT PRIMARY KEY NOT NULL,
+    thread_id TEXT NOT NULL,
+    sender TEXT,
+    role TEXT NOT NULL DEFAULT 'user',
+    content TEXT NOT NULL DEFAULT '',
+    metadata TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_drafts_thread_id ON drafts(thread_id);
+
+INSERT INTO schema_version (version) VALUES (6);
@@ -0,0 +1,5 @@
+-- Schema v7: Add extractive summary column to threads
+
+ALTER TABLE threads ADD COLUMN summary TEXT;
+
+INSERT INTO schema_version (version) VALUES (7);
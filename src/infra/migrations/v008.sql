@@ -0,0 +1,18 @@
+-- Schema v8: Add message cross-reference links
+
+CREATE TABLE IF NOT EXISTS message_links (
+    from_id TEXT NOT NULL,
+    to_id TEXT NOT NULL,
+    relation TEXT NOT NULL DEFAULT 'relates',
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (from_id, to_id, relation)
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_links_to_id ON message_links(to_id);
+
+-- Drop links together with either end of the link
+CREATE TRIGGER IF NOT EXISTS message_links_ad AFTER DELETE ON messages BEGIN
+    DELETE FROM message_links WHERE from_id = old.id OR to_id = old.id;
+END;
+
+INSERT INTO schema_version (version) VALUES (8);
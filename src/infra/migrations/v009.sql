@@ -0,0 +1,12 @@
+-- Schema v9: Add mention groups (@team:<name>)
+
+CREATE TABLE IF NOT EXISTS groups (
+    name TEXT NOT NULL,
+    member TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (name, member)
+);
+
+CREATE INDEX IF NOT EXISTS idx_groups_member ON groups(member);
+
+INSERT INTO schema_version (version) VALUES (9);
@@ -0,0 +1,14 @@
+-- Schema v10: Track mentions dismissed by each sender
+
+CREATE TABLE IF NOT EXISTS mention_dismissals (
+    message_id TEXT NOT NULL,
+    sender TEXT NOT NULL,
+    dismissed_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (message_id, sender)
+);
+
+CREATE TRIGGER IF NOT EXISTS mention_dismissals_ad AFTER DELETE ON messages BEGIN
+    DELETE FROM mention_dismissals WHERE message_id = old.id;
+END;
+
+INSERT INTO schema_version (version) VALUES (10);
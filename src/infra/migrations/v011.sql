@@ -0,0 +1,16 @@
+-- Schema v11: Add thread labels
+
+CREATE TABLE IF NOT EXISTS thread_tags (
+    thread_id TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (thread_id, tag)
+);
+
+CREATE INDEX IF NOT EXISTS idx_thread_tags_tag ON thread_tags(tag);
+
+CREATE TRIGGER IF NOT EXISTS thread_tags_ad AFTER DELETE ON threads BEGIN
+    DELETE FROM thread_tags WHERE thread_id = old.id;
+END;
+
+INSERT INTO schema_version (version) VALUES (11);
@@ -0,0 +1,5 @@
+-- Schema v12: Add pinned flag to threads
+
+ALTER TABLE threads ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+
+INSERT INTO schema_version (version) VALUES (12);
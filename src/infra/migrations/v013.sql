@@ -0,0 +1,51 @@
+-- Schema v13: Track thread participants (maintained by triggers on messages)
+
+CREATE TABLE IF NOT EXISTS thread_participants (
+    thread_id TEXT NOT NULL,
+    sender TEXT NOT NULL,
+    first_seen TEXT NOT NULL,
+    last_seen TEXT NOT NULL,
+    message_count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (thread_id, sender)
+);
+
+INSERT OR IGNORE INTO thread_participants (thread_id, sender, first_seen, last_seen, message_count)
+    SELECT thread_id, sender, MIN(created_at), MAX(created_at), COUNT(*)
+    FROM messages WHERE sender IS NOT NULL
+    GROUP BY thread_id, sender;
+
+CREATE TRIGGER IF NOT EXISTS thread_participants_ai AFTER INSERT ON messages
+WHEN new.sender IS NOT NULL BEGIN
+    INSERT INTO thread_participants (thread_id, sender, first_seen, last_seen, message_count)
+    VALUES (new.thread_id, new.sender, new.created_at, new.created_at, 1)
+    ON CONFLICT(thread_id, sender) DO UPDATE SET
+        first_seen = MIN(first_seen, excluded.first_seen),
+        last_seen = MAX(last_seen, excluded.last_seen),
+        message_count = message_count + 1;
+END;
+
+CREATE TRIGGER IF NOT EXISTS thread_participants_ad AFTER DELETE ON messages
+WHEN old.sender IS NOT NULL BEGIN
+    UPDATE thread_participants SET message_count = message_count - 1
+    WHERE thread_id = old.thread_id AND sender = old.sender;
+    DELETE FROM thread_participants
+    WHERE thread_id = old.thread_id AND sender = old.sender AND message_count <= 0;
+END;
+
+-- Messages moved between threads (thread split) count as leaving one thread
+-- and joining the other.
+CREATE TRIGGER IF NOT EXISTS thread_participants_au AFTER UPDATE OF thread_id ON messages
+WHEN old.thread_id != new.thread_id AND new.sender IS NOT NULL BEGIN
+    UPDATE thread_participants SET message_count = message_count - 1
+    WHERE thread_id = old.thread_id AND sender = old.sender;
+    DELETE FROM thread_participants
+    WHERE thread_id = old.thread_id AND sender = old.sender AND message_count <= 0;
+    INSERT INTO thread_participants (thread_id, sender, first_seen, last_seen, message_count)
+    VALUES (new.thread_id, new.sender, new.created_at, new.created_at, 1)
+    ON CONFLICT(thread_id, sender) DO UPDATE SET
+        first_seen = MIN(first_seen, excluded.first_seen),
+        last_seen = MAX(last_seen, excluded.last_seen),
+        message_count = message_count + 1;
+END;
+
+INSERT INTO schema_version (version) VALUES (13);
@@ -0,0 +1,15 @@
+-- Schema v14: Add thread subscriptions
+
+CREATE TABLE IF NOT EXISTS thread_subscriptions (
+    thread_id TEXT NOT NULL,
+    sender TEXT NOT NULL,
+    notify INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (thread_id, sender)
+);
+
+CREATE TRIGGER IF NOT EXISTS thread_subscriptions_ad AFTER DELETE ON threads BEGIN
+    DELETE FROM thread_subscriptions WHERE thread_id = old.id;
+END;
+
+INSERT INTO schema_version (version) VALUES (14);
@@ -0,0 +1,5 @@
+-- Schema v15: Add free-form JSON metadata to threads
+
+ALTER TABLE threads ADD COLUMN metadata TEXT;
+
+INSERT INTO schema_version (version) VALUES (15);
@@ -0,0 +1,6 @@
+-- Schema v16: Per-thread default sender and role for message post
+
+ALTER TABLE threads ADD COLUMN default_sender TEXT;
+ALTER TABLE threads ADD COLUMN default_role TEXT;
+
+INSERT INTO schema_version (version) VALUES (16);
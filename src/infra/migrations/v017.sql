@@ -0,0 +1,7 @@
+-- Schema v17: Thread locking. A locked thread only accepts posts from the
+-- senders in lock_allow (a JSON array).
+
+ALTER TABLE threads ADD COLUMN locked INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE threads ADD COLUMN lock_allow TEXT;
+
+INSERT INTO schema_version (version) VALUES (17);
@@ -0,0 +1,7 @@
+-- Schema v18: Per-thread retention policy, enforced by `cleanup apply-retention`.
+-- At most one of the two columns is set.
+
+ALTER TABLE threads ADD COLUMN retention_days INTEGER;
+ALTER TABLE threads ADD COLUMN retention_keep_last INTEGER;
+
+INSERT INTO schema_version (version) VALUES (18);
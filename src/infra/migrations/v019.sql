@@ -0,0 +1,19 @@
+-- Schema v19: Message embeddings for semantic search (`index embed`).
+-- content_hash records which version of the message was embedded, so
+-- edited messages are picked up again. One vector per message and model.
+
+CREATE TABLE IF NOT EXISTS message_embeddings (
+    message_id TEXT NOT NULL,
+    model TEXT NOT NULL,
+    content_hash TEXT,
+    dim INTEGER NOT NULL,
+    vector BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (message_id, model)
+);
+
+CREATE TRIGGER IF NOT EXISTS messages_embeddings_ad AFTER DELETE ON messages BEGIN
+    DELETE FROM message_embeddings WHERE message_id = old.id;
+END;
+
+INSERT INTO schema_version (version) VALUES (19);
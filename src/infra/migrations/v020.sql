@@ -0,0 +1,7 @@
+-- Schema v20: Per-thread reads in posting order (`message read --thread`,
+-- `--since-checkpoint`) can walk this index and stop at the LIMIT instead
+-- of sorting the whole thread.
+
+CREATE INDEX IF NOT EXISTS idx_messages_thread_created ON messages(thread_id, created_at);
+
+INSERT INTO schema_version (version) VALUES (20);
@@ -0,0 +1,15 @@
+-- Schema v21: The last exchange with each `sync` remote, per direction.
+-- remote_version is what the remote reported (an ETag, or the modification
+-- time and size of a file) so that a pull can tell nothing has changed.
+
+CREATE TABLE IF NOT EXISTS sync_state (
+    remote TEXT NOT NULL,
+    direction TEXT NOT NULL,
+    remote_version TEXT,
+    synced_at TEXT NOT NULL,
+    threads INTEGER NOT NULL DEFAULT 0,
+    messages INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (remote, direction)
+);
+
+INSERT INTO schema_version (version) VALUES (21);
@@ -0,0 +1,27 @@
+-- Schema v22: Append-only audit log of operations that change or remove
+-- content (post, update, delete, cleanup, merge). targets is a JSON array of
+-- the affected message and thread IDs; detail a JSON object of counts and
+-- parameters. Triggers refuse to change or delete rows once written.
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    operation TEXT NOT NULL,
+    actor TEXT,
+    targets TEXT NOT NULL DEFAULT '[]',
+    detail TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_created_at ON audit_log(created_at);
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+
+INSERT INTO schema_version (version) VALUES (22);
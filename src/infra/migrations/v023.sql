@@ -0,0 +1,21 @@
+-- Schema v23: Clean up rows left behind by deleted threads and messages
+-- (foreign keys are not enforced, see v1). Messages whose thread is gone get
+-- a 'recovered <id>' thread, as `db doctor --fix` does, so that listings
+-- show them again; rows of other tables that still point at a missing
+-- thread or message are dropped.
+
+INSERT INTO threads (id, title, created_at, updated_at)
+    SELECT thread_id, 'recovered ' || thread_id, MIN(created_at), MAX(created_at)
+    FROM messages
+    WHERE thread_id NOT IN (SELECT id FROM threads)
+    GROUP BY thread_id;
+
+DELETE FROM thread_tags WHERE thread_id NOT IN (SELECT id FROM threads);
+DELETE FROM thread_participants WHERE thread_id NOT IN (SELECT id FROM threads);
+DELETE FROM thread_subscriptions WHERE thread_id NOT IN (SELECT id FROM threads);
+DELETE FROM message_links
+    WHERE from_id NOT IN (SELECT id FROM messages) OR to_id NOT IN (SELECT id FROM messages);
+DELETE FROM mention_dismissals WHERE message_id NOT IN (SELECT id FROM messages);
+DELETE FROM message_embeddings WHERE message_id NOT IN (SELECT id FROM messages);
+
+INSERT INTO schema_version (version) VALUES (23);
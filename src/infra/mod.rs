@@ -1,4 +1,14 @@
+pub mod archive;
 pub mod backup;
+pub mod config;
+pub mod conversation;
+pub mod embedding;
+pub mod github;
+pub mod hash;
 pub mod http;
+pub mod json_path;
+pub mod key;
 pub mod logger;
 pub mod sqlite;
+pub mod sync;
+pub mod timing;
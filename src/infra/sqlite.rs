@@ -2,39 +2,278 @@ use chrono::{DateTime, NaiveDateTime, Utc};
 use rusqlite::{params, Connection, OptionalExtension};
 use std::path::Path;
 
-use crate::domain::entity::{Message, Role, Thread, ThreadPhase, ThreadStatus};
+use crate::domain::entity::{
+    AuditEntry, DanglingRows, DbCheck, DbStats, Draft, EmbeddingStatus, Group, IndexSize, LinkRelation, Message, MessageLink,
+    MessageSize, OrphanThread, Participant, Role, SearchHit, SearchIndexStatus, Subscription,
+    SyncDirection, SyncState, TableSize, Thread, ThreadPhase, ThreadSize, ThreadStatus,
+};
 use crate::domain::error::DomainError;
-use crate::domain::repository::{MessageRepository, ThreadRepository};
+use crate::domain::repository::{
+    AuditFilter, AuditRepository, DraftRepository, EmbeddingRepository, GroupRepository, MessageFilter, MessageRepository, Page,
+    QueryMode, SearchQuery, SearchScope, SyncStateRepository, ThreadFilter, ThreadRepository,
+    ThreadSort,
+};
+use crate::infra::hash::stable_hash;
+use crate::infra::key;
+use crate::infra::timing;
 
 const MIGRATION_V1: &str = include_str!("migrations/v001.sql");
 const MIGRATION_V2: &str = include_str!("migrations/v002.sql");
 const MIGRATION_V3: &str = include_str!("migrations/v003.sql");
 const MIGRATION_V4: &str = include_str!("migrations/v004.sql");
+const MIGRATION_V5: &str = include_str!("migrations/v005.sql");
+const MIGRATION_V6: &str = include_str!("migrations/v006.sql");
+const MIGRATION_V7: &str = include_str!("migrations/v007.sql");
+const MIGRATION_V8: &str = include_str!("migrations/v008.sql");
+const MIGRATION_V9: &str = include_str!("migrations/v009.sql");
+const MIGRATION_V10: &str = include_str!("migrations/v010.sql");
+const MIGRATION_V11: &str = include_str!("migrations/v011.sql");
+const MIGRATION_V12: &str = include_str!("migrations/v012.sql");
+const MIGRATION_V13: &str = include_str!("migrations/v013.sql");
+const MIGRATION_V14: &str = include_str!("migrations/v014.sql");
+const MIGRATION_V15: &str = include_str!("migrations/v015.sql");
+const MIGRATION_V16: &str = include_str!("migrations/v016.sql");
+const MIGRATION_V17: &str = include_str!("migrations/v017.sql");
+const MIGRATION_V18: &str = include_str!("migrations/v018.sql");
+const MIGRATION_V19: &str = include_str!("migrations/v019.sql");
+const MIGRATION_V20: &str = include_str!("migrations/v020.sql");
+const MIGRATION_V21: &str = include_str!("migrations/v021.sql");
+const MIGRATION_V22: &str = include_str!("migrations/v022.sql");
+const MIGRATION_V23: &str = include_str!("migrations/v023.sql");
+const STRICT_INTEGRITY: &str = include_str!("strict_integrity.sql");
+
+/// Rows of other tables that refer to a missing thread or message, by
+/// table. Rows of a thread that still has messages are left out:
+/// `db doctor --fix` recreates that thread.
+const DANGLING: [(&str, &str); 6] = [
+    (
+        "thread_tags",
+        "thread_id NOT IN (SELECT id FROM threads) AND thread_id NOT IN (SELECT thread_id FROM messages)",
+    ),
+    (
+        "thread_participants",
+        "thread_id NOT IN (SELECT id FROM threads) AND thread_id NOT IN (SELECT thread_id FROM messages)",
+    ),
+    (
+        "thread_subscriptions",
+        "thread_id NOT IN (SELECT id FROM threads) AND thread_id NOT IN (SELECT thread_id FROM messages)",
+    ),
+    (
+        "message_links",
+        "from_id NOT IN (SELECT id FROM messages) OR to_id NOT IN (SELECT id FROM messages)",
+    ),
+    ("mention_dismissals", "message_id NOT IN (SELECT id FROM messages)"),
+    ("message_embeddings", "message_id NOT IN (SELECT id FROM messages)"),
+];
+
+
+/// Attempts after the first once a write has waited out `busy_timeout`.
+/// Each attempt waits `busy_timeout` again, so a write gives up after
+/// about 20 seconds.
+const BUSY_RETRIES: u32 = 3;
+/// Delay before the first retry; doubled for each further one.
+const BUSY_BACKOFF_MS: u64 = 100;
+
+fn is_busy(e: &rusqlite::Error) -> bool {
+    matches!(
+        e.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+/// Runs `op` again while it fails with SQLITE_BUSY, sleeping with
+/// exponential backoff and jitter so that parallel writers (hooks of
+/// several sessions) do not retry in lockstep. The last error is returned
+/// when the retries run out and becomes `DomainError::Busy`.
+fn retry_busy<T>(mut op: impl FnMut() -> rusqlite::Result<T>) -> rusqlite::Result<T> {
+    use rand::Rng;
+
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(e) if is_busy(&e) && attempt < BUSY_RETRIES => {
+                let backoff = BUSY_BACKOFF_MS << attempt;
+                let delay = rand::thread_rng().gen_range(backoff / 2..=backoff);
+                std::thread::sleep(std::time::Duration::from_millis(delay));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// `DomainError::Database` described by `context`; a locked database stays
+/// `DomainError::Busy`.
+fn database_error(context: &str, e: rusqlite::Error) -> DomainError {
+    if is_busy(&e) {
+        e.into()
+    } else {
+        DomainError::Database(format!("{}: {}", context, e))
+    }
+}
+
+/// Writes that keep trying while another connection holds the write lock
+/// longer than `busy_timeout`.
+trait RetryBusy {
+    fn execute_retry<P: rusqlite::Params + Clone>(&self, sql: &str, params: P) -> rusqlite::Result<usize>;
+    fn execute_batch_retry(&self, sql: &str) -> rusqlite::Result<()>;
+}
+
+impl RetryBusy for Connection {
+    fn execute_retry<P: rusqlite::Params + Clone>(&self, sql: &str, params: P) -> rusqlite::Result<usize> {
+        retry_busy(|| self.execute(sql, params.clone()))
+    }
+
+    fn execute_batch_retry(&self, sql: &str) -> rusqlite::Result<()> {
+        retry_busy(|| self.execute_batch(sql))
+    }
+}
 
+/// First bytes of every plaintext SQLite file; an encrypted file starts
+/// with random salt instead.
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// The first 16 bytes of the file at `path`, or `None` when it is missing
+/// or empty (a board not created yet).
+fn file_header(path: &Path) -> Option<[u8; 16]> {
+    use std::io::Read;
+
+    let mut header = [0u8; 16];
+    let mut file = std::fs::File::open(path).ok()?;
+    file.read_exact(&mut header).ok()?;
+    Some(header)
+}
 
 pub struct Database {
     conn: Connection,
+    encrypted: bool,
+    /// The board had no schema before this connection migrated it.
+    created: bool,
 }
 
 impl Database {
+    /// Opens the board at `path`. An encrypted board is opened with the key
+    /// from `key::db_key`; a new board is created encrypted when this build
+    /// supports encryption and a key is set.
     pub fn open(path: &Path) -> Result<Self, DomainError> {
-        let conn = Connection::open(path)
-            .map_err(|e| DomainError::Database(format!("failed to open database: {}", e)))?;
-
-        Self::configure(&conn)?;
-        let mut db = Self { conn };
-        db.migrate()?;
+        let mut db = timing::phase("db open", || {
+            let key = Self::key_for(path)?;
+            let conn = Connection::open(path)
+                .map_err(|e| DomainError::Database(format!("failed to open database: {}", e)))?;
+            Self::apply_key(&conn, key.as_deref())?;
+
+            Self::configure(&conn).map_err(|e| match e {
+                DomainError::Database(_) if key.is_some() => Self::wrong_key(path),
+                e => e,
+            })?;
+            Ok::<_, DomainError>(Self { conn, encrypted: key.is_some(), created: false })
+        })?;
+        timing::phase("migration check", || db.migrate())?;
+        db.conn.profile(Some(timing::record_statement));
         Ok(db)
     }
 
-    #[allow(dead_code)]
-    pub fn open_in_memory() -> Result<Self, DomainError> {
-        let conn = Connection::open_in_memory()
-            .map_err(|e| DomainError::Database(format!("failed to open in-memory database: {}", e)))?;
+    /// The key to open the board at `path` with: `None` for a plaintext
+    /// board, and for a new one unless encryption is available and a key set.
+    fn key_for(path: &Path) -> Result<Option<String>, DomainError> {
+        match file_header(path) {
+            Some(header) if &header == SQLITE_HEADER => Ok(None),
+            Some(_) if !key::ENCRYPTION => Err(DomainError::InvalidInput(format!(
+                "{} は暗号化されているか SQLite の DB ではありません（暗号化された DB は --features encryption でビルドした aiboard で開けます）",
+                path.display()
+            ))),
+            Some(_) => key::db_key().map(Some).ok_or_else(|| {
+                DomainError::InvalidInput(format!(
+                    "{} は暗号化されています。鍵を {} か OS のキーリング（service aiboard, account db-key）に設定してください",
+                    path.display(),
+                    key::KEY_ENV
+                ))
+            }),
+            None if key::ENCRYPTION => Ok(key::db_key()),
+            None => Ok(None),
+        }
+    }
+
+    fn apply_key(conn: &Connection, key: Option<&str>) -> Result<(), DomainError> {
+        match key {
+            Some(key) => conn
+                .pragma_update(None, "key", key)
+                .map_err(|e| database_error("failed to set encryption key", e)),
+            None => Ok(()),
+        }
+    }
+
+    fn wrong_key(path: &Path) -> DomainError {
+        DomainError::InvalidInput(format!(
+            "{} を開けません。鍵が正しくないか、SQLite の DB ではありません",
+            path.display()
+        ))
+    }
+
+    /// Opens the board at `path` read-only and without migrating, to look
+    /// at a file such as a backup exactly as it is.
+    pub fn open_read_only(path: &Path) -> Result<Self, DomainError> {
+        Self::open_as_is(path, None, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+    }
+
+    /// Opens a board that nothing writes, such as a backup, without
+    /// migrating it. Unlike `open_read_only` this leaves no `-wal` or
+    /// `-shm` file beside it.
+    pub fn open_immutable(path: &Path) -> Result<Self, DomainError> {
+        let mut uri = url::Url::from_file_path(std::path::absolute(path)?)
+            .map_err(|()| DomainError::InvalidInput(format!("{} を開けません", path.display())))?;
+        uri.set_query(Some("immutable=1"));
+        Self::open_as_is(
+            path,
+            Some(uri.as_str()),
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_URI,
+        )
+    }
+
+    /// Opens the board at `path` without migrating it. `integrity_errors`
+    /// needs this rather than `open_read_only`, as the check of the
+    /// full-text index writes.
+    pub fn open_unmigrated(path: &Path) -> Result<Self, DomainError> {
+        Self::open_as_is(path, None, rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE)
+    }
+
+    /// `uri` is opened in place of `path` when given.
+    fn open_as_is(path: &Path, uri: Option<&str>, flags: rusqlite::OpenFlags) -> Result<Self, DomainError> {
+        if !path.exists() {
+            return Err(DomainError::InvalidInput(format!("{} が見つかりません", path.display())));
+        }
+        let key = Self::key_for(path)?;
+        let mut conn = match uri {
+            Some(uri) => Connection::open_with_flags(uri, flags),
+            None => Connection::open_with_flags(path, flags),
+        }
+        .map_err(|e| DomainError::Database(format!("failed to open database: {}", e)))?;
+        Self::apply_key(&conn, key.as_deref())?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))
+            .map_err(|e| database_error("failed to configure database", e))?;
+        // The first read fails on a file that is not a board or has another key.
+        conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(()))
+            .map_err(|e| match e {
+                rusqlite::Error::SqliteFailure(err, _)
+                    if key.is_some() && err.code == rusqlite::ErrorCode::NotADatabase =>
+                {
+                    Self::wrong_key(path)
+                }
+                e => database_error(&format!("failed to read {}", path.display()), e),
+            })?;
+        conn.profile(Some(timing::record_statement));
+        Ok(Self { conn, encrypted: key.is_some(), created: false })
+    }
 
-        Self::configure(&conn)?;
-        let mut db = Self { conn };
-        db.migrate()?;
+    pub fn open_in_memory() -> Result<Self, DomainError> {
+        let mut db = timing::phase("db open", || {
+            let conn = Connection::open_in_memory()
+                .map_err(|e| DomainError::Database(format!("failed to open in-memory database: {}", e)))?;
+            Self::configure(&conn)?;
+            Ok::<_, DomainError>(Self { conn, encrypted: false, created: false })
+        })?;
+        timing::phase("migration check", || db.migrate())?;
+        db.conn.profile(Some(timing::record_statement));
         Ok(db)
     }
 
@@ -42,15 +281,18 @@ impl Database {
         // foreign_keys = OFF: referential integrity is enforced at the application layer
         // (UseCase). This avoids FK-related performance overhead on bulk inserts and
         // keeps the schema compatible with FTS5 content-sync triggers.
-        conn.execute_batch(
-            "PRAGMA journal_mode = WAL;
-             PRAGMA busy_timeout = 5000;
+        // busy_timeout comes first so that switching to WAL also waits for
+        // other writers.
+        conn.execute_batch_retry(
+            "PRAGMA busy_timeout = 5000;
+             PRAGMA journal_mode = WAL;
              PRAGMA synchronous = NORMAL;
              PRAGMA foreign_keys = OFF;"
-        ).map_err(|e| DomainError::Database(format!("failed to configure database: {}", e)))
+        ).map_err(|e| database_error("failed to configure database", e))
     }
 
-    fn current_version(&self) -> Result<i64, DomainError> {
+    /// The highest migration applied to the board.
+    pub fn schema_version(&self) -> Result<i64, DomainError> {
         let has_table: bool = self.conn
             .query_row(
                 "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type='table' AND name='schema_version'",
@@ -75,7 +317,8 @@ impl Database {
     }
 
     fn migrate(&mut self) -> Result<(), DomainError> {
-        let version = self.current_version()?;
+        let version = self.schema_version()?;
+        self.created = version == 0;
 
         if version < 1 {
             self.conn
@@ -101,12 +344,525 @@ impl Database {
                 .map_err(|e| DomainError::Database(format!("migration v4 failed: {}", e)))?;
         }
 
+        if version < 5 {
+            self.conn
+                .execute_batch(MIGRATION_V5)
+                .map_err(|e| DomainError::Database(format!("migration v5 failed: {}", e)))?;
+        }
+
+        if version < 6 {
+            self.conn
+                .execute_batch(MIGRATION_V6)
+                .map_err(|e| DomainError::Database(format!("migration v6 failed: {}", e)))?;
+        }
+
+        if version < 7 {
+            self.conn
+                .execute_batch(MIGRATION_V7)
+                .map_err(|e| DomainError::Database(format!("migration v7 failed: {}", e)))?;
+        }
+
+        if version < 8 {
+            self.conn
+                .execute_batch(MIGRATION_V8)
+                .map_err(|e| DomainError::Database(format!("migration v8 failed: {}", e)))?;
+        }
+
+        if version < 9 {
+            self.conn
+                .execute_batch(MIGRATION_V9)
+                .map_err(|e| DomainError::Database(format!("migration v9 failed: {}", e)))?;
+        }
+
+        if version < 10 {
+            self.conn
+                .execute_batch(MIGRATION_V10)
+                .map_err(|e| DomainError::Database(format!("migration v10 failed: {}", e)))?;
+        }
+
+        if version < 11 {
+            self.conn
+                .execute_batch(MIGRATION_V11)
+                .map_err(|e| DomainError::Database(format!("migration v11 failed: {}", e)))?;
+        }
+
+        if version < 12 {
+            self.conn
+                .execute_batch(MIGRATION_V12)
+                .map_err(|e| DomainError::Database(format!("migration v12 failed: {}", e)))?;
+        }
+
+        if version < 13 {
+            self.conn
+                .execute_batch(MIGRATION_V13)
+                .map_err(|e| DomainError::Database(format!("migration v13 failed: {}", e)))?;
+        }
+
+        if version < 14 {
+            self.conn
+                .execute_batch(MIGRATION_V14)
+                .map_err(|e| DomainError::Database(format!("migration v14 failed: {}", e)))?;
+        }
+
+        if version < 15 {
+            self.conn
+                .execute_batch(MIGRATION_V15)
+                .map_err(|e| DomainError::Database(format!("migration v15 failed: {}", e)))?;
+        }
+
+        if version < 16 {
+            self.conn
+                .execute_batch(MIGRATION_V16)
+                .map_err(|e| DomainError::Database(format!("migration v16 failed: {}", e)))?;
+        }
+
+        if version < 17 {
+            self.conn
+                .execute_batch(MIGRATION_V17)
+                .map_err(|e| DomainError::Database(format!("migration v17 failed: {}", e)))?;
+        }
+
+        if version < 18 {
+            self.conn
+                .execute_batch(MIGRATION_V18)
+                .map_err(|e| DomainError::Database(format!("migration v18 failed: {}", e)))?;
+        }
+
+        if version < 19 {
+            self.conn
+                .execute_batch(MIGRATION_V19)
+                .map_err(|e| DomainError::Database(format!("migration v19 failed: {}", e)))?;
+        }
+
+        if version < 20 {
+            self.conn
+                .execute_batch(MIGRATION_V20)
+                .map_err(|e| DomainError::Database(format!("migration v20 failed: {}", e)))?;
+        }
+
+        if version < 21 {
+            self.conn
+                .execute_batch(MIGRATION_V21)
+                .map_err(|e| DomainError::Database(format!("migration v21 failed: {}", e)))?;
+        }
+
+        if version < 22 {
+            self.conn
+                .execute_batch(MIGRATION_V22)
+                .map_err(|e| DomainError::Database(format!("migration v22 failed: {}", e)))?;
+        }
+
+        if version < 23 {
+            self.conn
+                .execute_batch(MIGRATION_V23)
+                .map_err(|e| DomainError::Database(format!("migration v23 failed: {}", e)))?;
+        }
+
         Ok(())
     }
 
     pub fn connection(&self) -> &Connection {
         &self.conn
     }
+
+    /// Starts a write transaction spanning several commands; writes that
+    /// open their own transaction nest inside it.
+    pub fn begin(&self) -> Result<(), DomainError> {
+        self.conn
+            .execute_batch_retry("BEGIN IMMEDIATE")
+            .map_err(|e| database_error("failed to begin transaction", e))
+    }
+
+    pub fn commit(&self) -> Result<(), DomainError> {
+        self.conn
+            .execute_batch("COMMIT")
+            .map_err(|e| database_error("failed to commit transaction", e))
+    }
+
+    pub fn rollback(&self) -> Result<(), DomainError> {
+        self.conn
+            .execute_batch("ROLLBACK")
+            .map_err(|e| database_error("failed to roll back transaction", e))
+    }
+
+    /// Marks a point that `rollback_to` returns to; `release` forgets it.
+    pub fn savepoint(&self, name: &str) -> Result<(), DomainError> {
+        self.conn
+            .execute_batch(&format!("SAVEPOINT {}", name))
+            .map_err(|e| database_error("failed to create savepoint", e))
+    }
+
+    pub fn release(&self, name: &str) -> Result<(), DomainError> {
+        self.conn
+            .execute_batch(&format!("RELEASE {}", name))
+            .map_err(|e| database_error("failed to release savepoint", e))
+    }
+
+    /// Undoes the writes made since `savepoint(name)` and forgets it.
+    pub fn rollback_to(&self, name: &str) -> Result<(), DomainError> {
+        self.conn
+            .execute_batch(&format!("ROLLBACK TO {0}; RELEASE {0}", name))
+            .map_err(|e| database_error("failed to roll back to savepoint", e))
+    }
+
+    /// Size of the database file plus `suffix` (e.g. `-wal`); 0 in memory.
+    fn file_size(&self, suffix: &str) -> u64 {
+        self.conn
+            .path()
+            .filter(|path| !path.is_empty())
+            .and_then(|path| std::fs::metadata(format!("{}{}", path, suffix)).ok())
+            .map_or(0, |meta| meta.len())
+    }
+
+    /// Bytes on disk: the file together with its WAL, which holds pages not
+    /// yet written back.
+    pub fn disk_size(&self) -> u64 {
+        self.file_size("") + self.file_size("-wal")
+    }
+
+    /// Merges the full-text index segments, rewrites the file without free
+    /// pages and truncates the WAL so the reclaimed space goes back to the
+    /// file system.
+    pub fn vacuum(&self) -> Result<(), DomainError> {
+        self.conn
+            .execute_batch_retry(
+                "INSERT INTO messages_fts(messages_fts) VALUES('optimize');
+                 VACUUM;",
+            )
+            .map_err(|e| database_error("vacuum failed", e))?;
+        // wal_checkpoint returns a row, so it cannot go through execute_batch.
+        self.conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+            .map_err(|e| database_error("wal checkpoint failed", e))
+    }
+
+    /// Whether this connection created the board.
+    pub fn is_new(&self) -> bool {
+        self.created
+    }
+
+    /// Makes the board refuse messages and drafts without a thread and
+    /// delete them with their thread (`--strict-integrity`).
+    pub fn enforce_integrity(&self) -> Result<(), DomainError> {
+        self.conn
+            .execute_batch(STRICT_INTEGRITY)
+            .map_err(|e| database_error("failed to enable strict integrity", e))
+    }
+
+    /// Whether `enforce_integrity` was applied to the board.
+    pub fn strict_integrity(&self) -> Result<bool, DomainError> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'trigger' AND name = 'strict_messages_bi'",
+            [],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Whether the board file is encrypted with SQLCipher.
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted
+    }
+
+    /// Rewrites the board at `path` (the file of this connection) encrypted
+    /// with `key`, or in plaintext when `key` is `None`. The copy is written
+    /// next to it and then renamed over it, so the connection still refers
+    /// to the old file afterwards and must not be used any more.
+    pub fn rewrite(&self, path: &Path, key: Option<&str>) -> Result<(), DomainError> {
+        // Leaving WAL mode writes the WAL back and removes it, so that no
+        // pages are left behind beside the replaced file.
+        self.conn
+            .query_row("PRAGMA journal_mode = DELETE", [], |_| Ok(()))
+            .map_err(|e| database_error("failed to leave WAL mode", e))?;
+        let mut target = path.as_os_str().to_owned();
+        target.push(".rewrite");
+        let target = std::path::PathBuf::from(target);
+        let _ = std::fs::remove_file(&target);
+        let result = self
+            .conn
+            .execute(
+                "ATTACH DATABASE ?1 AS rewritten KEY ?2",
+                params![target.to_string_lossy(), key.unwrap_or("")],
+            )
+            .and_then(|_| self.conn.query_row("SELECT sqlcipher_export('rewritten')", [], |_| Ok(())))
+            .and_then(|()| self.conn.execute("DETACH DATABASE rewritten", []))
+            .map_err(|e| database_error("failed to rewrite database", e))
+            .and_then(|_| {
+                std::fs::rename(&target, path)
+                    .map_err(|e| DomainError::Io(format!("failed to replace {}: {}", path.display(), e)))
+            });
+        if result.is_err() {
+            let _ = std::fs::remove_file(&target);
+        }
+        result
+    }
+
+    /// Sizes of the file, its tables and indexes, and the `top` largest
+    /// threads and messages by content.
+    pub fn stats(&self, top: usize) -> Result<DbStats, DomainError> {
+        let pragma = |name: &str| -> Result<u64, DomainError> {
+            let value: i64 = self.conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get(0))?;
+            Ok(value as u64)
+        };
+
+        // Virtual tables own no pages; their shadow tables are listed instead.
+        let mut stmt = self.conn.prepare(
+            "SELECT m.type, m.name, m.tbl_name, COALESCE(s.bytes, 0)
+             FROM sqlite_master m
+             LEFT JOIN (SELECT name, SUM(pgsize) AS bytes FROM dbstat GROUP BY name) s ON s.name = m.name
+             WHERE (m.type = 'table' AND m.name NOT LIKE 'sqlite\\_%' ESCAPE '\\' AND m.rootpage > 0)
+                OR m.type = 'index'
+             ORDER BY m.name",
+        )?;
+        let objects = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut tables = Vec::new();
+        let mut indexes = Vec::new();
+        for (kind, name, table, bytes) in objects {
+            if kind == "table" {
+                let rows: i64 = self.conn.query_row(
+                    &format!("SELECT COUNT(*) FROM \"{}\"", name.replace('"', "\"\"")),
+                    [],
+                    |row| row.get(0),
+                )?;
+                tables.push(TableSize { name, rows: rows as u64, bytes: bytes as u64 });
+            } else {
+                indexes.push(IndexSize { name, table, bytes: bytes as u64 });
+            }
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT m.thread_id, COALESCE(t.title, ''), COUNT(*), SUM(LENGTH(CAST(m.content AS BLOB))) AS bytes
+             FROM messages m LEFT JOIN threads t ON t.id = m.thread_id
+             GROUP BY m.thread_id ORDER BY bytes DESC, m.thread_id LIMIT ?1",
+        )?;
+        let largest_threads = stmt
+            .query_map(params![top as i64], |row| {
+                Ok(ThreadSize {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    messages: row.get::<_, i64>(2)? as u64,
+                    bytes: row.get::<_, i64>(3)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, thread_id, sender, LENGTH(CAST(content AS BLOB)) AS bytes
+             FROM messages ORDER BY bytes DESC, id LIMIT ?1",
+        )?;
+        let largest_messages = stmt
+            .query_map(params![top as i64], |row| {
+                Ok(MessageSize {
+                    id: row.get(0)?,
+                    thread_id: row.get(1)?,
+                    sender: row.get(2)?,
+                    bytes: row.get::<_, i64>(3)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(DbStats {
+            file_bytes: self.file_size(""),
+            wal_bytes: self.file_size("-wal"),
+            schema_version: self.schema_version()?,
+            page_size: pragma("page_size")?,
+            pages: pragma("page_count")?,
+            free_pages: pragma("freelist_count")?,
+            tables,
+            indexes,
+            largest_threads,
+            largest_messages,
+        })
+    }
+
+    /// Runs SQLite's integrity check, the full-text index check and looks
+    /// for messages left without a thread and rows pointing at missing
+    /// threads or messages (foreign keys are not enforced). `orphans_only`
+    /// skips the first two, which read the whole file.
+    pub fn check(&self, orphans_only: bool) -> Result<DbCheck, DomainError> {
+        let (integrity_errors, search_index) = if orphans_only {
+            (None, None)
+        } else {
+            (
+                Some(self.integrity_errors()?),
+                Some(SqliteMessageRepository::new(&self.conn).search_index_status()?),
+            )
+        };
+
+        let mut stmt = self.conn.prepare(
+            "SELECT thread_id, COUNT(*) FROM messages
+             WHERE thread_id NOT IN (SELECT id FROM threads)
+             GROUP BY thread_id ORDER BY thread_id",
+        )?;
+        let orphans = stmt
+            .query_map([], |row| {
+                Ok(OrphanThread {
+                    thread_id: row.get(0)?,
+                    messages: row.get::<_, i64>(1)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut dangling = Vec::new();
+        for (table, condition) in DANGLING {
+            let rows: i64 = self.conn.query_row(
+                &format!("SELECT COUNT(*) FROM {} WHERE {}", table, condition),
+                [],
+                |row| row.get(0),
+            )?;
+            if rows > 0 {
+                dangling.push(DanglingRows { table: table.to_string(), rows: rows as u64 });
+            }
+        }
+
+        Ok(DbCheck {
+            integrity_errors,
+            search_index,
+            orphans,
+            dangling,
+            strict_integrity: self.strict_integrity()?,
+        })
+    }
+
+    /// Replaces the whole board with the content of `source` through
+    /// SQLite's backup API, which takes the locks other connections expect.
+    pub fn replace_with(&mut self, source: &Database) -> Result<(), DomainError> {
+        let backup = rusqlite::backup::Backup::new(&source.conn, &mut self.conn)
+            .map_err(|e| database_error("failed to start restore", e))?;
+        backup
+            .run_to_completion(256, std::time::Duration::from_millis(50), None)
+            .map_err(|e| database_error("failed to restore database", e))
+    }
+
+    /// Lines reported by `PRAGMA integrity_check`, which also checks the
+    /// full-text index; empty when the file is sound.
+    pub fn integrity_errors(&self) -> Result<Vec<String>, DomainError> {
+        let mut stmt = self.conn.prepare("PRAGMA integrity_check(100)")?;
+        let lines = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|line| line != "ok")
+            .collect();
+        Ok(lines)
+    }
+
+    /// Numbers of threads and messages on the board.
+    pub fn counts(&self) -> Result<(u64, u64), DomainError> {
+        let count = |table: &str| -> Result<u64, DomainError> {
+            let n: i64 = self.conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))?;
+            Ok(n as u64)
+        };
+        Ok((count("threads")?, count("messages")?))
+    }
+
+    /// Recreates the missing threads of orphan messages so that they can be
+    /// read again (or removed with `cleanup thread`). Returns the number of
+    /// threads created.
+    pub fn adopt_orphans(&self) -> Result<usize, DomainError> {
+        Ok(self.conn.execute_retry(
+            "INSERT INTO threads (id, title, created_at, updated_at)
+             SELECT thread_id, 'recovered ' || thread_id, MIN(created_at), MAX(created_at)
+             FROM messages
+             WHERE thread_id NOT IN (SELECT id FROM threads)
+             GROUP BY thread_id",
+            [],
+        )?)
+    }
+
+    /// Deletes the rows that `check` reports as dangling. Returns the
+    /// number of rows deleted.
+    pub fn remove_dangling(&self) -> Result<usize, DomainError> {
+        let mut removed = 0;
+        for (table, condition) in DANGLING {
+            removed += self
+                .conn
+                .execute_retry(&format!("DELETE FROM {} WHERE {}", table, condition), [])?;
+        }
+        Ok(removed)
+    }
+}
+
+/// A private copy of another board in a temporary file, migrated to the
+/// current schema, so it can be read or changed without touching the
+/// original. The copy is deleted on drop.
+pub struct BoardCopy {
+    db: Option<Database>,
+    path: std::path::PathBuf,
+}
+
+impl BoardCopy {
+    /// Snapshots the board at `source` with `VACUUM INTO` over a read-only
+    /// connection, which sees a consistent state even while it is written.
+    pub fn open(source: &Path) -> Result<Self, DomainError> {
+        let source = Database::open_read_only(source)?;
+        let mut copy = Self::unopened();
+        retry_busy(|| source.conn.execute("VACUUM INTO ?1", params![copy.path.to_string_lossy()]))
+            .map_err(|e| database_error("failed to copy database", e))?;
+        drop(source);
+        copy.db = Some(Database::open(&copy.path)?);
+        Ok(copy)
+    }
+
+    /// A new, empty board, for a sync remote that has none yet.
+    pub fn empty() -> Result<Self, DomainError> {
+        let mut copy = Self::unopened();
+        copy.db = Some(Database::open(&copy.path)?);
+        Ok(copy)
+    }
+
+    /// A board whose file `write` fills, such as one downloaded from a
+    /// sync remote. The file is removed again if `write` fails.
+    pub fn write_with(
+        write: impl FnOnce(&mut std::fs::File) -> Result<(), DomainError>,
+    ) -> Result<Self, DomainError> {
+        let mut copy = Self::unopened();
+        let mut file = std::fs::File::create(&copy.path)
+            .map_err(|e| DomainError::Io(format!("failed to create {}: {}", copy.path.display(), e)))?;
+        write(&mut file)?;
+        drop(file);
+        copy.db = Some(Database::open(&copy.path)?);
+        Ok(copy)
+    }
+
+    fn unopened() -> Self {
+        let path = std::env::temp_dir().join(format!("aiboard-copy-{}.db", uuid::Uuid::new_v4()));
+        Self { db: None, path }
+    }
+
+    pub fn database(&self) -> &Database {
+        self.db.as_ref().expect("opened when the copy is made")
+    }
+
+    /// Closes the board and returns its file, which then holds everything
+    /// without a WAL beside it. The file is still removed on drop.
+    pub fn close(&mut self) -> Result<&Path, DomainError> {
+        if let Some(db) = self.db.take() {
+            db.conn
+                .query_row("PRAGMA journal_mode = DELETE", [], |_| Ok(()))
+                .map_err(|e| database_error("failed to leave WAL mode", e))?;
+        }
+        Ok(&self.path)
+    }
+}
+
+impl Drop for BoardCopy {
+    fn drop(&mut self) {
+        drop(self.db.take());
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = self.path.as_os_str().to_owned();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    }
 }
 
 fn parse_datetime(s: &str) -> rusqlite::Result<DateTime<Utc>> {
@@ -125,8 +881,72 @@ fn format_datetime(dt: &DateTime<Utc>) -> String {
     dt.format("%Y-%m-%d %H:%M:%S").to_string()
 }
 
+/// FTS5 `highlight()` of the content column, with private-use characters as
+/// markers so they cannot be confused with ordinary text.
+const HIGHLIGHT_EXPR: &str = "highlight(messages_fts, 0, char(57344), char(57345))";
+const HIGHLIGHT_OPEN: char = '\u{E000}';
+const HIGHLIGHT_CLOSE: char = '\u{E001}';
+
+/// Character ranges of the spans `highlight()` marked, adjacent spans merged.
+fn highlight_ranges(marked: &str) -> Vec<[usize; 2]> {
+    let mut ranges: Vec<[usize; 2]> = Vec::new();
+    let mut pos = 0;
+    let mut start = None;
+    for c in marked.chars() {
+        match c {
+            HIGHLIGHT_OPEN => start = Some(pos),
+            HIGHLIGHT_CLOSE => {
+                if let Some(s) = start.take() {
+                    match ranges.last_mut() {
+                        Some(last) if last[1] >= s => last[1] = last[1].max(pos),
+                        _ => ranges.push([s, pos]),
+                    }
+                }
+            }
+            _ => pos += 1,
+        }
+    }
+    ranges
+}
+
+/// Character ranges of case-insensitive occurrences of any of `needles`,
+/// in order, overlapping ones merged.
+fn match_ranges(text: &str, needles: &[&str]) -> Vec<[usize; 2]> {
+    let fold = |s: &str| -> Vec<char> { s.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect() };
+    let hay = fold(text);
+    let mut found: Vec<[usize; 2]> = Vec::new();
+    for needle in needles.iter().map(|n| fold(n)).filter(|n| !n.is_empty()) {
+        let mut i = 0;
+        while i + needle.len() <= hay.len() {
+            if hay[i..i + needle.len()] == needle[..] {
+                found.push([i, i + needle.len()]);
+                i += needle.len();
+            } else {
+                i += 1;
+            }
+        }
+    }
+    found.sort();
+    let mut ranges: Vec<[usize; 2]> = Vec::new();
+    for [s, e] in found {
+        match ranges.last_mut() {
+            Some(last) if last[1] >= s => last[1] = last[1].max(e),
+            _ => ranges.push([s, e]),
+        }
+    }
+    ranges
+}
+
 // --- Thread Repository ---
 
+/// Columns read by `row_to_thread`; tags are folded into one comma-separated
+/// column (sorted when read, as the SQLite bundled with SQLCipher has no
+/// `ORDER BY` inside aggregates).
+const THREAD_COLUMNS: &str = "id, name, title, source_url, status, phase, created_at, updated_at, summary,
+    (SELECT group_concat(tag, ',') FROM thread_tags WHERE thread_tags.thread_id = threads.id),
+    pinned, metadata, default_sender, default_role, locked, lock_allow,
+    retention_days, retention_keep_last";
+
 pub struct SqliteThreadRepository<'a> {
     conn: &'a Connection,
 }
@@ -135,13 +955,54 @@ impl<'a> SqliteThreadRepository<'a> {
     pub fn new(conn: &'a Connection) -> Self {
         Self { conn }
     }
+
+    fn row_to_thread(row: &rusqlite::Row) -> rusqlite::Result<Thread> {
+        let status_str: String = row.get(4)?;
+        let status = status_str.parse::<ThreadStatus>().unwrap_or(ThreadStatus::Open);
+        let phase_str: Option<String> = row.get(5)?;
+        let phase = phase_str.and_then(|s| s.parse::<ThreadPhase>().ok());
+        Ok(Thread {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            title: row.get(2)?,
+            source_url: row.get(3)?,
+            status,
+            phase,
+            summary: row.get(8)?,
+            tags: row
+                .get::<_, Option<String>>(9)?
+                .map(|t| {
+                    let mut tags: Vec<String> = t.split(',').map(|s| s.to_string()).collect();
+                    tags.sort();
+                    tags
+                })
+                .unwrap_or_default(),
+            pinned: row.get::<_, i64>(10)? != 0,
+            metadata: row
+                .get::<_, Option<String>>(11)?
+                .and_then(|s| serde_json::from_str(&s).ok()),
+            default_sender: row.get(12)?,
+            default_role: row
+                .get::<_, Option<String>>(13)?
+                .and_then(|s| s.parse::<Role>().ok()),
+            locked: row.get::<_, i64>(14)? != 0,
+            lock_allow: row
+                .get::<_, Option<String>>(15)?
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            retention_days: row.get(16)?,
+            retention_keep_last: row.get(17)?,
+            created_at: parse_datetime(&row.get::<_, String>(6)?)?,
+            updated_at: parse_datetime(&row.get::<_, String>(7)?)?,
+        })
+    }
 }
 
 impl<'a> ThreadRepository for SqliteThreadRepository<'a> {
     fn create(&self, thread: &Thread) -> Result<(), DomainError> {
         let phase_str = thread.phase.map(|p| p.to_string());
         self.conn
-            .execute(
+            .execute_retry(
                 "INSERT INTO threads (id, name, title, source_url, status, phase, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
                 params![
                     thread.id,
@@ -154,14 +1015,21 @@ impl<'a> ThreadRepository for SqliteThreadRepository<'a> {
                     format_datetime(&thread.updated_at),
                 ],
             )
-            .map_err(|e| DomainError::Database(format!("failed to create thread: {}", e)))?;
+            .map_err(|e| match (e, &thread.name) {
+                (rusqlite::Error::SqliteFailure(err, _), Some(name))
+                    if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+                {
+                    DomainError::InvalidInput(format!("thread name '{}' is already in use", name))
+                }
+                (e, _) => DomainError::Database(format!("failed to create thread: {}", e)),
+            })?;
         Ok(())
     }
 
     fn upsert(&self, thread: &Thread) -> Result<(), DomainError> {
         let phase_str = thread.phase.map(|p| p.to_string());
         self.conn
-            .execute(
+            .execute_retry(
                 "INSERT OR IGNORE INTO threads (id, name, title, source_url, status, phase, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
                 params![
                     thread.id,
@@ -174,116 +1042,286 @@ impl<'a> ThreadRepository for SqliteThreadRepository<'a> {
                     format_datetime(&thread.updated_at),
                 ],
             )
-            .map_err(|e| DomainError::Database(format!("failed to upsert thread: {}", e)))?;
+            .map_err(|e| database_error("failed to upsert thread", e))?;
         Ok(())
     }
 
-    fn resolve_short_id(&self, short_id: &str) -> Result<String, DomainError> {
-        let pattern = format!("{}%", short_id);
-        let mut stmt = self.conn
-            .prepare("SELECT id FROM threads WHERE id LIKE ?1")?;
-
-        let ids: Vec<String> = stmt
-            .query_map(params![pattern], |row| row.get(0))?
-            .collect::<Result<Vec<_>, _>>()?;
-
-        match ids.len() {
-            0 => Err(DomainError::ThreadNotFound(short_id.to_string())),
-            1 => Ok(ids.into_iter().next().unwrap()),
-            n => Err(DomainError::AmbiguousShortId(short_id.to_string(), n)),
-        }
-    }
-
-    fn find_by_id(&self, id: &str) -> Result<Option<Thread>, DomainError> {
-        let mut stmt = self.conn
-            .prepare("SELECT id, name, title, source_url, status, phase, created_at, updated_at FROM threads WHERE id = ?1")?;
-
-        let result = stmt
-            .query_row(params![id], |row| {
-                let status_str: String = row.get(4)?;
-                let status = status_str.parse::<ThreadStatus>().unwrap_or(ThreadStatus::Open);
-                let phase_str: Option<String> = row.get(5)?;
-                let phase = phase_str.and_then(|s| s.parse::<ThreadPhase>().ok());
-                Ok(Thread {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    title: row.get(2)?,
-                    source_url: row.get(3)?,
-                    status,
-                    phase,
-                    created_at: parse_datetime(&row.get::<_, String>(6)?)?,
-                    updated_at: parse_datetime(&row.get::<_, String>(7)?)?,
-                })
-            });
+    fn replace(&self, thread: &Thread) -> Result<(), DomainError> {
+        let lock_allow = if thread.lock_allow.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&thread.lock_allow).map_err(|e| DomainError::Parse(e.to_string()))?)
+        };
+        let affected = self.conn
+            .execute_retry(
+                "UPDATE threads SET name = ?1, title = ?2, source_url = ?3, status = ?4, phase = ?5,
+                    summary = ?6, pinned = ?7, metadata = ?8, default_sender = ?9, default_role = ?10,
+                    locked = ?11, lock_allow = ?12, retention_days = ?13, retention_keep_last = ?14,
+                    created_at = ?15, updated_at = ?16
+                 WHERE id = ?17",
+                params![
+                    thread.name,
+                    thread.title,
+                    thread.source_url,
+                    thread.status.to_string(),
+                    thread.phase.map(|p| p.to_string()),
+                    thread.summary,
+                    thread.pinned as i64,
+                    thread.metadata.as_ref().map(|m| m.to_string()),
+                    thread.default_sender,
+                    thread.default_role.as_ref().map(|r| r.to_string()),
+                    thread.locked as i64,
+                    lock_allow,
+                    thread.retention_days,
+                    thread.retention_keep_last,
+                    format_datetime(&thread.created_at),
+                    format_datetime(&thread.updated_at),
+                    thread.id,
+                ],
+            )
+            .map_err(|e| match (e, &thread.name) {
+                (rusqlite::Error::SqliteFailure(err, _), Some(name))
+                    if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+                {
+                    DomainError::InvalidInput(format!("thread name '{}' is already in use", name))
+                }
+                (e, _) => database_error("failed to replace thread", e),
+            })?;
+        if affected == 0 {
+            return Err(DomainError::ThreadNotFound(thread.id.clone()));
+        }
+        self.conn
+            .execute_retry("DELETE FROM thread_tags WHERE thread_id = ?1", params![thread.id])
+            .map_err(|e| database_error("failed to replace thread tags", e))?;
+        for tag in &thread.tags {
+            self.add_tag(&thread.id, tag)?;
+        }
+        Ok(())
+    }
 
+    fn find_id_by_name(&self, name: &str) -> Result<Option<String>, DomainError> {
+        let result = self.conn.query_row(
+            "SELECT id FROM threads WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        );
         match result {
-            Ok(thread) => Ok(Some(thread)),
+            Ok(id) => Ok(Some(id)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
             Err(e) => Err(e.into()),
         }
     }
 
-    fn list(&self) -> Result<Vec<Thread>, DomainError> {
+    fn resolve_short_id(&self, short_id: &str) -> Result<String, DomainError> {
+        // A thread name wins over an ID prefix that happens to match it
+        if let Some(id) = self.find_id_by_name(short_id)? {
+            return Ok(id);
+        }
+
+        let pattern = format!("{}%", short_id);
+        let mut stmt = self.conn
+            .prepare("SELECT id FROM threads WHERE id LIKE ?1")?;
+
+        let ids: Vec<String> = stmt
+            .query_map(params![pattern], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match ids.len() {
+            0 => Err(DomainError::ThreadNotFound(short_id.to_string())),
+            1 => Ok(ids.into_iter().next().unwrap()),
+            n => Err(DomainError::AmbiguousShortId(short_id.to_string(), n)),
+        }
+    }
+
+    fn find_by_id(&self, id: &str) -> Result<Option<Thread>, DomainError> {
         let mut stmt = self.conn
-            .prepare("SELECT id, name, title, source_url, status, phase, created_at, updated_at FROM threads ORDER BY updated_at DESC")?;
+            .prepare(&format!("SELECT {} FROM threads WHERE id = ?1", THREAD_COLUMNS))?;
+
+        let result = stmt
+            .query_row(params![id], Self::row_to_thread);
+
+        match result {
+            Ok(thread) => Ok(Some(thread)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn find(&self, filter: &ThreadFilter) -> Result<Vec<Thread>, DomainError> {
+        let mut conditions: Vec<String> = Vec::new();
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+
+        if let Some(status) = filter.status {
+            values.push(rusqlite::types::Value::Text(status.to_string()));
+            conditions.push(format!("status = ?{}", values.len()));
+        }
+        if let Some(ref tag) = filter.tag {
+            values.push(rusqlite::types::Value::Text(tag.clone()));
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM thread_tags WHERE thread_tags.thread_id = threads.id AND tag = ?{})",
+                values.len()
+            ));
+        }
+        match filter.phase {
+            Some(Some(phase)) => {
+                values.push(rusqlite::types::Value::Text(phase.to_string()));
+                conditions.push(format!("phase = ?{}", values.len()));
+            }
+            Some(None) => conditions.push("phase IS NULL".to_string()),
+            None => {}
+        }
+        if let Some(ref query) = filter.query {
+            let escaped = query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            values.push(rusqlite::types::Value::Text(format!("%{}%", escaped)));
+            let n = values.len();
+            conditions.push(format!(
+                "(title LIKE ?{n} ESCAPE '\\' OR name LIKE ?{n} ESCAPE '\\' OR summary LIKE ?{n} ESCAPE '\\')"
+            ));
+        }
 
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let (key, natural_desc) = match filter.sort {
+            ThreadSort::Created => ("created_at", true),
+            ThreadSort::Updated => ("updated_at", true),
+            ThreadSort::Title => ("title COLLATE NOCASE", false),
+            ThreadSort::Messages => ("COALESCE(mc.message_count, 0)", true),
+        };
+        let dir = if natural_desc != filter.reverse { "DESC" } else { "ASC" };
+        let join = if filter.sort == ThreadSort::Messages {
+            "LEFT JOIN (SELECT thread_id, COUNT(*) AS message_count FROM messages GROUP BY thread_id) mc
+             ON mc.thread_id = threads.id"
+        } else {
+            ""
+        };
+        let sql = format!(
+            "SELECT {} FROM threads {} {} ORDER BY pinned DESC, {} {}, updated_at DESC",
+            THREAD_COLUMNS, join, where_clause, key, dir
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
         let threads = stmt
-            .query_map([], |row| {
-                let status_str: String = row.get(4)?;
-                let status = status_str.parse::<ThreadStatus>().unwrap_or(ThreadStatus::Open);
-                let phase_str: Option<String> = row.get(5)?;
-                let phase = phase_str.and_then(|s| s.parse::<ThreadPhase>().ok());
-                Ok(Thread {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    title: row.get(2)?,
-                    source_url: row.get(3)?,
-                    status,
-                    phase,
-                    created_at: parse_datetime(&row.get::<_, String>(6)?)?,
-                    updated_at: parse_datetime(&row.get::<_, String>(7)?)?,
+            .query_map(rusqlite::params_from_iter(values), Self::row_to_thread)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(threads)
+    }
+
+    fn participants(&self, id: &str) -> Result<Vec<Participant>, DomainError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT thread_id, sender, first_seen, last_seen, message_count
+             FROM thread_participants WHERE thread_id = ?1 ORDER BY first_seen, sender"
+        )?;
+        let participants = stmt
+            .query_map(params![id], |row| {
+                Ok(Participant {
+                    thread_id: row.get(0)?,
+                    sender: row.get(1)?,
+                    first_seen: parse_datetime(&row.get::<_, String>(2)?)?,
+                    last_seen: parse_datetime(&row.get::<_, String>(3)?)?,
+                    message_count: row.get::<_, i64>(4)? as usize,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
+        Ok(participants)
+    }
 
-        Ok(threads)
+    fn subscribe(&self, subscription: &Subscription) -> Result<(), DomainError> {
+        self.conn
+            .execute_retry(
+                "INSERT INTO thread_subscriptions (thread_id, sender, notify, created_at) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(thread_id, sender) DO UPDATE SET notify = excluded.notify",
+                params![
+                    subscription.thread_id,
+                    subscription.sender,
+                    subscription.notify as i64,
+                    format_datetime(&subscription.created_at),
+                ],
+            )
+            .map_err(|e| database_error("failed to subscribe", e))?;
+        Ok(())
     }
 
-    fn list_by_status(&self, status: Option<ThreadStatus>) -> Result<Vec<Thread>, DomainError> {
-        match status {
-            Some(s) => {
-                let mut stmt = self.conn
-                    .prepare("SELECT id, name, title, source_url, status, phase, created_at, updated_at FROM threads WHERE status = ?1 ORDER BY updated_at DESC")?;
-
-                let threads = stmt
-                    .query_map(params![s.to_string()], |row| {
-                        let status_str: String = row.get(4)?;
-                        let status = status_str.parse::<ThreadStatus>().unwrap_or(ThreadStatus::Open);
-                        let phase_str: Option<String> = row.get(5)?;
-                        let phase = phase_str.and_then(|s| s.parse::<ThreadPhase>().ok());
-                        Ok(Thread {
-                            id: row.get(0)?,
-                            name: row.get(1)?,
-                            title: row.get(2)?,
-                            source_url: row.get(3)?,
-                            status,
-                            phase,
-                            created_at: parse_datetime(&row.get::<_, String>(6)?)?,
-                            updated_at: parse_datetime(&row.get::<_, String>(7)?)?,
-                        })
-                    })?
-                    .collect::<Result<Vec<_>, _>>()?;
-
-                Ok(threads)
-            }
-            None => self.list(),
+    fn unsubscribe(&self, id: &str, sender: &str) -> Result<bool, DomainError> {
+        let affected = self.conn
+            .execute_retry(
+                "DELETE FROM thread_subscriptions WHERE thread_id = ?1 AND sender = ?2",
+                params![id, sender],
+            )?;
+        Ok(affected > 0)
+    }
+
+    fn subscriptions(&self, id: &str) -> Result<Vec<Subscription>, DomainError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT thread_id, sender, notify, created_at FROM thread_subscriptions
+             WHERE thread_id = ?1 ORDER BY created_at, sender"
+        )?;
+        let subscriptions = stmt
+            .query_map(params![id], |row| {
+                Ok(Subscription {
+                    thread_id: row.get(0)?,
+                    sender: row.get(1)?,
+                    notify: row.get::<_, i64>(2)? != 0,
+                    created_at: parse_datetime(&row.get::<_, String>(3)?)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(subscriptions)
+    }
+
+    fn set_pinned(&self, id: &str, pinned: bool) -> Result<(), DomainError> {
+        let affected = self.conn
+            .execute_retry(
+                "UPDATE threads SET pinned = ?1 WHERE id = ?2",
+                params![pinned as i64, id],
+            )?;
+
+        if affected == 0 {
+            return Err(DomainError::ThreadNotFound(id.to_string()));
         }
+        Ok(())
+    }
+
+    fn find_inactive(&self, before: &DateTime<Utc>) -> Result<Vec<Thread>, DomainError> {
+        let sql = format!(
+            "SELECT {} FROM threads
+             WHERE status = 'open' AND pinned = 0
+               AND COALESCE((SELECT MAX(created_at) FROM messages WHERE messages.thread_id = threads.id), created_at) < ?1
+             ORDER BY updated_at",
+            THREAD_COLUMNS
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let threads = stmt
+            .query_map(params![format_datetime(before)], Self::row_to_thread)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(threads)
+    }
+
+    fn add_tag(&self, id: &str, tag: &str) -> Result<bool, DomainError> {
+        let affected = self.conn
+            .execute_retry(
+                "INSERT OR IGNORE INTO thread_tags (thread_id, tag, created_at) VALUES (?1, ?2, ?3)",
+                params![id, tag, format_datetime(&Utc::now())],
+            )
+            .map_err(|e| database_error("failed to tag thread", e))?;
+        Ok(affected > 0)
+    }
+
+    fn remove_tag(&self, id: &str, tag: &str) -> Result<bool, DomainError> {
+        let affected = self.conn
+            .execute_retry(
+                "DELETE FROM thread_tags WHERE thread_id = ?1 AND tag = ?2",
+                params![id, tag],
+            )?;
+        Ok(affected > 0)
     }
 
     fn update_status(&self, id: &str, status: ThreadStatus) -> Result<(), DomainError> {
         let now = format_datetime(&Utc::now());
         let affected = self.conn
-            .execute(
+            .execute_retry(
                 "UPDATE threads SET status = ?1, updated_at = ?2 WHERE id = ?3",
                 params![status.to_string(), now, id],
             )?;
@@ -298,7 +1336,7 @@ impl<'a> ThreadRepository for SqliteThreadRepository<'a> {
         let now = format_datetime(&Utc::now());
         let phase_str = phase.map(|p| p.to_string());
         let affected = self.conn
-            .execute(
+            .execute_retry(
                 "UPDATE threads SET phase = ?1, updated_at = ?2 WHERE id = ?3",
                 params![phase_str, now, id],
             )?;
@@ -309,9 +1347,106 @@ impl<'a> ThreadRepository for SqliteThreadRepository<'a> {
         Ok(())
     }
 
+    fn rename(&self, id: &str, title: Option<&str>, name: Option<&str>) -> Result<(), DomainError> {
+        let now = format_datetime(&Utc::now());
+        let affected = self.conn
+            .execute_retry(
+                "UPDATE threads SET title = COALESCE(?1, title), name = COALESCE(?2, name), updated_at = ?3 WHERE id = ?4",
+                params![title, name, now, id],
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::SqliteFailure(err, _)
+                    if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+                {
+                    DomainError::InvalidInput(format!(
+                        "thread name '{}' is already in use",
+                        name.unwrap_or_default()
+                    ))
+                }
+                other => other.into(),
+            })?;
+
+        if affected == 0 {
+            return Err(DomainError::ThreadNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    fn update_summary(&self, id: &str, summary: Option<&str>) -> Result<(), DomainError> {
+        let affected = self.conn
+            .execute_retry(
+                "UPDATE threads SET summary = ?1 WHERE id = ?2",
+                params![summary, id],
+            )?;
+
+        if affected == 0 {
+            return Err(DomainError::ThreadNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    fn update_metadata(&self, id: &str, metadata: Option<&serde_json::Value>) -> Result<(), DomainError> {
+        let metadata_str = metadata.map(|m| m.to_string());
+        let affected = self.conn
+            .execute_retry(
+                "UPDATE threads SET metadata = ?1 WHERE id = ?2",
+                params![metadata_str, id],
+            )?;
+
+        if affected == 0 {
+            return Err(DomainError::ThreadNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    fn update_defaults(&self, id: &str, sender: Option<&str>, role: Option<&Role>) -> Result<(), DomainError> {
+        let role_str = role.map(|r| r.to_string());
+        let affected = self.conn
+            .execute_retry(
+                "UPDATE threads SET default_sender = ?1, default_role = ?2 WHERE id = ?3",
+                params![sender, role_str, id],
+            )?;
+
+        if affected == 0 {
+            return Err(DomainError::ThreadNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    fn set_lock(&self, id: &str, locked: bool, allow: &[String]) -> Result<(), DomainError> {
+        let allow_str = if locked && !allow.is_empty() {
+            Some(serde_json::to_string(allow).map_err(|e| DomainError::Parse(e.to_string()))?)
+        } else {
+            None
+        };
+        let affected = self.conn
+            .execute_retry(
+                "UPDATE threads SET locked = ?1, lock_allow = ?2 WHERE id = ?3",
+                params![locked as i64, allow_str, id],
+            )?;
+
+        if affected == 0 {
+            return Err(DomainError::ThreadNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    fn set_retention(&self, id: &str, days: Option<i64>, keep_last: Option<i64>) -> Result<(), DomainError> {
+        let affected = self.conn
+            .execute_retry(
+                "UPDATE threads SET retention_days = ?1, retention_keep_last = ?2 WHERE id = ?3",
+                params![days, keep_last, id],
+            )?;
+
+        if affected == 0 {
+            return Err(DomainError::ThreadNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
     fn delete(&self, id: &str) -> Result<(), DomainError> {
         let affected = self.conn
-            .execute("DELETE FROM threads WHERE id = ?1", params![id])?;
+            .execute_retry("DELETE FROM threads WHERE id = ?1", params![id])?;
 
         if affected == 0 {
             return Err(DomainError::ThreadNotFound(id.to_string()));
@@ -362,9 +1497,9 @@ impl<'a> MessageRepository for SqliteMessageRepository<'a> {
             .map(|v| serde_json::to_string(v).unwrap_or_else(|_| "{}".to_string()));
 
         self.conn
-            .execute(
-                "INSERT INTO messages (id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            .execute_retry(
+                "INSERT INTO messages (id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at, content_hash)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
                 params![
                     message.id,
                     message.thread_id,
@@ -377,28 +1512,69 @@ impl<'a> MessageRepository for SqliteMessageRepository<'a> {
                     message.source,
                     format_datetime(&message.created_at),
                     format_datetime(&message.updated_at),
+                    stable_hash(&message.content),
+                ],
+            )
+            .map_err(|e| database_error("failed to insert message", e))?;
+        Ok(())
+    }
+
+    fn replace(&self, message: &Message) -> Result<(), DomainError> {
+        let metadata_json = message
+            .metadata
+            .as_ref()
+            .map(|v| serde_json::to_string(v).unwrap_or_else(|_| "{}".to_string()));
+        let affected = self.conn
+            .execute_retry(
+                "UPDATE messages SET thread_id = ?1, session_id = ?2, sender = ?3, role = ?4, content = ?5,
+                    metadata = ?6, parent_id = ?7, source = ?8, created_at = ?9, updated_at = ?10,
+                    content_hash = ?11
+                 WHERE id = ?12",
+                params![
+                    message.thread_id,
+                    message.session_id,
+                    message.sender,
+                    message.role.to_string(),
+                    message.content,
+                    metadata_json,
+                    message.parent_id,
+                    message.source,
+                    format_datetime(&message.created_at),
+                    format_datetime(&message.updated_at),
+                    stable_hash(&message.content),
+                    message.id,
                 ],
             )
-            .map_err(|e| DomainError::Database(format!("failed to insert message: {}", e)))?;
+            .map_err(|e| database_error("failed to replace message", e))?;
+        if affected == 0 {
+            return Err(DomainError::MessageNotFound(message.id.clone()));
+        }
         Ok(())
     }
 
     fn insert_batch(&self, messages: &[Message]) -> Result<usize, DomainError> {
+        // Inside a transaction already open (`batch`), a savepoint keeps the
+        // batch all-or-nothing.
+        let (begin, commit, rollback) = if self.conn.is_autocommit() {
+            ("BEGIN IMMEDIATE", "COMMIT", "ROLLBACK")
+        } else {
+            ("SAVEPOINT insert_batch", "RELEASE insert_batch", "ROLLBACK TO insert_batch; RELEASE insert_batch")
+        };
         self.conn
-            .execute_batch("BEGIN IMMEDIATE")
-            .map_err(|e| DomainError::Database(format!("failed to begin transaction: {}", e)))?;
+            .execute_batch_retry(begin)
+            .map_err(|e| database_error("failed to begin transaction", e))?;
 
         let result = messages.iter().try_for_each(|msg| self.insert(msg));
 
         match result {
             Ok(()) => {
                 self.conn
-                    .execute_batch("COMMIT")
-                    .map_err(|e| DomainError::Database(format!("failed to commit transaction: {}", e)))?;
+                    .execute_batch(commit)
+                    .map_err(|e| database_error("failed to commit transaction", e))?;
                 Ok(messages.len())
             }
             Err(e) => {
-                let _ = self.conn.execute_batch("ROLLBACK");
+                let _ = self.conn.execute_batch(rollback);
                 Err(e)
             }
         }
@@ -440,7 +1616,7 @@ impl<'a> MessageRepository for SqliteMessageRepository<'a> {
         let mut stmt = self.conn
             .prepare(
                 "SELECT id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at
-                 FROM messages WHERE thread_id = ?1 ORDER BY created_at ASC"
+                 FROM messages WHERE thread_id = ?1 ORDER BY created_at ASC, rowid ASC"
             )?;
 
         let messages = stmt
@@ -450,35 +1626,137 @@ impl<'a> MessageRepository for SqliteMessageRepository<'a> {
         Ok(messages)
     }
 
-    fn list_recent(&self, limit: usize) -> Result<Vec<Message>, DomainError> {
-        let mut stmt = self.conn
-            .prepare(
-                "SELECT id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at
-                 FROM messages ORDER BY created_at DESC LIMIT ?1"
-            )?;
+    fn find(&self, filter: &MessageFilter, page: &Page) -> Result<Vec<Message>, DomainError> {
+        let mut messages = Vec::new();
+        self.for_each(filter, page, &mut |m| {
+            messages.push(m);
+            Ok(())
+        })?;
+        Ok(messages)
+    }
 
-        let messages = stmt
-            .query_map(params![limit], Self::row_to_message)?
-            .collect::<Result<Vec<_>, _>>()?;
+    fn for_each(
+        &self,
+        filter: &MessageFilter,
+        page: &Page,
+        f: &mut dyn FnMut(Message) -> Result<(), DomainError>,
+    ) -> Result<usize, DomainError> {
+        use rusqlite::types::Value;
+
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        Self::push_filter_conditions(filter, &mut conditions, &mut values);
+
+        let (cmp, dir, no_rowid) = if page.descending { ("<", "DESC", i64::MAX) } else { (">", "ASC", -1) };
+        if let Some(ref cursor) = page.cursor {
+            values.push(Value::Text(format_datetime(&cursor.created_at)));
+            let ts_idx = values.len();
+            values.push(Value::Text(cursor.id.clone()));
+            let id_idx = values.len();
+            // Timestamps have second precision; rowid breaks ties in insertion order.
+            // If the cursor's message has since been deleted, the whole second is
+            // kept rather than dropped.
+            conditions.push(format!(
+                "(created_at {cmp} ?{t} OR (created_at = ?{t} AND rowid {cmp} COALESCE((SELECT rowid FROM messages WHERE id = ?{i}), {none})))",
+                cmp = cmp,
+                t = ts_idx,
+                i = id_idx,
+                none = no_rowid
+            ));
+        }
 
-        Ok(messages)
+        let where_clause = Self::where_clause(&conditions);
+        let limit = Self::limit_clause(page, &mut values);
+
+        let sql = format!(
+            "SELECT id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at
+             FROM messages {} ORDER BY created_at {dir}, rowid {dir} {}",
+            where_clause,
+            limit,
+            dir = dir
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut rows = stmt.query(rusqlite::params_from_iter(values))?;
+        let mut count = 0;
+        while let Some(row) = rows.next()? {
+            f(Self::row_to_message(row)?)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn list_recent(&self, filter: &MessageFilter, limit: usize) -> Result<Vec<Message>, DomainError> {
+        let page = Page {
+            limit: Some(limit),
+            descending: true,
+            ..Default::default()
+        };
+        self.find(filter, &page)
     }
 
-    fn search(&self, query: &str, thread_id: Option<&str>) -> Result<Vec<Message>, DomainError> {
+    fn search(
+        &self,
+        query: &SearchQuery,
+        scope: SearchScope,
+        filter: &MessageFilter,
+        page: &Page,
+    ) -> Result<Vec<SearchHit>, DomainError> {
+        // Metadata is not FTS-indexed, so it is only ever matched with LIKE.
+        if scope == SearchScope::Metadata {
+            return self.search_like(query, scope, filter, page);
+        }
+        let Some(expr) = Self::fts_expression(query) else {
+            return self.search_like(query, scope, filter, page);
+        };
         // Prefer FTS5 for speed, but fall back to LIKE when FTS is unavailable
         // or when FTS returns no hits (e.g. very short query terms).
-        match self.search_fts(query, thread_id) {
-            Ok(messages) if !messages.is_empty() => Ok(messages),
-            Ok(_) | Err(_) => self.search_like(query, thread_id),
+        match self.search_fts(&expr, query, scope, filter, page) {
+            Ok(hits) if !hits.is_empty() => Ok(hits),
+            // An offset past the last FTS hit is an empty page, not a reason to switch engines
+            Ok(_) if page.offset > 0
+                && self
+                    .search_fts(&expr, query, scope, filter, &Page::default())
+                    .is_ok_and(|h| !h.is_empty()) =>
+            {
+                Ok(Vec::new())
+            }
+            Ok(_) | Err(_) => self.search_like(query, scope, filter, page),
         }
     }
 
+    fn find_fuzzy_candidates(
+        &self,
+        query: &str,
+        filter: &MessageFilter,
+        limit: usize,
+    ) -> Result<Vec<Message>, DomainError> {
+        let Some(match_expr) = Self::trigram_match_expr(query) else {
+            return Ok(Vec::new());
+        };
+        let mut conditions = Vec::new();
+        let mut values = vec![rusqlite::types::Value::Text(match_expr)];
+        Self::push_filter_conditions(filter, &mut conditions, &mut values);
+        values.push(rusqlite::types::Value::Integer(limit as i64));
+
+        // With OR-ed trigrams, bm25 favours messages sharing more of them.
+        let sql = format!(
+            "SELECT m.id, m.thread_id, m.session_id, m.sender, m.role, m.content, m.metadata, m.parent_id, m.source, m.created_at, m.updated_at
+             FROM messages m
+             JOIN (SELECT rowid, bm25(messages_fts) AS rank FROM messages_fts WHERE messages_fts MATCH ?1) fts
+               ON m.rowid = fts.rowid
+             {} ORDER BY fts.rank ASC, m.created_at DESC, m.rowid DESC LIMIT ?{}",
+            Self::where_clause(&conditions),
+            values.len()
+        );
+        self.query_with_values(&sql, values)
+    }
+
     fn update_content(&self, id: &str, content: &str) -> Result<(), DomainError> {
         let now = format_datetime(&Utc::now());
         let affected = self.conn
-            .execute(
-                "UPDATE messages SET content = ?1, updated_at = ?2 WHERE id = ?3",
-                params![content, now, id],
+            .execute_retry(
+                "UPDATE messages SET content = ?1, content_hash = ?2, updated_at = ?3 WHERE id = ?4",
+                params![content, stable_hash(content), now, id],
             )?;
 
         if affected == 0 {
@@ -487,176 +1765,1081 @@ impl<'a> MessageRepository for SqliteMessageRepository<'a> {
         Ok(())
     }
 
+    fn move_from(&self, from: &Message, to_thread_id: &str) -> Result<usize, DomainError> {
+        Ok(self.conn.execute_retry(
+            "UPDATE messages SET thread_id = ?1
+             WHERE thread_id = ?2
+               AND (created_at > ?3 OR (created_at = ?3 AND rowid >= (SELECT rowid FROM messages WHERE id = ?4)))",
+            params![to_thread_id, from.thread_id, format_datetime(&from.created_at), from.id],
+        )?)
+    }
+
     fn delete_by_thread(&self, thread_id: &str) -> Result<usize, DomainError> {
         Ok(self.conn
-            .execute("DELETE FROM messages WHERE thread_id = ?1", params![thread_id])?)
+            .execute_retry("DELETE FROM messages WHERE thread_id = ?1", params![thread_id])?)
     }
 
     fn delete_by_session(&self, session_id: &str) -> Result<usize, DomainError> {
         Ok(self.conn
-            .execute("DELETE FROM messages WHERE session_id = ?1", params![session_id])?)
+            .execute_retry("DELETE FROM messages WHERE session_id = ?1", params![session_id])?)
     }
 
     fn delete_older_than(&self, before: &DateTime<Utc>) -> Result<usize, DomainError> {
         let cutoff = format_datetime(before);
         Ok(self.conn
-            .execute("DELETE FROM messages WHERE created_at < ?1", params![cutoff])?)
+            .execute_retry("DELETE FROM messages WHERE created_at < ?1", params![cutoff])?)
     }
 
-    fn find_mentions(&self, thread_id: Option<&str>, mention_target: &str) -> Result<Vec<Message>, DomainError> {
-        let escaped = mention_target.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
-        let pattern = format!("%@{}%", escaped);
-
-        let messages: Vec<Message> = match thread_id {
-            Some(tid) => {
-                let mut stmt = self.conn.prepare(
-                    "SELECT id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at
-                     FROM messages WHERE thread_id = ?1 AND content LIKE ?2 ESCAPE '\\' ORDER BY created_at DESC"
-                )?;
-                let rows = stmt.query_map(params![tid, pattern], Self::row_to_message)?
-                    .collect::<Result<Vec<_>, _>>()?;
-                rows
-            }
-            None => {
-                let mut stmt = self.conn.prepare(
-                    "SELECT id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at
-                     FROM messages WHERE content LIKE ?1 ESCAPE '\\' ORDER BY created_at DESC"
-                )?;
-                let rows = stmt.query_map(params![pattern], Self::row_to_message)?
-                    .collect::<Result<Vec<_>, _>>()?;
-                rows
-            }
-        };
-
-        Ok(Self::filter_mention_boundary(messages, mention_target))
+    fn delete_in_thread_older_than(&self, thread_id: &str, before: &DateTime<Utc>) -> Result<usize, DomainError> {
+        let cutoff = format_datetime(before);
+        Ok(self.conn.execute_retry(
+            "DELETE FROM messages WHERE thread_id = ?1 AND created_at < ?2",
+            params![thread_id, cutoff],
+        )?)
     }
 
-    fn count_mentions(&self, thread_id: Option<&str>, mention_target: &str) -> Result<usize, DomainError> {
-        self.find_mentions(thread_id, mention_target).map(|v| v.len())
+    fn delete_in_thread_except_last(&self, thread_id: &str, keep: usize) -> Result<usize, DomainError> {
+        Ok(self.conn.execute_retry(
+            "DELETE FROM messages WHERE thread_id = ?1 AND rowid NOT IN (
+                SELECT rowid FROM messages WHERE thread_id = ?1
+                ORDER BY created_at DESC, rowid DESC LIMIT ?2
+             )",
+            params![thread_id, keep as i64],
+        )?)
     }
 
-    fn find_by_type(&self, thread_id: Option<&str>, msg_type: &str) -> Result<Vec<Message>, DomainError> {
-        let messages: Vec<Message> = match thread_id {
-            Some(tid) => {
-                let mut stmt = self.conn.prepare(
-                    "SELECT id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at
-                     FROM messages WHERE thread_id = ?1 AND json_extract(metadata, '$.msg_type') = ?2 ORDER BY created_at DESC"
-                )?;
-                let rows = stmt.query_map(params![tid, msg_type], Self::row_to_message)?
-                    .collect::<Result<Vec<_>, _>>()?;
-                rows
-            }
-            None => {
-                let mut stmt = self.conn.prepare(
-                    "SELECT id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at
-                     FROM messages WHERE json_extract(metadata, '$.msg_type') = ?1 ORDER BY created_at DESC"
-                )?;
-                let rows = stmt.query_map(params![msg_type], Self::row_to_message)?
-                    .collect::<Result<Vec<_>, _>>()?;
-                rows
-            }
-        };
-        Ok(messages)
-    }
+    fn find_duplicate(&self, thread_id: &str, sender: Option<&str>, content: &str, since: &DateTime<Utc>) -> Result<Option<Message>, DomainError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at
+             FROM messages
+             WHERE thread_id = ?1 AND content_hash = ?2 AND sender IS ?3 AND content = ?4 AND created_at >= ?5
+             ORDER BY created_at DESC LIMIT 1"
+        )?;
 
-    fn find_since_last_type(&self, thread_id: &str, msg_type: &str) -> Result<Vec<Message>, DomainError> {
-        // Find the created_at of the most recent message with the given msg_type
-        let checkpoint_time: Option<String> = self.conn
+        let result = stmt
             .query_row(
-                "SELECT created_at FROM messages
-                 WHERE thread_id = ?1 AND json_extract(metadata, '$.msg_type') = ?2
-                 ORDER BY created_at DESC LIMIT 1",
-                params![thread_id, msg_type],
-                |row| row.get(0),
+                params![thread_id, stable_hash(content), sender, content, format_datetime(since)],
+                Self::row_to_message,
             )
-            .optional()
-            .map_err(|e| DomainError::Database(format!("failed to find last type: {}", e)))?;
+            .optional()?;
 
-        let messages = match checkpoint_time {
-            Some(ct) => {
-                let mut stmt = self.conn.prepare(
-                    "SELECT id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at
-                     FROM messages WHERE thread_id = ?1 AND created_at > ?2 ORDER BY created_at ASC"
-                )?;
-                let rows = stmt.query_map(params![thread_id, ct], Self::row_to_message)?
-                    .collect::<Result<Vec<_>, _>>()?;
-                rows
-            }
-            None => {
-                // No checkpoint found, return all messages
-                self.find_by_thread(thread_id)?
-            }
-        };
-        Ok(messages)
+        Ok(result)
     }
-}
 
-impl<'a> SqliteMessageRepository<'a> {
-    /// Filter messages to ensure `@mention_target` is followed by a non-word character or EOF.
-    /// This prevents `@alice` from matching `@alicex`.
-    fn filter_mention_boundary(messages: Vec<Message>, mention_target: &str) -> Vec<Message> {
-        let mention = format!("@{}", mention_target);
-        messages.into_iter().filter(|msg| {
-            let content = &msg.content;
-            let mut start = 0;
-            while let Some(pos) = content[start..].find(&mention) {
-                let abs_pos = start + pos + mention.len();
-                if abs_pos >= content.len() {
-                    return true; // mention at EOF
-                }
-                let next_char = content[abs_pos..].chars().next().unwrap();
-                if !next_char.is_alphanumeric() && next_char != '_' {
-                    return true; // followed by non-word character
-                }
-                start = start + pos + 1;
-            }
-            false
-        }).collect()
+    fn count_by_role(&self, filter: &MessageFilter) -> Result<Vec<(Role, usize)>, DomainError> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        Self::push_filter_conditions(filter, &mut conditions, &mut values);
+
+        let sql = format!(
+            "SELECT role, COUNT(*) FROM messages {} GROUP BY role ORDER BY role",
+            Self::where_clause(&conditions)
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(values), |row| {
+                let role_str: String = row.get(0)?;
+                let count: i64 = row.get(1)?;
+                // An unknown stored role is an error rather than a miscounted "user".
+                let role = role_str.parse::<Role>().map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e.into())
+                })?;
+                Ok((role, count as usize))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(rows)
+    }
+
+    fn find_mentions(&self, thread_id: Option<&str>, mention_target: &str, unread_only: bool) -> Result<Vec<Message>, DomainError> {
+        let tokens = self.mention_tokens(mention_target)?;
+
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        if let Some(tid) = thread_id {
+            values.push(rusqlite::types::Value::Text(tid.to_string()));
+            conditions.push(format!("thread_id = ?{}", values.len()));
+        }
+        if unread_only {
+            values.push(rusqlite::types::Value::Text(mention_target.to_string()));
+            conditions.push(format!(
+                "NOT EXISTS (SELECT 1 FROM mention_dismissals d WHERE d.message_id = messages.id AND d.sender = ?{})",
+                values.len()
+            ));
+        }
+        let mut likes = Vec::new();
+        for (token, _) in &tokens {
+            let escaped = token.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            values.push(rusqlite::types::Value::Text(format!("%@{}%", escaped)));
+            likes.push(format!("content LIKE ?{} ESCAPE '\\'", values.len()));
+        }
+        conditions.push(format!("({})", likes.join(" OR ")));
+
+        let sql = format!(
+            "SELECT id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at
+             FROM messages {} ORDER BY created_at DESC",
+            Self::where_clause(&conditions)
+        );
+        let messages = self.query_with_values(&sql, values)?;
+
+        Ok(messages
+            .into_iter()
+            .filter(|msg| {
+                tokens.iter().any(|(token, is_group)| {
+                    // A broadcast does not notify its own author
+                    if *is_group && msg.sender.as_deref() == Some(mention_target) {
+                        return false;
+                    }
+                    Self::contains_mention(&msg.content, token)
+                })
+            })
+            .collect())
+    }
+
+    fn count_mentions(&self, thread_id: Option<&str>, mention_target: &str) -> Result<usize, DomainError> {
+        self.find_mentions(thread_id, mention_target, true).map(|v| v.len())
+    }
+
+    fn dismiss_mention(&self, message_id: &str, sender: &str) -> Result<bool, DomainError> {
+        let affected = self.conn
+            .execute_retry(
+                "INSERT OR IGNORE INTO mention_dismissals (message_id, sender, dismissed_at) VALUES (?1, ?2, ?3)",
+                params![message_id, sender, format_datetime(&Utc::now())],
+            )
+            .map_err(|e| database_error("failed to dismiss mention", e))?;
+        Ok(affected > 0)
+    }
+
+    fn insert_link(&self, link: &MessageLink) -> Result<(), DomainError> {
+        self.conn
+            .execute_retry(
+                "INSERT OR IGNORE INTO message_links (from_id, to_id, relation, created_at) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    link.from_id,
+                    link.to_id,
+                    link.relation.to_string(),
+                    format_datetime(&link.created_at),
+                ],
+            )
+            .map_err(|e| database_error("failed to insert link", e))?;
+        Ok(())
+    }
+
+    fn find_links(&self, message_id: &str) -> Result<Vec<MessageLink>, DomainError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT from_id, to_id, relation, created_at FROM message_links
+             WHERE from_id = ?1 OR to_id = ?1 ORDER BY created_at, from_id, to_id"
+        )?;
+        let links = stmt
+            .query_map(params![message_id], |row| {
+                let relation_str: String = row.get(2)?;
+                Ok(MessageLink {
+                    from_id: row.get(0)?,
+                    to_id: row.get(1)?,
+                    relation: relation_str.parse().unwrap_or(LinkRelation::Relates),
+                    created_at: parse_datetime(&row.get::<_, String>(3)?)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(links)
+    }
+
+    fn rebuild_search_index(&self) -> Result<usize, DomainError> {
+        self.conn.execute_batch_retry(
+            "INSERT INTO messages_fts(messages_fts) VALUES('rebuild');
+             INSERT INTO messages_fts(messages_fts) VALUES('optimize');",
+        )?;
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM messages", [], |row| row.get(0))?;
+        Ok(count as usize)
     }
 
-    fn query_messages(&self, base_sql: &str, thread_filter: &str, search_param: &str, thread_id: Option<&str>) -> Result<Vec<Message>, DomainError> {
-        let sql = match thread_id {
-            Some(_) => format!("{} {} ORDER BY created_at DESC", base_sql, thread_filter),
-            None => format!("{} ORDER BY created_at DESC", base_sql),
+    fn search_index_status(&self) -> Result<SearchIndexStatus, DomainError> {
+        let messages: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM messages", [], |row| row.get(0))?;
+        // COUNT(*) on an external-content table reads the content table, so
+        // count the index's own per-document rows instead.
+        let indexed: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM messages_fts_docsize", [], |row| row.get(0))?;
+        // With rank 1 the check also compares the index with the messages
+        // table; a mismatch is reported as an error.
+        let consistent = match self.conn.execute(
+            "INSERT INTO messages_fts(messages_fts, rank) VALUES('integrity-check', 1)",
+            [],
+        ) {
+            Ok(_) => true,
+            Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == rusqlite::ErrorCode::DatabaseCorrupt => false,
+            Err(e) => return Err(e.into()),
         };
+        Ok(SearchIndexStatus {
+            messages: messages as usize,
+            indexed: indexed as usize,
+            consistent,
+        })
+    }
 
-        let mut stmt = self.conn.prepare(&sql)?;
+    fn find_since_last_type(
+        &self,
+        thread_id: &str,
+        msg_type: &str,
+        filter: &MessageFilter,
+        page: &Page,
+    ) -> Result<Vec<Message>, DomainError> {
+        // Find the created_at of the most recent message with the given msg_type
+        let checkpoint_time: Option<String> = self.conn
+            .query_row(
+                "SELECT created_at FROM messages
+                 WHERE thread_id = ?1 AND json_extract(metadata, '$.msg_type') = ?2
+                 ORDER BY created_at DESC LIMIT 1",
+                params![thread_id, msg_type],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| database_error("failed to find last type", e))?;
 
-        let messages: Vec<Message> = match thread_id {
-            Some(tid) => {
-                stmt.query_map(params![search_param, tid], Self::row_to_message)?
-                    .collect::<Result<Vec<_>, _>>()?
-            }
-            None => {
-                stmt.query_map(params![search_param], Self::row_to_message)?
-                    .collect::<Result<Vec<_>, _>>()?
-            }
+        // No checkpoint found: every message in the thread qualifies
+        let mut filter = MessageFilter {
+            thread_id: Some(thread_id.to_string()),
+            ..filter.clone()
         };
+        if let Some(ct) = checkpoint_time {
+            let ct = parse_datetime(&ct)?;
+            filter.after = Some(filter.after.map_or(ct, |after| after.max(ct)));
+        }
+        self.find(&filter, page)
+    }
+}
 
+impl<'a> SqliteMessageRepository<'a> {
+    /// Append SQL conditions for every field set in `filter`, numbering the
+    /// positional parameters after those already in `values`.
+    fn push_filter_conditions(
+        filter: &MessageFilter,
+        conditions: &mut Vec<String>,
+        values: &mut Vec<rusqlite::types::Value>,
+    ) {
+        use rusqlite::types::Value;
+
+        if let Some(ref tid) = filter.thread_id {
+            values.push(Value::Text(tid.clone()));
+            conditions.push(format!("thread_id = ?{}", values.len()));
+        }
+        if let Some(ref sender) = filter.sender {
+            values.push(Value::Text(sender.clone()));
+            conditions.push(format!("sender = ?{}", values.len()));
+        }
+        if let Some(ref role) = filter.role {
+            values.push(Value::Text(role.to_string()));
+            conditions.push(format!("role = ?{}", values.len()));
+        }
+        if let Some(ref msg_type) = filter.msg_type {
+            values.push(Value::Text(msg_type.clone()));
+            conditions.push(format!("json_extract(metadata, '$.msg_type') = ?{}", values.len()));
+        }
+        if !filter.sources.is_empty() {
+            let placeholders: Vec<String> = filter
+                .sources
+                .iter()
+                .map(|src| {
+                    values.push(Value::Text(src.clone()));
+                    format!("?{}", values.len())
+                })
+                .collect();
+            conditions.push(format!("source IN ({})", placeholders.join(", ")));
+        }
+        if !filter.exclude_sources.is_empty() {
+            let placeholders: Vec<String> = filter
+                .exclude_sources
+                .iter()
+                .map(|src| {
+                    values.push(Value::Text(src.clone()));
+                    format!("?{}", values.len())
+                })
+                .collect();
+            conditions.push(format!("(source IS NULL OR source NOT IN ({}))", placeholders.join(", ")));
+        }
+        for (path, value) in &filter.meta {
+            // json_extract covers string values; `->` yields the JSON text form
+            // so numbers and booleans can be matched by their literal spelling.
+            values.push(Value::Text(path.clone()));
+            let path_idx = values.len();
+            values.push(Value::Text(value.clone()));
+            let value_idx = values.len();
+            conditions.push(format!(
+                "(json_extract(metadata, ?{p}) = ?{v} OR (metadata -> ?{p}) = ?{v})",
+                p = path_idx,
+                v = value_idx
+            ));
+        }
+        if let Some(ref since) = filter.since {
+            values.push(Value::Text(format_datetime(since)));
+            conditions.push(format!("created_at >= ?{}", values.len()));
+        }
+        if let Some(ref after) = filter.after {
+            values.push(Value::Text(format_datetime(after)));
+            conditions.push(format!("created_at > ?{}", values.len()));
+        }
+        if let Some(ref before) = filter.before {
+            values.push(Value::Text(format_datetime(before)));
+            conditions.push(format!("created_at < ?{}", values.len()));
+        }
+        if let Some(ref id) = filter.since_message {
+            // rowid follows insertion order, so messages posted within the
+            // same second as the reference message are still ordered correctly.
+            values.push(Value::Text(id.clone()));
+            conditions.push(format!("rowid > (SELECT rowid FROM messages WHERE id = ?{})", values.len()));
+        }
+    }
+
+    fn where_clause(conditions: &[String]) -> String {
+        if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        }
+    }
+
+    fn query_with_values(&self, sql: &str, values: Vec<rusqlite::types::Value>) -> Result<Vec<Message>, DomainError> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let messages = stmt
+            .query_map(rusqlite::params_from_iter(values), Self::row_to_message)?
+            .collect::<Result<Vec<_>, _>>()?;
         Ok(messages)
     }
 
-    fn search_fts(&self, query: &str, thread_id: Option<&str>) -> Result<Vec<Message>, DomainError> {
-        self.query_messages(
-            "SELECT m.id, m.thread_id, m.session_id, m.sender, m.role, m.content, m.metadata, m.parent_id, m.source, m.created_at, m.updated_at
-             FROM messages m
-             JOIN messages_fts fts ON m.rowid = fts.rowid
-             WHERE messages_fts MATCH ?1",
-            "AND m.thread_id = ?2",
-            query,
-            thread_id,
+    /// Mention tokens that reach `mention_target`, paired with whether the
+    /// token is a group broadcast: the name itself, `all`, and `team:<group>`
+    /// for every group the target belongs to.
+    fn mention_tokens(&self, mention_target: &str) -> Result<Vec<(String, bool)>, DomainError> {
+        let mut stmt = self.conn
+            .prepare("SELECT name FROM groups WHERE member = ?1 ORDER BY name")?;
+        let groups: Vec<String> = stmt
+            .query_map(params![mention_target], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut tokens = vec![(mention_target.to_string(), false), ("all".to_string(), true)];
+        tokens.extend(groups.into_iter().map(|g| (format!("team:{}", g), true)));
+        Ok(tokens)
+    }
+
+    /// Whether `@token` occurs followed by a non-word character or EOF.
+    /// This prevents `@alice` from matching `@alicex`, and `-` counts as a
+    /// word character so that `@team:back` does not match `@team:back-end`.
+    fn contains_mention(content: &str, token: &str) -> bool {
+        let mention = format!("@{}", token);
+        let mut start = 0;
+        while let Some(pos) = content[start..].find(&mention) {
+            let abs_pos = start + pos + mention.len();
+            if abs_pos >= content.len() {
+                return true; // mention at EOF
+            }
+            let next_char = content[abs_pos..].chars().next().unwrap();
+            if !next_char.is_alphanumeric() && next_char != '_' && next_char != '-' {
+                return true; // followed by non-word character
+            }
+            start = start + pos + 1;
+        }
+        false
+    }
+
+    /// `LIMIT ?n OFFSET ?m` for `page`, binding its values.
+    fn limit_clause(page: &Page, values: &mut Vec<rusqlite::types::Value>) -> String {
+        // SQLite requires a LIMIT when OFFSET is used; -1 means unbounded.
+        values.push(rusqlite::types::Value::Integer(page.limit.map(|l| l as i64).unwrap_or(-1)));
+        values.push(rusqlite::types::Value::Integer(page.offset as i64));
+        format!("LIMIT ?{} OFFSET ?{}", values.len() - 1, values.len())
+    }
+
+    /// Matches any scalar value in the metadata JSON against LIKE pattern `?idx`.
+    fn metadata_like_condition(idx: usize) -> String {
+        format!(
+            "(CASE WHEN json_valid(metadata) THEN EXISTS (
+                 SELECT 1 FROM json_tree(metadata)
+                 WHERE atom IS NOT NULL AND CAST(atom AS TEXT) LIKE ?{} ESCAPE '\\'
+             ) ELSE 0 END)",
+            idx
         )
     }
 
-    fn search_like(&self, query: &str, thread_id: Option<&str>) -> Result<Vec<Message>, DomainError> {
+    fn like_pattern(query: &str) -> String {
         let escaped = query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
-        let pattern = format!("%{}%", escaped);
-        self.query_messages(
-            "SELECT id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at
-             FROM messages WHERE content LIKE ?1 ESCAPE '\\'",
-            "AND thread_id = ?2",
-            &pattern,
-            thread_id,
+        format!("%{}%", escaped)
+    }
+
+    /// `"abc" OR "bcd" OR ...` over the distinct lowercase trigrams of `query`.
+    fn trigram_match_expr(query: &str) -> Option<String> {
+        let chars: Vec<char> = query.to_lowercase().chars().collect();
+        let mut trigrams: Vec<String> = chars.windows(3).map(|w| w.iter().collect()).collect();
+        trigrams.sort();
+        trigrams.dedup();
+        if trigrams.is_empty() {
+            return None;
+        }
+        Some(
+            trigrams
+                .iter()
+                .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+                .collect::<Vec<_>>()
+                .join(" OR "),
         )
     }
+
+    /// The FTS5 expression for `query`, with words and excluded terms quoted
+    /// as phrases. `None` when the trigram index cannot answer the query:
+    /// terms shorter than three characters match nothing there.
+    fn fts_expression(query: &SearchQuery) -> Option<String> {
+        let quote = |t: &str| format!("\"{}\"", t.replace('"', "\"\""));
+        let short = |t: &str| t.chars().count() < 3;
+        if query.exclude.iter().any(|t| short(t)) {
+            return None;
+        }
+        let base = match query.mode {
+            QueryMode::Raw => query.text.clone(),
+            QueryMode::Literal if short(&query.text) => return None,
+            QueryMode::Literal => quote(&query.text),
+            QueryMode::All | QueryMode::Any => {
+                let terms = query.terms();
+                if terms.iter().any(|t| short(t)) {
+                    return None;
+                }
+                let joiner = if query.mode == QueryMode::Any { " OR " } else { " AND " };
+                terms.iter().map(|t| quote(t)).collect::<Vec<_>>().join(joiner)
+            }
+        };
+        if query.exclude.is_empty() {
+            return Some(base);
+        }
+        let excluded: String = query.exclude.iter().map(|t| format!(" NOT {}", quote(t))).collect();
+        Some(format!("({}){}", base, excluded))
+    }
+
+    /// LIKE counterpart of `fts_expression`: `cond(param)` for each term,
+    /// combined per the query mode, with excluded terms kept out of `content`.
+    /// The patterns are pushed onto `values`.
+    fn like_query_condition(
+        query: &SearchQuery,
+        values: &mut Vec<rusqlite::types::Value>,
+        cond: impl Fn(usize) -> String,
+    ) -> String {
+        let mut pattern = |term: &str| {
+            values.push(rusqlite::types::Value::Text(Self::like_pattern(term)));
+            values.len()
+        };
+        let joiner = if query.mode == QueryMode::Any { " OR " } else { " AND " };
+        let terms: Vec<String> = query.terms().iter().map(|t| cond(pattern(t))).collect();
+        let mut out = format!("({})", terms.join(joiner));
+        for term in &query.exclude {
+            out.push_str(&format!(" AND content NOT LIKE ?{} ESCAPE '\\'", pattern(term)));
+        }
+        out
+    }
+
+    fn search_fts(
+        &self,
+        expr: &str,
+        query: &SearchQuery,
+        scope: SearchScope,
+        filter: &MessageFilter,
+        page: &Page,
+    ) -> Result<Vec<SearchHit>, DomainError> {
+        let mut values = vec![rusqlite::types::Value::Text(expr.to_string())];
+        // bm25() is lower for better matches. With metadata in scope, messages
+        // matching only there have no rank and follow the ranked ones.
+        let (from, mut conditions) = if scope == SearchScope::All {
+            let metadata = Self::like_query_condition(query, &mut values, Self::metadata_like_condition);
+            (
+                "FROM messages m
+                 LEFT JOIN (SELECT rowid, bm25(messages_fts) AS rank, {HIGHLIGHT} AS marked FROM messages_fts WHERE messages_fts MATCH ?1) fts
+                   ON m.rowid = fts.rowid",
+                vec![format!("(fts.rowid IS NOT NULL OR {})", metadata)],
+            )
+        } else {
+            (
+                "FROM messages m
+                 JOIN (SELECT rowid, bm25(messages_fts) AS rank, {HIGHLIGHT} AS marked FROM messages_fts WHERE messages_fts MATCH ?1) fts
+                   ON m.rowid = fts.rowid",
+                Vec::new(),
+            )
+        };
+        Self::push_filter_conditions(filter, &mut conditions, &mut values);
+        let where_clause = Self::where_clause(&conditions);
+        let limit = Self::limit_clause(page, &mut values);
+
+        // Recency breaks ties.
+        let sql = format!(
+            "SELECT m.id, m.thread_id, m.session_id, m.sender, m.role, m.content, m.metadata, m.parent_id, m.source, m.created_at, m.updated_at,
+                    fts.rank, fts.marked
+             {} {} ORDER BY fts.rank IS NULL, fts.rank ASC, m.created_at DESC, m.rowid DESC {}",
+            from.replace("{HIGHLIGHT}", HIGHLIGHT_EXPR),
+            where_clause,
+            limit
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let hits = stmt
+            .query_map(rusqlite::params_from_iter(values), |row| {
+                let rank: Option<f64> = row.get(11)?;
+                let marked: Option<String> = row.get(12)?;
+                Ok(SearchHit {
+                    message: Self::row_to_message(row)?,
+                    score: rank.map(|r| -r),
+                    fuzzy: None,
+                    matches: marked.as_deref().map(highlight_ranges).unwrap_or_default(),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(hits)
+    }
+
+    fn search_like(
+        &self,
+        query: &SearchQuery,
+        scope: SearchScope,
+        filter: &MessageFilter,
+        page: &Page,
+    ) -> Result<Vec<SearchHit>, DomainError> {
+        let content = |i: usize| format!("content LIKE ?{} ESCAPE '\\'", i);
+        let mut values = Vec::new();
+        let mut conditions = vec![match scope {
+            SearchScope::Content => Self::like_query_condition(query, &mut values, content),
+            SearchScope::Metadata => Self::like_query_condition(query, &mut values, Self::metadata_like_condition),
+            SearchScope::All => Self::like_query_condition(query, &mut values, |i| {
+                format!("({} OR {})", content(i), Self::metadata_like_condition(i))
+            }),
+        }];
+        Self::push_filter_conditions(filter, &mut conditions, &mut values);
+        let where_clause = Self::where_clause(&conditions);
+        let limit = Self::limit_clause(page, &mut values);
+
+        let sql = format!(
+            "SELECT id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at
+             FROM messages {} ORDER BY created_at DESC, rowid DESC {}",
+            where_clause, limit
+        );
+        let hits = self
+            .query_with_values(&sql, values)?
+            .into_iter()
+            .map(|message| SearchHit {
+                matches: if scope == SearchScope::Metadata {
+                    Vec::new()
+                } else {
+                    match_ranges(&message.content, &query.terms())
+                },
+                message,
+                score: None,
+                fuzzy: None,
+            })
+            .collect();
+        Ok(hits)
+    }
+}
+
+// --- Draft Repository ---
+
+pub struct SqliteDraftRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> SqliteDraftRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { conn }
+    }
+
+    fn row_to_draft(row: &rusqlite::Row) -> rusqlite::Result<Draft> {
+        let role_str: String = row.get(3)?;
+        let role = role_str.parse::<Role>().unwrap_or(Role::User);
+        let metadata_str: Option<String> = row.get(5)?;
+        let metadata = metadata_str.and_then(|s| serde_json::from_str(&s).ok());
+
+        Ok(Draft {
+            id: row.get(0)?,
+            thread_id: row.get(1)?,
+            sender: row.get(2)?,
+            role,
+            content: row.get(4)?,
+            metadata,
+            created_at: parse_datetime(&row.get::<_, String>(6)?)?,
+            updated_at: parse_datetime(&row.get::<_, String>(7)?)?,
+        })
+    }
+}
+
+impl<'a> DraftRepository for SqliteDraftRepository<'a> {
+    fn save(&self, draft: &Draft) -> Result<(), DomainError> {
+        let metadata_json = draft
+            .metadata
+            .as_ref()
+            .map(|v| serde_json::to_string(v).unwrap_or_else(|_| "{}".to_string()));
+
+        self.conn
+            .execute_retry(
+                "INSERT INTO drafts (id, thread_id, sender, role, content, metadata, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT(id) DO UPDATE SET
+                     content = excluded.content,
+                     role = excluded.role,
+                     metadata = excluded.metadata,
+                     updated_at = excluded.updated_at",
+                params![
+                    draft.id,
+                    draft.thread_id,
+                    draft.sender,
+                    draft.role.to_string(),
+                    draft.content,
+                    metadata_json,
+                    format_datetime(&draft.created_at),
+                    format_datetime(&draft.updated_at),
+                ],
+            )
+            .map_err(|e| database_error("failed to save draft", e))?;
+        Ok(())
+    }
+
+    fn find_by_id(&self, id: &str) -> Result<Option<Draft>, DomainError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, thread_id, sender, role, content, metadata, created_at, updated_at
+             FROM drafts WHERE id = ?1",
+        )?;
+
+        Ok(stmt.query_row(params![id], Self::row_to_draft).optional()?)
+    }
+
+    fn resolve_short_id(&self, short_id: &str) -> Result<String, DomainError> {
+        let pattern = format!("{}%", short_id);
+        let mut stmt = self.conn
+            .prepare("SELECT id FROM drafts WHERE id LIKE ?1")?;
+
+        let ids: Vec<String> = stmt
+            .query_map(params![pattern], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match ids.len() {
+            0 => Err(DomainError::DraftNotFound(short_id.to_string())),
+            1 => Ok(ids.into_iter().next().unwrap()),
+            n => Err(DomainError::AmbiguousShortId(short_id.to_string(), n)),
+        }
+    }
+
+    fn list(&self, thread_id: Option<&str>, sender: Option<&str>) -> Result<Vec<Draft>, DomainError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, thread_id, sender, role, content, metadata, created_at, updated_at
+             FROM drafts
+             WHERE (?1 IS NULL OR thread_id = ?1) AND (?2 IS NULL OR sender = ?2)
+             ORDER BY updated_at DESC",
+        )?;
+
+        let drafts = stmt
+            .query_map(params![thread_id, sender], Self::row_to_draft)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(drafts)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), DomainError> {
+        let affected = self.conn
+            .execute_retry("DELETE FROM drafts WHERE id = ?1", params![id])?;
+
+        if affected == 0 {
+            return Err(DomainError::DraftNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+}
+
+// --- Group Repository ---
+
+pub struct SqliteGroupRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> SqliteGroupRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { conn }
+    }
+}
+
+impl<'a> GroupRepository for SqliteGroupRepository<'a> {
+    fn add_member(&self, name: &str, member: &str) -> Result<bool, DomainError> {
+        let affected = self.conn
+            .execute_retry(
+                "INSERT OR IGNORE INTO groups (name, member, created_at) VALUES (?1, ?2, ?3)",
+                params![name, member, format_datetime(&Utc::now())],
+            )
+            .map_err(|e| database_error("failed to add group member", e))?;
+        Ok(affected > 0)
+    }
+
+    fn remove_member(&self, name: &str, member: &str) -> Result<bool, DomainError> {
+        let affected = self.conn
+            .execute_retry(
+                "DELETE FROM groups WHERE name = ?1 AND member = ?2",
+                params![name, member],
+            )?;
+        Ok(affected > 0)
+    }
+
+    fn list(&self) -> Result<Vec<Group>, DomainError> {
+        let mut stmt = self.conn
+            .prepare("SELECT name, member FROM groups ORDER BY name, member")?;
+        let rows: Vec<(String, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut groups: Vec<Group> = Vec::new();
+        for (name, member) in rows {
+            match groups.last_mut() {
+                Some(g) if g.name == name => g.members.push(member),
+                _ => groups.push(Group { name, members: vec![member] }),
+            }
+        }
+        Ok(groups)
+    }
+}
+
+// --- Sync State Repository ---
+
+pub struct SqliteSyncStateRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> SqliteSyncStateRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { conn }
+    }
+
+    fn row_to_state(row: &rusqlite::Row) -> rusqlite::Result<SyncState> {
+        let direction: String = row.get(1)?;
+        Ok(SyncState {
+            remote: row.get(0)?,
+            direction: direction.parse().map_err(|e: String| {
+                rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, e.into())
+            })?,
+            remote_version: row.get(2)?,
+            synced_at: parse_datetime(&row.get::<_, String>(3)?)?,
+            threads: row.get::<_, i64>(4)? as usize,
+            messages: row.get::<_, i64>(5)? as usize,
+        })
+    }
+}
+
+const SYNC_STATE_COLUMNS: &str = "remote, direction, remote_version, synced_at, threads, messages";
+
+impl SyncStateRepository for SqliteSyncStateRepository<'_> {
+    fn find(&self, remote: &str, direction: SyncDirection) -> Result<Option<SyncState>, DomainError> {
+        self.conn
+            .query_row(
+                &format!("SELECT {} FROM sync_state WHERE remote = ?1 AND direction = ?2", SYNC_STATE_COLUMNS),
+                params![remote, direction.to_string()],
+                Self::row_to_state,
+            )
+            .optional()
+            .map_err(|e| database_error("failed to read sync state", e))
+    }
+
+    fn save(&self, state: &SyncState) -> Result<(), DomainError> {
+        self.conn
+            .execute_retry(
+                &format!("INSERT OR REPLACE INTO sync_state ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6)", SYNC_STATE_COLUMNS),
+                params![
+                    state.remote,
+                    state.direction.to_string(),
+                    state.remote_version,
+                    format_datetime(&state.synced_at),
+                    state.threads as i64,
+                    state.messages as i64,
+                ],
+            )
+            .map_err(|e| database_error("failed to save sync state", e))?;
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<SyncState>, DomainError> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM sync_state ORDER BY remote, direction",
+            SYNC_STATE_COLUMNS
+        ))?;
+        let states = stmt
+            .query_map([], Self::row_to_state)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(states)
+    }
+}
+
+// --- Audit Repository ---
+
+pub struct SqliteAuditRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> SqliteAuditRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { conn }
+    }
+
+    fn row_to_entry(row: &rusqlite::Row) -> rusqlite::Result<AuditEntry> {
+        let conversion = |column: usize, e: Box<dyn std::error::Error + Send + Sync>| {
+            rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, e)
+        };
+        let operation: String = row.get(1)?;
+        let targets: String = row.get(3)?;
+        let detail: Option<String> = row.get(4)?;
+        Ok(AuditEntry {
+            id: row.get(0)?,
+            operation: operation.parse().map_err(|e: String| conversion(1, e.into()))?,
+            actor: row.get(2)?,
+            targets: serde_json::from_str(&targets).map_err(|e| conversion(3, e.into()))?,
+            detail: detail
+                .map(|d| serde_json::from_str(&d))
+                .transpose()
+                .map_err(|e| conversion(4, e.into()))?,
+            created_at: parse_datetime(&row.get::<_, String>(5)?)?,
+        })
+    }
+}
+
+impl AuditRepository for SqliteAuditRepository<'_> {
+    fn append(&self, entry: &AuditEntry) -> Result<i64, DomainError> {
+        self.conn
+            .execute_retry(
+                "INSERT INTO audit_log (operation, actor, targets, detail, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    entry.operation.as_str(),
+                    entry.actor,
+                    serde_json::to_string(&entry.targets).unwrap_or_else(|_| "[]".to_string()),
+                    entry.detail.as_ref().map(|d| d.to_string()),
+                    format_datetime(&entry.created_at),
+                ],
+            )
+            .map_err(|e| database_error("failed to write audit log", e))?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    fn find(&self, filter: &AuditFilter, limit: usize) -> Result<Vec<AuditEntry>, DomainError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, operation, actor, targets, detail, created_at
+             FROM audit_log
+             WHERE (?1 IS NULL OR operation = ?1)
+               AND (?2 IS NULL OR actor = ?2)
+               AND (?3 IS NULL OR EXISTS (
+                   SELECT 1 FROM json_each(targets) WHERE substr(value, 1, length(?3)) = ?3))
+               AND (?4 IS NULL OR created_at > ?4)
+             ORDER BY created_at DESC, id DESC
+             LIMIT ?5",
+        )?;
+        let entries = stmt
+            .query_map(
+                params![
+                    filter.operation.map(|o| o.as_str()),
+                    filter.actor,
+                    filter.target,
+                    filter.after.as_ref().map(format_datetime),
+                    limit as i64,
+                ],
+                Self::row_to_entry,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+}
+
+// --- Embedding Repository ---
+
+pub struct SqliteEmbeddingRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> SqliteEmbeddingRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { conn }
+    }
+}
+
+/// Vectors are stored as little-endian f32 values.
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+const UNEMBEDDED_CONDITION: &str = "content != '' AND NOT EXISTS (
+    SELECT 1 FROM message_embeddings e
+    WHERE e.message_id = messages.id AND e.model = ?1 AND e.content_hash IS messages.content_hash
+)";
+
+impl EmbeddingRepository for SqliteEmbeddingRepository<'_> {
+    fn find_unembedded(&self, model: &str, limit: usize) -> Result<Vec<Message>, DomainError> {
+        let sql = format!(
+            "SELECT id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at
+             FROM messages WHERE {} ORDER BY rowid LIMIT ?2",
+            UNEMBEDDED_CONDITION
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let messages = stmt
+            .query_map(params![model, limit as i64], SqliteMessageRepository::row_to_message)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(messages)
+    }
+
+    fn count_unembedded(&self, model: &str) -> Result<usize, DomainError> {
+        let sql = format!("SELECT COUNT(*) FROM messages WHERE {}", UNEMBEDDED_CONDITION);
+        let count: i64 = self.conn.query_row(&sql, params![model], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    fn save(&self, message_id: &str, model: &str, vector: &[f32]) -> Result<(), DomainError> {
+        self.conn.execute_retry(
+            "INSERT OR REPLACE INTO message_embeddings (message_id, model, content_hash, dim, vector, created_at)
+             VALUES (?1, ?2, (SELECT content_hash FROM messages WHERE id = ?1), ?3, ?4, ?5)",
+            params![
+                message_id,
+                model,
+                vector.len() as i64,
+                encode_vector(vector),
+                format_datetime(&Utc::now())
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn find_vectors(&self, model: &str, filter: &MessageFilter) -> Result<Vec<(Message, Vec<f32>)>, DomainError> {
+        let mut values = vec![rusqlite::types::Value::Text(model.to_string())];
+        let mut conditions = vec![
+            "id IN (SELECT message_id FROM message_embeddings WHERE model = ?1)".to_string(),
+        ];
+        SqliteMessageRepository::push_filter_conditions(filter, &mut conditions, &mut values);
+
+        let sql = format!(
+            "SELECT id, thread_id, session_id, sender, role, content, metadata, parent_id, source, created_at, updated_at,
+                    (SELECT vector FROM message_embeddings WHERE message_id = messages.id AND model = ?1)
+             FROM messages {}",
+            SqliteMessageRepository::where_clause(&conditions)
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(values), |row| {
+                let bytes: Vec<u8> = row.get(11)?;
+                Ok((SqliteMessageRepository::row_to_message(row)?, decode_vector(&bytes)))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    fn clear(&self, model: &str) -> Result<usize, DomainError> {
+        Ok(self
+            .conn
+            .execute_retry("DELETE FROM message_embeddings WHERE model = ?1", params![model])?)
+    }
+
+    fn status(&self) -> Result<Vec<EmbeddingStatus>, DomainError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT e.model, COUNT(*), SUM(e.content_hash IS NOT m.content_hash)
+             FROM message_embeddings e JOIN messages m ON m.id = e.message_id
+             GROUP BY e.model ORDER BY e.model",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(EmbeddingStatus {
+                    model: row.get(0)?,
+                    vectors: row.get::<_, i64>(1)? as usize,
+                    stale: row.get::<_, i64>(2)? as usize,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn busy() -> rusqlite::Error {
+        rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY), None)
+    }
+
+    #[test]
+    fn retry_busy_retries_until_the_lock_is_released() {
+        let mut calls = 0;
+        let result = retry_busy(|| {
+            calls += 1;
+            if calls < 3 { Err(busy()) } else { Ok(calls) }
+        });
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn retry_busy_returns_other_errors_at_once() {
+        let mut calls = 0;
+        let result: rusqlite::Result<()> = retry_busy(|| {
+            calls += 1;
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn busy_errors_map_to_domain_busy() {
+        assert!(matches!(DomainError::from(busy()), DomainError::Busy(_)));
+        assert!(matches!(
+            database_error("failed to begin transaction", busy()),
+            DomainError::Busy(_)
+        ));
+        assert!(matches!(
+            DomainError::from(rusqlite::Error::QueryReturnedNoRows),
+            DomainError::Database(_)
+        ));
+    }
+
+    #[test]
+    fn count_by_role_rejects_an_unknown_stored_role() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(&dir.path().join("aiboard.db")).unwrap();
+        db.connection()
+            .execute_batch(
+                "INSERT INTO threads (id, title) VALUES ('t1', 'roles');
+                 INSERT INTO messages (id, thread_id, role, content) VALUES ('m1', 't1', 'user', 'a');
+                 INSERT INTO messages (id, thread_id, role, content) VALUES ('m2', 't1', 'robot', 'b');",
+            )
+            .unwrap();
+        let repo = SqliteMessageRepository::new(db.connection());
+        assert!(matches!(
+            repo.count_by_role(&MessageFilter::default()),
+            Err(DomainError::Database(_))
+        ));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn rewrite_encrypts_and_decrypts_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("aiboard.db");
+        let count = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM schema_version", [], |row| row.get(0)).unwrap()
+        };
+        let versions = count(Database::open(&path).unwrap().connection());
+
+        Database::open(&path).unwrap().rewrite(&path, Some("secret")).unwrap();
+        assert_ne!(&file_header(&path).unwrap(), SQLITE_HEADER);
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "key", "secret").unwrap();
+        assert_eq!(count(&conn), versions);
+        drop(conn);
+
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "key", "secret").unwrap();
+        let db = Database { conn, encrypted: true };
+        db.rewrite(&path, None).unwrap();
+        drop(db);
+        assert_eq!(&file_header(&path).unwrap(), SQLITE_HEADER);
+        assert_eq!(count(&Database::open(&path).unwrap().conn), versions);
+    }
 }
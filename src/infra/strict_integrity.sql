@@ -0,0 +1,30 @@
+-- Referential integrity of messages and drafts to their thread, installed on
+-- boards created with --strict-integrity. Triggers stand in for foreign keys,
+-- which the schema does not declare (see migrations/v001.sql) and which
+-- would need every table rebuilt.
+
+CREATE TRIGGER IF NOT EXISTS strict_messages_bi BEFORE INSERT ON messages
+WHEN NOT EXISTS (SELECT 1 FROM threads WHERE id = new.thread_id) BEGIN
+    SELECT RAISE(ABORT, 'strict integrity: message refers to a missing thread');
+END;
+
+CREATE TRIGGER IF NOT EXISTS strict_messages_bu BEFORE UPDATE OF thread_id ON messages
+WHEN NOT EXISTS (SELECT 1 FROM threads WHERE id = new.thread_id) BEGIN
+    SELECT RAISE(ABORT, 'strict integrity: message refers to a missing thread');
+END;
+
+CREATE TRIGGER IF NOT EXISTS strict_drafts_bi BEFORE INSERT ON drafts
+WHEN NOT EXISTS (SELECT 1 FROM threads WHERE id = new.thread_id) BEGIN
+    SELECT RAISE(ABORT, 'strict integrity: draft refers to a missing thread');
+END;
+
+CREATE TRIGGER IF NOT EXISTS strict_drafts_bu BEFORE UPDATE OF thread_id ON drafts
+WHEN NOT EXISTS (SELECT 1 FROM threads WHERE id = new.thread_id) BEGIN
+    SELECT RAISE(ABORT, 'strict integrity: draft refers to a missing thread');
+END;
+
+-- A deleted thread takes its messages and drafts with it.
+CREATE TRIGGER IF NOT EXISTS strict_threads_ad AFTER DELETE ON threads BEGIN
+    DELETE FROM messages WHERE thread_id = old.id;
+    DELETE FROM drafts WHERE thread_id = old.id;
+END;
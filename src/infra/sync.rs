@@ -0,0 +1,191 @@
+//! Where `aiboard sync` keeps the shared board: an aiboard DB file on a
+//! shared drive, or an HTTP endpoint that serves the file on GET and
+//! replaces it on PUT (404 while there is none yet).
+
+use std::path::{Path, PathBuf};
+
+use crate::domain::error::DomainError;
+use crate::infra::http::{self, FetchOptions, IfMatch};
+use crate::infra::sqlite::BoardCopy;
+
+pub enum Remote {
+    File(PathBuf),
+    Http { url: String, opts: FetchOptions },
+}
+
+/// What the remote held when it was fetched.
+#[derive(Debug, Clone, PartialEq)]
+enum Held {
+    Nothing,
+    Version(String),
+    /// A board from a server that sends no ETag.
+    Unversioned,
+}
+
+/// A private copy of the remote board and the version it was taken from.
+pub struct Snapshot {
+    pub copy: BoardCopy,
+    held: Held,
+}
+
+impl Snapshot {
+    /// The remote's version when it was fetched, if it reports one.
+    pub fn version(&self) -> Option<&str> {
+        match &self.held {
+            Held::Version(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl Remote {
+    /// An `http://` or `https://` URL, otherwise the path of a DB file.
+    pub fn parse(raw: &str, opts: FetchOptions) -> Self {
+        if raw.starts_with("http://") || raw.starts_with("https://") {
+            Remote::Http { url: raw.to_string(), opts }
+        } else {
+            Remote::File(PathBuf::from(raw))
+        }
+    }
+
+    /// How the remote is identified in the sync state: the URL, or the
+    /// absolute path of the file.
+    pub fn name(&self) -> String {
+        match self {
+            Remote::File(path) => std::path::absolute(path)
+                .unwrap_or_else(|_| path.clone())
+                .display()
+                .to_string(),
+            Remote::Http { url, .. } => url.clone(),
+        }
+    }
+
+    /// The current version of the remote board; `None` when there is none
+    /// or the server sends no ETag.
+    pub fn version(&self) -> Result<Option<String>, DomainError> {
+        match self {
+            Remote::File(path) => file_version(path),
+            Remote::Http { url, opts } => http::etag(url, opts),
+        }
+    }
+
+    /// Copies the remote board; an empty one when the remote has none yet.
+    pub fn fetch(&self) -> Result<Snapshot, DomainError> {
+        match self {
+            Remote::File(path) => match file_version(path)? {
+                None => Ok(Snapshot { copy: BoardCopy::empty()?, held: Held::Nothing }),
+                Some(version) => Ok(Snapshot {
+                    copy: BoardCopy::open(path)?,
+                    held: Held::Version(version),
+                }),
+            },
+            Remote::Http { url, opts } => {
+                // Nothing is written on 404, and an empty file opens as a
+                // new board.
+                let mut fetched = None;
+                let copy = BoardCopy::write_with(|file| {
+                    fetched = http::download(url, opts, file)?;
+                    Ok(())
+                })?;
+                let held = match fetched {
+                    None => Held::Nothing,
+                    Some(etag) => etag.map_or(Held::Unversioned, Held::Version),
+                };
+                Ok(Snapshot { copy, held })
+            }
+        }
+    }
+
+    /// Replaces the remote board with the snapshot's copy and returns the
+    /// new version. Fails with `DomainError::Conflict` when the remote
+    /// changed after the snapshot was fetched, and for a file with
+    /// `DomainError::Busy` while another push holds its lock file.
+    pub fn store(&self, snapshot: &mut Snapshot) -> Result<Option<String>, DomainError> {
+        let source = snapshot.copy.close()?.to_path_buf();
+        match self {
+            Remote::File(path) => {
+                // Held from the version check until the rename, so that two
+                // pushers cannot both pass the check.
+                let _lock = ShareLock::acquire(path)?;
+                let replaced = match &snapshot.held {
+                    Held::Version(v) => file_version(path)?.as_ref() != Some(v),
+                    _ => path.exists(),
+                };
+                if replaced {
+                    return Err(DomainError::Conflict(format!(
+                        "{} was replaced after it was read",
+                        path.display()
+                    )));
+                }
+                replace_file(&source, path)?;
+                file_version(path)
+            }
+            Remote::Http { url, opts } => {
+                let body = std::fs::File::open(&source)
+                    .map_err(|e| DomainError::Io(format!("failed to open {}: {}", source.display(), e)))?;
+                let len = body.metadata()?.len();
+                let precondition = match &snapshot.held {
+                    Held::Nothing => IfMatch::Absent,
+                    Held::Version(tag) => IfMatch::Tag(tag),
+                    Held::Unversioned => IfMatch::Any,
+                };
+                http::upload(url, opts, body, len, precondition)
+            }
+        }
+    }
+}
+
+/// `.<name>.lock` beside a shared board file, created exclusively for the
+/// duration of a push and removed on drop.
+struct ShareLock(PathBuf);
+
+impl ShareLock {
+    fn acquire(target: &Path) -> Result<Self, DomainError> {
+        let name = target.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        let path = target.with_file_name(format!(".{}.lock", name));
+        match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => Ok(ShareLock(path)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Err(DomainError::Busy(format!(
+                "{} exists: another push is in progress (remove it if none is)",
+                path.display()
+            ))),
+            Err(e) => Err(DomainError::Io(format!("failed to create {}: {}", path.display(), e))),
+        }
+    }
+}
+
+impl Drop for ShareLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Modification time and size of the file, which change with every push.
+fn file_version(path: &Path) -> Result<Option<String>, DomainError> {
+    let meta = match std::fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(DomainError::Io(format!("failed to read {}: {}", path.display(), e))),
+    };
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos());
+    Ok(Some(format!("{}:{}", modified, meta.len())))
+}
+
+/// Copies `source` beside `target` and renames it over `target`, so that
+/// readers of the share never see a half-written board.
+fn replace_file(source: &Path, target: &Path) -> Result<(), DomainError> {
+    let name = target.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let temp = target.with_file_name(format!(".{}.{}.tmp", name, uuid::Uuid::new_v4()));
+    let result = std::fs::copy(source, &temp)
+        .and_then(|_| std::fs::File::open(&temp)?.sync_all())
+        .and_then(|()| std::fs::rename(&temp, target))
+        .map_err(|e| DomainError::Io(format!("failed to write {}: {}", target.display(), e)));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
@@ -0,0 +1,69 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::infra::logger;
+
+/// Statements that take at least this long are written to error.log.
+pub const SLOW_QUERY: Duration = Duration::from_millis(100);
+
+static PHASES: Mutex<Vec<(&'static str, Duration)>> = Mutex::new(Vec::new());
+static QUERY_NANOS: AtomicU64 = AtomicU64::new(0);
+static QUERIES: AtomicU64 = AtomicU64::new(0);
+
+/// Runs `f` and adds its duration to the phase `name` for `--timing`.
+pub fn phase<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = f();
+    let elapsed = started.elapsed();
+    let mut phases = PHASES.lock().unwrap_or_else(|e| e.into_inner());
+    match phases.iter_mut().find(|(phase, _)| *phase == name) {
+        Some((_, total)) => *total += elapsed,
+        None => phases.push((name, elapsed)),
+    }
+    result
+}
+
+/// The total duration of the phase `name` so far.
+pub fn phase_duration(name: &str) -> Duration {
+    let phases = PHASES.lock().unwrap_or_else(|e| e.into_inner());
+    phases
+        .iter()
+        .find(|(phase, _)| *phase == name)
+        .map_or(Duration::ZERO, |(_, total)| *total)
+}
+
+/// Profile callback of every connection: counts the statement and logs it
+/// when it is slow.
+pub fn record_statement(sql: &str, elapsed: Duration) {
+    QUERY_NANOS.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    QUERIES.fetch_add(1, Ordering::Relaxed);
+    if elapsed >= SLOW_QUERY {
+        logger::log_slow_query(sql, elapsed);
+    }
+}
+
+/// Statements run so far and their total duration.
+pub fn queries() -> (u64, Duration) {
+    (
+        QUERIES.load(Ordering::Relaxed),
+        Duration::from_nanos(QUERY_NANOS.load(Ordering::Relaxed)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_of_the_same_name_add_up() {
+        let value = phase("test phase", || {
+            std::thread::sleep(Duration::from_millis(2));
+            7
+        });
+        phase("test phase", || std::thread::sleep(Duration::from_millis(2)));
+        assert_eq!(value, 7);
+        assert!(phase_duration("test phase") >= Duration::from_millis(4));
+        assert_eq!(phase_duration("no such phase"), Duration::ZERO);
+    }
+}
@@ -3,44 +3,112 @@ mod domain;
 mod infra;
 mod usecase;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use clap::Parser;
-use cli::args::{Cli, Commands};
+use clap::{CommandFactory, FromArgMatches};
+use cli::args::{BackupAction, Cli, Commands, DbAction, MessageAction, SyncAction};
 use cli::handler;
 use domain::error::DomainError;
 use infra::logger;
-use infra::sqlite::{Database, SqliteMessageRepository, SqliteThreadRepository};
+use infra::timing;
+use infra::sqlite::{
+    Database, SqliteDraftRepository, SqliteEmbeddingRepository, SqliteGroupRepository,
+    SqliteAuditRepository, SqliteMessageRepository, SqliteSyncStateRepository, SqliteThreadRepository,
+};
 use usecase::cleanup::CleanupUseCase;
+use usecase::draft::DraftUseCase;
+use usecase::group::GroupUseCase;
 use usecase::hook::HookUseCase;
+use usecase::merge::MergeUseCase;
 use usecase::message::MessageUseCase;
+use usecase::semantic::SemanticUseCase;
+use usecase::stats::StatsUseCase;
+use usecase::audit::AuditUseCase;
+use usecase::sync::SyncUseCase;
 use usecase::thread::ThreadUseCase;
 
 fn main() {
-    let cli = Cli::parse();
+    let started = Instant::now();
+    let args: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    logger::start(args.get(1..).unwrap_or_default());
+    cli::i18n::set_lang(cli::i18n::detect(&args));
+    match infra::config::load() {
+        Ok(config) => infra::config::set(config),
+        Err(e) => fail(e.into(), json_errors_requested()),
+    }
+    let config = infra::config::get();
+    let matches = match command().try_get_matches_from(args) {
+        Ok(matches) => matches,
+        Err(e) => usage_error(e),
+    };
+    let cli = match Cli::from_arg_matches(&matches) {
+        Ok(cli) => cli,
+        Err(e) => usage_error(e),
+    };
+    let json_errors = json_errors_requested() || json_format(&matches);
+    logger::set_command(subcommand_path(&matches));
+    cli::formatter::set_color_choice(&cli.color);
+    handler::set_quiet(cli.quiet);
+    cli::formatter::set_compact_json(cli.compact);
+    cli::formatter::set_relative_time(cli.relative_time);
+    if let Some(len) = config.truncate {
+        cli::formatter::set_truncate_len(len);
+    }
+
+    if cli::pager::wanted(&cli) {
+        cli::pager::start();
+    }
 
-    let result = run(cli);
+    let report_timing = cli.timing;
+    let result = run(cli).and_then(|()| timing::phase("command", cli::output::commit));
+    if result.is_err() {
+        cli::output::discard();
+    }
+    cli::pager::finish();
+    if report_timing {
+        print_timing(started.elapsed());
+    }
 
     match result {
         Ok(()) => std::process::exit(0),
-        Err(e) => {
-            let (exit_code, user_msg) = classify_error(&e);
-            logger::log_error(&format!("{:#}", e));
-            eprintln!("エラー: {}", user_msg);
-            std::process::exit(exit_code);
-        }
+        Err(e) => fail(e, json_errors),
+    }
+}
+
+/// Logs `e`, reports it on stderr (as JSON when `json`) and exits with its
+/// exit code.
+fn fail(e: anyhow::Error, json: bool) -> ! {
+    let (exit_code, error_code, user_msg) = classify_error(&e);
+    logger::log_error(&format!("{:#}", e), error_code, exit_code);
+    if json {
+        eprintln!("{}", cli::formatter::format_error_json(error_code, &user_msg, exit_code));
+    } else {
+        eprintln!("{}", cli::i18n::fill(cli::i18n::tr("エラー: {}"), &[&user_msg]));
     }
+    std::process::exit(exit_code);
 }
 
-fn db_path() -> PathBuf {
-    let data_dir = dirs_fallback();
-    data_dir.join("aiboard.db")
+/// `--db`, otherwise `aiboard.db` in the data directory.
+fn db_path(cli: &Cli, data_dir: &Path) -> PathBuf {
+    match cli.db {
+        Some(ref db) => db.clone(),
+        None => data_dir.join("aiboard.db"),
+    }
 }
 
-fn dirs_fallback() -> PathBuf {
+/// `--data-dir`, then `AIBOARD_DATA_DIR`, then `data_dir` in the config,
+/// then `~/.aiboard`.
+fn data_dir(cli: &Cli) -> PathBuf {
+    if let Some(ref dir) = cli.data_dir {
+        return dir.clone();
+    }
     if let Some(dir) = std::env::var_os("AIBOARD_DATA_DIR") {
         return PathBuf::from(dir);
     }
+    if let Some(ref dir) = infra::config::get().data_dir {
+        return dir.clone();
+    }
     if let Some(profile) = std::env::var_os("USERPROFILE") {
         return PathBuf::from(profile).join(".aiboard");
     }
@@ -50,54 +118,281 @@ fn dirs_fallback() -> PathBuf {
     PathBuf::from(".aiboard")
 }
 
+/// The command line definition with configured defaults, in the chosen
+/// language.
+fn command() -> clap::Command {
+    let command = cli::args::with_config_defaults(Cli::command(), infra::config::get());
+    cli::i18n::localize_command(command)
+}
+
 fn run(cli: Cli) -> anyhow::Result<()> {
-    let path = db_path();
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)?;
+    let data_dir = data_dir(&cli);
+    let path = (!cli.ephemeral).then(|| db_path(&cli, &data_dir));
+    let db = match path {
+        Some(ref path) => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            Database::open(path)?
+        }
+        None => Database::open_in_memory()?,
+    };
+    if db.is_new() && (cli.strict_integrity || infra::config::get().strict_integrity == Some(true)) {
+        db.enforce_integrity()?;
+    }
+    timing::phase("command", || match cli.command {
+        Commands::Batch { atomic } => run_batch(&db, path.as_deref(), &data_dir, atomic),
+        command => execute(command, &db, path.as_deref(), &data_dir),
+    })
+}
+
+/// Prints the phases of `--timing` to stderr. Formatting is the time the
+/// command spent outside SQL statements.
+fn print_timing(total: Duration) {
+    use cli::i18n::{fill, tr};
+
+    let (statements, query) = timing::queries();
+    let phases = [
+        (tr("DB オープン").to_string(), timing::phase_duration("db open")),
+        (tr("マイグレーション確認").to_string(), timing::phase_duration("migration check")),
+        (fill(tr("クエリ（{} 件）"), &[&statements]), query),
+        (tr("整形・出力").to_string(), timing::phase_duration("command").saturating_sub(query)),
+        (tr("合計").to_string(), total),
+    ];
+    for (label, duration) in phases {
+        eprintln!("{}: {:.2} ms", label, duration.as_secs_f64() * 1000.0);
+    }
+}
+
+/// Runs the commands on stdin (see `cli::batch`) in one transaction, each
+/// inside a savepoint so that a failing command leaves no partial writes,
+/// and prints one JSON result per line.
+fn run_batch(db: &Database, path: Option<&Path>, data_dir: &Path, atomic: bool) -> anyhow::Result<()> {
+    use std::io::BufRead;
+
+    db.begin()?;
+    let mut failed = 0;
+    for (index, text) in std::io::stdin().lock().lines().enumerate() {
+        let text = match text {
+            Ok(text) => text,
+            Err(e) => {
+                db.rollback()?;
+                return Err(e.into());
+            }
+        };
+        let number = index + 1;
+        let line = match cli::batch::parse_line(&text) {
+            Ok(None) => continue,
+            Ok(Some(line)) => Ok(line),
+            Err(e) => Err(e),
+        };
+        db.savepoint("batch_line")?;
+        cli::output::capture();
+        let result = line
+            .and_then(|line| {
+                cli::batch::set_stdin(Some(line.stdin.unwrap_or_default()));
+                batch_command(line.args)
+            })
+            .and_then(|command| execute(command, db, path, data_dir))
+            .and_then(|()| cli::output::commit());
+        cli::batch::set_stdin(None);
+        let output = cli::output::take_captured();
+        match result {
+            Ok(()) => {
+                db.release("batch_line")?;
+                println!("{}", cli::formatter::format_batch_result(number, &output));
+            }
+            Err(e) => {
+                cli::output::discard();
+                db.rollback_to("batch_line")?;
+                let (exit_code, error_code, message) = classify_error(&e);
+                logger::log_error(&format!("batch line {}: {:#}", number, e), error_code, exit_code);
+                println!(
+                    "{}",
+                    cli::formatter::format_batch_error(number, error_code, &message, exit_code)
+                );
+                if atomic {
+                    db.rollback()?;
+                    return Err(e);
+                }
+                failed += 1;
+            }
+        }
+    }
+    db.commit()?;
+    if failed > 0 {
+        anyhow::bail!(cli::i18n::fill(cli::i18n::tr("{} 行のコマンドが失敗しました"), &[&failed]));
+    }
+    Ok(())
+}
+
+/// Parses one command of `batch`. The board and the global options are
+/// those of the `batch` invocation itself.
+fn batch_command(args: Vec<String>) -> anyhow::Result<Commands> {
+    let argv = std::iter::once("aiboard".to_string()).chain(args);
+    let matches = command().try_get_matches_from(argv).map_err(|e| {
+        let rendered = e.render().to_string();
+        let message = rendered.lines().next().unwrap_or_default();
+        DomainError::InvalidInput(message.strip_prefix("error: ").unwrap_or(message).to_string())
+    })?;
+    let cli = Cli::from_arg_matches(&matches).map_err(|e| DomainError::InvalidInput(e.to_string()))?;
+    if cli.data_dir.is_some() || cli.db.is_some() || cli.ephemeral {
+        return Err(DomainError::InvalidInput(
+            cli::i18n::tr("batch の各行では --data-dir・--db・--ephemeral は指定できません（batch 自体に指定してください）")
+                .to_string(),
+        )
+        .into());
     }
-    let db = Database::open(&path)?;
+    // VACUUM cannot run inside a transaction, and restore and sync push/pull
+    // write the board through other connections that the batch transaction
+    // would block.
+    match cli.command {
+        Commands::Batch { .. }
+        | Commands::Setup { .. }
+        | Commands::Message { action: MessageAction::Watch { .. } }
+        | Commands::Db { action: DbAction::Encrypt | DbAction::Decrypt | DbAction::Vacuum { .. } }
+        | Commands::Backup { action: BackupAction::Restore { .. } }
+        | Commands::Sync { action: SyncAction::Push { .. } | SyncAction::Pull { .. } } => {
+            Err(DomainError::InvalidInput(cli::i18n::tr("このコマンドは batch では実行できません").to_string()).into())
+        }
+        command => Ok(command),
+    }
+}
+
+fn execute(command: Commands, db: &Database, path: Option<&Path>, data_dir: &Path) -> anyhow::Result<()> {
     let conn = db.connection();
 
     let msg = || SqliteMessageRepository::new(conn);
     let thr = || SqliteThreadRepository::new(conn);
 
     let thread_uc = ThreadUseCase::new(thr(), msg());
-    let message_uc = MessageUseCase::new(msg());
+    let message_uc = MessageUseCase::new(thr(), msg());
     let hook_uc = HookUseCase::new(thr(), msg());
     let cleanup_uc = CleanupUseCase::new(thr(), msg());
     let thread_uc2 = ThreadUseCase::new(thr(), msg());
+    let draft_uc = DraftUseCase::new(SqliteDraftRepository::new(conn));
+    let stats_uc = StatsUseCase::new(thr(), msg());
+    let group_uc = GroupUseCase::new(SqliteGroupRepository::new(conn));
+    let semantic_uc = SemanticUseCase::new(SqliteEmbeddingRepository::new(conn));
+    let merge_uc = MergeUseCase::new(thr(), msg());
+    let sync_uc = SyncUseCase::new(SqliteSyncStateRepository::new(conn));
+    let audit_uc = AuditUseCase::new(SqliteAuditRepository::new(conn));
 
-    match cli.command {
+    match command {
+        Commands::Message { action: MessageAction::Draft { action } } => {
+            handler::handle_draft(action, db, &draft_uc, &message_uc, &thread_uc2, &audit_uc)?;
+        }
         Commands::Message { action } => {
-            handler::handle_message(action, &message_uc, &thread_uc2)?;
+            handler::handle_message(action, &message_uc, &thread_uc2, &hook_uc, &semantic_uc, &audit_uc)?;
         }
         Commands::Thread { action } => {
-            handler::handle_thread(action, &thread_uc)?;
+            handler::handle_thread(action, db, &thread_uc, &audit_uc)?;
         }
         Commands::Hook { action } => {
-            handler::handle_hook(action, &hook_uc)?;
+            handler::handle_hook(action, &hook_uc, &thread_uc2, data_dir)?;
         }
         Commands::Cleanup { action } => {
-            handler::handle_cleanup(action, &cleanup_uc, &path)?;
+            handler::handle_cleanup(action, &cleanup_uc, &audit_uc, path)?;
         }
         Commands::Setup { action } => {
             handler::handle_setup(action)?;
         }
+        Commands::Group { action } => {
+            handler::handle_group(action, &group_uc)?;
+        }
+        Commands::Stats { action } => {
+            handler::handle_stats(action, &stats_uc)?;
+        }
+        Commands::Search { action } => {
+            handler::handle_search(action, &message_uc)?;
+        }
+        Commands::Index { action } => {
+            handler::handle_index(action, &message_uc, &semantic_uc)?;
+        }
+        Commands::Db { action } => {
+            handler::handle_db(action, db, path, &message_uc, &merge_uc, &audit_uc)?;
+        }
+        Commands::Backup { action } => {
+            handler::handle_backup(action, path)?;
+        }
+        Commands::Sync { action } => {
+            handler::handle_sync(action, db, path, &merge_uc, &sync_uc, &audit_uc)?;
+        }
+        Commands::Audit { action } => {
+            handler::handle_audit(action, &audit_uc)?;
+        }
+        Commands::Export { output } => {
+            handler::handle_export(&output, db, &thread_uc, &group_uc)?;
+        }
+        Commands::Import { path } => {
+            handler::handle_import(&path, db, &thread_uc, &group_uc)?;
+        }
+        Commands::Schema { command } => {
+            handler::handle_schema(&command)?;
+        }
+        Commands::Completions { shell } => {
+            handler::handle_completions(shell)?;
+        }
         Commands::Util { action } => {
             handler::handle_util(action)?;
         }
         Commands::Notify { message, title } => {
             handler::handle_notify(&message, &title)?;
         }
+        Commands::Batch { .. } => unreachable!("batch is run by run_batch"),
     }
 
     Ok(())
 }
 
-fn classify_error(e: &anyhow::Error) -> (i32, String) {
+fn classify_error(e: &anyhow::Error) -> (i32, &'static str, String) {
     if let Some(domain_err) = e.downcast_ref::<DomainError>() {
-        (domain_err.exit_code(), domain_err.to_string())
+        (domain_err.exit_code(), domain_err.code(), cli::i18n::domain_error(domain_err))
     } else {
-        (1, e.to_string())
+        (1, "ERROR", e.to_string())
+    }
+}
+
+/// `AIBOARD_ERRORS=json` asks for JSON errors whatever the output format.
+fn json_errors_requested() -> bool {
+    std::env::var("AIBOARD_ERRORS").is_ok_and(|v| v.eq_ignore_ascii_case("json"))
+}
+
+/// Whether the command was run with `--format json` (or `jsonl`).
+fn json_format(matches: &clap::ArgMatches) -> bool {
+    let mut leaf = matches;
+    while let Some((_, sub)) = leaf.subcommand() {
+        leaf = sub;
+    }
+    matches!(leaf.try_get_one::<String>("format"), Ok(Some(f)) if f == "json" || f == "jsonl")
+}
+
+/// The names of the subcommands run, e.g. `message post`.
+fn subcommand_path(matches: &clap::ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut leaf = matches;
+    while let Some((name, sub)) = leaf.subcommand() {
+        names.push(name);
+        leaf = sub;
+    }
+    names.join(" ")
+}
+
+/// Exits on a command-line error, as JSON with code `USAGE` when
+/// `AIBOARD_ERRORS=json`. Help and version output are not errors.
+fn usage_error(e: clap::Error) -> ! {
+    use clap::error::ErrorKind;
+
+    let informational = matches!(
+        e.kind(),
+        ErrorKind::DisplayHelp | ErrorKind::DisplayVersion | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand
+    );
+    if informational || !json_errors_requested() {
+        e.exit();
     }
+    let rendered = e.render().to_string();
+    let message = rendered.lines().next().unwrap_or_default();
+    let message = message.strip_prefix("error: ").unwrap_or(message);
+    eprintln!("{}", cli::formatter::format_error_json("USAGE", message, e.exit_code()));
+    std::process::exit(e.exit_code());
 }
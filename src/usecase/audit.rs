@@ -0,0 +1,39 @@
+use chrono::Utc;
+
+use crate::domain::entity::{AuditEntry, AuditOperation};
+use crate::domain::error::DomainError;
+use crate::domain::repository::{AuditFilter, AuditRepository};
+
+pub struct AuditUseCase<A: AuditRepository> {
+    pub(crate) repo: A,
+}
+
+impl<A: AuditRepository> AuditUseCase<A> {
+    pub fn new(repo: A) -> Self {
+        Self { repo }
+    }
+
+    /// Appends an entry for an operation that has just been done.
+    pub fn record(
+        &self,
+        operation: AuditOperation,
+        actor: Option<&str>,
+        targets: &[&str],
+        detail: Option<serde_json::Value>,
+    ) -> Result<AuditEntry, DomainError> {
+        let mut entry = AuditEntry {
+            id: 0,
+            operation,
+            actor: actor.map(|a| a.to_string()),
+            targets: targets.iter().map(|t| t.to_string()).collect(),
+            detail,
+            created_at: Utc::now(),
+        };
+        entry.id = self.repo.append(&entry)?;
+        Ok(entry)
+    }
+
+    pub fn list(&self, filter: &AuditFilter, limit: usize) -> Result<Vec<AuditEntry>, DomainError> {
+        self.repo.find(filter, limit)
+    }
+}
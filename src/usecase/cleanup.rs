@@ -1,5 +1,6 @@
+use crate::domain::entity::Thread;
 use crate::domain::error::DomainError;
-use crate::domain::repository::{MessageRepository, ThreadRepository};
+use crate::domain::repository::{MessageRepository, ThreadFilter, ThreadRepository};
 use chrono::{Duration, Utc};
 
 pub struct CleanupUseCase<T: ThreadRepository, M: MessageRepository> {
@@ -20,11 +21,35 @@ impl<T: ThreadRepository, M: MessageRepository> CleanupUseCase<T, M> {
         self.message_repo.delete_older_than(&cutoff)
     }
 
-    pub fn by_thread(&self, short_id: &str) -> Result<usize, DomainError> {
+    /// Deletes the thread and its messages. Returns the thread's full ID
+    /// and the number of messages deleted.
+    pub fn by_thread(&self, short_id: &str) -> Result<(String, usize), DomainError> {
         let full_id = self.thread_repo.resolve_short_id(short_id)?;
         let count = self.message_repo.delete_by_thread(&full_id)?;
         self.thread_repo.delete(&full_id)?;
-        Ok(count)
+        Ok((full_id, count))
+    }
+
+    /// Enforces each thread's retention policy. Returns the threads that lost
+    /// messages together with the number deleted from each.
+    pub fn apply_retention(&self) -> Result<Vec<(Thread, usize)>, DomainError> {
+        let mut results = Vec::new();
+        for thread in self.thread_repo.find(&ThreadFilter::default())? {
+            let deleted = match (thread.retention_days, thread.retention_keep_last) {
+                (Some(days), _) => {
+                    let cutoff = Utc::now() - Duration::days(days);
+                    self.message_repo.delete_in_thread_older_than(&thread.id, &cutoff)?
+                }
+                (None, Some(keep)) => self
+                    .message_repo
+                    .delete_in_thread_except_last(&thread.id, keep.max(0) as usize)?,
+                (None, None) => continue,
+            };
+            if deleted > 0 {
+                results.push((thread, deleted));
+            }
+        }
+        Ok(results)
     }
 
     pub fn by_session(&self, session_id: &str) -> Result<usize, DomainError> {
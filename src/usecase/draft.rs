@@ -0,0 +1,87 @@
+use crate::domain::entity::{Draft, Message, Role};
+use crate::domain::error::DomainError;
+use crate::domain::repository::{DraftRepository, MessageRepository, ThreadRepository};
+use crate::usecase::message::MessageUseCase;
+use chrono::Utc;
+use uuid::Uuid;
+
+pub struct DraftUseCase<D: DraftRepository> {
+    pub(crate) draft_repo: D,
+}
+
+impl<D: DraftRepository> DraftUseCase<D> {
+    pub fn new(draft_repo: D) -> Self {
+        Self { draft_repo }
+    }
+
+    pub fn create(
+        &self,
+        thread_id: &str,
+        sender: Option<&str>,
+        role: Role,
+        content: &str,
+        metadata: Option<serde_json::Value>,
+    ) -> Result<Draft, DomainError> {
+        let now = Utc::now();
+        let draft = Draft {
+            id: Uuid::new_v4().to_string(),
+            thread_id: thread_id.to_string(),
+            sender: sender.map(|s| s.to_string()),
+            role,
+            content: content.to_string(),
+            metadata,
+            created_at: now,
+            updated_at: now,
+        };
+        self.draft_repo.save(&draft)?;
+        Ok(draft)
+    }
+
+    /// Saves a draft whose content was changed with `Draft::edit_content`.
+    pub fn update(&self, draft: &mut Draft) -> Result<(), DomainError> {
+        draft.updated_at = Utc::now();
+        self.draft_repo.save(draft)
+    }
+
+    pub fn get(&self, short_id: &str) -> Result<Draft, DomainError> {
+        let full_id = self.draft_repo.resolve_short_id(short_id)?;
+        self.draft_repo
+            .find_by_id(&full_id)?
+            .ok_or(DomainError::DraftNotFound(full_id))
+    }
+
+    pub fn list(&self, thread_id: Option<&str>, sender: Option<&str>) -> Result<Vec<Draft>, DomainError> {
+        self.draft_repo.list(thread_id, sender)
+    }
+
+    /// Posts a draft through `MessageUseCase::post`, so the same rules as
+    /// `message post` apply, and removes the draft. The caller runs this
+    /// inside a savepoint so that a failure leaves neither behind.
+    pub fn post<T: ThreadRepository, M: MessageRepository>(
+        &self,
+        short_id: &str,
+        message_uc: &MessageUseCase<T, M>,
+    ) -> Result<Message, DomainError> {
+        let draft = self.get(short_id)?;
+        if draft.content.is_empty() {
+            return Err(DomainError::InvalidInput(format!("draft {} is empty", draft.id)));
+        }
+
+        let msg = message_uc.post(
+            &draft.thread_id,
+            draft.role,
+            &draft.content,
+            None,
+            draft.sender.as_deref(),
+            draft.metadata,
+            None,
+        )?;
+        self.draft_repo.delete(&draft.id)?;
+        Ok(msg)
+    }
+
+    pub fn delete(&self, short_id: &str) -> Result<(), DomainError> {
+        let full_id = self.draft_repo.resolve_short_id(short_id)?;
+        self.draft_repo.delete(&full_id)
+    }
+}
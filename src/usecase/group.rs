@@ -0,0 +1,56 @@
+use crate::domain::entity::Group;
+use crate::domain::error::DomainError;
+use crate::domain::repository::GroupRepository;
+
+/// Group names end up in `@team:<name>` mentions, so they are limited to
+/// characters that the mention boundary check treats as word characters.
+fn validate_group_name(name: &str) -> Result<(), DomainError> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(DomainError::InvalidInput(format!(
+            "invalid group name: '{}' (use letters, digits, '_' or '-')",
+            name
+        )));
+    }
+    Ok(())
+}
+
+pub struct GroupUseCase<G: GroupRepository> {
+    pub(crate) repo: G,
+}
+
+impl<G: GroupRepository> GroupUseCase<G> {
+    pub fn new(repo: G) -> Self {
+        Self { repo }
+    }
+
+    /// Adds members to the group, returning how many were newly added.
+    pub fn add(&self, name: &str, members: &[String]) -> Result<usize, DomainError> {
+        validate_group_name(name)?;
+        let mut added = 0;
+        for member in members {
+            if self.repo.add_member(name, member)? {
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Removes members from the group, returning how many were removed.
+    pub fn remove(&self, name: &str, members: &[String]) -> Result<usize, DomainError> {
+        let mut removed = 0;
+        for member in members {
+            if self.repo.remove_member(name, member)? {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    pub fn list(&self) -> Result<Vec<Group>, DomainError> {
+        self.repo.list()
+    }
+}
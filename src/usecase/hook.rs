@@ -1,9 +1,26 @@
 use crate::domain::entity::{Message, Role, Thread, ThreadStatus};
 use crate::domain::error::DomainError;
 use crate::domain::repository::{MessageRepository, ThreadRepository};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
+/// Turns at the end of the transcript kept in a PreCompact marker.
+const COMPACT_TAIL_TURNS: usize = 20;
+
+/// Characters kept of each turn in a PreCompact marker.
+const COMPACT_TURN_CHARS: usize = 2000;
+
+/// A text turn extracted from a Claude Code transcript JSONL file.
+struct TranscriptTurn {
+    /// The entry's `uuid`, used as the message ID so a re-import can tell
+    /// which turns it already has.
+    uuid: Option<String>,
+    role: Role,
+    text: String,
+    session_id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+}
+
 pub struct HookUseCase<T: ThreadRepository, R: MessageRepository> {
     pub(crate) thread_repo: T,
     pub(crate) repo: R,
@@ -19,11 +36,12 @@ impl<T: ThreadRepository, R: MessageRepository> HookUseCase<T, R> {
     /// The JSON contains common fields (session_id, hook_event_name, etc.)
     /// plus event-specific fields. A thread_id override can be provided
     /// via CLI; otherwise session_id is used as the thread_id.
+    /// Returns the stored messages (empty when the event is skipped).
     pub fn ingest(
         &self,
         thread_id_override: Option<&str>,
         json_input: &str,
-    ) -> Result<usize, DomainError> {
+    ) -> Result<Vec<Message>, DomainError> {
         let parsed: serde_json::Value = serde_json::from_str(json_input)
             .map_err(|e| DomainError::Parse(format!("invalid JSON: {}", e)))?;
 
@@ -32,8 +50,13 @@ impl<T: ThreadRepository, R: MessageRepository> HookUseCase<T, R> {
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
 
+        // An override naming an existing thread targets it; anything else is
+        // taken as a literal thread ID and created on demand.
         let thread_id = match thread_id_override {
-            Some(tid) => tid.to_string(),
+            Some(tid) => self
+                .thread_repo
+                .find_id_by_name(tid)?
+                .unwrap_or_else(|| tid.to_string()),
             None => session_id
                 .clone()
                 .ok_or_else(|| DomainError::Parse("no session_id and no --thread provided".to_string()))?,
@@ -44,14 +67,14 @@ impl<T: ThreadRepository, R: MessageRepository> HookUseCase<T, R> {
             .and_then(|v| v.as_str())
             .unwrap_or("Unknown");
 
-        let (role, content, sender, source) = match event_name {
+        let (role, content, sender, source, metadata) = match event_name {
             "UserPromptSubmit" => {
                 let prompt = parsed
                     .get("prompt")
                     .and_then(|v| v.as_str())
                     .unwrap_or("")
                     .to_string();
-                (Role::User, prompt, None, "user")
+                (Role::User, prompt, None, "user", None)
             }
             "PostToolUse" => {
                 let tool_name = parsed
@@ -61,21 +84,21 @@ impl<T: ThreadRepository, R: MessageRepository> HookUseCase<T, R> {
 
                 if tool_name == "AskUserQuestion" {
                     match Self::parse_ask_user_question(&parsed) {
-                        Some(content) => (Role::User, content, None, "user"),
-                        None => return Ok(0),
+                        Some(content) => (Role::User, content, None, "user", None),
+                        None => return Ok(Vec::new()),
                     }
                 } else {
                     // Other tool events are skipped to avoid storing large outputs
-                    return Ok(0);
+                    return Ok(Vec::new());
                 }
             }
             "Stop" => {
                 // Extract main agent's last response from transcript_path
                 match Self::parse_transcript_last_assistant(&parsed, "transcript_path") {
                     Some(content) => {
-                        (Role::Assistant, content, Some("claude".to_string()), "agent")
+                        (Role::Assistant, content, Some("claude".to_string()), "agent", None)
                     }
-                    None => return Ok(0),
+                    None => return Ok(Vec::new()),
                 }
             }
             "SubagentStop" => {
@@ -87,23 +110,35 @@ impl<T: ThreadRepository, R: MessageRepository> HookUseCase<T, R> {
                 match Self::parse_transcript_last_assistant(&parsed, "agent_transcript_path") {
                     Some(content) => {
                         let sender = format!("subagent:{}", agent_type);
-                        (Role::Assistant, content, Some(sender), "agent")
+                        (Role::Assistant, content, Some(sender), "agent", None)
                     }
                     None => {
                         // Fallback if transcript is unavailable
                         let content = "[SubagentStop] event received".to_string();
-                        (Role::System, content, None, "system")
+                        (Role::System, content, None, "system", None)
                     }
                 }
             }
+            "PreCompact" => {
+                // Keep the tail of the conversation that compaction is about
+                // to summarize away, as a marker that `--type compact` finds.
+                let trigger = parsed
+                    .get("trigger")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown");
+                let content = Self::parse_transcript_tail(&parsed)
+                    .unwrap_or_else(|| "[PreCompact] event received".to_string());
+                let metadata = serde_json::json!({ "msg_type": "compact", "trigger": trigger });
+                (Role::System, content, None, "system", Some(metadata))
+            }
             other => {
                 let content = format!("[{}] event received", other);
-                (Role::System, content, None, "system")
+                (Role::System, content, None, "system", None)
             }
         };
 
         if content.is_empty() {
-            return Ok(0);
+            return Ok(Vec::new());
         }
 
         let now = Utc::now();
@@ -117,6 +152,16 @@ impl<T: ThreadRepository, R: MessageRepository> HookUseCase<T, R> {
             source_url: None,
             status: ThreadStatus::default(),
             phase: None,
+            summary: None,
+            tags: Vec::new(),
+            pinned: false,
+            metadata: None,
+            default_sender: None,
+            default_role: None,
+            locked: false,
+            lock_allow: Vec::new(),
+            retention_days: None,
+            retention_keep_last: None,
             created_at: now,
             updated_at: now,
         };
@@ -127,6 +172,9 @@ impl<T: ThreadRepository, R: MessageRepository> HookUseCase<T, R> {
             if existing.status == ThreadStatus::Closed {
                 eprintln!("警告: thread {} はクローズされています", &thread_id[..8.min(thread_id.len())]);
             }
+            if !existing.accepts_post_from(sender.as_deref()) {
+                return Err(DomainError::ThreadLocked(existing.id));
+            }
         }
 
         let message = Message {
@@ -136,14 +184,15 @@ impl<T: ThreadRepository, R: MessageRepository> HookUseCase<T, R> {
             sender,
             role,
             content,
-            metadata: None,
+            metadata,
             parent_id: None,
             source: Some(source.to_string()),
             created_at: now,
             updated_at: now,
         };
 
-        self.repo.insert_batch(&[message])
+        self.repo.insert_batch(std::slice::from_ref(&message))?;
+        Ok(vec![message])
     }
 
     /// Extract the last assistant message from a transcript JSONL file.
@@ -153,6 +202,36 @@ impl<T: ThreadRepository, R: MessageRepository> HookUseCase<T, R> {
         parsed: &serde_json::Value,
         path_key: &str,
     ) -> Option<String> {
+        let content = Self::read_transcript(parsed, path_key)?;
+        Self::parse_transcript_turns(&content)
+            .into_iter()
+            .rev()
+            .find(|turn| turn.role == Role::Assistant)
+            .map(|turn| turn.text)
+    }
+
+    /// The last `COMPACT_TAIL_TURNS` text turns of the main transcript, one
+    /// `[role] text` block each (long turns are cut), under a header line.
+    fn parse_transcript_tail(parsed: &serde_json::Value) -> Option<String> {
+        let content = Self::read_transcript(parsed, "transcript_path")?;
+        let turns = Self::parse_transcript_turns(&content);
+        if turns.is_empty() {
+            return None;
+        }
+        let tail = &turns[turns.len().saturating_sub(COMPACT_TAIL_TURNS)..];
+        let mut lines = vec![format!("[PreCompact] 圧縮前の会話の末尾（直近 {} 件）", tail.len())];
+        for turn in tail {
+            let mut text: String = turn.text.chars().take(COMPACT_TURN_CHARS).collect();
+            if text.len() < turn.text.len() {
+                text.push('…');
+            }
+            lines.push(format!("[{}] {}", turn.role, text));
+        }
+        Some(lines.join("\n\n"))
+    }
+
+    /// Reads the transcript file named by `path_key` in the hook JSON.
+    fn read_transcript(parsed: &serde_json::Value, path_key: &str) -> Option<String> {
         let transcript_path = parsed
             .get(path_key)
             .and_then(|v| v.as_str());
@@ -166,34 +245,155 @@ impl<T: ThreadRepository, R: MessageRepository> HookUseCase<T, R> {
         };
 
         // Read the transcript file (JSONL format)
-        let content = match std::fs::read_to_string(transcript_path) {
-            Ok(c) => c,
+        match std::fs::read_to_string(transcript_path) {
+            Ok(c) => Some(c),
             Err(e) => {
                 eprintln!("DEBUG: failed to read transcript '{}': {}", transcript_path, e);
-                return None;
+                None
             }
+        }
+    }
+
+    /// Import every user and assistant text turn of a transcript JSONL file
+    /// into a thread. The thread defaults to the transcript's sessionId, so
+    /// imported sessions line up with threads created by `hook ingest`.
+    /// Turns already in the thread are skipped, so an import can be re-run:
+    /// those whose `uuid` is the ID of one of its messages, and those matching
+    /// one by role, content and (second-precision) timestamp. A turn whose
+    /// `uuid` is taken by a message of another thread gets a fresh ID.
+    pub fn import_transcript(
+        &self,
+        thread_id_override: Option<&str>,
+        path: &std::path::Path,
+    ) -> Result<usize, DomainError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            DomainError::Io(format!("failed to read transcript '{}': {}", path.display(), e))
+        })?;
+
+        let turns = Self::parse_transcript_turns(&content);
+        if turns.is_empty() {
+            return Ok(0);
+        }
+
+        let session_id = turns.iter().find_map(|t| t.session_id.clone());
+        let thread_id = match thread_id_override {
+            Some(tid) => self.thread_repo.resolve_short_id(tid)?,
+            None => session_id
+                .clone()
+                .ok_or_else(|| DomainError::Parse("transcript has no sessionId and no --thread provided".to_string()))?,
         };
 
-        // Parse JSONL and find the last assistant text message.
-        // Transcript format: each line is a JSON object with "type" field.
-        // Assistant messages have: {"type": "assistant", "message": {"role": "assistant", "content": [...]}}
-        let mut last_assistant_content: Option<String> = None;
-
-        for line in content.lines() {
-            if let Ok(entry) = serde_json::from_str::<serde_json::Value>(line) {
-                let entry_type = entry.get("type").and_then(|t| t.as_str()).unwrap_or("");
-                if entry_type == "assistant" {
-                    // content is inside "message" object
-                    if let Some(msg) = entry.get("message") {
-                        if let Some(text) = Self::extract_text_content(msg) {
-                            last_assistant_content = Some(text);
-                        }
-                    }
+        let now = Utc::now();
+        let short_id = &thread_id[..8.min(thread_id.len())];
+        let thread = Thread {
+            id: thread_id.clone(),
+            name: None,
+            title: format!("Session {}", short_id),
+            source_url: None,
+            status: ThreadStatus::default(),
+            phase: None,
+            summary: None,
+            tags: Vec::new(),
+            pinned: false,
+            metadata: None,
+            default_sender: None,
+            default_role: None,
+            locked: false,
+            lock_allow: Vec::new(),
+            retention_days: None,
+            retention_keep_last: None,
+            created_at: now,
+            updated_at: now,
+        };
+        self.thread_repo.upsert(&thread)?;
+
+        let existing_messages = self.repo.find_by_thread(&thread_id)?;
+        let mut fresh = Vec::new();
+        for mut turn in turns {
+            if let Some(uuid) = &turn.uuid {
+                if existing_messages.iter().any(|m| &m.id == uuid) {
+                    continue;
+                }
+                if self.repo.find_by_id(uuid)?.is_some() {
+                    turn.uuid = None;
                 }
             }
+            if let Some(ts) = turn.timestamp {
+                let imported = existing_messages
+                    .iter()
+                    .any(|m| m.role == turn.role && m.content == turn.text && m.created_at.timestamp() == ts.timestamp());
+                if imported {
+                    continue;
+                }
+            }
+            fresh.push(turn);
+        }
+
+        let messages: Vec<Message> = fresh
+            .into_iter()
+            .map(|turn| {
+                let (sender, source) = match turn.role {
+                    Role::Assistant => (Some("claude".to_string()), "agent"),
+                    _ => (None, "user"),
+                };
+                let ts = turn.timestamp.unwrap_or(now);
+                Message {
+                    id: turn.uuid.unwrap_or_else(|| Uuid::new_v4().to_string()),
+                    thread_id: thread_id.clone(),
+                    session_id: turn.session_id.or_else(|| session_id.clone()),
+                    sender,
+                    role: turn.role,
+                    content: turn.text,
+                    metadata: None,
+                    parent_id: None,
+                    source: Some(source.to_string()),
+                    created_at: ts,
+                    updated_at: ts,
+                }
+            })
+            .collect();
+
+        if let Some(existing) = self.thread_repo.find_by_id(&thread_id)? {
+            if messages.iter().any(|m| !existing.accepts_post_from(m.sender.as_deref())) {
+                return Err(DomainError::ThreadLocked(existing.id));
+            }
+        }
+
+        if messages.is_empty() {
+            return Ok(0);
         }
+        self.repo.insert_batch(&messages)
+    }
 
-        last_assistant_content
+    /// Parse transcript JSONL into user/assistant text turns, in file order.
+    /// Transcript format: each line is a JSON object with "type" field.
+    /// Messages have: {"type": "assistant", "message": {"role": "assistant", "content": [...]}}
+    /// Entries without text (e.g. tool_use / tool_result blocks) are skipped.
+    fn parse_transcript_turns(content: &str) -> Vec<TranscriptTurn> {
+        content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .filter_map(|entry| {
+                let role = match entry.get("type").and_then(|t| t.as_str()) {
+                    Some("user") => Role::User,
+                    Some("assistant") => Role::Assistant,
+                    _ => return None,
+                };
+                // content is inside "message" object
+                let text = Self::extract_text_content(entry.get("message")?)?;
+                let uuid = entry.get("uuid").and_then(|v| v.as_str()).map(|s| s.to_string());
+                let session_id = entry
+                    .get("sessionId")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string());
+                let timestamp = entry
+                    .get("timestamp")
+                    .and_then(|v| v.as_str())
+                    .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                    .map(|dt| dt.with_timezone(&Utc));
+                Some(TranscriptTurn { uuid, role, text, session_id, timestamp })
+            })
+            .collect()
     }
 
     /// Extract text from a transcript entry's "content" field.
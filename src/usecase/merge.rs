@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::domain::entity::Message;
+use crate::domain::error::DomainError;
+use crate::domain::repository::{MessageRepository, ThreadFilter, ThreadRepository};
+
+/// What `db merge` did. A conflict is a thread or message present on both
+/// boards with different contents where ours was kept.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MergeReport {
+    pub threads_added: usize,
+    pub threads_updated: usize,
+    pub messages_added: usize,
+    pub messages_updated: usize,
+    pub links_added: usize,
+    pub conflicts_kept: usize,
+    /// Threads copied without their name, which another thread here has.
+    pub names_dropped: usize,
+}
+
+impl MergeReport {
+    /// Whether anything was added or updated.
+    pub fn changed(&self) -> bool {
+        self.threads_added + self.threads_updated + self.messages_added + self.messages_updated + self.links_added > 0
+    }
+}
+
+pub struct MergeUseCase<T: ThreadRepository, M: MessageRepository> {
+    pub(crate) thread_repo: T,
+    pub(crate) message_repo: M,
+}
+
+impl<T: ThreadRepository, M: MessageRepository> MergeUseCase<T, M> {
+    pub fn new(thread_repo: T, message_repo: M) -> Self {
+        Self {
+            thread_repo,
+            message_repo,
+        }
+    }
+
+    /// Copies the threads, messages and links of another board into this
+    /// one, matching them by ID. Rows only the other board has are added.
+    /// Rows both boards have but with different contents are left alone
+    /// unless `prefer_newer` is set and the other board's copy has the later
+    /// `updated_at`. Thread locks are not checked: the messages were already
+    /// accepted on the other board, and a sync must not drop them.
+    pub fn merge_from<OT: ThreadRepository, OM: MessageRepository>(
+        &self,
+        other_threads: &OT,
+        other_messages: &OM,
+        prefer_newer: bool,
+    ) -> Result<MergeReport, DomainError> {
+        let mut report = MergeReport::default();
+        for mut theirs in other_threads.find(&ThreadFilter::default())? {
+            let name_taken = match &theirs.name {
+                Some(name) => self
+                    .thread_repo
+                    .find_id_by_name(name)?
+                    .is_some_and(|id| id != theirs.id),
+                None => false,
+            };
+            if name_taken {
+                theirs.name = None;
+            }
+            match self.thread_repo.find_by_id(&theirs.id)? {
+                None => {
+                    self.thread_repo.create(&theirs)?;
+                    self.thread_repo.replace(&theirs)?;
+                    report.threads_added += 1;
+                    report.names_dropped += name_taken as usize;
+                }
+                Some(ours) if ours == theirs => {}
+                Some(ours) if prefer_newer && theirs.updated_at > ours.updated_at => {
+                    self.thread_repo.replace(&theirs)?;
+                    report.threads_updated += 1;
+                    report.names_dropped += name_taken as usize;
+                }
+                Some(_) => report.conflicts_kept += 1,
+            }
+
+            let messages = other_messages.find_by_thread(&theirs.id)?;
+            let ours: HashMap<String, Message> = self
+                .message_repo
+                .find_by_thread(&theirs.id)?
+                .into_iter()
+                .map(|m| (m.id.clone(), m))
+                .collect();
+            let mut added = Vec::new();
+            for msg in &messages {
+                // A message split off into another thread on this board is
+                // still the same message.
+                let existing = match ours.get(&msg.id) {
+                    Some(m) => Some(m.clone()),
+                    None => self.message_repo.find_by_id(&msg.id)?,
+                };
+                match existing {
+                    None => added.push(msg.clone()),
+                    Some(m) if m == *msg => {}
+                    Some(m) if prefer_newer && msg.updated_at > m.updated_at => {
+                        self.message_repo.replace(msg)?;
+                        report.messages_updated += 1;
+                    }
+                    Some(_) => report.conflicts_kept += 1,
+                }
+            }
+            if !added.is_empty() {
+                report.messages_added += self.message_repo.insert_batch(&added)?;
+            }
+
+            for msg in &messages {
+                let ours = self.message_repo.find_links(&msg.id)?;
+                for link in other_messages.find_links(&msg.id)? {
+                    let known = ours.iter().any(|l| {
+                        l.from_id == link.from_id && l.to_id == link.to_id && l.relation == link.relation
+                    });
+                    if link.from_id == msg.id && !known {
+                        self.message_repo.insert_link(&link)?;
+                        report.links_added += 1;
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+}
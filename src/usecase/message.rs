@@ -1,16 +1,24 @@
-use crate::domain::entity::{Message, Role};
+use crate::domain::entity::{
+    LinkRelation, Message, MessageLink, Role, SearchGroup, SearchHit, SearchIndexStatus,
+};
 use crate::domain::error::DomainError;
-use crate::domain::repository::MessageRepository;
-use chrono::Utc;
+use crate::domain::repository::{
+    MessageFilter, MessageRepository, Page, SearchQuery, SearchScope, ThreadFilter, ThreadRepository,
+};
+use chrono::{DateTime, Duration, Utc};
 use uuid::Uuid;
 
-pub struct MessageUseCase<R: MessageRepository> {
+/// How many trigram-sharing messages the fuzzy fallback compares against.
+const FUZZY_CANDIDATES: usize = 500;
+
+pub struct MessageUseCase<T: ThreadRepository, R: MessageRepository> {
+    pub(crate) thread_repo: T,
     pub(crate) repo: R,
 }
 
-impl<R: MessageRepository> MessageUseCase<R> {
-    pub fn new(repo: R) -> Self {
-        Self { repo }
+impl<T: ThreadRepository, R: MessageRepository> MessageUseCase<T, R> {
+    pub fn new(thread_repo: T, repo: R) -> Self {
+        Self { thread_repo, repo }
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -24,6 +32,12 @@ impl<R: MessageRepository> MessageUseCase<R> {
         metadata: Option<serde_json::Value>,
         parent_id: Option<&str>,
     ) -> Result<Message, DomainError> {
+        if let Some(thread) = self.thread_repo.find_by_id(thread_id)? {
+            if !thread.accepts_post_from(sender) {
+                return Err(DomainError::ThreadLocked(thread.id));
+            }
+        }
+
         let now = Utc::now();
         let source = if sender.is_some() { "agent" } else { "manual" };
         let msg = Message {
@@ -43,28 +57,184 @@ impl<R: MessageRepository> MessageUseCase<R> {
         Ok(msg)
     }
 
+    /// Returns an existing message in the thread with the same sender and
+    /// content posted within `window`, if any.
+    pub fn find_duplicate(
+        &self,
+        thread_id: &str,
+        sender: Option<&str>,
+        content: &str,
+        window: Duration,
+    ) -> Result<Option<Message>, DomainError> {
+        let since = Utc::now().checked_sub_signed(window).unwrap_or(DateTime::<Utc>::MIN_UTC);
+        self.repo.find_duplicate(thread_id, sender, content, &since)
+    }
+
     pub fn read(&self, thread_id: &str) -> Result<Vec<Message>, DomainError> {
         self.repo.find_by_thread(thread_id)
     }
 
-    pub fn list_recent(&self, limit: usize) -> Result<Vec<Message>, DomainError> {
-        self.repo.list_recent(limit)
+    pub fn find(&self, filter: &MessageFilter, page: &Page) -> Result<Vec<Message>, DomainError> {
+        self.repo.find(filter, page)
+    }
+
+    /// Streams the messages `find` would return to `f`, one at a time.
+    pub fn for_each(
+        &self,
+        filter: &MessageFilter,
+        page: &Page,
+        f: &mut dyn FnMut(Message) -> Result<(), DomainError>,
+    ) -> Result<usize, DomainError> {
+        self.repo.for_each(filter, page, f)
+    }
+
+    pub fn list_recent(&self, filter: &MessageFilter, limit: usize) -> Result<Vec<Message>, DomainError> {
+        self.repo.list_recent(filter, limit)
     }
 
+    /// With `fuzzy`, a content search that finds nothing is retried allowing
+    /// a few typos; such hits carry the spelling that was found.
     pub fn search(
         &self,
-        query: &str,
-        thread_id: Option<&str>,
-    ) -> Result<Vec<Message>, DomainError> {
-        self.repo.search(query, thread_id)
+        query: &SearchQuery,
+        scope: SearchScope,
+        filter: &MessageFilter,
+        page: &Page,
+        fuzzy: bool,
+    ) -> Result<Vec<SearchHit>, DomainError> {
+        let hits = self.repo.search(query, scope, filter, page)?;
+        if !hits.is_empty() || !fuzzy || scope != SearchScope::Content {
+            return Ok(hits);
+        }
+        // Exact hits that merely end before the offset still rule out the fallback
+        if page.offset > 0 && !self.repo.search(query, scope, filter, &Page::default())?.is_empty() {
+            return Ok(hits);
+        }
+
+        let needle: Vec<char> = query.text.to_lowercase().chars().collect();
+        let max_distance = (needle.len() / 4).clamp(1, 3);
+        let excluded: Vec<String> = query.exclude.iter().map(|t| t.to_lowercase()).collect();
+        let mut matches: Vec<(usize, SearchHit)> = self
+            .repo
+            .find_fuzzy_candidates(&query.text, filter, FUZZY_CANDIDATES)?
+            .into_iter()
+            .filter(|message| {
+                let content = message.content.to_lowercase();
+                !excluded.iter().any(|t| content.contains(t.as_str()))
+            })
+            .filter_map(|message| {
+                let (distance, start, end) = closest_substring(&needle, &message.content)?;
+                let found = message.content.chars().skip(start).take(end - start).collect();
+                (distance <= max_distance).then_some((
+                    distance,
+                    SearchHit {
+                        message,
+                        score: None,
+                        fuzzy: Some(found),
+                        matches: vec![[start, end]],
+                    },
+                ))
+            })
+            .collect();
+        // Stable: among equal distances the candidates keep their trigram ranking
+        matches.sort_by_key(|(distance, _)| *distance);
+        Ok(matches
+            .into_iter()
+            .map(|(_, hit)| hit)
+            .skip(page.offset)
+            .take(page.limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    /// Threads whose title, name or summary contain `query`, plus matching
+    /// messages (at most `limit`) grouped under their thread. Thread matches
+    /// come first, then threads in order of their most relevant message.
+    pub fn search_all(&self, query: &str, limit: Option<usize>) -> Result<Vec<SearchGroup>, DomainError> {
+        let thread_filter = ThreadFilter {
+            query: Some(query.to_string()),
+            ..Default::default()
+        };
+        let mut groups: Vec<SearchGroup> = self
+            .thread_repo
+            .find(&thread_filter)?
+            .into_iter()
+            .map(|thread| SearchGroup {
+                thread,
+                thread_match: true,
+                messages: Vec::new(),
+            })
+            .collect();
+
+        let page = Page {
+            limit,
+            ..Default::default()
+        };
+        let hits = self
+            .repo
+            .search(&SearchQuery::new(query), SearchScope::Content, &MessageFilter::default(), &page)?;
+        self.add_to_groups(&mut groups, hits)?;
+        Ok(groups)
+    }
+
+    /// Groups hits under their threads, threads in order of their first hit.
+    pub fn group_by_thread(&self, hits: Vec<SearchHit>) -> Result<Vec<SearchGroup>, DomainError> {
+        let mut groups = Vec::new();
+        self.add_to_groups(&mut groups, hits)?;
+        Ok(groups)
+    }
+
+    fn add_to_groups(&self, groups: &mut Vec<SearchGroup>, hits: Vec<SearchHit>) -> Result<(), DomainError> {
+        for hit in hits {
+            match groups.iter_mut().find(|g| g.thread.id == hit.message.thread_id) {
+                Some(group) => group.messages.push(hit),
+                None => {
+                    let Some(thread) = self.thread_repo.find_by_id(&hit.message.thread_id)? else {
+                        continue;
+                    };
+                    groups.push(SearchGroup {
+                        thread,
+                        thread_match: false,
+                        messages: vec![hit],
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn count_by_role(&self, filter: &MessageFilter) -> Result<Vec<(Role, usize)>, DomainError> {
+        self.repo.count_by_role(filter)
     }
 
     pub fn find_mentions(
         &self,
         thread_id: Option<&str>,
         mention_target: &str,
+        unread_only: bool,
     ) -> Result<Vec<Message>, DomainError> {
-        self.repo.find_mentions(thread_id, mention_target)
+        self.repo.find_mentions(thread_id, mention_target, unread_only)
+    }
+
+    /// Marks mentions as handled by `sender`, returning how many were newly
+    /// dismissed.
+    pub fn dismiss_mentions(&self, short_ids: &[String], sender: &str) -> Result<usize, DomainError> {
+        let mut dismissed = 0;
+        for short_id in short_ids {
+            let full_id = self.repo.resolve_short_id(short_id)?;
+            if self.repo.dismiss_mention(&full_id, sender)? {
+                dismissed += 1;
+            }
+        }
+        Ok(dismissed)
+    }
+
+    /// Dismisses every unread mention of `sender`.
+    pub fn dismiss_all_mentions(&self, sender: &str) -> Result<usize, DomainError> {
+        let unread = self.repo.find_mentions(None, sender, true)?;
+        for msg in &unread {
+            self.repo.dismiss_mention(&msg.id, sender)?;
+        }
+        Ok(unread.len())
     }
 
     pub fn count_mentions(
@@ -75,20 +245,58 @@ impl<R: MessageRepository> MessageUseCase<R> {
         self.repo.count_mentions(thread_id, mention_target)
     }
 
-    pub fn find_by_type(
+    pub fn find_since_last_type(
         &self,
-        thread_id: Option<&str>,
+        thread_id: &str,
         msg_type: &str,
+        filter: &MessageFilter,
+        page: &Page,
     ) -> Result<Vec<Message>, DomainError> {
-        self.repo.find_by_type(thread_id, msg_type)
+        self.repo.find_since_last_type(thread_id, msg_type, filter, page)
     }
 
-    pub fn find_since_last_type(
+    pub fn get(&self, short_id: &str) -> Result<Message, DomainError> {
+        let full_id = self.repo.resolve_short_id(short_id)?;
+        self.repo
+            .find_by_id(&full_id)?
+            .ok_or(DomainError::MessageNotFound(full_id))
+    }
+
+    pub fn link(
         &self,
-        thread_id: &str,
-        msg_type: &str,
-    ) -> Result<Vec<Message>, DomainError> {
-        self.repo.find_since_last_type(thread_id, msg_type)
+        from_short_id: &str,
+        to_short_id: &str,
+        relation: LinkRelation,
+    ) -> Result<MessageLink, DomainError> {
+        let from_id = self.repo.resolve_short_id(from_short_id)?;
+        let to_id = self.repo.resolve_short_id(to_short_id)?;
+        if from_id == to_id {
+            return Err(DomainError::InvalidInput(
+                "cannot link a message to itself".to_string(),
+            ));
+        }
+        let link = MessageLink {
+            from_id,
+            to_id,
+            relation,
+            created_at: Utc::now(),
+        };
+        self.repo.insert_link(&link)?;
+        Ok(link)
+    }
+
+    /// Rebuilds the full-text search index, e.g. after a restore or an
+    /// interrupted bulk import left it out of sync with the messages.
+    pub fn rebuild_search_index(&self) -> Result<usize, DomainError> {
+        self.repo.rebuild_search_index()
+    }
+
+    pub fn search_index_status(&self) -> Result<SearchIndexStatus, DomainError> {
+        self.repo.search_index_status()
+    }
+
+    pub fn links(&self, message_id: &str) -> Result<Vec<MessageLink>, DomainError> {
+        self.repo.find_links(message_id)
     }
 
     pub fn update(&self, short_id: &str, content: &str) -> Result<String, DomainError> {
@@ -97,3 +305,34 @@ impl<R: MessageRepository> MessageUseCase<R> {
         Ok(full_id)
     }
 }
+
+/// Smallest edit distance between `needle` (lowercase) and any substring of
+/// `text`, with that substring's character range. Case-insensitive.
+fn closest_substring(needle: &[char], text: &str) -> Option<(usize, usize, usize)> {
+    let lower: Vec<char> = text
+        .chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect();
+    if needle.is_empty() || lower.is_empty() {
+        return None;
+    }
+    // Column j holds (distance, start) of the best alignment of the needle
+    // prefix ending at text position j; a free start row makes it a substring match.
+    let mut prev: Vec<(usize, usize)> = (0..=lower.len()).map(|j| (0, j)).collect();
+    for (i, nc) in needle.iter().enumerate() {
+        let mut cur = vec![(i + 1, 0); lower.len() + 1];
+        for j in 1..=lower.len() {
+            let substitute = (prev[j - 1].0 + usize::from(lower[j - 1] != *nc), prev[j - 1].1);
+            let delete = (prev[j].0 + 1, prev[j].1);
+            let insert = (cur[j - 1].0 + 1, cur[j - 1].1);
+            cur[j] = substitute.min(delete).min(insert);
+        }
+        prev = cur;
+    }
+    let (end, &(distance, start)) = prev
+        .iter()
+        .enumerate()
+        .skip(1)
+        .min_by_key(|(_, (distance, _))| *distance)?;
+    Some((distance, start, end))
+}
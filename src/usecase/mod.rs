@@ -3,3 +3,10 @@ pub mod thread;
 pub mod hook;
 pub mod cleanup;
 pub mod setup;
+pub mod draft;
+pub mod stats;
+pub mod group;
+pub mod semantic;
+pub mod merge;
+pub mod sync;
+pub mod audit;
@@ -0,0 +1,129 @@
+use crate::domain::entity::{EmbeddingStatus, SearchHit};
+use crate::domain::error::DomainError;
+use crate::domain::repository::{EmbeddingProvider, EmbeddingRepository, MessageFilter, Page};
+
+/// Longer messages are cut before embedding; most embedding models only
+/// read the first few thousand tokens anyway.
+const MAX_EMBED_CHARS: usize = 8000;
+
+pub struct SemanticUseCase<E: EmbeddingRepository> {
+    pub(crate) repo: E,
+}
+
+impl<E: EmbeddingRepository> SemanticUseCase<E> {
+    pub fn new(repo: E) -> Self {
+        Self { repo }
+    }
+
+    /// Number of messages still lacking an up-to-date vector for the provider's model.
+    pub fn pending(&self, provider: &dyn EmbeddingProvider) -> Result<usize, DomainError> {
+        self.repo.count_unembedded(provider.model())
+    }
+
+    /// Stored vectors per model, including those of other models than the
+    /// one currently configured.
+    pub fn status(&self) -> Result<Vec<EmbeddingStatus>, DomainError> {
+        self.repo.status()
+    }
+
+    /// Embeds every message without an up-to-date vector, `batch` messages
+    /// per request. With `rebuild`, existing vectors of the model are
+    /// discarded first. `progress(done, total)` is called after each batch.
+    pub fn embed(
+        &self,
+        provider: &dyn EmbeddingProvider,
+        rebuild: bool,
+        batch: usize,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<usize, DomainError> {
+        let model = provider.model();
+        if rebuild {
+            self.repo.clear(model)?;
+        }
+        let total = self.repo.count_unembedded(model)?;
+        let mut done = 0;
+        while done < total {
+            let messages = self.repo.find_unembedded(model, batch.max(1))?;
+            if messages.is_empty() {
+                break;
+            }
+            let texts: Vec<String> = messages
+                .iter()
+                .map(|m| m.content.chars().take(MAX_EMBED_CHARS).collect())
+                .collect();
+            let vectors = provider.embed(&texts)?;
+            if vectors.len() != messages.len() {
+                return Err(DomainError::Parse(format!(
+                    "embedding provider returned {} vectors for {} inputs",
+                    vectors.len(),
+                    messages.len()
+                )));
+            }
+            for (message, vector) in messages.iter().zip(&vectors) {
+                self.repo.save(&message.id, model, vector)?;
+            }
+            done += messages.len();
+            progress(done.min(total), total);
+        }
+        Ok(done)
+    }
+
+    /// Messages closest in meaning to `query`, scored by cosine similarity.
+    /// Only messages embedded with the provider's model are considered.
+    pub fn search(
+        &self,
+        provider: &dyn EmbeddingProvider,
+        query: &str,
+        filter: &MessageFilter,
+        page: &Page,
+    ) -> Result<Vec<SearchHit>, DomainError> {
+        let query_vector = provider
+            .embed(&[query.to_string()])?
+            .into_iter()
+            .next()
+            .ok_or_else(|| DomainError::Parse("embedding provider returned no vector".to_string()))?;
+
+        let mut hits: Vec<SearchHit> = self
+            .repo
+            .find_vectors(provider.model(), filter)?
+            .into_iter()
+            .filter_map(|(message, vector)| {
+                cosine_similarity(&query_vector, &vector).map(|score| SearchHit {
+                    message,
+                    score: Some(score),
+                    fuzzy: None,
+                    matches: Vec::new(),
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| b.message.created_at.cmp(&a.message.created_at))
+        });
+        Ok(hits
+            .into_iter()
+            .skip(page.offset)
+            .take(page.limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+}
+
+/// `None` when the vectors cannot be compared (different dimensions or zero length).
+fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f64> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f64, 0.0f64, 0.0f64);
+    for (x, y) in a.iter().zip(b) {
+        let (x, y) = (*x as f64, *y as f64);
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return None;
+    }
+    Some(dot / (norm_a.sqrt() * norm_b.sqrt()))
+}
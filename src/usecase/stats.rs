@@ -0,0 +1,110 @@
+use serde::Serialize;
+
+use crate::domain::error::DomainError;
+use crate::domain::repository::{MessageRepository, ThreadRepository};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageTokenStats {
+    pub id: String,
+    pub sender: Option<String>,
+    pub role: String,
+    pub bytes: usize,
+    pub tokens: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SenderTokenStats {
+    pub sender: Option<String>,
+    pub messages: usize,
+    pub bytes: usize,
+    pub tokens: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadTokenStats {
+    pub thread_id: String,
+    pub messages: usize,
+    pub bytes: usize,
+    pub tokens: usize,
+    pub by_sender: Vec<SenderTokenStats>,
+    pub by_message: Vec<MessageTokenStats>,
+}
+
+/// Rough token estimate without a real tokenizer.
+///
+/// ASCII word runs count as one token per 4 characters (rounded up), ASCII
+/// punctuation counts as one token each, and every other non-whitespace
+/// character (CJK, emoji, ...) counts as one token.
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut run: usize = 0;
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            run += 1;
+            continue;
+        }
+        tokens += run.div_ceil(4);
+        run = 0;
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+    tokens + run.div_ceil(4)
+}
+
+pub struct StatsUseCase<T: ThreadRepository, M: MessageRepository> {
+    pub(crate) thread_repo: T,
+    pub(crate) message_repo: M,
+}
+
+impl<T: ThreadRepository, M: MessageRepository> StatsUseCase<T, M> {
+    pub fn new(thread_repo: T, message_repo: M) -> Self {
+        Self {
+            thread_repo,
+            message_repo,
+        }
+    }
+
+    pub fn tokens(&self, short_id: &str) -> Result<ThreadTokenStats, DomainError> {
+        let thread_id = self.thread_repo.resolve_short_id(short_id)?;
+        let messages = self.message_repo.find_by_thread(&thread_id)?;
+
+        let by_message: Vec<MessageTokenStats> = messages
+            .iter()
+            .map(|m| MessageTokenStats {
+                id: m.id.clone(),
+                sender: m.sender.clone(),
+                role: m.role.to_string(),
+                bytes: m.content.len(),
+                tokens: estimate_tokens(&m.content),
+            })
+            .collect();
+
+        // Keep senders in order of first appearance
+        let mut by_sender: Vec<SenderTokenStats> = Vec::new();
+        for stat in &by_message {
+            match by_sender.iter_mut().find(|s| s.sender == stat.sender) {
+                Some(s) => {
+                    s.messages += 1;
+                    s.bytes += stat.bytes;
+                    s.tokens += stat.tokens;
+                }
+                None => by_sender.push(SenderTokenStats {
+                    sender: stat.sender.clone(),
+                    messages: 1,
+                    bytes: stat.bytes,
+                    tokens: stat.tokens,
+                }),
+            }
+        }
+
+        Ok(ThreadTokenStats {
+            thread_id,
+            messages: by_message.len(),
+            bytes: by_message.iter().map(|m| m.bytes).sum(),
+            tokens: by_message.iter().map(|m| m.tokens).sum(),
+            by_sender,
+            by_message,
+        })
+    }
+}
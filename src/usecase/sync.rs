@@ -0,0 +1,65 @@
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::domain::entity::{SyncDirection, SyncState};
+use crate::domain::error::DomainError;
+use crate::domain::repository::SyncStateRepository;
+use crate::usecase::merge::MergeReport;
+
+/// What `sync push` or `sync pull` did. The counts are of the receiving
+/// side: the remote for a push, this board for a pull.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncReport {
+    pub remote: String,
+    pub direction: SyncDirection,
+    /// Nothing had to be exchanged.
+    pub up_to_date: bool,
+    #[serde(flatten)]
+    pub merged: MergeReport,
+}
+
+pub struct SyncUseCase<S: SyncStateRepository> {
+    pub(crate) repo: S,
+}
+
+impl<S: SyncStateRepository> SyncUseCase<S> {
+    pub fn new(repo: S) -> Self {
+        Self { repo }
+    }
+
+    /// Whether the remote still has the version the last pull saw, so that
+    /// pulling again would bring nothing new. A remote without a version
+    /// always has to be fetched.
+    pub fn pulled_already(&self, remote: &str, version: Option<&str>) -> Result<bool, DomainError> {
+        let Some(version) = version else {
+            return Ok(false);
+        };
+        let last = self.repo.find(remote, SyncDirection::Pull)?;
+        Ok(last.is_some_and(|s| s.remote_version.as_deref() == Some(version)))
+    }
+
+    /// Records an exchange with the remote, which now has `version`.
+    pub fn record(
+        &self,
+        remote: &str,
+        direction: SyncDirection,
+        version: Option<String>,
+        report: &MergeReport,
+    ) -> Result<SyncState, DomainError> {
+        let state = SyncState {
+            remote: remote.to_string(),
+            direction,
+            remote_version: version,
+            synced_at: Utc::now(),
+            threads: report.threads_added + report.threads_updated,
+            messages: report.messages_added + report.messages_updated,
+        };
+        self.repo.save(&state)?;
+        Ok(state)
+    }
+
+    /// The last push and pull of every remote.
+    pub fn status(&self) -> Result<Vec<SyncState>, DomainError> {
+        self.repo.list()
+    }
+}
@@ -1,10 +1,291 @@
-use crate::domain::entity::{Message, Role, Thread, ThreadPhase, ThreadStatus};
+use crate::domain::entity::{
+    Message, MessageLink, Participant, Role, Subscription, Thread, ThreadPhase, ThreadStatus,
+};
 use crate::domain::error::DomainError;
-use crate::domain::repository::{MessageRepository, ThreadRepository};
-use crate::infra::http;
-use chrono::Utc;
+use crate::domain::repository::{
+    MessageFilter, MessageRepository, ThreadFilter, ThreadRepository, ThreadSort,
+};
+use crate::infra::github::{self, GitHubPost, IssueRef};
+use crate::infra::{conversation, http, json_path};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
 use uuid::Uuid;
 
+#[derive(Debug, Clone, Serialize)]
+pub struct CountEntry {
+    pub key: String,
+    pub count: usize,
+}
+
+/// Message statistics for one thread, used for triage of stale threads.
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadStats {
+    pub thread_id: String,
+    pub title: String,
+    pub message_count: usize,
+    pub by_sender: Vec<CountEntry>,
+    pub by_role: Vec<CountEntry>,
+    pub first_post: Option<DateTime<Utc>>,
+    pub last_post: Option<DateTime<Utc>>,
+    /// Mean gap between consecutive posts; `None` with fewer than two posts.
+    pub average_interval_secs: Option<i64>,
+}
+
+fn count_into(entries: &mut Vec<CountEntry>, key: String) {
+    match entries.iter_mut().find(|e| e.key == key) {
+        Some(e) => e.count += 1,
+        None => entries.push(CountEntry { key, count: 1 }),
+    }
+}
+
+/// Format of a local document imported with `thread import-file`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Html,
+    Markdown,
+    Text,
+}
+
+impl DocumentFormat {
+    /// Guesses the format from the file extension; anything unknown is text.
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("html" | "htm" | "xhtml") => DocumentFormat::Html,
+            Some("md" | "markdown") => DocumentFormat::Markdown,
+            _ => DocumentFormat::Text,
+        }
+    }
+
+    fn to_markdown(self, body: &str) -> String {
+        match self {
+            DocumentFormat::Html => http::html_to_markdown(body),
+            DocumentFormat::Markdown | DocumentFormat::Text => body.trim_end().to_string(),
+        }
+    }
+}
+
+/// Same cap as HTTP responses, so a stray log file cannot bloat the database.
+const MAX_DOCUMENT_SIZE: u64 = 10 * 1024 * 1024;
+
+fn read_document(path: &Path) -> Result<String, DomainError> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| DomainError::Io(format!("failed to open '{}': {}", path.display(), e)))?
+        .len();
+    if size > MAX_DOCUMENT_SIZE {
+        return Err(DomainError::InvalidInput(format!(
+            "file too large: {} bytes (limit: {} bytes)",
+            size, MAX_DOCUMENT_SIZE
+        )));
+    }
+    std::fs::read_to_string(path)
+        .map_err(|e| DomainError::Io(format!("failed to read '{}': {}", path.display(), e)))
+}
+
+/// Pagination for `thread fetch`: how many further pages to follow, and a
+/// selector for the next link on pages without a `Link: rel="next"` header.
+#[derive(Debug, Clone, Default)]
+pub struct FollowNext {
+    pub pages: usize,
+    pub selector: Option<String>,
+}
+
+/// Outcome of `thread fetch` and `thread import-file`.
+#[derive(Debug, Clone)]
+pub struct FetchResult {
+    pub thread: Thread,
+    pub messages: usize,
+    /// Number of pages fetched (more than one with `FollowNext`).
+    pub pages: usize,
+    /// Whether the page was split into per-post messages.
+    pub split: bool,
+}
+
+/// A thread together with activity derived from its messages.
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadDetail {
+    #[serde(flatten)]
+    pub thread: Thread,
+    pub message_count: usize,
+    pub participants: Vec<String>,
+    pub last_activity: Option<DateTime<Utc>>,
+}
+
+pub const BUNDLE_FORMAT: &str = "aiboard-thread-bundle";
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Self-contained export of one thread: the thread row, its messages in
+/// posting order, and the links between those messages.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadBundle {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub thread: Thread,
+    pub messages: Vec<Message>,
+    #[serde(default)]
+    pub links: Vec<MessageLink>,
+}
+
+pub const ARCHIVE_FORMAT: &str = "aiboard-board-archive";
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// First file of a board archive, describing what follows: one
+/// `ThreadBundle` per thread and the mention groups.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Version of the aiboard that wrote the archive.
+    pub aiboard_version: String,
+    /// Schema version of the exported board; the archive itself is plain
+    /// JSON and can be imported into any schema version.
+    pub schema_version: i64,
+    pub threads: usize,
+}
+
+impl ArchiveManifest {
+    pub fn new(schema_version: i64, threads: usize) -> Self {
+        Self {
+            format: ARCHIVE_FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+            exported_at: Utc::now(),
+            aiboard_version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_version,
+            threads,
+        }
+    }
+
+    pub fn check(&self) -> Result<(), DomainError> {
+        if self.format != ARCHIVE_FORMAT {
+            return Err(DomainError::InvalidInput(format!(
+                "not a board archive (format '{}')",
+                self.format
+            )));
+        }
+        if self.version > ARCHIVE_VERSION {
+            return Err(DomainError::InvalidInput(format!(
+                "unsupported archive version {} (supported: {})",
+                self.version, ARCHIVE_VERSION
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Which messages `thread clone` copies into the new thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloneMessages {
+    None,
+    All,
+    /// Only messages whose metadata has `"pinned": true`.
+    Pinned,
+}
+
+fn is_pinned_message(msg: &Message) -> bool {
+    msg.metadata
+        .as_ref()
+        .and_then(|m| m.get("pinned"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Lines of `current` that were not already present in `previous`, in order.
+/// Lines are matched as a multiset so repeated lines are only dropped as often
+/// as they were seen; runs of blank lines left behind are collapsed.
+fn unseen_lines(previous: &str, current: &str) -> String {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for line in previous.lines().map(str::trim_end).filter(|l| !l.is_empty()) {
+        *seen.entry(line).or_insert(0) += 1;
+    }
+
+    let mut kept: Vec<&str> = Vec::new();
+    for line in current.lines().map(str::trim_end) {
+        if line.is_empty() {
+            if kept.last().is_some_and(|l| !l.is_empty()) {
+                kept.push(line);
+            }
+            continue;
+        }
+        match seen.get_mut(line) {
+            Some(n) if *n > 0 => *n -= 1,
+            _ => kept.push(line),
+        }
+    }
+    kept.join("\n").trim().to_string()
+}
+
+const SUMMARY_LINE_LEN: usize = 200;
+
+fn summary_line(msg: &Message, text: &str) -> String {
+    let sender = msg.sender.clone().unwrap_or_else(|| msg.role.to_string());
+    let mut line: String = text.trim().chars().take(SUMMARY_LINE_LEN).collect();
+    if text.trim().chars().count() > SUMMARY_LINE_LEN {
+        line.push('…');
+    }
+    format!("- [{}] {}: {}", &msg.id[..8.min(msg.id.len())], sender, line)
+}
+
+fn first_line(content: &str) -> &str {
+    content
+        .lines()
+        .find(|l| !l.trim().is_empty())
+        .unwrap_or("")
+}
+
+/// Whether the line contains an `@name` mention (the `@` must start a word).
+fn has_mention(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().collect();
+    chars.iter().enumerate().any(|(i, &c)| {
+        c == '@'
+            && (i == 0 || chars[i - 1].is_whitespace())
+            && chars
+                .get(i + 1)
+                .is_some_and(|n| n.is_alphanumeric() || *n == '_' || *n == '-')
+    })
+}
+
+/// Thread names are slugs: ASCII letters, digits, `-` and `_`.
+fn validate_thread_name(name: &str) -> Result<(), DomainError> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(DomainError::InvalidInput(format!(
+            "invalid thread name: '{}' (use ASCII letters, digits, '-' or '_')",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Tags are stored comma-joined when read back, so commas and whitespace
+/// are not allowed.
+fn validate_tag(tag: &str) -> Result<(), DomainError> {
+    if tag.is_empty() || tag.chars().any(|c| c == ',' || c.is_whitespace()) {
+        return Err(DomainError::InvalidInput(format!(
+            "invalid tag: '{}' (must be non-empty without commas or whitespace)",
+            tag
+        )));
+    }
+    Ok(())
+}
+
+fn msg_type(msg: &Message) -> Option<&str> {
+    msg.metadata
+        .as_ref()
+        .and_then(|m| m.get("msg_type"))
+        .and_then(|v| v.as_str())
+}
+
 pub struct ThreadUseCase<T: ThreadRepository, M: MessageRepository> {
     pub(crate) thread_repo: T,
     pub(crate) message_repo: M,
@@ -18,15 +299,28 @@ impl<T: ThreadRepository, M: MessageRepository> ThreadUseCase<T, M> {
         }
     }
 
-    pub fn create(&self, title: &str) -> Result<Thread, DomainError> {
+    pub fn create(&self, title: &str, name: Option<&str>) -> Result<Thread, DomainError> {
+        if let Some(n) = name {
+            validate_thread_name(n)?;
+        }
         let now = Utc::now();
         let thread = Thread {
             id: Uuid::new_v4().to_string(),
-            name: None,
+            name: name.map(|n| n.to_string()),
             title: title.to_string(),
             source_url: None,
             status: ThreadStatus::default(),
             phase: None,
+            summary: None,
+            tags: Vec::new(),
+            pinned: false,
+            metadata: None,
+            default_sender: None,
+            default_role: None,
+            locked: false,
+            lock_allow: Vec::new(),
+            retention_days: None,
+            retention_keep_last: None,
             created_at: now,
             updated_at: now,
         };
@@ -34,14 +328,153 @@ impl<T: ThreadRepository, M: MessageRepository> ThreadUseCase<T, M> {
         Ok(thread)
     }
 
-    pub fn list_by_status(&self, status: Option<ThreadStatus>) -> Result<Vec<Thread>, DomainError> {
-        self.thread_repo.list_by_status(status)
+    pub fn list(&self, filter: &ThreadFilter) -> Result<Vec<Thread>, DomainError> {
+        self.thread_repo.find(filter)
+    }
+
+    pub fn set_pinned(&self, id: &str, pinned: bool) -> Result<(), DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        self.thread_repo.set_pinned(&full_id, pinned)
+    }
+
+    pub fn tag(&self, id: &str, tags: &[String]) -> Result<usize, DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        let mut added = 0;
+        for tag in tags {
+            validate_tag(tag)?;
+            if self.thread_repo.add_tag(&full_id, tag)? {
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    pub fn untag(&self, id: &str, tags: &[String]) -> Result<usize, DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        let mut removed = 0;
+        for tag in tags {
+            if self.thread_repo.remove_tag(&full_id, tag)? {
+                removed += 1;
+            }
+        }
+        Ok(removed)
     }
 
     pub fn find_by_id(&self, id: &str) -> Result<Option<Thread>, DomainError> {
         self.thread_repo.find_by_id(id)
     }
 
+    pub fn show(&self, id: &str) -> Result<ThreadDetail, DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        let thread = self
+            .thread_repo
+            .find_by_id(&full_id)?
+            .ok_or_else(|| DomainError::ThreadNotFound(full_id.clone()))?;
+
+        let filter = MessageFilter {
+            thread_id: Some(full_id.clone()),
+            ..Default::default()
+        };
+        let message_count = self
+            .message_repo
+            .count_by_role(&filter)?
+            .iter()
+            .map(|(_, n)| n)
+            .sum();
+        let last_activity = self
+            .message_repo
+            .list_recent(&filter, 1)?
+            .first()
+            .map(|m| m.created_at);
+        let participants = self
+            .thread_repo
+            .participants(&full_id)?
+            .into_iter()
+            .map(|p| p.sender)
+            .collect();
+
+        Ok(ThreadDetail {
+            thread,
+            message_count,
+            participants,
+            last_activity,
+        })
+    }
+
+    pub fn stats(&self, id: &str) -> Result<ThreadStats, DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        let thread = self
+            .thread_repo
+            .find_by_id(&full_id)?
+            .ok_or_else(|| DomainError::ThreadNotFound(full_id.clone()))?;
+        let messages = self.message_repo.find_by_thread(&full_id)?;
+
+        let mut by_sender = Vec::new();
+        let mut by_role = Vec::new();
+        for msg in &messages {
+            count_into(&mut by_sender, msg.sender.clone().unwrap_or_else(|| "-".to_string()));
+            count_into(&mut by_role, msg.role.to_string());
+        }
+        by_sender.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+        by_role.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+
+        let first_post = messages.iter().map(|m| m.created_at).min();
+        let last_post = messages.iter().map(|m| m.created_at).max();
+        let average_interval_secs = match (first_post, last_post) {
+            (Some(first), Some(last)) if messages.len() > 1 => {
+                Some((last - first).num_seconds() / (messages.len() as i64 - 1))
+            }
+            _ => None,
+        };
+
+        Ok(ThreadStats {
+            thread_id: full_id,
+            title: thread.title,
+            message_count: messages.len(),
+            by_sender,
+            by_role,
+            first_post,
+            last_post,
+            average_interval_secs,
+        })
+    }
+
+    pub fn participants(&self, id: &str) -> Result<Vec<Participant>, DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        self.thread_repo.participants(&full_id)
+    }
+
+    pub fn watch(&self, id: &str, sender: &str, notify: bool) -> Result<String, DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        self.thread_repo.subscribe(&Subscription {
+            thread_id: full_id.clone(),
+            sender: sender.to_string(),
+            notify,
+            created_at: Utc::now(),
+        })?;
+        Ok(full_id)
+    }
+
+    pub fn unwatch(&self, id: &str, sender: &str) -> Result<bool, DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        self.thread_repo.unsubscribe(&full_id, sender)
+    }
+
+    /// Subscriptions to notify about a post to `thread_id` by `poster`;
+    /// subscribers are not notified of their own posts.
+    pub fn subscribers_to_notify(
+        &self,
+        thread_id: &str,
+        poster: Option<&str>,
+    ) -> Result<Vec<Subscription>, DomainError> {
+        Ok(self
+            .thread_repo
+            .subscriptions(thread_id)?
+            .into_iter()
+            .filter(|s| Some(s.sender.as_str()) != poster)
+            .collect())
+    }
+
     pub fn resolve_id(&self, short_id: &str) -> Result<String, DomainError> {
         self.thread_repo.resolve_short_id(short_id)
     }
@@ -51,60 +484,856 @@ impl<T: ThreadRepository, M: MessageRepository> ThreadUseCase<T, M> {
         self.thread_repo.update_status(&full_id, ThreadStatus::Closed)
     }
 
+    /// Closes open threads without messages in the last `days` days. With
+    /// `dry_run`, only returns the threads that would be closed.
+    pub fn autoclose(&self, days: i64, dry_run: bool) -> Result<Vec<Thread>, DomainError> {
+        let cutoff = chrono::Duration::try_days(days)
+            .and_then(|d| Utc::now().checked_sub_signed(d))
+            .ok_or_else(|| DomainError::InvalidInput(format!("inactive days out of range: {}", days)))?;
+        let mut threads = self.thread_repo.find_inactive(&cutoff)?;
+        if !dry_run {
+            for thread in &mut threads {
+                self.thread_repo.update_status(&thread.id, ThreadStatus::Closed)?;
+                thread.status = ThreadStatus::Closed;
+            }
+        }
+        Ok(threads)
+    }
+
     pub fn reopen(&self, id: &str) -> Result<(), DomainError> {
         let full_id = self.thread_repo.resolve_short_id(id)?;
         self.thread_repo.update_status(&full_id, ThreadStatus::Open)
     }
 
+    pub fn rename(
+        &self,
+        id: &str,
+        title: Option<&str>,
+        name: Option<&str>,
+    ) -> Result<String, DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        if let Some(t) = title {
+            if t.trim().is_empty() {
+                return Err(DomainError::InvalidInput("thread title is empty".to_string()));
+            }
+        }
+        if let Some(n) = name {
+            validate_thread_name(n)?;
+        }
+        self.thread_repo.rename(&full_id, title, name)?;
+        Ok(full_id)
+    }
+
+    /// Replaces the thread's metadata with `value`, or with `merge` shallow-merges
+    /// its top-level keys into the existing object (a `null` value removes the
+    /// key). Returns the stored metadata.
+    pub fn set_metadata(
+        &self,
+        id: &str,
+        value: serde_json::Value,
+        merge: bool,
+    ) -> Result<serde_json::Value, DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        let serde_json::Value::Object(patch) = value else {
+            return Err(DomainError::InvalidInput(
+                "thread metadata must be a JSON object".to_string(),
+            ));
+        };
+
+        let merged = if merge {
+            let thread = self
+                .thread_repo
+                .find_by_id(&full_id)?
+                .ok_or_else(|| DomainError::ThreadNotFound(full_id.clone()))?;
+            let mut current = match thread.metadata {
+                Some(serde_json::Value::Object(map)) => map,
+                _ => serde_json::Map::new(),
+            };
+            for (key, v) in patch {
+                if v.is_null() {
+                    current.remove(&key);
+                } else {
+                    current.insert(key, v);
+                }
+            }
+            current
+        } else {
+            patch
+        };
+
+        let metadata = serde_json::Value::Object(merged);
+        self.thread_repo.update_metadata(&full_id, Some(&metadata))?;
+        Ok(metadata)
+    }
+
+    /// Updates the sender and role `message post` falls back to for this
+    /// thread. Unset arguments keep their current value; `clear` resets both
+    /// first. Returns the updated thread.
+    pub fn set_defaults(
+        &self,
+        id: &str,
+        sender: Option<&str>,
+        role: Option<Role>,
+        clear: bool,
+    ) -> Result<Thread, DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        let mut thread = self
+            .thread_repo
+            .find_by_id(&full_id)?
+            .ok_or_else(|| DomainError::ThreadNotFound(full_id.clone()))?;
+        if clear {
+            thread.default_sender = None;
+            thread.default_role = None;
+        }
+        if let Some(s) = sender {
+            if s.trim().is_empty() {
+                return Err(DomainError::InvalidInput("default sender is empty".to_string()));
+            }
+            thread.default_sender = Some(s.to_string());
+        }
+        if role.is_some() {
+            thread.default_role = role;
+        }
+        self.thread_repo.update_defaults(
+            &full_id,
+            thread.default_sender.as_deref(),
+            thread.default_role.as_ref(),
+        )?;
+        Ok(thread)
+    }
+
+    /// Locks a thread so only the `allow`ed senders can post to it.
+    pub fn lock(&self, id: &str, allow: &[String]) -> Result<String, DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        if allow.iter().any(|a| a.trim().is_empty()) {
+            return Err(DomainError::InvalidInput("allowed sender is empty".to_string()));
+        }
+        self.thread_repo.set_lock(&full_id, true, allow)?;
+        Ok(full_id)
+    }
+
+    pub fn unlock(&self, id: &str) -> Result<(), DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        self.thread_repo.set_lock(&full_id, false, &[])
+    }
+
+    /// Sets the thread's retention policy (at most one of `days` and
+    /// `keep_last`); both `None` clears it.
+    pub fn set_retention(
+        &self,
+        id: &str,
+        days: Option<i64>,
+        keep_last: Option<i64>,
+    ) -> Result<(), DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        if days.is_some() && keep_last.is_some() {
+            return Err(DomainError::InvalidInput(
+                "retention takes either days or keep-last, not both".to_string(),
+            ));
+        }
+        if days.is_some_and(|d| d < 0) || keep_last.is_some_and(|k| k < 0) {
+            return Err(DomainError::InvalidInput("retention must not be negative".to_string()));
+        }
+        self.thread_repo.set_retention(&full_id, days, keep_last)
+    }
+
     pub fn set_phase(&self, id: &str, phase: Option<ThreadPhase>) -> Result<(), DomainError> {
         let full_id = self.thread_repo.resolve_short_id(id)?;
         self.thread_repo.update_phase(&full_id, phase)
     }
 
+    /// Builds an extractive summary of the thread and stores it in
+    /// `threads.summary`.
+    ///
+    /// The summary lists the first `head` and last `tail` messages, every
+    /// `decision` message, and every line that mentions someone.
+    pub fn summarize(&self, id: &str, head: usize, tail: usize) -> Result<String, DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        let thread = self
+            .thread_repo
+            .find_by_id(&full_id)?
+            .ok_or_else(|| DomainError::ThreadNotFound(full_id.clone()))?;
+        let messages = self.message_repo.find_by_thread(&full_id)?;
+
+        let mut sections = vec![format!("# {}（{} 件の message）", thread.title, messages.len())];
+
+        let total = messages.len();
+        let head_end = head.min(total);
+        let tail_start = total.saturating_sub(tail).max(head_end);
+
+        if head_end > 0 {
+            let lines: Vec<String> = messages[..head_end]
+                .iter()
+                .map(|m| summary_line(m, first_line(&m.content)))
+                .collect();
+            sections.push(format!("## 冒頭\n{}", lines.join("\n")));
+        }
+
+        let decisions: Vec<String> = messages
+            .iter()
+            .filter(|m| msg_type(m) == Some("decision"))
+            .map(|m| summary_line(m, &m.content.replace('\n', " ")))
+            .collect();
+        if !decisions.is_empty() {
+            sections.push(format!("## 決定事項\n{}", decisions.join("\n")));
+        }
+
+        let mentions: Vec<String> = messages
+            .iter()
+            .flat_map(|m| {
+                m.content
+                    .lines()
+                    .filter(|l| has_mention(l))
+                    .map(move |l| summary_line(m, l))
+            })
+            .collect();
+        if !mentions.is_empty() {
+            sections.push(format!("## メンション\n{}", mentions.join("\n")));
+        }
+
+        if tail_start < total {
+            let lines: Vec<String> = messages[tail_start..]
+                .iter()
+                .map(|m| summary_line(m, first_line(&m.content)))
+                .collect();
+            sections.push(format!("## 直近\n{}", lines.join("\n")));
+        }
+
+        let summary = sections.join("\n\n");
+        self.thread_repo.update_summary(&full_id, Some(&summary))?;
+        Ok(summary)
+    }
+
+    /// Creates a new thread and moves the given message and everything after
+    /// it into it. Like `import_bundle`, callers run this inside a savepoint.
+    /// Returns the new thread and the number of moved messages.
+    pub fn split(
+        &self,
+        id: &str,
+        from_message: &str,
+        title: Option<&str>,
+    ) -> Result<(Thread, usize), DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        let source = self
+            .thread_repo
+            .find_by_id(&full_id)?
+            .ok_or_else(|| DomainError::ThreadNotFound(full_id.clone()))?;
+        let message_id = self.message_repo.resolve_short_id(from_message)?;
+        let message = self
+            .message_repo
+            .find_by_id(&message_id)?
+            .ok_or_else(|| DomainError::MessageNotFound(message_id.clone()))?;
+        if message.thread_id != full_id {
+            return Err(DomainError::InvalidInput(format!(
+                "message {} does not belong to thread {}",
+                message.id, full_id
+            )));
+        }
+
+        let default_title = format!("{}（分割）", source.title);
+        let thread = self.create(title.unwrap_or(&default_title), None)?;
+        let moved = self.message_repo.move_from(&message, &thread.id)?;
+        Ok((thread, moved))
+    }
+
+    pub fn export_bundle(&self, id: &str) -> Result<ThreadBundle, DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        let thread = self
+            .thread_repo
+            .find_by_id(&full_id)?
+            .ok_or_else(|| DomainError::ThreadNotFound(full_id.clone()))?;
+        self.bundle(thread, false)
+    }
+
+    /// IDs of every thread, for a board archive.
+    pub fn all_thread_ids(&self) -> Result<Vec<String>, DomainError> {
+        let threads = self.thread_repo.find(&ThreadFilter {
+            sort: ThreadSort::Created,
+            ..Default::default()
+        })?;
+        Ok(threads.into_iter().map(|t| t.id).collect())
+    }
+
+    /// The bundle of thread `id` within a board archive: unlike
+    /// `export_bundle` it keeps links to messages of other threads, which
+    /// are part of the archive too.
+    pub fn export_board_bundle(&self, id: &str) -> Result<ThreadBundle, DomainError> {
+        let thread = self
+            .thread_repo
+            .find_by_id(id)?
+            .ok_or_else(|| DomainError::ThreadNotFound(id.to_string()))?;
+        self.bundle(thread, true)
+    }
+
+    fn bundle(&self, thread: Thread, other_threads: bool) -> Result<ThreadBundle, DomainError> {
+        let messages = self.message_repo.find_by_thread(&thread.id)?;
+
+        // Each link is reported for both of its ends; keep it once, from the
+        // `from` side, and only when the other end is part of the export.
+        let mut links = Vec::new();
+        for msg in &messages {
+            for link in self.message_repo.find_links(&msg.id)? {
+                if link.from_id == msg.id
+                    && (other_threads || messages.iter().any(|m| m.id == link.to_id))
+                {
+                    links.push(link);
+                }
+            }
+        }
+
+        Ok(ThreadBundle {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            exported_at: Utc::now(),
+            thread,
+            messages,
+            links,
+        })
+    }
+
+    /// Recreates a thread from a bundle. With `new_ids` the thread and its
+    /// messages get fresh IDs (and the thread name is dropped) so a bundle
+    /// can be imported next to its original; otherwise IDs are preserved and
+    /// importing over an existing thread or message is refused.
+    /// The writes are not atomic on their own: callers run this inside a
+    /// savepoint so that a failure leaves no half-imported thread behind.
+    /// Returns the created thread and the number of imported messages.
+    pub fn import_bundle(
+        &self,
+        bundle: ThreadBundle,
+        new_ids: bool,
+    ) -> Result<(Thread, usize), DomainError> {
+        if bundle.format != BUNDLE_FORMAT {
+            return Err(DomainError::InvalidInput(format!(
+                "not a thread bundle (format '{}')",
+                bundle.format
+            )));
+        }
+        if bundle.version > BUNDLE_VERSION {
+            return Err(DomainError::InvalidInput(format!(
+                "unsupported bundle version {} (supported: {})",
+                bundle.version, BUNDLE_VERSION
+            )));
+        }
+
+        let mut thread = bundle.thread;
+        let mut ids: HashMap<String, String> = HashMap::new();
+        if new_ids {
+            thread.id = Uuid::new_v4().to_string();
+            thread.name = None;
+            for msg in &bundle.messages {
+                ids.insert(msg.id.clone(), Uuid::new_v4().to_string());
+            }
+        } else if self.thread_repo.find_by_id(&thread.id)?.is_some() {
+            return Err(DomainError::InvalidInput(format!(
+                "thread {} already exists (use --new-ids to import a copy)",
+                thread.id
+            )));
+        } else {
+            for msg in &bundle.messages {
+                if self.message_repo.find_by_id(&msg.id)?.is_some() {
+                    return Err(DomainError::InvalidInput(format!(
+                        "message {} already exists (use --new-ids to import a copy)",
+                        msg.id
+                    )));
+                }
+            }
+        }
+        let remap = |id: String| ids.get(&id).cloned().unwrap_or(id);
+
+        self.thread_repo.create(&thread)?;
+        if thread.summary.is_some() {
+            self.thread_repo.update_summary(&thread.id, thread.summary.as_deref())?;
+        }
+        for tag in &thread.tags {
+            self.thread_repo.add_tag(&thread.id, tag)?;
+        }
+        if thread.pinned {
+            self.thread_repo.set_pinned(&thread.id, true)?;
+        }
+        if thread.metadata.is_some() {
+            self.thread_repo.update_metadata(&thread.id, thread.metadata.as_ref())?;
+        }
+        if thread.locked {
+            self.thread_repo.set_lock(&thread.id, true, &thread.lock_allow)?;
+        }
+        if thread.retention_days.is_some() || thread.retention_keep_last.is_some() {
+            self.thread_repo.set_retention(
+                &thread.id,
+                thread.retention_days,
+                thread.retention_keep_last,
+            )?;
+        }
+        if thread.default_sender.is_some() || thread.default_role.is_some() {
+            self.thread_repo.update_defaults(
+                &thread.id,
+                thread.default_sender.as_deref(),
+                thread.default_role.as_ref(),
+            )?;
+        }
+
+        let messages: Vec<Message> = bundle
+            .messages
+            .into_iter()
+            .map(|mut msg| {
+                msg.id = remap(msg.id);
+                msg.parent_id = msg.parent_id.map(remap);
+                msg.thread_id = thread.id.clone();
+                msg
+            })
+            .collect();
+        let imported = self.message_repo.insert_batch(&messages)?;
+
+        for mut link in bundle.links {
+            link.from_id = remap(link.from_id);
+            link.to_id = remap(link.to_id);
+            self.message_repo.insert_link(&link)?;
+        }
+
+        Ok((thread, imported))
+    }
+
+    /// Imports a bundle of a board archive with its IDs kept. Returns `None`
+    /// without importing anything when the thread already exists. As with
+    /// `db merge`, a thread whose name another thread here has is imported
+    /// without it; the returned flag says whether that happened.
+    pub fn import_board_bundle(
+        &self,
+        mut bundle: ThreadBundle,
+    ) -> Result<Option<(Thread, usize, bool)>, DomainError> {
+        if self.thread_repo.find_by_id(&bundle.thread.id)?.is_some() {
+            return Ok(None);
+        }
+        let name_taken = match &bundle.thread.name {
+            Some(name) => self.thread_repo.find_id_by_name(name)?.is_some(),
+            None => false,
+        };
+        if name_taken {
+            bundle.thread.name = None;
+        }
+        let (thread, imported) = self.import_bundle(bundle, false)?;
+        Ok(Some((thread, imported, name_taken)))
+    }
+
+    /// Starts a new open thread seeded from an existing one: tags, phase and
+    /// source URL are carried over, and the selected messages are copied with
+    /// fresh IDs. Like `import_bundle`, callers run this inside a savepoint.
+    /// Returns the new thread and the number of copied messages.
+    pub fn clone_thread(
+        &self,
+        id: &str,
+        title: Option<&str>,
+        messages: CloneMessages,
+    ) -> Result<(Thread, usize), DomainError> {
+        let mut bundle = self.export_bundle(id)?;
+        bundle.thread.title = match title {
+            Some(t) => t.to_string(),
+            None => format!("{}（複製）", bundle.thread.title),
+        };
+        bundle.thread.status = ThreadStatus::Open;
+        bundle.thread.summary = None;
+        bundle.thread.pinned = false;
+        bundle.thread.locked = false;
+        bundle.thread.lock_allow.clear();
+        let now = Utc::now();
+        bundle.thread.created_at = now;
+        bundle.thread.updated_at = now;
+
+        bundle.messages.retain(|m| match messages {
+            CloneMessages::None => false,
+            CloneMessages::All => true,
+            CloneMessages::Pinned => is_pinned_message(m),
+        });
+        let kept: Vec<String> = bundle.messages.iter().map(|m| m.id.clone()).collect();
+        for msg in &mut bundle.messages {
+            if msg.parent_id.as_ref().is_some_and(|p| !kept.contains(p)) {
+                msg.parent_id = None;
+            }
+        }
+        bundle
+            .links
+            .retain(|l| kept.contains(&l.from_id) && kept.contains(&l.to_id));
+
+        self.import_bundle(bundle, true)
+    }
+
     pub fn delete(&self, id: &str) -> Result<(), DomainError> {
         let full_id = self.thread_repo.resolve_short_id(id)?;
         self.message_repo.delete_by_thread(&full_id)?;
         self.thread_repo.delete(&full_id)
     }
 
+    /// Fetches a URL into a new thread. With `split`, recognized conversation
+    /// layouts are stored as one message per post; otherwise (or when the
+    /// layout is not recognized) the whole page becomes a single message.
+    /// `follow` adds further pages, each stored as its own message(s).
+    #[allow(clippy::too_many_arguments)]
     pub fn fetch(
         &self,
         url: &str,
         title: Option<&str>,
         sender: Option<&str>,
-    ) -> Result<Thread, DomainError> {
-        let html = http::fetch_url(url)?;
-        let markdown = http::html_to_markdown(&html);
+        split: bool,
+        json_path: Option<&str>,
+        follow: &FollowNext,
+        opts: &http::FetchOptions,
+    ) -> Result<FetchResult, DomainError> {
+        if let Some(path) = json_path {
+            json_path::validate(path)?;
+        }
+        let selector = follow
+            .selector
+            .as_deref()
+            .map(conversation::Selector::parse)
+            .transpose()?;
+        let origin = url::Url::parse(url).ok().map(|u| u.origin());
+        // Credentials stay with the origin the user asked for.
+        let anonymous = http::FetchOptions::default();
 
-        let thread_title = title.unwrap_or(url);
         let now = Utc::now();
-        let thread = Thread {
-            id: Uuid::new_v4().to_string(),
-            name: None,
-            title: thread_title.to_string(),
-            source_url: Some(url.to_string()),
-            status: ThreadStatus::default(),
-            phase: None,
-            created_at: now,
-            updated_at: now,
+        let mut thread = source_thread(title.unwrap_or(url), url, now);
+        let mut messages = Vec::new();
+        let mut was_split = false;
+        let mut visited: Vec<String> = Vec::new();
+        let mut page = http::fetch_page(url, opts)?;
+        loop {
+            visited.push(page.url.clone());
+            let mut metadata = serde_json::Map::new();
+            let (body, format, split_page) = if page.is_json() {
+                metadata.insert("content_type".to_string(), serde_json::json!(page.content_type));
+                (pretty_json(&page.body, json_path)?, DocumentFormat::Text, false)
+            } else if json_path.is_some() {
+                return Err(DomainError::InvalidInput(format!(
+                    "--json-path requires a JSON response (got {})",
+                    page.content_type.as_deref().unwrap_or("no content type")
+                )));
+            } else {
+                (page.body.clone(), DocumentFormat::Html, split)
+            };
+            if follow.pages > 0 {
+                metadata.insert("page".to_string(), serde_json::json!(visited.len()));
+                metadata.insert("url".to_string(), serde_json::json!(page.url));
+            }
+            let metadata = (!metadata.is_empty()).then_some(serde_json::Value::Object(metadata));
+            let (page_messages, page_split) = document_messages(
+                &thread.id, sender, &body, format, split_page, "url-fetch", metadata, now,
+            );
+            messages.extend(page_messages);
+            was_split |= page_split;
+
+            if visited.len() > follow.pages {
+                break;
+            }
+            let next = page.next.clone().or_else(|| {
+                let href = conversation::find_href(&page.body, selector.as_ref()?)?;
+                let next = url::Url::parse(&page.url).ok()?.join(&href).ok()?;
+                Some(next.to_string())
+            });
+            let Some(next) = next.filter(|n| !visited.contains(n)) else {
+                break;
+            };
+            let same_origin = url::Url::parse(&next).is_ok_and(|u| Some(u.origin()) == origin);
+            page = http::fetch_page(&next, if same_origin { opts } else { &anonymous })?;
+        }
+
+        self.thread_repo.create(&thread)?;
+        // Remembered so that `--refresh` selects the same part of the document.
+        if let Some(path) = json_path {
+            let metadata = serde_json::json!({ "json_path": path });
+            self.thread_repo.update_metadata(&thread.id, Some(&metadata))?;
+            thread.metadata = Some(metadata);
+        }
+        let count = self.message_repo.insert_batch(&messages)?;
+        Ok(FetchResult {
+            thread,
+            messages: count,
+            pages: visited.len(),
+            split: was_split,
+        })
+    }
+
+    /// Imports a local document into a new thread, like `fetch` does for URLs.
+    /// The format defaults to a guess from the file extension.
+    pub fn import_file(
+        &self,
+        path: &Path,
+        format: Option<DocumentFormat>,
+        title: Option<&str>,
+        sender: Option<&str>,
+        split: bool,
+    ) -> Result<FetchResult, DomainError> {
+        let path = path.canonicalize().map_err(|e| {
+            DomainError::Io(format!("failed to open '{}': {}", path.display(), e))
+        })?;
+        let url = url::Url::from_file_path(&path)
+            .map_err(|_| DomainError::InvalidInput(format!("invalid file path: {}", path.display())))?
+            .to_string();
+        let format = format.unwrap_or_else(|| DocumentFormat::from_path(&path));
+        let body = read_document(&path)?;
+        let default_title = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| url.clone());
+
+        let now = Utc::now();
+        let thread = source_thread(title.unwrap_or(&default_title), &url, now);
+        self.thread_repo.create(&thread)?;
+        let (messages, was_split) =
+            document_messages(&thread.id, sender, &body, format, split, "file-import", None, now);
+        let count = self.message_repo.insert_batch(&messages)?;
+        Ok(FetchResult {
+            thread,
+            messages: count,
+            pages: 1,
+            split: was_split,
+        })
+    }
+
+    /// Fetches a GitHub issue or pull request through the REST API, storing
+    /// the description and each comment as its own message with the author as
+    /// sender. The token defaults to `GITHUB_TOKEN`/`GH_TOKEN`.
+    pub fn fetch_github(
+        &self,
+        issue: &IssueRef,
+        title: Option<&str>,
+        opts: &http::FetchOptions,
+    ) -> Result<FetchResult, DomainError> {
+        let token = opts.bearer.clone().or_else(github::token_from_env);
+        let fetched = github::fetch_issue(issue, token.as_deref())?;
+
+        let title = match title {
+            Some(t) => t.to_string(),
+            None => format!("{} {}", issue, fetched.title),
         };
+        let mut thread = source_thread(&title, &fetched.html_url, Utc::now());
         self.thread_repo.create(&thread)?;
+        let metadata = serde_json::json!({ "github": issue.to_string() });
+        self.thread_repo.update_metadata(&thread.id, Some(&metadata))?;
+        thread.metadata = Some(metadata);
 
+        let messages: Vec<Message> = fetched
+            .posts
+            .into_iter()
+            .map(|p| github_message(&thread.id, p))
+            .collect();
+        let count = self.message_repo.insert_batch(&messages)?;
+        Ok(FetchResult {
+            thread,
+            messages: count,
+            pages: 1,
+            split: true,
+        })
+    }
+
+    /// Re-fetches a thread's source URL and appends only the content not seen
+    /// in earlier fetches as a new message. Returns `None` when nothing changed.
+    pub fn refresh(
+        &self,
+        id: &str,
+        sender: Option<&str>,
+        opts: &http::FetchOptions,
+    ) -> Result<Vec<Message>, DomainError> {
+        let full_id = self.thread_repo.resolve_short_id(id)?;
+        let thread = self
+            .thread_repo
+            .find_by_id(&full_id)?
+            .ok_or_else(|| DomainError::ThreadNotFound(full_id.clone()))?;
+        if let Some(issue) = thread
+            .metadata
+            .as_ref()
+            .and_then(|m| m["github"].as_str())
+            .and_then(|r| r.parse::<IssueRef>().ok())
+        {
+            return self.refresh_github(&thread, &issue, opts);
+        }
+        let url = thread.source_url.clone().ok_or_else(|| {
+            DomainError::InvalidInput(format!("thread {} has no source_url to refresh", full_id))
+        })?;
+
+        let mut metadata = None;
+        let (markdown, source) = if url.starts_with("file:") {
+            let path = url::Url::parse(&url)
+                .ok()
+                .and_then(|u| u.to_file_path().ok())
+                .ok_or_else(|| DomainError::InvalidInput(format!("invalid file URL: {}", url)))?;
+            let format = DocumentFormat::from_path(&path);
+            (format.to_markdown(&read_document(&path)?), "file-import")
+        } else {
+            let page = http::fetch_page(&url, opts)?;
+            if page.is_json() {
+                let json_path = thread.metadata.as_ref().and_then(|m| m["json_path"].as_str());
+                metadata = Some(serde_json::json!({ "content_type": page.content_type }));
+                (pretty_json(&page.body, json_path)?, "url-fetch")
+            } else {
+                (http::html_to_markdown(&page.body), "url-fetch")
+            }
+        };
+
+        let previous: Vec<String> = self
+            .message_repo
+            .find_by_thread(&full_id)?
+            .into_iter()
+            .filter(|m| m.source.as_deref() == Some(source))
+            .map(|m| m.content)
+            .collect();
+        let diff = unseen_lines(&previous.join("\n"), &markdown);
+        if diff.is_empty() {
+            return Ok(Vec::new());
+        }
+        if !thread.accepts_post_from(sender) {
+            return Err(DomainError::ThreadLocked(thread.id));
+        }
+
+        let now = Utc::now();
         let msg = Message {
             id: Uuid::new_v4().to_string(),
-            thread_id: thread.id.clone(),
+            thread_id: full_id,
             session_id: None,
             sender: sender.map(|s| s.to_string()),
             role: Role::System,
-            content: markdown,
-            metadata: None,
+            content: diff,
+            metadata,
             parent_id: None,
-            source: Some("url-fetch".to_string()),
+            source: Some(source.to_string()),
             created_at: now,
             updated_at: now,
         };
         self.message_repo.insert(&msg)?;
+        Ok(vec![msg])
+    }
 
-        Ok(thread)
+    /// Adds the comments of a GitHub thread that are not stored yet.
+    fn refresh_github(
+        &self,
+        thread: &Thread,
+        issue: &IssueRef,
+        opts: &http::FetchOptions,
+    ) -> Result<Vec<Message>, DomainError> {
+        let token = opts.bearer.clone().or_else(github::token_from_env);
+        let fetched = github::fetch_issue(issue, token.as_deref())?;
+
+        let stored: std::collections::HashSet<u64> = self
+            .message_repo
+            .find_by_thread(&thread.id)?
+            .iter()
+            .filter_map(|m| m.metadata.as_ref()?["github_id"].as_u64())
+            .collect();
+        let messages: Vec<Message> = fetched
+            .posts
+            .into_iter()
+            .filter(|p| !stored.contains(&p.id))
+            .map(|p| github_message(&thread.id, p))
+            .collect();
+        if messages.iter().any(|m| !thread.accepts_post_from(m.sender.as_deref())) {
+            return Err(DomainError::ThreadLocked(thread.id.clone()));
+        }
+        self.message_repo.insert_batch(&messages)?;
+        Ok(messages)
+    }
+}
+
+/// Pretty-prints a JSON response body, narrowed to `json_path` when given.
+fn pretty_json(body: &str, json_path: Option<&str>) -> Result<String, DomainError> {
+    let value: serde_json::Value = serde_json::from_str(body)
+        .map_err(|e| DomainError::Parse(format!("invalid JSON response: {}", e)))?;
+    let selected = match json_path {
+        Some(path) => json_path::select(&value, path)?
+            .ok_or_else(|| DomainError::InvalidInput(format!("JSON path '{}' matched nothing", path)))?,
+        None => &value,
+    };
+    serde_json::to_string_pretty(selected)
+        .map_err(|e| DomainError::Parse(format!("failed to format JSON: {}", e)))
+}
+
+/// Messages for one fetched document: one per post when `split` recognizes
+/// the layout, otherwise a single message. Also reports whether it split.
+#[allow(clippy::too_many_arguments)]
+fn document_messages(
+    thread_id: &str,
+    sender: Option<&str>,
+    body: &str,
+    format: DocumentFormat,
+    split: bool,
+    source: &str,
+    metadata: Option<serde_json::Value>,
+    now: DateTime<Utc>,
+) -> (Vec<Message>, bool) {
+    let posts = if split && format == DocumentFormat::Html {
+        conversation::extract_posts(body)
+    } else {
+        None
+    };
+    let was_split = posts.is_some();
+    let fetched = |sender: Option<String>, role: Role, content: String, at: DateTime<Utc>| Message {
+        id: Uuid::new_v4().to_string(),
+        thread_id: thread_id.to_string(),
+        session_id: None,
+        sender,
+        role,
+        content,
+        metadata: metadata.clone(),
+        parent_id: None,
+        source: Some(source.to_string()),
+        created_at: at,
+        updated_at: at,
+    };
+    let messages = match posts {
+        Some(posts) => posts
+            .into_iter()
+            .map(|p| {
+                let sender = p.sender.or_else(|| sender.map(|s| s.to_string()));
+                fetched(sender, p.role, p.content, p.timestamp.unwrap_or(now))
+            })
+            .collect(),
+        None => vec![fetched(
+            sender.map(|s| s.to_string()),
+            Role::System,
+            format.to_markdown(body),
+            now,
+        )],
+    };
+    (messages, was_split)
+}
+
+/// A new, open thread for content pulled from `source_url`.
+fn source_thread(title: &str, source_url: &str, now: DateTime<Utc>) -> Thread {
+    Thread {
+        id: Uuid::new_v4().to_string(),
+        name: None,
+        title: title.to_string(),
+        source_url: Some(source_url.to_string()),
+        status: ThreadStatus::default(),
+        phase: None,
+        summary: None,
+        tags: Vec::new(),
+        pinned: false,
+        metadata: None,
+        default_sender: None,
+        default_role: None,
+        locked: false,
+        lock_allow: Vec::new(),
+        retention_days: None,
+        retention_keep_last: None,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+/// One GitHub post as a message. The GitHub ID is kept in metadata so that
+/// refreshes can tell which comments are already stored.
+fn github_message(thread_id: &str, post: GitHubPost) -> Message {
+    let at = post.created_at.unwrap_or_else(Utc::now);
+    Message {
+        id: Uuid::new_v4().to_string(),
+        thread_id: thread_id.to_string(),
+        session_id: None,
+        sender: post.author,
+        role: Role::User,
+        content: post.body,
+        metadata: Some(serde_json::json!({ "github_id": post.id })),
+        parent_id: None,
+        source: Some("url-fetch".to_string()),
+        created_at: at,
+        updated_at: at,
     }
 }
@@ -2,7 +2,7 @@ use assert_cmd::Command;
 use predicates::prelude::*;
 
 fn cmd() -> Command {
-    Command::cargo_bin("aiboard").unwrap()
+    assert_cmd::cargo::cargo_bin_cmd!("aiboard")
 }
 
 /// Test helper: create a temp dir and return its path as a String.
@@ -372,7 +372,22 @@ fn invalid_role_rejected() {
         ])
         .env("AIBOARD_DATA_DIR", db_path)
         .assert()
-        .failure();
+        .failure()
+        .code(2);
+
+    for args in [
+        vec!["message", "draft", "save", "--thread", &thread_id, "--sender", "a", "--content", "x", "--role", "asistant"],
+        vec!["thread", "set-default", &thread_id, "--role", "asistant"],
+        vec!["message", "read", "--thread", &thread_id, "--role", "asistant"],
+    ] {
+        cmd()
+            .args(&args)
+            .env("AIBOARD_DATA_DIR", db_path)
+            .assert()
+            .failure()
+            .code(2);
+    }
+    assert_eq!(show_thread_json(db_path, &thread_id)["message_count"], 0);
 }
 
 #[test]
@@ -805,6 +820,49 @@ fn message_read_with_after_filter() {
     assert_eq!(arr.len(), 0);
 }
 
+#[test]
+fn datetime_filters_accept_dates_and_relative_values() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "relative-dates");
+    post_message(&db_path, &thread_id, "fresh message");
+
+    let read = |flag: &str, value: &str| -> usize {
+        let output = cmd()
+            .args(["message", "read", "--thread", &thread_id, flag, value, "--format", "json"])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{} {}: {}", flag, value, String::from_utf8_lossy(&output.stderr));
+        let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        parsed.as_array().unwrap().len()
+    };
+
+    assert_eq!(read("--after", "2d"), 1);
+    assert_eq!(read("--after", "1w"), 1);
+    assert_eq!(read("--after", "yesterday"), 1);
+    assert_eq!(read("--after", "2000-01-01"), 1);
+    assert_eq!(read("--before", "2000-01-01"), 0);
+    assert_eq!(read("--before", "2d"), 0);
+    assert_eq!(read("--after", "2024-06-01T12:00"), 1);
+    assert_eq!(read("--after", "2024-06-01T12:00:00+09:00"), 1);
+
+    // Unparseable values are rejected instead of silently ignored
+    for args in [
+        vec!["message", "read", "--after", "last tuesday"],
+        vec!["message", "search", "fresh", "--before", "2024-13-01"],
+        vec!["message", "count", "--since", "soon"],
+        vec!["message", "read", "--after", "99999999999d"],
+        vec!["message", "read", "--after", "99999999d"],
+    ] {
+        cmd()
+            .args(&args)
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("の日時形式が不正です"));
+    }
+}
+
 #[test]
 fn message_read_with_before_filter() {
     let (_dir, db_path) = test_db();
@@ -1053,6 +1111,46 @@ fn hook_ingest_stop() {
     assert_eq!(arr.len(), 0);
 }
 
+#[test]
+fn hook_ingest_pre_compact_stores_transcript_tail() {
+    let (dir, db_path) = test_db();
+    let transcript = dir.path().join("transcript.jsonl");
+    let turns: Vec<String> = (1..=25)
+        .map(|i| {
+            let role = if i % 2 == 1 { "user" } else { "assistant" };
+            format!(r#"{{"type":"{0}","message":{{"role":"{0}","content":"turn {1}"}}}}"#, role, i)
+        })
+        .collect();
+    std::fs::write(&transcript, turns.join("\n")).unwrap();
+    let json = serde_json::json!({
+        "session_id": "sess-compact",
+        "hook_event_name": "PreCompact",
+        "transcript_path": transcript.to_str().unwrap(),
+        "trigger": "auto",
+    });
+    cmd()
+        .args(["hook", "ingest"])
+        .write_stdin(json.to_string())
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let output = cmd()
+        .args(["message", "read", "--thread", "sess-compact", "--type", "compact", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let messages: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let marker = &messages.as_array().unwrap()[0];
+    assert_eq!(marker["role"], "system");
+    assert_eq!(marker["metadata"]["trigger"], "auto");
+    let content = marker["content"].as_str().unwrap();
+    assert!(content.contains("直近 20 件"));
+    assert!(content.contains("[assistant] turn 24") && content.ends_with("[user] turn 25"));
+    assert!(!content.contains("turn 5\n"), "only the tail is kept");
+}
+
 #[test]
 fn hook_ingest_no_session_no_thread() {
     let (_dir, db_path) = test_db();
@@ -1298,13 +1396,232 @@ fn backup_file_naming_format() {
     assert_eq!(backups.len(), 1, "exactly one backup file should be created");
 
     let name = backups[0].file_name().unwrap().to_str().unwrap();
-    // Format: aiboard.db.bak.YYYYMMDDHHmmss (14 digits)
+    // Format: aiboard.db.bak.YYYYMMDDHHmmss.gz (14 digits)
     assert!(name.starts_with("aiboard.db.bak."), "backup name should start with 'aiboard.db.bak.'");
-    let timestamp_part = &name["aiboard.db.bak.".len()..];
+    assert!(name.ends_with(".gz"), "backup should be gzip compressed");
+    let timestamp_part = &name["aiboard.db.bak.".len()..name.len() - ".gz".len()];
     assert_eq!(timestamp_part.len(), 14, "timestamp should be 14 digits (YYYYMMDDHHmmss)");
     assert!(timestamp_part.chars().all(|c| c.is_ascii_digit()), "timestamp should be all digits");
 }
 
+fn fake_backup(db_path: &str, timestamp: &str) -> std::path::PathBuf {
+    let path = std::path::Path::new(db_path).join(format!("aiboard.db.bak.{}", timestamp));
+    std::fs::write(&path, b"old backup").unwrap();
+    path
+}
+
+#[test]
+fn backup_prune_keeps_newest() {
+    let (_dir, db_path) = test_db();
+    create_thread(&db_path, "prune");
+    let oldest = fake_backup(&db_path, "20200101000000");
+    let older = fake_backup(&db_path, "20210101000000");
+    let newest = fake_backup(&db_path, "20220101000000");
+
+    cmd()
+        .args(["backup", "prune", "--keep", "1", "--dry-run"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("20210101000000"))
+        .stdout(predicate::str::contains("20200101000000"))
+        .stderr(predicate::str::contains("2 件のバックアップが削除対象です"));
+    assert_eq!(find_backup_files(&db_path).len(), 3);
+
+    cmd()
+        .args(["backup", "prune", "--keep", "1", "--older-than", "365"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("2 件のバックアップを削除しました"));
+    assert!(newest.exists());
+    assert!(!older.exists() && !oldest.exists());
+}
+
+#[test]
+fn backup_prune_requires_a_policy() {
+    let (_dir, db_path) = test_db();
+    let home = tempfile::tempdir().unwrap();
+    cmd()
+        .args(["backup", "prune"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .env("HOME", home.path())
+        .env_remove("USERPROFILE")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("[backup] keep"));
+}
+
+#[test]
+fn cleanup_prunes_backups_by_config() {
+    let (_dir, db_path) = test_db();
+    let home = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(home.path().join(".aiboard")).unwrap();
+    std::fs::write(home.path().join(".aiboard").join("config.toml"), "[backup]\nkeep = 1\n").unwrap();
+    let thread_id = create_thread(&db_path, "auto-prune");
+    post_message(&db_path, &thread_id, "message");
+    let old = fake_backup(&db_path, "20200101000000");
+
+    cmd()
+        .args(["cleanup", "age", "0"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .env("HOME", home.path())
+        .env_remove("USERPROFILE")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("古いバックアップを 1 件削除しました"));
+    assert!(!old.exists());
+    assert_eq!(find_backup_files(&db_path).len(), 1);
+}
+
+#[test]
+fn backup_list_shows_counts_and_unreadable_files() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "listed");
+    post_message(&db_path, &thread_id, "kept in the backup");
+    cmd().args(["cleanup", "age", "0"]).env("AIBOARD_DATA_DIR", &db_path).assert().success();
+    fake_backup(&db_path, "20200101000000");
+
+    let output = cmd()
+        .args(["backup", "list", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let list = parsed.as_array().unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0]["threads"], 1);
+    assert_eq!(list[0]["messages"], 1);
+    assert!(list[0]["error"].is_null());
+    assert_eq!(list[1]["name"], "aiboard.db.bak.20200101000000");
+    assert!(list[1]["threads"].is_null());
+    assert!(list[1]["error"].is_string());
+    // Reading a backup leaves nothing beside it.
+    assert_eq!(find_backup_files(&db_path).len(), 2);
+}
+
+#[test]
+fn backup_verify_reports_healthy_and_damaged_backups() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "verified");
+    post_message(&db_path, &thread_id, "message");
+    cmd().args(["cleanup", "age", "0"]).env("AIBOARD_DATA_DIR", &db_path).assert().success();
+    let good = find_backup_files(&db_path).remove(0);
+    let name = good.file_name().unwrap().to_str().unwrap().to_string();
+    let damaged = std::path::Path::new(&db_path).join("aiboard.db.bak.20200101000000");
+    let bytes = std::fs::read(&good).unwrap();
+    std::fs::write(&damaged, &bytes[..bytes.len() / 2]).unwrap();
+
+    cmd()
+        .args(["backup", "verify", &name])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("正常"))
+        .stdout(predicate::str::contains("thread 1 件・message 1 件"));
+    assert_eq!(std::fs::read(&good).unwrap(), bytes);
+
+    cmd()
+        .args(["backup", "verify", "20200101000000"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .code(9)
+        .stdout(predicate::str::contains("異常"));
+
+    cmd()
+        .args(["backup", "verify", "20190101000000"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("バックアップが見つかりません"));
+}
+
+#[test]
+fn backup_restore_brings_back_a_compressed_backup() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "restored");
+    post_message(&db_path, &thread_id, "restored message");
+    cmd().args(["cleanup", "thread", &thread_id]).env("AIBOARD_DATA_DIR", &db_path).assert().success();
+    let backup = find_backup_files(&db_path).remove(0);
+    let name = backup.file_name().unwrap().to_str().unwrap().to_string();
+    // Restored straight away, usually within the second of the backup: the
+    // backup taken before restoring must get a name of its own.
+
+    cmd()
+        .args(["backup", "restore", &name])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("バックアップを作成しました"))
+        .stderr(predicate::str::contains("から復元しました"));
+
+    cmd()
+        .args(["message", "search", "restored"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("restored message"));
+    assert_eq!(find_backup_files(&db_path).len(), 2);
+    assert!(backup.exists());
+}
+
+#[test]
+fn backup_create_with_label_is_listed_and_restorable_by_label() {
+    let (_dir, db_path) = test_db();
+    create_thread(&db_path, "before import");
+
+    let output = cmd()
+        .args(["backup", "create", "--label", "before-import"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let path = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    assert!(path.ends_with(".before-import.gz"), "unexpected path {}", path);
+    assert!(std::path::Path::new(&path).exists());
+
+    let output = cmd()
+        .args(["backup", "list", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(parsed[0]["label"], "before-import");
+    assert_eq!(parsed[0]["compressed"], true);
+    assert_eq!(parsed[0]["threads"], 1);
+
+    cmd()
+        .args(["backup", "verify", "before-import"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    cmd()
+        .args(["backup", "create", "--label", "not/allowed"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn backup_restore_refuses_a_damaged_backup() {
+    let (_dir, db_path) = test_db();
+    create_thread(&db_path, "kept");
+    fake_backup(&db_path, "20200101000000");
+
+    cmd()
+        .args(["backup", "restore", "20200101000000"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+    cmd()
+        .args(["thread", "list"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("kept"));
+}
+
 // --- Mention tests ---
 
 #[test]
@@ -1367,6 +1684,49 @@ fn message_post_requires_sender() {
         .failure();
 }
 
+#[test]
+fn message_post_uses_thread_default_sender_and_role() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "worker thread");
+    cmd()
+        .args(["thread", "set-default", &thread_id, "--sender", "worker-1", "--role", "assistant"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--content", "progress"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    // An explicit --sender still wins; the role keeps falling back
+    post_message_with_sender(&db_path, &thread_id, "from someone else", "reviewer");
+
+    let output = cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    let messages = parsed.as_array().unwrap();
+    assert_eq!(messages[0]["sender"], "worker-1");
+    assert_eq!(messages[0]["role"], "assistant");
+    assert_eq!(messages[1]["sender"], "reviewer");
+    assert_eq!(messages[1]["role"], "assistant");
+
+    cmd()
+        .args(["thread", "set-default", &thread_id, "--clear"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--content", "no identity"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("set-default"));
+}
+
 #[test]
 fn mention_notification_on_read() {
     let (_dir, db_path) = test_db();
@@ -1887,6 +2247,39 @@ fn message_list_type_filter() {
     assert_eq!(arr[0]["metadata"]["msg_type"], "open");
 }
 
+#[test]
+fn message_type_filter_returns_every_match_newest_first() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "type-filter-window");
+    for i in 0..25 {
+        let content = format!("decision {}", i);
+        cmd()
+            .args(["message", "post", "--thread", &thread_id, "--content", &content, "--sender", "a", "--type", "decision"])
+            .env("AIBOARD_DATA_DIR", &db_path).assert().success();
+    }
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--content", "plain", "--sender", "a"])
+        .env("AIBOARD_DATA_DIR", &db_path).assert().success();
+
+    let read = |args: &[&str]| -> Vec<String> {
+        let output = cmd().args(args).env("AIBOARD_DATA_DIR", &db_path).output().unwrap();
+        assert!(output.status.success());
+        let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        parsed.as_array().unwrap().iter().map(|m| m["content"].as_str().unwrap().to_string()).collect()
+    };
+    // Neither the 20-row default of `read` nor `list --limit` cuts the matches short.
+    for args in [
+        &["message", "read", "--type", "decision", "--format", "json"][..],
+        &["message", "read", "--thread", thread_id.as_str(), "--type", "decision", "--format", "json"][..],
+        &["message", "list", "--type", "decision", "--limit", "5", "--format", "json"][..],
+    ] {
+        let contents = read(args);
+        assert_eq!(contents.len(), 25, "{:?}", args);
+        assert_eq!(contents[0], "decision 24", "{:?}", args);
+        assert_eq!(contents[24], "decision 0", "{:?}", args);
+    }
+}
+
 #[test]
 fn message_search_type_filter() {
     let (_dir, db_path) = test_db();
@@ -1980,6 +2373,5031 @@ fn message_read_since_checkpoint_no_checkpoint() {
     assert_eq!(arr.len(), 2, "should return all messages when no checkpoint exists");
 }
 
+#[test]
+fn message_read_since_checkpoint_supports_paging() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "checkpoint-paging");
+    for i in 1..=4 {
+        post_message(&db_path, &thread_id, &format!("msg {}", i));
+    }
+
+    let read = |extra: &[&str]| -> serde_json::Value {
+        let output = cmd()
+            .args(["message", "read", "--thread", &thread_id, "--since-checkpoint", "--format", "json"])
+            .args(extra)
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+    let contents = |v: &serde_json::Value| -> Vec<String> {
+        v.as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["content"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    assert_eq!(contents(&read(&["--limit", "2"])), vec!["msg 1", "msg 2"]);
+    assert_eq!(contents(&read(&["--limit", "2", "--desc"])), vec!["msg 4", "msg 3"]);
+
+    let page = read(&["--limit", "2", "--offset", "1"]);
+    assert_eq!(contents(&page["messages"]), vec!["msg 2", "msg 3"]);
+    let cursor = page["next_cursor"].as_str().unwrap().to_string();
+    let page = read(&["--limit", "2", "--cursor", &cursor]);
+    assert_eq!(contents(&page["messages"]), vec!["msg 4"]);
+}
+
+// --- Dedupe tests ---
+
+#[test]
+fn message_post_dedupe_skips_duplicate() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "dedupe-test");
+
+    let post = |content: &str, sender: &str| {
+        let output = cmd()
+            .args(["message", "post", "--thread", &thread_id, "--content", content, "--sender", sender, "--dedupe"])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    };
+
+    let first = post("same content", "a");
+    let second = post("same content", "a");
+    assert_eq!(first, second, "duplicate post should return the existing ID");
+
+    // Different sender is not a duplicate
+    let third = post("same content", "b");
+    assert_ne!(first, third);
+
+    let output = cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(parsed.as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn message_post_without_dedupe_allows_duplicate() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "no-dedupe-test");
+
+    let first = post_message(&db_path, &thread_id, "repeat");
+    let second = post_message(&db_path, &thread_id, "repeat");
+    assert_ne!(first, second);
+}
+
+#[test]
+fn message_post_dedupe_invalid_window() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "dedupe-window-test");
+
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--content", "x", "--sender", "a", "--dedupe", "--window", "10x"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn message_post_dedupe_huge_window() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "dedupe-huge-window");
+
+    // Out of range for a duration: an error rather than a panic.
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--content", "x", "--sender", "a", "--dedupe", "--window", "99999999999d"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .code(1);
+
+    // Reaches further back than any date: every earlier message counts.
+    for _ in 0..2 {
+        cmd()
+            .args(["message", "post", "--thread", &thread_id, "--content", "x", "--sender", "a", "--dedupe", "--window", "99999999d"])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .assert()
+            .success();
+    }
+    let output = cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(parsed.as_array().unwrap().len(), 1);
+}
+
+// --- Message get tests ---
+
+#[test]
+fn message_get_by_short_id() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "get-test");
+    let parent_id = post_message(&db_path, &thread_id, "parent message");
+
+    let output = cmd()
+        .args([
+            "message", "post",
+            "--thread", &thread_id,
+            "--content", "child message",
+            "--sender", "test-agent",
+            "--parent", &parent_id,
+            "--metadata", r#"{"priority":"high"}"#,
+        ])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let msg_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+    cmd()
+        .args(["message", "get", &msg_id[..8]])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(&msg_id))
+        .stdout(predicate::str::contains(&parent_id))
+        .stdout(predicate::str::contains("priority"))
+        .stdout(predicate::str::contains("child message"));
+
+    let output = cmd()
+        .args(["message", "get", &msg_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(parsed["id"], msg_id.as_str());
+    assert_eq!(parsed["parent_id"], parent_id.as_str());
+    assert_eq!(parsed["metadata"]["priority"], "high");
+}
+
+#[test]
+fn message_get_nonexistent() {
+    let (_dir, db_path) = test_db();
+
+    cmd()
+        .args(["message", "get", "nonexistent"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Message count tests ---
+
+#[test]
+fn message_count_total_and_filters() {
+    let (_dir, db_path) = test_db();
+    let thread_a = create_thread(&db_path, "count-a");
+    let thread_b = create_thread(&db_path, "count-b");
+
+    post_message_with_sender(&db_path, &thread_a, "one", "alice");
+    post_message_with_sender(&db_path, &thread_a, "two", "bob");
+    post_message_with_sender(&db_path, &thread_b, "three", "alice");
+
+    cmd()
+        .args(["message", "count"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout("3\n");
+
+    cmd()
+        .args(["message", "count", "--thread", &thread_a])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout("2\n");
+
+    cmd()
+        .args(["message", "count", "--sender", "alice"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout("2\n");
+
+    cmd()
+        .args(["message", "count", "--since", "2999-01-01T00:00:00"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout("0\n");
+}
+
+#[test]
+fn message_count_json_by_role() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "count-json");
+
+    post_message(&db_path, &thread_id, "user msg");
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--content", "reply", "--sender", "a", "--role", "assistant"])
+        .env("AIBOARD_DATA_DIR", &db_path).assert().success();
+
+    let output = cmd()
+        .args(["message", "count", "--thread", &thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(parsed["total"], 2);
+    assert_eq!(parsed["by_role"]["user"], 1);
+    assert_eq!(parsed["by_role"]["assistant"], 1);
+}
+
+#[test]
+fn message_count_invalid_since() {
+    let (_dir, db_path) = test_db();
+
+    cmd()
+        .args(["message", "count", "--since", "yesterday-ish"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Role filter tests ---
+
+#[test]
+fn message_role_filter_on_read_list_search() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "role-filter-test");
+
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--content", "prompt about auth", "--sender", "a", "--role", "user"])
+        .env("AIBOARD_DATA_DIR", &db_path).assert().success();
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--content", "answer about auth", "--sender", "a", "--role", "assistant"])
+        .env("AIBOARD_DATA_DIR", &db_path).assert().success();
+
+    for args in [
+        vec!["message", "read", "--thread", &thread_id, "--role", "assistant", "--format", "json"],
+        vec!["message", "list", "--role", "assistant", "--format", "json"],
+        vec!["message", "search", "auth", "--role", "assistant", "--format", "json"],
+    ] {
+        let output = cmd()
+            .args(&args)
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?} failed", args);
+        let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+        let arr = parsed.as_array().unwrap();
+        assert_eq!(arr.len(), 1, "{:?} should return one message", args);
+        assert_eq!(arr[0]["role"], "assistant");
+    }
+}
+
+#[test]
+fn message_role_filter_invalid() {
+    let (_dir, db_path) = test_db();
+
+    cmd()
+        .args(["message", "list", "--role", "robot"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Sender (--from) filter tests ---
+
+#[test]
+fn message_from_filter_on_read_list_search() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "from-filter-test");
+
+    post_message_with_sender(&db_path, &thread_id, "alice on deploy", "alice");
+    post_message_with_sender(&db_path, &thread_id, "bob on deploy", "bob");
+
+    for args in [
+        vec!["message", "read", "--thread", &thread_id, "--from", "bob", "--format", "json"],
+        vec!["message", "list", "--from", "bob", "--format", "json"],
+        vec!["message", "search", "deploy", "--from", "bob", "--format", "json"],
+    ] {
+        let output = cmd()
+            .args(&args)
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?} failed", args);
+        let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+        let arr = parsed.as_array().unwrap();
+        assert_eq!(arr.len(), 1, "{:?} should return one message", args);
+        assert_eq!(arr[0]["sender"], "bob");
+    }
+}
+
+// --- Source filter tests ---
+
+#[test]
+fn message_read_source_filter() {
+    let (_dir, db_path) = test_db();
+
+    // hook-ingested message (source=user) and an agent post (source=agent) in the same thread
+    let hook_input = r#"{"session_id":"source-filter-session","hook_event_name":"UserPromptSubmit","prompt":"hello from user"}"#;
+    cmd()
+        .args(["hook", "ingest"])
+        .write_stdin(hook_input)
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    post_message(&db_path, "source-filter-session", "hello from agent");
+
+    let read = |extra: &[&str]| {
+        let mut args = vec!["message", "read", "--thread", "source-filter-session", "--format", "json"];
+        args.extend_from_slice(extra);
+        let output = cmd()
+            .args(&args)
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+        parsed.as_array().unwrap().clone()
+    };
+
+    let arr = read(&["--source", "user"]);
+    assert_eq!(arr.len(), 1);
+    assert_eq!(arr[0]["source"], "user");
+
+    let arr = read(&["--source", "user,agent"]);
+    assert_eq!(arr.len(), 2);
+
+    let arr = read(&["--exclude-source", "user"]);
+    assert_eq!(arr.len(), 1);
+    assert_eq!(arr[0]["source"], "agent");
+}
+
+// --- Metadata filter tests ---
+
+#[test]
+fn message_read_meta_filters() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "meta-filter-test");
+
+    for (content, meta) in [
+        ("approved one", r#"{"status":"approved","review":{"score":5,"final":true}}"#),
+        ("pending one", r#"{"status":"pending","review":{"score":3,"final":false}}"#),
+    ] {
+        cmd()
+            .args(["message", "post", "--thread", &thread_id, "--content", content, "--sender", "a", "--metadata", meta])
+            .env("AIBOARD_DATA_DIR", &db_path).assert().success();
+    }
+
+    let read = |extra: &[&str]| {
+        let mut args = vec!["message", "read", "--thread", &thread_id, "--format", "json"];
+        args.extend_from_slice(extra);
+        let output = cmd()
+            .args(&args)
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?} failed", args);
+        let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+        parsed.as_array().unwrap().clone()
+    };
+
+    let arr = read(&["--meta", "status=approved"]);
+    assert_eq!(arr.len(), 1);
+    assert_eq!(arr[0]["content"], "approved one");
+
+    let arr = read(&["--meta-path", "$.review.score=3"]);
+    assert_eq!(arr.len(), 1);
+    assert_eq!(arr[0]["content"], "pending one");
+
+    let arr = read(&["--meta-path", "$.review.final=true", "--meta", "status=approved"]);
+    assert_eq!(arr.len(), 1);
+
+    let arr = read(&["--meta", "status=rejected"]);
+    assert!(arr.is_empty());
+}
+
+#[test]
+fn message_read_meta_filter_invalid() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "meta-invalid-test");
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--meta", "no-equals"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--meta-path", "status=approved"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Pagination tests ---
+
+fn read_page(db_path: &str, thread_id: &str, extra: &[&str]) -> serde_json::Value {
+    let mut args = vec!["message", "read", "--thread", thread_id, "--format", "json"];
+    args.extend_from_slice(extra);
+    let output = cmd()
+        .args(&args)
+        .env("AIBOARD_DATA_DIR", db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?} failed", args);
+    serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap()
+}
+
+#[test]
+fn message_read_offset() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "offset-test");
+    for i in 0..5 {
+        post_message(&db_path, &thread_id, &format!("page msg {}", i));
+    }
+
+    let page = read_page(&db_path, &thread_id, &["--limit", "2", "--offset", "4"]);
+    let arr = page["messages"].as_array().unwrap();
+    assert_eq!(arr.len(), 1);
+    assert!(page["next_cursor"].is_null());
+
+    let all = read_page(&db_path, &thread_id, &[]);
+    assert_eq!(all.as_array().unwrap().len(), 5, "plain reads still return an array");
+}
+
+#[test]
+fn message_read_cursor_walks_all_pages() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "cursor-test");
+    for i in 0..5 {
+        post_message(&db_path, &thread_id, &format!("cursor msg {}", i));
+    }
+
+    let mut seen = Vec::new();
+    let mut page = read_page(&db_path, &thread_id, &["--limit", "2", "--offset", "0"]);
+    loop {
+        for m in page["messages"].as_array().unwrap() {
+            seen.push(m["id"].as_str().unwrap().to_string());
+        }
+        match page["next_cursor"].as_str() {
+            Some(c) => {
+                let c = c.to_string();
+                page = read_page(&db_path, &thread_id, &["--limit", "2", "--cursor", &c]);
+            }
+            None => break,
+        }
+    }
+
+    assert_eq!(seen.len(), 5);
+    seen.sort();
+    seen.dedup();
+    assert_eq!(seen.len(), 5, "pages must not overlap");
+}
+
+#[test]
+fn message_read_desc_order() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "desc-test");
+
+    post_message(&db_path, &thread_id, "older");
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    post_message(&db_path, &thread_id, "newer");
+
+    let arr = read_page(&db_path, &thread_id, &["--desc"]);
+    let arr = arr.as_array().unwrap();
+    assert_eq!(arr[0]["content"], "newer");
+    assert_eq!(arr[1]["content"], "older");
+
+    let page = read_page(&db_path, &thread_id, &["--desc", "--limit", "1", "--offset", "0"]);
+    assert_eq!(page["order"], "desc");
+    assert_eq!(page["messages"][0]["content"], "newer");
+
+    let page = read_page(&db_path, &thread_id, &["--limit", "1", "--offset", "0"]);
+    assert_eq!(page["order"], "asc");
+    assert_eq!(page["messages"][0]["content"], "older");
+}
+
+#[test]
+fn message_read_since_message_id() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "since-id-test");
+
+    post_message(&db_path, &thread_id, "before mine");
+    let mine = post_message(&db_path, &thread_id, "my post");
+    post_message(&db_path, &thread_id, "reply one");
+    post_message(&db_path, &thread_id, "reply two");
+
+    let arr = read_page(&db_path, &thread_id, &["--since", &mine[..8]]);
+    let arr = arr.as_array().unwrap();
+    assert_eq!(arr.len(), 2);
+    let contents: Vec<_> = arr.iter().map(|m| m["content"].as_str().unwrap()).collect();
+    assert!(contents.contains(&"reply one"));
+    assert!(contents.contains(&"reply two"));
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--since", "nonexistent"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn message_read_invalid_cursor() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "bad-cursor-test");
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--cursor", "zz"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .code(2);
+}
+
+#[test]
+fn read_keeps_insertion_order_within_same_second() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "tie-order");
+    let expected: Vec<String> = (0..8).map(|i| format!("msg {}", i)).collect();
+    for c in &expected {
+        post_message(&db_path, &thread_id, c);
+    }
+
+    let page = read_page(&db_path, &thread_id, &["--limit", "3", "--offset", "0"]);
+    let mut contents: Vec<String> = page["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["content"].as_str().unwrap().to_string())
+        .collect();
+    let cursor = page["next_cursor"].as_str().unwrap().to_string();
+    let rest = read_page(&db_path, &thread_id, &["--cursor", &cursor]);
+    contents.extend(
+        rest["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["content"].as_str().unwrap().to_string()),
+    );
+    assert_eq!(contents, expected);
+}
+
+#[test]
+fn read_cursor_survives_deleted_message() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "cursor-deleted");
+    for i in 0..6 {
+        post_message(&db_path, &thread_id, &format!("msg {}", i));
+    }
+    let conn = rusqlite::Connection::open(std::path::Path::new(&db_path).join("aiboard.db")).unwrap();
+    conn.execute("UPDATE messages SET created_at = (SELECT MIN(created_at) FROM messages)", [])
+        .unwrap();
+
+    let page = read_page(&db_path, &thread_id, &["--limit", "3", "--offset", "0"]);
+    let cursor = page["next_cursor"].as_str().unwrap().to_string();
+    let last_id = page["messages"][2]["id"].as_str().unwrap().to_string();
+    conn.execute("DELETE FROM messages WHERE id = ?1", [&last_id]).unwrap();
+
+    let rest = read_page(&db_path, &thread_id, &["--cursor", &cursor]);
+    let contents: Vec<&str> = rest["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["content"].as_str().unwrap())
+        .collect();
+    assert!(contents.ends_with(&["msg 3", "msg 4", "msg 5"]), "{:?}", contents);
+}
+
+// --- Export tests ---
+
+#[test]
+fn message_export_markdown() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "Export Title");
+    post_message_with_sender(&db_path, &thread_id, "first export line", "alice");
+    post_message_with_sender(&db_path, &thread_id, "second export line", "bob");
+
+    cmd()
+        .args(["message", "export", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# Export Title"))
+        .stdout(predicate::str::contains("### alice (user)"))
+        .stdout(predicate::str::contains("second export line"));
+}
+
+#[test]
+fn message_export_json_and_jsonl() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "export-json");
+    post_message(&db_path, &thread_id, "one");
+    post_message(&db_path, &thread_id, "two");
+
+    let output = cmd()
+        .args(["message", "export", "--thread", &thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(parsed["thread"]["id"], thread_id.as_str());
+    assert_eq!(parsed["messages"].as_array().unwrap().len(), 2);
+
+    let output = cmd()
+        .args(["message", "export", "--thread", &thread_id, "--format", "jsonl"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    for line in lines {
+        let v: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(v["thread_id"], thread_id.as_str());
+    }
+}
+
+#[test]
+fn message_export_to_file() {
+    let (dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "export-file");
+    post_message(&db_path, &thread_id, "saved to file");
+
+    let out_path = dir.path().join("transcript.md");
+    cmd()
+        .args(["message", "export", "--thread", &thread_id, "--output", out_path.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let written = std::fs::read_to_string(&out_path).unwrap();
+    assert!(written.contains("saved to file"));
+}
+
+#[test]
+fn message_export_unknown_format() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "export-bad");
+
+    cmd()
+        .args(["message", "export", "--thread", &thread_id, "--format", "pdf"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Transcript import tests ---
+
+#[test]
+fn message_import_transcript() {
+    let (dir, db_path) = test_db();
+    let transcript = dir.path().join("transcript.jsonl");
+    std::fs::write(
+        &transcript,
+        [
+            r#"{"type":"user","sessionId":"import-session-1","timestamp":"2025-01-01T10:00:00.000Z","message":{"role":"user","content":"please add tests"}}"#,
+            r#"{"type":"assistant","sessionId":"import-session-1","timestamp":"2025-01-01T10:00:05.000Z","message":{"role":"assistant","content":[{"type":"text","text":"Adding tests now"},{"type":"tool_use","name":"Edit"}]}}"#,
+            r#"{"type":"user","sessionId":"import-session-1","message":{"role":"user","content":[{"type":"tool_result","content":"ok"}]}}"#,
+            r#"{"type":"summary","summary":"ignored"}"#,
+            r#"{"type":"assistant","sessionId":"import-session-1","timestamp":"2025-01-01T10:01:00.000Z","message":{"role":"assistant","content":"Done"}}"#,
+        ]
+        .join("\n"),
+    )
+    .unwrap();
+
+    cmd()
+        .args(["message", "import", "--transcript", transcript.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("3 件"));
+
+    let output = cmd()
+        .args(["message", "read", "--thread", "import-session-1", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    let arr = parsed.as_array().unwrap();
+    assert_eq!(arr.len(), 3);
+    assert_eq!(arr[0]["role"], "user");
+    assert_eq!(arr[0]["content"], "please add tests");
+    assert_eq!(arr[1]["role"], "assistant");
+    assert_eq!(arr[1]["content"], "Adding tests now");
+    assert_eq!(arr[1]["sender"], "claude");
+    assert_eq!(arr[2]["content"], "Done");
+}
+
+#[test]
+fn message_import_transcript_into_existing_thread() {
+    let (dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "import-target");
+    let transcript = dir.path().join("t.jsonl");
+    std::fs::write(&transcript, r#"{"type":"user","message":{"role":"user","content":"hi"}}"#).unwrap();
+
+    cmd()
+        .args(["message", "import", "--transcript", transcript.to_str().unwrap(), "--thread", &thread_id[..8]])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hi"));
+}
+
+#[test]
+fn message_import_transcript_twice_skips_imported_turns() {
+    let (dir, db_path) = test_db();
+    let transcript = dir.path().join("t.jsonl");
+    let first = [
+        r#"{"type":"user","uuid":"6f1c2a9e-0000-4000-8000-000000000001","sessionId":"reimport-session","timestamp":"2025-01-01T10:00:00.000Z","message":{"role":"user","content":"first"}}"#,
+        r#"{"type":"assistant","sessionId":"reimport-session","timestamp":"2025-01-01T10:00:05.250Z","message":{"role":"assistant","content":"no uuid"}}"#,
+    ];
+    std::fs::write(&transcript, first.join("\n")).unwrap();
+    let import = || {
+        cmd()
+            .args(["message", "import", "--transcript", transcript.to_str().unwrap()])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .assert()
+            .success()
+    };
+    import().stderr(predicate::str::contains("2 件"));
+    import().stderr(predicate::str::contains("0 件"));
+
+    // The transcript grew: only the new turn is taken.
+    let grown = [
+        first[0],
+        first[1],
+        r#"{"type":"user","uuid":"6f1c2a9e-0000-4000-8000-000000000002","sessionId":"reimport-session","timestamp":"2025-01-01T10:01:00.000Z","message":{"role":"user","content":"second"}}"#,
+    ];
+    std::fs::write(&transcript, grown.join("\n")).unwrap();
+    import().stderr(predicate::str::contains("1 件"));
+
+    let output = cmd()
+        .args(["message", "read", "--thread", "reimport-session", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    let contents: Vec<&str> = parsed.as_array().unwrap().iter().map(|m| m["content"].as_str().unwrap()).collect();
+    assert_eq!(contents, ["first", "no uuid", "second"]);
+}
+
+#[test]
+fn message_import_transcript_into_two_threads() {
+    let (dir, db_path) = test_db();
+    let transcript = dir.path().join("t.jsonl");
+    std::fs::write(
+        &transcript,
+        r#"{"type":"user","uuid":"6f1c2a9e-0000-4000-8000-000000000003","sessionId":"two-threads","timestamp":"2025-01-01T10:00:00.000Z","message":{"role":"user","content":"shared turn"}}"#,
+    )
+    .unwrap();
+    let other = create_thread(&db_path, "second home");
+    // First into the transcript's own thread, then into another one.
+    for target in [&[][..], &["--thread", other.as_str()][..]] {
+        for expected in ["1 件", "0 件"] {
+            cmd()
+                .args(["message", "import", "--transcript", transcript.to_str().unwrap()])
+                .args(target)
+                .env("AIBOARD_DATA_DIR", &db_path)
+                .assert()
+                .success()
+                .stderr(predicate::str::contains(expected));
+        }
+    }
+    cmd()
+        .args(["message", "read", "--thread", &other])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("shared turn"));
+}
+
+#[test]
+fn message_import_missing_file() {
+    let (_dir, db_path) = test_db();
+
+    cmd()
+        .args(["message", "import", "--transcript", "/nonexistent/transcript.jsonl"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Message link tests ---
+
+#[test]
+fn message_link_shown_in_get() {
+    let (_dir, db_path) = test_db();
+    let thread_a = create_thread(&db_path, "link-a");
+    let thread_b = create_thread(&db_path, "link-b");
+    let task = post_message(&db_path, &thread_a, "implement auth");
+    let question = post_message(&db_path, &thread_b, "which token format?");
+
+    cmd()
+        .args(["message", "link", &question[..8], &task[..8], "--relation", "blocks"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let output = cmd()
+        .args(["message", "get", &question, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    let links = parsed["links"].as_array().unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0]["from_id"], question);
+    assert_eq!(links[0]["to_id"], task);
+    assert_eq!(links[0]["relation"], "blocks");
+
+    // Incoming side also lists the link
+    cmd()
+        .args(["message", "get", &task])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("blocks ← {}", question)));
+}
+
+#[test]
+fn message_link_defaults_and_validation() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "link-validate");
+    let a = post_message(&db_path, &thread_id, "first");
+    let b = post_message(&db_path, &thread_id, "second");
+
+    // Default relation, duplicate links are ignored
+    for _ in 0..2 {
+        cmd()
+            .args(["message", "link", &a, &b])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .assert()
+            .success();
+    }
+    let output = cmd()
+        .args(["message", "get", &a, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(parsed["links"].as_array().unwrap().len(), 1);
+    assert_eq!(parsed["links"][0]["relation"], "relates");
+
+    cmd()
+        .args(["message", "link", &a, &a])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+
+    cmd()
+        .args(["message", "link", &a, &b, "--relation", "causes"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Mention group tests ---
+
+#[test]
+fn mention_all_reaches_everyone_but_author() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "mention-all");
+    post_message_with_sender(&db_path, &thread_id, "@all standup in 5", "lead");
+    post_message_with_sender(&db_path, &thread_id, "@allison not a broadcast", "lead");
+
+    let output = cmd()
+        .args(["message", "mentions", "--sender", "alice", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    let arr = parsed.as_array().unwrap();
+    assert_eq!(arr.len(), 1);
+    assert_eq!(arr[0]["content"], "@all standup in 5");
+
+    let output = cmd()
+        .args(["message", "mentions", "--sender", "lead", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert!(parsed.as_array().unwrap().is_empty());
+}
+
+#[test]
+fn mention_team_reaches_group_members() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "mention-team");
+
+    cmd()
+        .args(["group", "add", "backend", "alice", "bob"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    post_message_with_sender(&db_path, &thread_id, "@team:backend please review the API", "lead");
+
+    for (member, expected) in [("alice", 1), ("bob", 1), ("carol", 0)] {
+        let output = cmd()
+            .args(["message", "mentions", "--sender", member, "--format", "json"])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+        assert_eq!(parsed.as_array().unwrap().len(), expected, "member {}", member);
+    }
+
+    cmd()
+        .args(["group", "remove", "backend", "bob"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let output = cmd()
+        .args(["message", "mentions", "--sender", "bob", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert!(parsed.as_array().unwrap().is_empty());
+
+    cmd()
+        .args(["group", "list"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("backend\talice"));
+}
+
+#[test]
+fn mention_team_does_not_reach_group_with_name_prefix() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "mention-prefix");
+
+    for (group, member) in [("back", "alice"), ("back-end", "bob")] {
+        cmd()
+            .args(["group", "add", group, member])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .assert()
+            .success();
+    }
+
+    post_message_with_sender(&db_path, &thread_id, "@team:back-end deploy is ready", "lead");
+
+    for (member, expected) in [("alice", 0), ("bob", 1)] {
+        let output = cmd()
+            .args(["message", "mentions", "--sender", member, "--format", "json"])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+        assert_eq!(parsed.as_array().unwrap().len(), expected, "member {}", member);
+    }
+}
+
+#[test]
+fn group_add_rejects_invalid_name() {
+    let (_dir, db_path) = test_db();
+
+    cmd()
+        .args(["group", "add", "back end", "alice"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Mention dismissal tests ---
+
+fn mentions_json(db_path: &str, sender: &str, extra: &[&str]) -> Vec<serde_json::Value> {
+    let mut args = vec!["message", "mentions", "--sender", sender, "--format", "json"];
+    args.extend_from_slice(extra);
+    let output = cmd()
+        .args(&args)
+        .env("AIBOARD_DATA_DIR", db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    parsed.as_array().unwrap().clone()
+}
+
+#[test]
+fn mention_dismiss_hides_from_unread_and_notification() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "dismiss-test");
+    let first = post_message_with_sender(&db_path, &thread_id, "@bob take a look", "alice");
+    post_message_with_sender(&db_path, &thread_id, "@bob one more", "alice");
+
+    cmd()
+        .args(["message", "mentions", "dismiss", &first[..8], "--sender", "bob"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    assert_eq!(mentions_json(&db_path, "bob", &[]).len(), 2);
+    let unread = mentions_json(&db_path, "bob", &["--unread"]);
+    assert_eq!(unread.len(), 1);
+    assert_eq!(unread[0]["content"], "@bob one more");
+
+    // Dismissal is per sender
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--sender", "bob"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("@bob: 1件の未読メンション"));
+
+    cmd()
+        .args(["message", "mentions", "dismiss", "--all", "--sender", "bob"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    assert!(mentions_json(&db_path, "bob", &["--unread"]).is_empty());
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--sender", "bob"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("メンション").not());
+}
+
+#[test]
+fn mention_dismiss_requires_sender() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "dismiss-validate");
+    let id = post_message_with_sender(&db_path, &thread_id, "@bob hi", "alice");
+
+    cmd()
+        .args(["message", "mentions", "dismiss", &id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+
+    cmd()
+        .args(["message", "mentions"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Draft tests ---
+
+#[test]
+fn draft_save_append_post() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "draft-test");
+
+    let output = cmd()
+        .args(["message", "draft", "save", "--thread", &thread_id, "--sender", "writer", "--content", "part one"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let draft_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+    cmd()
+        .args(["message", "draft", "save", "--id", &draft_id[..8], "--append", "--content", "part two"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    // Draft is not visible in the thread yet
+    let output = cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert!(parsed.as_array().unwrap().is_empty());
+
+    cmd()
+        .args(["message", "draft", "list", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(&draft_id[..8]))
+        .stdout(predicate::str::contains("writer"));
+
+    cmd()
+        .args(["message", "draft", "post", &draft_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let output = cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    let arr = parsed.as_array().unwrap();
+    assert_eq!(arr.len(), 1);
+    assert_eq!(arr[0]["content"], "part one\npart two");
+    assert_eq!(arr[0]["sender"], "writer");
+
+    // Posted draft is removed
+    let output = cmd()
+        .args(["message", "draft", "list", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert!(parsed.as_array().unwrap().is_empty());
+}
+
+#[test]
+fn draft_save_requires_thread_without_id() {
+    let (_dir, db_path) = test_db();
+
+    cmd()
+        .args(["message", "draft", "save", "--content", "orphan"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn draft_append_over_limit_is_not_saved() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "draft-limit");
+    let half = "a".repeat(600 * 1024);
+
+    let output = cmd()
+        .args(["message", "draft", "save", "--thread", &thread_id, "--sender", "writer"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .write_stdin(half.clone())
+        .output()
+        .unwrap();
+    let draft_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+    cmd()
+        .args(["message", "draft", "save", "--id", &draft_id, "--append"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .write_stdin(half.clone())
+        .assert()
+        .failure();
+
+    let output = cmd()
+        .args(["message", "draft", "list", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(parsed[0]["content"].as_str().unwrap().len(), half.len());
+}
+
+#[test]
+fn draft_save_rejects_role_and_type_with_id() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "draft-role");
+    let output = cmd()
+        .args(["message", "draft", "save", "--thread", &thread_id, "--sender", "a", "--content", "x"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let draft_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+    for flag in [["--role", "assistant"], ["--type", "decision"]] {
+        cmd()
+            .args(["message", "draft", "save", "--id", &draft_id, "--content", "y"])
+            .args(flag)
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .assert()
+            .failure()
+            .code(2);
+    }
+}
+
+#[test]
+fn draft_delete_and_post_nonexistent() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "draft-delete-test");
+
+    let output = cmd()
+        .args(["message", "draft", "save", "--thread", &thread_id, "--sender", "a", "--content", "discard me"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let draft_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+    cmd()
+        .args(["message", "draft", "delete", &draft_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    cmd()
+        .args(["message", "draft", "post", &draft_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Stats tests ---
+
+#[test]
+fn stats_tokens_by_sender() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "stats-test");
+    post_message_with_sender(&db_path, &thread_id, "hello world", "alice");
+    post_message_with_sender(&db_path, &thread_id, "こんにちは", "bob");
+    post_message_with_sender(&db_path, &thread_id, "abcdefgh", "alice");
+
+    let output = cmd()
+        .args(["stats", "tokens", "--thread", &thread_id, "--format", "json", "--by-message"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(parsed["messages"], 3);
+    assert_eq!(parsed["bytes"], 11 + 15 + 8);
+    // "hello world" -> 2 + 2, "こんにちは" -> 5, "abcdefgh" -> 2
+    assert_eq!(parsed["tokens"], 11);
+    let senders = parsed["by_sender"].as_array().unwrap();
+    assert_eq!(senders.len(), 2);
+    assert_eq!(senders[0]["sender"], "alice");
+    assert_eq!(senders[0]["messages"], 2);
+    assert_eq!(senders[0]["tokens"], 6);
+    assert_eq!(parsed["by_message"].as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn stats_tokens_text_omits_messages_by_default() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "stats-text-test");
+    post_message_with_sender(&db_path, &thread_id, "hello", "alice");
+
+    cmd()
+        .args(["stats", "tokens", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("total\t1 messages\t5 bytes\t~2 tokens"))
+        .stdout(predicate::str::contains("alice\t1 messages"));
+}
+
+#[test]
+fn stats_tokens_unknown_thread_fails() {
+    let (_dir, db_path) = test_db();
+
+    cmd()
+        .args(["stats", "tokens", "--thread", "nonexistent"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Thread tag tests ---
+
+#[test]
+fn thread_tag_and_list_by_tag() {
+    let (_dir, db_path) = test_db();
+    let api = create_thread(&db_path, "api work");
+    let ui = create_thread(&db_path, "ui work");
+
+    cmd()
+        .args(["thread", "tag", &api[..8], "backend", "project-x"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    cmd()
+        .args(["thread", "tag", &ui, "project-x"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let output = cmd()
+        .args(["thread", "list", "--tag", "backend", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    let arr = parsed.as_array().unwrap();
+    assert_eq!(arr.len(), 1);
+    assert_eq!(arr[0]["id"], api);
+    assert_eq!(arr[0]["tags"], serde_json::json!(["backend", "project-x"]));
+
+    let output = cmd()
+        .args(["thread", "list", "--tag", "project-x", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(parsed.as_array().unwrap().len(), 2);
+
+    cmd()
+        .args(["thread", "untag", &api, "backend"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    cmd()
+        .args(["thread", "list", "--tag", "backend"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("api work").not());
+
+    cmd()
+        .args(["thread", "list"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[project-x]"));
+}
+
+#[test]
+fn thread_tag_rejects_invalid_label() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "bad-tag");
+
+    cmd()
+        .args(["thread", "tag", &thread_id, "a,b"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Thread split tests ---
+
+#[test]
+fn thread_split_moves_tail_messages() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "long thread");
+    post_message(&db_path, &thread_id, "on topic 1");
+    post_message(&db_path, &thread_id, "on topic 2");
+    let drift = post_message(&db_path, &thread_id, "off topic 1");
+    post_message(&db_path, &thread_id, "off topic 2");
+
+    let output = cmd()
+        .args(["thread", "split", &thread_id[..8], "--from", &drift[..8], "--title", "side discussion"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("2 件の message"));
+    let new_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+    let contents = |tid: &str| -> Vec<String> {
+        let output = cmd()
+            .args(["message", "read", "--thread", tid, "--format", "json"])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+        parsed.as_array().unwrap().iter().map(|m| m["content"].as_str().unwrap().to_string()).collect()
+    };
+    assert_eq!(contents(&thread_id), vec!["on topic 1", "on topic 2"]);
+    assert_eq!(contents(&new_id), vec!["off topic 1", "off topic 2"]);
+
+    cmd()
+        .args(["thread", "show", &new_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("side discussion"));
+}
+
+#[test]
+fn thread_split_rejects_message_from_other_thread() {
+    let (_dir, db_path) = test_db();
+    let a = create_thread(&db_path, "a");
+    let b = create_thread(&db_path, "b");
+    let msg = post_message(&db_path, &b, "belongs to b");
+
+    cmd()
+        .args(["thread", "split", &a, "--from", &msg])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+
+    // No stray thread was created
+    let output = cmd()
+        .args(["thread", "list", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(parsed.as_array().unwrap().len(), 2);
+}
+
+// --- Thread bundle tests ---
+
+#[test]
+fn thread_export_import_preserves_ids_across_databases() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "portable thread");
+    let first = post_message(&db_path, &thread_id, "first");
+    let second = post_message(&db_path, &thread_id, "second");
+    cmd()
+        .args(["message", "link", &second, &first, "--relation", "blocks"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    cmd()
+        .args(["thread", "tag", &thread_id, "ticket"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let bundle_path = _dir.path().join("bundle.json");
+    cmd()
+        .args(["thread", "export", &thread_id[..8], "--output", bundle_path.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("2 件の message"));
+    let bundle: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&bundle_path).unwrap()).unwrap();
+    assert_eq!(bundle["format"], "aiboard-thread-bundle");
+    assert_eq!(bundle["messages"].as_array().unwrap().len(), 2);
+    assert_eq!(bundle["links"].as_array().unwrap().len(), 1);
+
+    let (_other_dir, other_db) = test_db();
+    let output = cmd()
+        .args(["thread", "import", bundle_path.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &other_db)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap().trim(), thread_id);
+
+    let detail = show_thread_json(&other_db, &thread_id);
+    assert_eq!(detail["title"], "portable thread");
+    assert_eq!(detail["tags"], serde_json::json!(["ticket"]));
+    assert_eq!(detail["message_count"], 2);
+
+    let output = cmd()
+        .args(["message", "get", &second, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &other_db)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let msg: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(msg["content"], "second");
+    assert_eq!(msg["links"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn thread_import_requires_new_ids_for_existing_thread() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "original");
+    post_message(&db_path, &thread_id, "hello");
+
+    let output = cmd()
+        .args(["thread", "export", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let bundle_path = _dir.path().join("bundle.json");
+    std::fs::write(&bundle_path, output.stdout).unwrap();
+
+    cmd()
+        .args(["thread", "import", bundle_path.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--new-ids"));
+
+    let output = cmd()
+        .args(["thread", "import", bundle_path.to_str().unwrap(), "--new-ids"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let copy_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    assert_ne!(copy_id, thread_id);
+
+    let detail = show_thread_json(&db_path, &copy_id);
+    assert_eq!(detail["title"], "original");
+    assert_eq!(detail["message_count"], 1);
+    assert_eq!(show_thread_json(&db_path, &thread_id)["message_count"], 1);
+}
+
+#[test]
+fn thread_import_refuses_existing_message_ids() {
+    let (dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "original");
+    post_message(&db_path, &thread_id, "hello");
+
+    let output = cmd()
+        .args(["thread", "export", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let mut bundle: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    bundle["thread"]["id"] = "00000000-0000-4000-8000-000000000001".into();
+    bundle["thread"]["name"] = serde_json::Value::Null;
+    let bundle_path = dir.path().join("bundle.json");
+    std::fs::write(&bundle_path, bundle.to_string()).unwrap();
+
+    cmd()
+        .args(["thread", "import", bundle_path.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--new-ids"));
+
+    cmd()
+        .args(["thread", "show", "00000000-0000-4000-8000-000000000001"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn thread_import_failure_leaves_no_thread() {
+    let (dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "original");
+    post_message(&db_path, &thread_id, "hello");
+
+    let output = cmd()
+        .args(["thread", "export", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let mut bundle: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    // Two messages with one ID: the thread is created before the second insert fails.
+    let duplicate = bundle["messages"][0].clone();
+    bundle["messages"].as_array_mut().unwrap().push(duplicate);
+    let bundle_path = dir.path().join("bundle.json");
+    std::fs::write(&bundle_path, bundle.to_string()).unwrap();
+
+    cmd()
+        .args(["thread", "import", bundle_path.to_str().unwrap(), "--new-ids"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+
+    let output = cmd()
+        .args(["thread", "list", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let threads: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(threads.as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn thread_clone_copies_selected_messages() {
+    let (_dir, db_path) = test_db();
+    let plan = create_thread(&db_path, "plan");
+    cmd()
+        .args(["thread", "tag", &plan, "design"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    cmd()
+        .args([
+            "message", "post", "--thread", &plan, "--sender", "a",
+            "--content", "key decision", "--metadata", r#"{"pinned":true}"#,
+        ])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    post_message(&db_path, &plan, "chatter");
+
+    let clone = |extra: &[&str]| -> String {
+        let mut args = vec!["thread", "clone", &plan[..8]];
+        args.extend_from_slice(extra);
+        let output = cmd().args(&args).env("AIBOARD_DATA_DIR", &db_path).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    };
+
+    let empty = clone(&[]);
+    let detail = show_thread_json(&db_path, &empty);
+    assert_eq!(detail["title"], "plan（複製）");
+    assert_eq!(detail["tags"], serde_json::json!(["design"]));
+    assert_eq!(detail["message_count"], 0);
+
+    let all = clone(&["--messages", "all", "--title", "attempt 2"]);
+    let detail = show_thread_json(&db_path, &all);
+    assert_eq!(detail["title"], "attempt 2");
+    assert_eq!(detail["message_count"], 2);
+
+    let pinned = clone(&["--messages", "pinned"]);
+    let output = cmd()
+        .args(["message", "read", "--thread", &pinned, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    let contents: Vec<&str> = parsed.as_array().unwrap().iter().map(|m| m["content"].as_str().unwrap()).collect();
+    assert_eq!(contents, vec!["key decision"]);
+
+    // The source thread is untouched
+    assert_eq!(show_thread_json(&db_path, &plan)["message_count"], 2);
+}
+
+// --- Thread list sort tests ---
+
+#[test]
+fn thread_list_sorts_by_key() {
+    let (_dir, db_path) = test_db();
+    let beta = create_thread(&db_path, "beta");
+    let alpha = create_thread(&db_path, "Alpha");
+    let gamma = create_thread(&db_path, "gamma");
+    post_message(&db_path, &beta, "one");
+    post_message(&db_path, &beta, "two");
+    post_message(&db_path, &gamma, "three");
+
+    let ids = |extra: &[&str]| -> Vec<String> {
+        let mut args = vec!["thread", "list", "--format", "json"];
+        args.extend_from_slice(extra);
+        let output = cmd().args(&args).env("AIBOARD_DATA_DIR", &db_path).output().unwrap();
+        assert!(output.status.success());
+        let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+        parsed.as_array().unwrap().iter().map(|t| t["id"].as_str().unwrap().to_string()).collect()
+    };
+
+    assert_eq!(ids(&["--sort", "title"]), vec![alpha.clone(), beta.clone(), gamma.clone()]);
+    assert_eq!(ids(&["--sort", "title", "--reverse"]), vec![gamma.clone(), beta.clone(), alpha.clone()]);
+    assert_eq!(ids(&["--sort", "messages"]), vec![beta.clone(), gamma.clone(), alpha.clone()]);
+    assert_eq!(ids(&["--sort", "messages", "--reverse"])[0], alpha);
+
+    cmd()
+        .args(["thread", "list", "--sort", "size"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn thread_list_filters_by_phase() {
+    let (_dir, db_path) = test_db();
+    let review = create_thread(&db_path, "in review");
+    let plain = create_thread(&db_path, "no phase");
+    cmd()
+        .args(["thread", "set-phase", &review, "reviewing"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let ids = |extra: &[&str]| -> Vec<String> {
+        let mut args = vec!["thread", "list", "--format", "json"];
+        args.extend_from_slice(extra);
+        let output = cmd().args(&args).env("AIBOARD_DATA_DIR", &db_path).output().unwrap();
+        assert!(output.status.success());
+        let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+        parsed.as_array().unwrap().iter().map(|t| t["id"].as_str().unwrap().to_string()).collect()
+    };
+
+    assert_eq!(ids(&["--phase", "reviewing"]), vec![review.clone()]);
+    assert!(ids(&["--phase", "implementing"]).is_empty());
+    assert_eq!(ids(&["--no-phase"]), vec![plain]);
+
+    cmd()
+        .args(["thread", "list", "--phase", "reviewing", "--no-phase"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Thread search tests ---
+
+#[test]
+fn thread_search_matches_title_name_and_summary() {
+    let (_dir, db_path) = test_db();
+    let by_title = create_thread(&db_path, "Login redesign");
+    let by_name = create_thread(&db_path, "misc");
+    cmd()
+        .args(["thread", "rename", &by_name, "--name", "auth-login-bug"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    let by_summary = create_thread(&db_path, "weekly sync");
+    post_message(&db_path, &by_summary, "we discussed the login flow");
+    cmd()
+        .args(["thread", "summarize", &by_summary])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    let _unrelated = create_thread(&db_path, "billing");
+
+    let output = cmd()
+        .args(["thread", "search", "login", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    let mut ids: Vec<String> = parsed.as_array().unwrap().iter().map(|t| t["id"].as_str().unwrap().to_string()).collect();
+    ids.sort();
+    let mut expected = vec![by_title, by_name, by_summary];
+    expected.sort();
+    assert_eq!(ids, expected);
+
+    // LIKE wildcards in the query are matched literally
+    let output = cmd()
+        .args(["thread", "search", "%", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert!(parsed.as_array().unwrap().is_empty());
+}
+
+// --- Thread metadata tests ---
+
+#[test]
+fn thread_set_meta_replaces_and_merges() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "worker");
+
+    cmd()
+        .args(["thread", "set-meta", &thread_id, r#"{"repo":"aiboard","branch":"main"}"#])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    cmd()
+        .args(["thread", "set-meta", &thread_id, r#"{"branch":"feature","ticket":"T-1","repo":null}"#, "--merge"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let output = cmd()
+        .args(["thread", "list", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(
+        parsed[0]["metadata"],
+        serde_json::json!({"branch": "feature", "ticket": "T-1"})
+    );
+
+    // Without --merge the whole object is replaced
+    cmd()
+        .args(["thread", "set-meta", &thread_id, r#"{"ticket":"T-2"}"#])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    assert_eq!(show_thread_json(&db_path, &thread_id)["metadata"], serde_json::json!({"ticket": "T-2"}));
+
+    cmd()
+        .args(["thread", "set-meta", &thread_id, "[1, 2]"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Thread lock tests ---
+
+#[test]
+fn thread_lock_restricts_posting_to_allowed_senders() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "decision record");
+    cmd()
+        .args(["thread", "lock", &thread_id, "--allow", "curator,lead"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--sender", "random", "--content", "drive-by"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("ロックされています"));
+    post_message_with_sender(&db_path, &thread_id, "decided", "curator");
+
+    cmd()
+        .args(["hook", "ingest", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .write_stdin(r#"{"session_id":"s","hook_event_name":"UserPromptSubmit","prompt":"hi"}"#)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("ロックされているため hook イベントを取り込みませんでした"));
+
+    let detail = show_thread_json(&db_path, &thread_id);
+    assert_eq!(detail["locked"], true);
+    assert_eq!(detail["lock_allow"], serde_json::json!(["curator", "lead"]));
+    assert_eq!(detail["message_count"], 1);
+
+    cmd()
+        .args(["thread", "unlock", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    post_message_with_sender(&db_path, &thread_id, "now open", "random");
+}
+
+#[test]
+fn thread_lock_blocks_draft_post() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "locked");
+    let output = cmd()
+        .args(["message", "draft", "save", "--thread", &thread_id, "--sender", "writer", "--content", "wip"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let draft_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+    cmd()
+        .args(["thread", "lock", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    cmd()
+        .args(["message", "draft", "post", &draft_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+    assert_eq!(show_thread_json(&db_path, &thread_id)["message_count"], 0);
+}
+
+#[test]
+fn thread_lock_blocks_fetch_refresh() {
+    let (dir, db_path) = test_db();
+    let notes = dir.path().join("notes.md");
+    std::fs::write(&notes, "- first point\n").unwrap();
+    let output = cmd()
+        .args(["thread", "import-file", notes.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let thread_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    cmd()
+        .args(["thread", "lock", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    std::fs::write(&notes, "- first point\n- second point\n").unwrap();
+    cmd()
+        .args(["thread", "fetch", "--refresh", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .code(7);
+    assert_eq!(show_thread_json(&db_path, &thread_id)["message_count"], 1);
+}
+
+#[test]
+fn thread_fetch_refresh_requires_source_url() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "local notes");
+
+    cmd()
+        .args(["thread", "fetch", "--refresh", &thread_id[..8]])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no source_url"));
+
+    cmd()
+        .args(["thread", "fetch", "https://example.com", "--refresh", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn thread_fetch_validates_auth_options_before_fetching() {
+    let (_dir, db_path) = test_db();
+
+    cmd()
+        .args(["thread", "fetch", "https://example.com", "--header", "no colon"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid header"));
+    cmd()
+        .args(["thread", "fetch", "https://example.com", "--header", "Host: evil"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+    cmd()
+        .args(["thread", "fetch", "https://example.com", "--bearer-env", "AIBOARD_TEST_UNSET_TOKEN"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .env_remove("AIBOARD_TEST_UNSET_TOKEN")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("AIBOARD_TEST_UNSET_TOKEN"));
+
+    // SSRF protection still applies to authenticated fetches
+    cmd()
+        .args(["thread", "fetch", "http://localhost/", "--bearer", "secret"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not allowed"));
+}
+
+#[test]
+fn thread_fetch_github_validates_reference() {
+    let (_dir, db_path) = test_db();
+
+    cmd()
+        .args(["thread", "fetch", "--github", "not-a-ref"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("owner/repo#123"));
+    cmd()
+        .args(["thread", "fetch", "--github", "../etc#1"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+    cmd()
+        .args(["thread", "fetch", "https://example.com", "--github", "o/r#1"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+    cmd()
+        .args(["thread", "fetch", "--github", "o/r#1", "--split"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn thread_fetch_validates_json_path_before_fetching() {
+    let (_dir, db_path) = test_db();
+
+    cmd()
+        .args(["thread", "fetch", "https://example.com", "--json-path", "$.items["])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid JSON path"));
+    cmd()
+        .args(["thread", "fetch", "https://example.com", "--json-path", "$.items", "--split"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn thread_fetch_validates_pagination_options() {
+    let (_dir, db_path) = test_db();
+
+    cmd()
+        .args(["thread", "fetch", "https://example.com", "--next-selector", "a.next"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+    cmd()
+        .args(["thread", "fetch", "https://example.com", "--follow-next", "3", "--next-selector", "ul > li"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unsupported selector"));
+    cmd()
+        .args(["thread", "fetch", "--github", "o/r#1", "--follow-next", "3"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn thread_import_file_stores_local_documents() {
+    let (dir, db_path) = test_db();
+    let notes = dir.path().join("notes.md");
+    std::fs::write(&notes, "# Design\n\n- first point\n").unwrap();
+
+    let output = cmd()
+        .args(["thread", "import-file", notes.to_str().unwrap(), "--sender", "alice"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let thread_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+    let thread = show_thread_json(&db_path, &thread_id);
+    assert_eq!(thread["title"], "notes.md");
+    assert!(thread["source_url"].as_str().unwrap().starts_with("file://"));
+    assert_eq!(thread["message_count"], 1);
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--source", "file-import"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- first point"));
+
+    // Refresh re-reads the file and adds only the new lines
+    std::fs::write(&notes, "# Design\n\n- first point\n- second point\n").unwrap();
+    cmd()
+        .args(["thread", "fetch", "--refresh", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    assert_eq!(show_thread_json(&db_path, &thread_id)["message_count"], 2);
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"content\": \"- second point\""));
+}
+
+#[test]
+fn thread_import_file_converts_and_splits_html() {
+    let (dir, db_path) = test_db();
+    let page = dir.path().join("share.html");
+    std::fs::write(
+        &page,
+        r#"<html><body>
+            <div data-message-author-role="user"><p>How do I sort?</p></div>
+            <div data-message-author-role="assistant"><p>Use <code>sort()</code>.</p></div>
+        </body></html>"#,
+    )
+    .unwrap();
+
+    let output = cmd()
+        .args(["thread", "import-file", page.to_str().unwrap(), "--split", "--title", "Sorting"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let thread_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    let thread = show_thread_json(&db_path, &thread_id);
+    assert_eq!(thread["title"], "Sorting");
+    assert_eq!(thread["message_count"], 2);
+    cmd()
+        .args(["message", "read", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Use `sort()`."))
+        .stdout(predicate::str::contains("<code>").not());
+
+    // --format overrides the extension
+    let output = cmd()
+        .args(["thread", "import-file", page.to_str().unwrap(), "--format", "txt"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let raw_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    cmd()
+        .args(["message", "read", "--thread", &raw_id, "--full"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("<code>sort()</code>"));
+}
+
+#[test]
+fn thread_import_file_rejects_bad_input() {
+    let (dir, db_path) = test_db();
+    let missing = dir.path().join("missing.md");
+
+    cmd()
+        .args(["thread", "import-file", missing.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("missing.md"));
+
+    let notes = dir.path().join("notes.txt");
+    std::fs::write(&notes, "hello").unwrap();
+    cmd()
+        .args(["thread", "import-file", notes.to_str().unwrap(), "--format", "pdf"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("pdf"));
+}
+
+// --- Search index tests ---
+
+#[test]
+fn index_rebuild_keeps_japanese_search_working() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "検索");
+    post_message(&db_path, &thread_id, "日本語の全文検索を試す");
+    post_message(&db_path, &thread_id, "別の話題");
+
+    cmd()
+        .args(["index", "rebuild"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("2 件"));
+
+    cmd()
+        .args(["message", "search", "全文検索"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("日本語の全文検索を試す"))
+        .stdout(predicate::str::contains("別の話題").not());
+    // Shorter than a trigram: served by the LIKE fallback
+    cmd()
+        .args(["message", "search", "話題"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("別の話題"));
+}
+
+#[test]
+fn message_search_any_all_of_and_exclude() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "boolean");
+    post_message(&db_path, &thread_id, "redis cache warmup");
+    post_message(&db_path, &thread_id, "memcached cache eviction");
+    post_message(&db_path, &thread_id, "postgres vacuum");
+
+    let contents = |args: &[&str]| -> Vec<String> {
+        let output = cmd()
+            .args(["message", "search"])
+            .args(args)
+            .args(["--format", "json"])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let hits: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let mut contents: Vec<String> = hits
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|h| h["content"].as_str().unwrap().to_string())
+            .collect();
+        contents.sort();
+        contents
+    };
+
+    assert_eq!(
+        contents(&["redis postgres", "--any"]),
+        vec!["postgres vacuum", "redis cache warmup"]
+    );
+    assert_eq!(contents(&["warmup redis", "--all-of"]), vec!["redis cache warmup"]);
+    assert_eq!(contents(&["cache", "--exclude", "redis"]), vec!["memcached cache eviction"]);
+    // FTS operators inside the words are quoted, not interpreted
+    assert!(contents(&["cache NOT", "--all-of"]).is_empty());
+    assert_eq!(contents(&["redis OR", "--any"]), vec!["redis cache warmup"]);
+    // Short terms and exclusions go through the substring fallback
+    assert_eq!(
+        contents(&["cache", "--exclude", "ev", "--exclude", "wa"]),
+        Vec::<String>::new()
+    );
+    assert_eq!(contents(&["va", "--any", "--exclude", "redis"]), vec!["postgres vacuum"]);
+
+    let output = cmd()
+        .args(["message", "search", "redis postgres", "--any", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let hits: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(hits.as_array().unwrap().iter().all(|h| !h["matches"].as_array().unwrap().is_empty()));
+}
+
+#[test]
+fn message_search_literal_ignores_fts_syntax() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "literal");
+    post_message(&db_path, &thread_id, "set retry-count: 3 in \"worker\" config");
+    post_message(&db_path, &thread_id, "retry later, count the workers");
+
+    let search = |args: &[&str]| -> serde_json::Value {
+        let output = cmd()
+            .args(["message", "search"])
+            .args(args)
+            .args(["--format", "json"])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+
+    for query in ["retry-count: 3", "\"worker\" config", "count: 3 in \""] {
+        let hits = search(&[query, "--literal"]);
+        let hits = hits.as_array().unwrap();
+        assert_eq!(hits.len(), 1, "{}", query);
+        assert!(hits[0]["content"].as_str().unwrap().starts_with("set retry-count"));
+        // Served by FTS, so ranked
+        assert!(hits[0]["score"].is_number(), "{}", query);
+    }
+    assert_eq!(search(&["retry count", "--literal"]).as_array().unwrap().len(), 0);
+    // Short literals fall back to substring matching
+    assert_eq!(search(&["y-", "--literal"]).as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn message_search_group_by_thread() {
+    let (_dir, db_path) = test_db();
+    let first = create_thread(&db_path, "Auth design");
+    post_message(&db_path, &first, "token rotation every hour");
+    post_message(&db_path, &first, "token storage in keychain");
+    let second = create_thread(&db_path, "Release");
+    post_message(&db_path, &second, "token for the registry expired");
+
+    let output = cmd()
+        .args(["message", "search", "token", "--group-by", "thread", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let groups: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let groups = groups.as_array().unwrap();
+    assert_eq!(groups.len(), 2);
+    let count = |id: &str| {
+        groups
+            .iter()
+            .find(|g| g["thread"]["id"] == id)
+            .map(|g| g["messages"].as_array().unwrap().len())
+    };
+    assert_eq!(count(&first), Some(2));
+    assert_eq!(count(&second), Some(1));
+    assert!(groups.iter().all(|g| g["messages"][0]["score"].is_number()));
+
+    cmd()
+        .args(["message", "search", "token", "--group-by", "thread"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Auth design\t(message 2 件)"))
+        .stdout(predicate::str::contains("Release\t(message 1 件)"));
+
+    cmd()
+        .args(["message", "search", "token", "--group-by", "thread", "--format", "markdown"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("## Auth design (`{}`) — 2 件", first)));
+
+    cmd()
+        .args(["message", "search", "token", "--group-by", "sender"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("不明なグループ化の単位です"));
+}
+
+#[test]
+fn search_all_groups_threads_and_messages() {
+    let (_dir, db_path) = test_db();
+    let titled = create_thread(&db_path, "Caching strategy");
+    post_message(&db_path, &titled, "LRU with a 5 minute TTL");
+    let other = create_thread(&db_path, "Weekly sync");
+    post_message(&db_path, &other, "we still need to decide on caching");
+    post_message(&db_path, &other, "and on caching headers too");
+    let unrelated = create_thread(&db_path, "Lunch");
+    post_message(&db_path, &unrelated, "pizza");
+
+    let output = cmd()
+        .args(["search", "all", "caching", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let groups: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let groups = groups.as_array().unwrap();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0]["thread"]["id"], titled.as_str());
+    assert_eq!(groups[0]["thread_match"], true);
+    assert_eq!(groups[0]["messages"].as_array().unwrap().len(), 0);
+    assert_eq!(groups[1]["thread"]["id"], other.as_str());
+    assert_eq!(groups[1]["thread_match"], false);
+    assert_eq!(groups[1]["messages"].as_array().unwrap().len(), 2);
+
+    cmd()
+        .args(["search", "all", "caching"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Caching strategy\t(タイトル・要約に一致)"))
+        .stdout(predicate::str::contains("Weekly sync\t(message 2 件)"))
+        .stdout(predicate::str::contains("\n  [").and(predicate::str::contains("Lunch").not()));
+}
+
+#[test]
+fn message_search_reports_and_marks_matches() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "highlight");
+    post_message(&db_path, &thread_id, "We Deploy on Friday, then deploy again; 日本語のデプロイ手順");
+
+    let output = cmd()
+        .args(["message", "search", "deploy", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let hits: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(hits[0]["matches"], serde_json::json!([[3, 9], [26, 32]]));
+
+    // Offsets count characters, not bytes
+    let output = cmd()
+        .args(["message", "search", "デプロイ", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let hits: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(hits[0]["matches"], serde_json::json!([[44, 48]]));
+
+    // Short queries go through the LIKE fallback and still report offsets
+    let output = cmd()
+        .args(["message", "search", "手順", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let hits: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(hits[0]["matches"], serde_json::json!([[48, 50]]));
+
+    cmd()
+        .args(["message", "search", "DEPLOY", "--format", "markdown"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "We <mark>Deploy</mark> on Friday, then <mark>deploy</mark> again",
+        ));
+
+    // Not a terminal: no ANSI escapes
+    cmd()
+        .args(["message", "search", "deploy"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("We Deploy on Friday"))
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+#[test]
+fn message_search_fuzzy_tolerates_typos() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "fuzzy");
+    post_message(&db_path, &thread_id, "Renamed SessionManager to SessionRegistry");
+    post_message(&db_path, &thread_id, "unrelated lunch plans");
+
+    // Without --fuzzy a misspelling finds nothing
+    cmd()
+        .args(["message", "search", "SesionManager", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[]"));
+
+    let output = cmd()
+        .args(["message", "search", "SesionManager", "--fuzzy", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let hits: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let hits = hits.as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["fuzzy"], "SessionManager");
+    assert!(hits[0]["content"].as_str().unwrap().contains("SessionRegistry"));
+
+    cmd()
+        .args(["message", "search", "SesionManager", "--fuzzy"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("~ "))
+        .stderr(predicate::str::contains("近い表記").and(predicate::str::contains("SessionManager")));
+
+    // Exact hits are returned unflagged and the fallback does not kick in
+    let output = cmd()
+        .args(["message", "search", "SessionManager", "--fuzzy", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let hits: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(hits.as_array().unwrap().len(), 1);
+    assert!(hits[0].get("fuzzy").is_none());
+
+    // Too far from anything stored
+    cmd()
+        .args(["message", "search", "SxxxxxManager", "--fuzzy", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[]"));
+}
+
+#[test]
+fn index_status_detects_drift_and_rebuild_repairs_it() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "status");
+    post_message(&db_path, &thread_id, "first indexed message");
+    post_message(&db_path, &thread_id, "second indexed message");
+
+    let status = || -> serde_json::Value {
+        let output = cmd()
+            .args(["index", "status", "--format", "json"])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+    let healthy = status();
+    assert_eq!(healthy["search"]["messages"], 2);
+    assert_eq!(healthy["search"]["indexed"], 2);
+    assert_eq!(healthy["search"]["consistent"], true);
+    assert_eq!(healthy["embeddings"], serde_json::json!([]));
+
+    // Simulate an import that bypassed the triggers
+    let conn = rusqlite::Connection::open(std::path::Path::new(&db_path).join("aiboard.db")).unwrap();
+    conn.execute_batch("DROP TRIGGER messages_ai;").unwrap();
+    drop(conn);
+    post_message(&db_path, &thread_id, "third message missing from the index");
+
+    let drifted = status();
+    assert_eq!(drifted["search"]["messages"], 3);
+    assert_eq!(drifted["search"]["indexed"], 2);
+    assert_eq!(drifted["search"]["consistent"], false);
+    cmd()
+        .args(["index", "status"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("不整合"))
+        .stderr(predicate::str::contains("index rebuild"));
+
+    cmd()
+        .args(["index", "rebuild"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    let repaired = status();
+    assert_eq!(repaired["search"]["indexed"], 3);
+    assert_eq!(repaired["search"]["consistent"], true);
+}
+
+#[test]
+fn message_search_ranks_by_relevance() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "ranking");
+    post_message(&db_path, &thread_id, "deploy deploy deploy: the deploy checklist");
+    post_message(
+        &db_path,
+        &thread_id,
+        "A long unrelated note about lunch plans, parking, the weather and, once, deploy.",
+    );
+
+    let output = cmd()
+        .args(["message", "search", "deploy", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let hits: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(hits.len(), 2);
+    // The older but more relevant message comes first
+    assert!(hits[0]["content"].as_str().unwrap().contains("checklist"));
+    let (first, second) = (hits[0]["score"].as_f64().unwrap(), hits[1]["score"].as_f64().unwrap());
+    assert!(first > second);
+
+    // Substring fallback hits carry no score
+    let output = cmd()
+        .args(["message", "search", "de", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let hits: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(!hits.is_empty());
+    assert!(hits[0]["score"].is_null());
+}
+
+#[test]
+fn message_search_limit_and_offset() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "paging");
+    for i in 0..5 {
+        post_message(&db_path, &thread_id, &format!("release note {}", i));
+    }
+    let search = |args: &[&str]| -> Vec<serde_json::Value> {
+        let output = cmd()
+            .args(["message", "search", "--format", "json"])
+            .args(args)
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+
+    assert_eq!(search(&["release", "--limit", "2"]).len(), 2);
+    assert_eq!(search(&["release", "--limit", "2", "--offset", "4"]).len(), 1);
+    assert!(search(&["release", "--offset", "10"]).is_empty());
+    // Pages do not overlap
+    let first = search(&["release", "--limit", "3"]);
+    let rest = search(&["release", "--offset", "3"]);
+    assert_eq!(rest.len(), 2);
+    assert!(rest.iter().all(|r| first.iter().all(|f| f["id"] != r["id"])));
+    // The substring fallback pages the same way
+    assert_eq!(search(&["no", "--limit", "2", "--offset", "1"]).len(), 2);
+
+    cmd()
+        .args(["message", "search", "release", "--limit", "2"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("--offset 2"));
+}
+
+#[test]
+fn message_search_date_range() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "dated");
+    post_message(&db_path, &thread_id, "decision about caching");
+
+    let search = |args: &[&str]| -> usize {
+        let output = cmd()
+            .args(["message", "search", "caching", "--format", "json"])
+            .args(args)
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice::<Vec<serde_json::Value>>(&output.stdout).unwrap().len()
+    };
+
+    assert_eq!(search(&["--after", "2000-01-01T00:00:00"]), 1);
+    assert_eq!(search(&["--after", "2099-01-01T00:00:00"]), 0);
+    assert_eq!(search(&["--before", "2099-01-01T00:00:00"]), 1);
+    assert_eq!(search(&["--before", "2000-01-01T00:00:00"]), 0);
+    assert_eq!(search(&["--after", "2000-01-01T00:00:00", "--before", "2099-01-01T00:00:00"]), 1);
+}
+
+#[test]
+fn message_search_in_metadata() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "meta search");
+    cmd()
+        .args([
+            "message", "post", "--thread", &thread_id, "--sender", "pm",
+            "--content", "task A",
+            "--metadata", r#"{"assignee":"tanaka","labels":["urgent","backend"]}"#,
+        ])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    post_message(&db_path, &thread_id, "ask tanaka about the schema");
+
+    let search = |args: &[&str]| -> Vec<serde_json::Value> {
+        let output = cmd()
+            .args(["message", "search", "--format", "json"])
+            .args(args)
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+
+    let content = search(&["tanaka"]);
+    assert_eq!(content.len(), 1);
+    assert_eq!(content[0]["content"], "ask tanaka about the schema");
+
+    let meta = search(&["tanaka", "--in", "metadata"]);
+    assert_eq!(meta.len(), 1);
+    assert_eq!(meta[0]["content"], "task A");
+    assert_eq!(search(&["backend", "--in", "metadata"]).len(), 1);
+    // Keys are not matched, only values
+    assert!(search(&["assignee", "--in", "metadata"]).is_empty());
+
+    // Content hits are ranked; metadata-only hits follow without a score
+    let all = search(&["tanaka", "--in", "all"]);
+    assert_eq!(all.len(), 2);
+    assert!(all[0]["score"].is_number());
+    assert_eq!(all[1]["content"], "task A");
+    assert!(all[1]["score"].is_null());
+
+    cmd()
+        .args(["message", "search", "tanaka", "--in", "title"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+/// Test helper: a local OpenAI-compatible embedding server. Each text is
+/// embedded as word counts per synonym group, so paraphrases share a vector.
+fn spawn_embedding_server(groups: &'static [&'static [&'static str]]) -> String {
+    use std::io::{BufRead, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                    break;
+                }
+                if let Some(v) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = v.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let data: Vec<serde_json::Value> = request["input"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .enumerate()
+                .map(|(i, text)| {
+                    let text = text.as_str().unwrap().to_lowercase();
+                    let vector: Vec<f32> = groups
+                        .iter()
+                        .map(|words| words.iter().map(|w| text.matches(w).count()).sum::<usize>() as f32)
+                        .collect();
+                    serde_json::json!({ "index": i, "embedding": vector })
+                })
+                .collect();
+            let response = serde_json::json!({ "data": data }).to_string();
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.len(),
+                response
+            );
+        }
+    });
+    format!("http://{}/v1/embeddings", addr)
+}
+
+#[test]
+fn semantic_search_with_embeddings() {
+    let (_dir, db_path) = test_db();
+    let url = spawn_embedding_server(&[&["cach", "memoiz"], &["deploy", "release", "ship"]]);
+    let thread_id = create_thread(&db_path, "semantic");
+    post_message(&db_path, &thread_id, "We decided on caching for the API");
+    let ship_id = post_message(&db_path, &thread_id, "Ship it on Friday");
+
+    let run = |args: &[&str]| {
+        let output = cmd()
+            .args(args)
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .env("AIBOARD_EMBEDDING_URL", &url)
+            .env("AIBOARD_EMBEDDING_MODEL", "test-model")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        output
+    };
+
+    // Nothing is embedded yet
+    let output = run(&["message", "search", "--semantic", "memoize results", "--format", "json"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("2 件"));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "[]");
+
+    let output = run(&["index", "embed"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("2 件"));
+
+    // No shared keyword, but the same meaning
+    let output = run(&["message", "search", "--semantic", "memoize results", "--format", "json"]);
+    let hits: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(hits.len(), 2);
+    assert!(hits[0]["content"].as_str().unwrap().contains("caching"));
+    assert!((hits[0]["score"].as_f64().unwrap() - 1.0).abs() < 1e-6);
+    assert!(hits[1]["score"].as_f64().unwrap().abs() < 1e-6);
+
+    // Only new or edited messages are embedded again
+    let output = run(&["index", "embed"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("0 件"));
+    run(&["message", "update", &ship_id, "--content", "Release it on Monday"]);
+    let output = run(&["index", "status", "--format", "json"]);
+    let status: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        status["embeddings"],
+        serde_json::json!([{ "model": "test-model", "vectors": 2, "stale": 1 }])
+    );
+    let output = run(&["index", "embed"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 件"));
+    let output = run(&["index", "embed", "--rebuild"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("2 件"));
+
+    let output = run(&["message", "search", "--semantic", "deployment", "--format", "json"]);
+    let hits: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(hits[0]["content"], "Release it on Monday");
+}
+
+#[test]
+fn semantic_search_requires_provider_config() {
+    let (_dir, db_path) = test_db();
+
+    for args in [&["index", "embed"][..], &["message", "search", "--semantic", "x"][..]] {
+        cmd()
+            .args(args)
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .env_remove("AIBOARD_EMBEDDING_URL")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("AIBOARD_EMBEDDING_URL"));
+    }
+    cmd()
+        .args(["message", "search", "x", "--semantic", "--in", "metadata"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Markdown output tests ---
+
+#[test]
+fn markdown_format_for_read_list_and_thread_list() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "Design | review");
+    post_message_with_sender(&db_path, &thread_id, "first line\n\nsecond line", "alice");
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "markdown", "--full"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("### alice (user) — "))
+        .stdout(predicate::str::contains("> first line\n>\n> second line\n"));
+
+    cmd()
+        .args(["message", "list", "--format", "md"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("### alice (user)"));
+
+    cmd()
+        .args(["thread", "list", "--format", "markdown"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("| ID | status | phase | name | title | updated | tags |"))
+        .stdout(predicate::str::contains(format!("| `{}` | open |", &thread_id[..8])))
+        .stdout(predicate::str::contains("Design \\| review"));
+}
+
+#[test]
+fn csv_and_tsv_formats_quote_fields() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "Report, Q3");
+    post_message_with_sender(&db_path, &thread_id, "said \"hi\", then\nleft\tearly", "bob");
+
+    let output = cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "csv"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let csv = String::from_utf8(output.stdout).unwrap();
+    let mut lines = csv.split("\r\n");
+    assert_eq!(
+        lines.next().unwrap(),
+        "id,thread_id,created_at,sender,role,type,source,content,metadata"
+    );
+    assert!(csv.contains(",bob,user,,agent,\"said \"\"hi\"\", then\nleft\tearly\","));
+
+    let output = cmd()
+        .args(["message", "search", "hi", "--format", "tsv"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let tsv = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = tsv.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("score\tid\tthread_id\t"));
+    assert!(lines[1].contains("\tsaid \"hi\", then\\nleft\\tearly\t"));
+
+    cmd()
+        .args(["thread", "list", "--format", "csv"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "id,name,title,status,phase,pinned,tags,summary,created_at,updated_at\r\n",
+        ))
+        .stdout(predicate::str::contains(format!("{},,\"Report, Q3\",open,,false,,,", thread_id)));
+}
+
+#[test]
+fn jsonl_format_writes_one_object_per_line() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "jsonl");
+    post_message(&db_path, &thread_id, "first\nline");
+    post_message(&db_path, &thread_id, "second");
+    post_message(&db_path, &thread_id, "third");
+
+    let output = cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "jsonl"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let rows: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0]["content"], "first\nline");
+    assert_eq!(rows[2]["content"], "third");
+
+    let output = cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "jsonl", "--limit", "2", "--offset", "0"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap().lines().count(), 2);
+    assert!(String::from_utf8(output.stderr).unwrap().contains("--cursor"));
+
+    let output = cmd()
+        .args(["thread", "list", "--format", "jsonl"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let thread: serde_json::Value = serde_json::from_str(stdout.lines().next().unwrap()).unwrap();
+    assert_eq!(thread["id"], thread_id);
+}
+
+#[test]
+fn yaml_format_uses_blocks_and_quotes_ambiguous_strings() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "yes");
+    post_message_with_sender(&db_path, &thread_id, "line one\n  line two", "off");
+
+    let output = cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "yaml"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let yaml = String::from_utf8(output.stdout).unwrap();
+    assert!(yaml.starts_with("- content: |-\n    line one\n      line two\n"));
+    assert!(yaml.contains("\n  sender: \"off\"\n"));
+    assert!(yaml.contains("\n  parent_id: null\n"));
+    assert!(yaml.contains("\n  created_at: \"20"));
+
+    cmd()
+        .args(["thread", "list", "--format", "yaml"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("  title: \"yes\"\n"));
+
+    cmd()
+        .args(["message", "search", "nothing-matches-this", "--format", "yaml"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout("[]\n");
+}
+
+#[test]
+fn color_option_controls_ansi_output() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "color");
+    post_message_with_sender(&db_path, &thread_id, "ping @bob, mail a@b.c", "alice");
+
+    let output = cmd()
+        .args(["message", "read", "--thread", &thread_id, "--color", "always"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\x1b[32m(user)\x1b[0m \x1b[1malice\x1b[0m"));
+    assert!(stdout.contains("ping \x1b[1;34m@bob\x1b[0m, mail a@b.c"));
+
+    // Piped output is plain under the default `auto`.
+    cmd()
+        .args(["message", "read", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+
+    cmd()
+        .args(["--color", "never", "message", "search", "ping"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+#[test]
+fn quiet_flag_silences_notices_but_not_errors() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "quiet");
+    for i in 0..3 {
+        post_message_with_sender(&db_path, &thread_id, &format!("@bob note {}", i), "alice");
+    }
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--sender", "bob", "--limit", "2", "--offset", "0"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty().not());
+
+    cmd()
+        .args(["-q", "message", "read", "--thread", &thread_id, "--sender", "bob", "--limit", "2", "--offset", "0"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("note 1"))
+        .stderr("");
+
+    cmd()
+        .args(["thread", "delete", &thread_id, "--quiet"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr("");
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--quiet"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("エラー"));
+}
+
+#[test]
+fn fields_option_limits_output() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "fields");
+    post_message_with_sender(&db_path, &thread_id, "hello, fields", "alice");
+
+    let output = cmd()
+        .args(["message", "read", "--thread", &thread_id, "--fields", "sender,content", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(parsed, serde_json::json!([{ "sender": "alice", "content": "hello, fields" }]));
+
+    cmd()
+        .args(["message", "list", "--fields", "content,sender"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout("hello, fields\talice\n");
+
+    cmd()
+        .args(["message", "search", "fields", "--fields", "content,matches", "--format", "csv"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout("content,matches\r\n\"hello, fields\",\"[[7,13]]\"\r\n");
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--fields", "score"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("不明な項目です: score"));
+}
+
+#[test]
+fn template_option_renders_each_record() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "templated");
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--content", "hello", "--sender", "alice", "--type", "note"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--template", "{{sender}}: {{content}} [{{metadata.msg_type}}]\\t{{ parent_id }}."])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout("alice: hello [note]\t.\n");
+
+    cmd()
+        .args(["thread", "list", "--template", "{{title}} ({{status}})"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout("templated (open)\n");
+
+    cmd()
+        .args(["message", "search", "hello", "--template", "{{matches.0}}"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout("[0,5]\n");
+
+    cmd()
+        .args(["message", "list", "--template", "{{sendr}}"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("不明な項目です: sendr"));
+}
+
+#[test]
+fn compact_flag_prints_single_line_json() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "compact");
+    post_message(&db_path, &thread_id, "one");
+    post_message(&db_path, &thread_id, "two");
+
+    let output = cmd()
+        .args(["--compact", "message", "read", "--thread", &thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1);
+    let parsed: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(parsed.as_array().unwrap().len(), 2);
+
+    let output = cmd()
+        .args(["thread", "show", &thread_id, "--format", "json", "--compact"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap().lines().count(), 1);
+}
+
+#[test]
+fn schema_command_and_schema_version() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "schema");
+
+    let output = cmd()
+        .args(["thread", "show", &thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(parsed["schema_version"], 1);
+
+    let output = cmd()
+        .args(["schema", "thread", "show"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let schema: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(schema["title"], "aiboard thread show");
+    assert_eq!(schema["x-schema-version"], 1);
+    assert_eq!(schema["properties"]["schema_version"]["const"], 1);
+    assert!(schema["$defs"]["thread"]["required"].as_array().unwrap().contains(&"title".into()));
+
+    cmd()
+        .args(["schema"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("message read"));
+
+    cmd()
+        .args(["schema", "draft", "list"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("draft list"));
+}
+
+#[test]
+fn relative_time_flag_shows_elapsed_time() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "relative");
+    post_message(&db_path, &thread_id, "just now");
+    let old_id = post_message(&db_path, &thread_id, "earlier");
+    let conn = rusqlite::Connection::open(format!("{}/aiboard.db", db_path)).unwrap();
+    conn.execute(
+        "UPDATE messages SET created_at = strftime('%Y-%m-%d %H:%M:%S', 'now', '-150 minutes') WHERE id = ?1",
+        [&old_id],
+    )
+    .unwrap();
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--relative-time"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[2時間前]"))
+        .stdout(predicate::str::contains("[たった今]"));
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("前]").not());
+}
+
+#[test]
+fn lang_flag_switches_messages_to_english() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "lang");
+
+    cmd()
+        .args(["thread", "close", &thread_id, "--lang", "en"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!("Closed thread {}", thread_id)));
+
+    cmd()
+        .args(["thread", "show", "no-such-thread"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .env("AIBOARD_LANG", "en_US.UTF-8")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Error: thread not found: no-such-thread"));
+
+    cmd()
+        .args(["thread", "show", "no-such-thread", "--lang", "ja"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .env("AIBOARD_LANG", "en")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("エラー: thread が見つかりません"));
+
+    cmd()
+        .args(["--lang", "en", "--help"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Manage threads"));
+}
+
+#[test]
+fn lang_flag_translates_cli_errors() {
+    let (_dir, db_path) = test_db();
+
+    cmd()
+        .args(["message", "read", "--since-checkpoint", "--lang", "en"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Error: --since-checkpoint needs --thread"));
+
+    cmd()
+        .args(["backup", "create", "--label", "no/slash", "--lang", "en"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("invalid input: A label may have up to 64 letters"));
+}
+
+#[test]
+fn pager_is_not_used_when_stdout_is_not_a_terminal() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "pager");
+    post_message(&db_path, &thread_id, "paged line");
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .env("PAGER", "sed s/^/PAGED:/")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("paged line"))
+        .stdout(predicate::str::contains("PAGED:").not());
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--no-pager"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("paged line"));
+}
+
+#[test]
+fn output_flag_writes_and_appends_to_file() {
+    let (dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "output");
+    post_message(&db_path, &thread_id, "first report line");
+    let path = dir.path().join("report.jsonl");
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "jsonl", "--output"])
+        .arg(&path)
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+
+    cmd()
+        .args(["message", "search", "first", "--format", "jsonl", "--append", "--output"])
+        .arg(&path)
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    let written = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(written.lines().count(), 2);
+    assert!(written.lines().all(|line| line.contains("first report line")));
+
+    cmd()
+        .args(["message", "read", "--thread", "no-such-thread", "--output"])
+        .arg(&path)
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), written);
+    let leftovers = std::fs::read_dir(dir.path())
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().ends_with(".tmp"))
+        .count();
+    assert_eq!(leftovers, 0);
+}
+
+#[test]
+fn message_read_transcript_format_keeps_paragraphs() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "transcript");
+    post_message_with_sender(&db_path, &thread_id, "First paragraph.\n\nSecond paragraph.", "alice");
+    post_message_with_sender(&db_path, &thread_id, "reply", "bob");
+
+    let output = cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "transcript"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines[0].starts_with("alice (user) "));
+    assert_eq!(&lines[1..5], ["    First paragraph.", "", "    Second paragraph.", ""]);
+    assert!(lines[5].starts_with("bob (user) "));
+    assert_eq!(lines[6], "    reply");
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "transcript", "--fields", "id"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--fields は transcript 形式では使えません"));
+}
+
+#[test]
+fn completions_include_subcommands_and_format_values() {
+    let (_dir, db_path) = test_db();
+
+    cmd()
+        .args(["completions", "bash"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("aiboard__subcmd__message__subcmd__read"))
+        .stdout(predicate::str::contains("transcript"));
+
+    cmd()
+        .args(["completions", "fish"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("-l format"));
+
+    cmd()
+        .args(["message", "list", "--format", "bogus"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("possible values"));
+}
+
+#[test]
+fn errors_are_json_with_distinct_exit_codes() {
+    let (_dir, db_path) = test_db();
+
+    let output = cmd()
+        .args(["thread", "show", "no-such-thread", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.lines().count(), 1);
+    let error: serde_json::Value = serde_json::from_str(&stderr).unwrap();
+    assert_eq!(error["error_code"], "THREAD_NOT_FOUND");
+    assert_eq!(error["exit_code"], 4);
+    assert!(error["message"].as_str().unwrap().contains("no-such-thread"));
+
+    cmd()
+        .args(["message", "get", "no-such-message"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .env("AIBOARD_ERRORS", "json")
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains(r#""error_code":"MESSAGE_NOT_FOUND""#));
+
+    cmd()
+        .args(["thread", "no-such-subcommand"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .env("AIBOARD_ERRORS", "json")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(r#""error_code":"USAGE""#));
+
+    cmd()
+        .args(["thread", "show", "no-such-thread"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("エラー: thread が見つかりません"));
+}
+
+#[test]
+fn data_dir_and_db_flags_override_environment() {
+    let (dir, env_path) = test_db();
+    let flag_dir = dir.path().join("flag");
+    let db_file = dir.path().join("other.db");
+
+    cmd()
+        .args(["thread", "create", "from-flag", "--data-dir"])
+        .arg(&flag_dir)
+        .env("AIBOARD_DATA_DIR", &env_path)
+        .assert()
+        .success();
+    assert!(flag_dir.join("aiboard.db").exists());
+    assert!(!dir.path().join("aiboard.db").exists());
+
+    cmd()
+        .args(["thread", "create", "from-db", "--db"])
+        .arg(&db_file)
+        .args(["--data-dir"])
+        .arg(&flag_dir)
+        .assert()
+        .success();
+
+    cmd()
+        .args(["thread", "list", "--format", "json", "--db"])
+        .arg(&db_file)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("from-db"))
+        .stdout(predicate::str::contains("from-flag").not());
+}
+
+#[test]
+fn config_files_provide_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let home = dir.path().join("home");
+    let project = dir.path().join("project");
+    let work = project.join("sub");
+    std::fs::create_dir_all(home.join(".aiboard")).unwrap();
+    std::fs::create_dir_all(&work).unwrap();
+    std::fs::write(
+        home.join(".aiboard").join("config.toml"),
+        "sender = \"config-bot\"\nformat = \"json\"\n",
+    )
+    .unwrap();
+    std::fs::write(project.join(".aiboard.toml"), "data_dir = \"board\"\ntruncate = 5\n").unwrap();
+    let run = |args: &[&str]| {
+        let mut command = cmd();
+        command
+            .args(args)
+            .current_dir(&work)
+            .env("HOME", &home)
+            .env_remove("USERPROFILE")
+            .env_remove("AIBOARD_DATA_DIR");
+        command
+    };
+
+    let output = run(&["thread", "create", "configured"]).output().unwrap();
+    assert!(output.status.success());
+    let thread_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    assert!(project.join("board").join("aiboard.db").exists());
+
+    run(&["message", "post", "--thread", &thread_id, "--content", "posted without flags"])
+        .assert()
+        .success();
+
+    run(&["message", "read", "--thread", &thread_id])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""sender": "config-bot""#));
+
+    run(&["message", "read", "--thread", &thread_id, "--format", "text"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("config-bot").and(predicate::str::contains("poste…")));
+
+    std::fs::write(project.join(".aiboard.toml"), "sendr = \"typo\"\n").unwrap();
+    run(&["thread", "list"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("sendr"));
+}
+
+// --- DB maintenance tests ---
+
+#[test]
+fn db_vacuum_shrinks_file_after_cleanup() {
+    let (dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "vacuum");
+    let content = "x".repeat(4000);
+    for _ in 0..100 {
+        post_message(&db_path, &thread_id, &content);
+    }
+    cmd()
+        .args(["cleanup", "thread", &thread_id, "--no-backup"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let output = cmd()
+        .args(["db", "vacuum", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let before = report["before_bytes"].as_u64().unwrap();
+    let after = report["after_bytes"].as_u64().unwrap();
+    assert!(after < before);
+    assert_eq!(report["reclaimed_bytes"].as_u64().unwrap(), before - after);
+    let size = std::fs::metadata(dir.path().join("aiboard.db")).unwrap().len();
+    assert_eq!(size, after);
+}
+
+#[test]
+fn db_vacuum_text_reports_sizes() {
+    let (_dir, db_path) = test_db();
+    cmd()
+        .args(["db", "vacuum"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("DB サイズ:").and(predicate::str::contains("削減")));
+}
+
+#[test]
+fn db_stats_reports_tables_and_largest_content() {
+    let (_dir, db_path) = test_db();
+    let small = create_thread(&db_path, "small");
+    let large = create_thread(&db_path, "large");
+    post_message(&db_path, &small, "hi");
+    post_message(&db_path, &large, &"y".repeat(2000));
+    let big_id = post_message(&db_path, &large, &"z".repeat(3000));
+
+    let output = cmd()
+        .args(["db", "stats", "--top", "1", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(stats["file_bytes"].as_u64().unwrap() > 0);
+    assert!(stats["schema_version"].as_i64().unwrap() > 0);
+    let messages = stats["tables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|t| t["name"] == "messages")
+        .unwrap();
+    assert_eq!(messages["rows"], 3);
+    assert!(stats["indexes"].as_array().unwrap().iter().any(|i| i["name"] == "idx_messages_thread_id"));
+    assert_eq!(stats["largest_threads"].as_array().unwrap().len(), 1);
+    assert_eq!(stats["largest_threads"][0]["id"], large.as_str());
+    assert_eq!(stats["largest_threads"][0]["messages"], 2);
+    assert_eq!(stats["largest_threads"][0]["bytes"], 5000);
+    assert_eq!(stats["largest_messages"][0]["id"], big_id.as_str());
+    assert_eq!(stats["largest_messages"][0]["bytes"], 3000);
+}
+
+#[test]
+fn db_stats_text_output() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "stats");
+    post_message(&db_path, &thread_id, "hello");
+    cmd()
+        .args(["db", "stats"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("スキーマバージョン:").and(predicate::str::contains("messages\t1 rows")));
+}
+
+#[test]
+fn db_doctor_healthy_board() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "doctor");
+    post_message(&db_path, &thread_id, "fine");
+    cmd()
+        .args(["db", "doctor"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("整合性チェック: 正常"));
+}
+
+#[test]
+fn db_doctor_finds_and_fixes_orphan_messages() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "doomed");
+    post_message(&db_path, &thread_id, "left behind");
+    post_message(&db_path, &thread_id, "also left behind");
+    let conn = rusqlite::Connection::open(format!("{}/aiboard.db", db_path)).unwrap();
+    conn.execute("DELETE FROM threads WHERE id = ?1", [&thread_id]).unwrap();
+    drop(conn);
+
+    let output = cmd()
+        .args(["db", "doctor", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(9));
+    let check: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(check["healthy"], false);
+    assert_eq!(check["orphans"][0]["thread_id"], thread_id.as_str());
+    assert_eq!(check["orphans"][0]["messages"], 2);
+
+    cmd()
+        .args(["db", "doctor", "--fix"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("1 件の thread を再作成しました"));
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("also left behind"));
+}
+
+#[test]
+fn db_doctor_fix_rebuilds_search_index() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "fts");
+    post_message(&db_path, &thread_id, "searchable words");
+    let conn = rusqlite::Connection::open(format!("{}/aiboard.db", db_path)).unwrap();
+    conn.execute("INSERT INTO messages_fts(messages_fts) VALUES('delete-all')", []).unwrap();
+    drop(conn);
+
+    cmd()
+        .args(["db", "doctor"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .code(9)
+        .stdout(predicate::str::contains("不整合"));
+    cmd()
+        .args(["db", "doctor", "--fix"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    cmd()
+        .args(["message", "search", "searchable"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("searchable words"));
+}
+
+#[test]
+fn db_doctor_orphans_finds_and_removes_dangling_rows() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "kept");
+    post_message(&db_path, &thread_id, "fine");
+    let conn = rusqlite::Connection::open(format!("{}/aiboard.db", db_path)).unwrap();
+    conn.execute("INSERT INTO thread_tags (thread_id, tag) VALUES ('gone-thread', 'stale')", []).unwrap();
+    conn.execute("INSERT INTO mention_dismissals (message_id, sender) VALUES ('gone-message', 'alice')", [])
+        .unwrap();
+    drop(conn);
+
+    let output = cmd()
+        .args(["db", "doctor", "--orphans", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(9));
+    let check: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(check.get("integrity_errors").is_none(), "--orphans skips the integrity check");
+    assert!(check.get("search_index").is_none());
+    let tables: Vec<&str> = check["dangling"].as_array().unwrap().iter().map(|d| d["table"].as_str().unwrap()).collect();
+    assert_eq!(tables, ["thread_tags", "mention_dismissals"]);
+
+    cmd()
+        .args(["db", "doctor", "--orphans", "--fix"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("2 行を削除しました"));
+    cmd()
+        .args(["db", "doctor"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("存在しない thread・message を指す行: なし"));
+}
+
+#[test]
+fn migration_recovers_orphan_messages_of_existing_boards() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "doomed");
+    post_message(&db_path, &thread_id, "left behind");
+    let conn = rusqlite::Connection::open(format!("{}/aiboard.db", db_path)).unwrap();
+    conn.execute("DELETE FROM threads WHERE id = ?1", [&thread_id]).unwrap();
+    conn.execute("INSERT INTO thread_subscriptions (thread_id, sender) VALUES ('gone-thread', 'alice')", [])
+        .unwrap();
+    // Make the board look like one from before the cleanup migration.
+    conn.execute("DELETE FROM schema_version WHERE version = 23", []).unwrap();
+    drop(conn);
+
+    cmd()
+        .args(["db", "doctor"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    cmd()
+        .args(["thread", "show", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("recovered {}", thread_id)));
+}
+
+#[test]
+fn strict_integrity_applies_to_new_boards() {
+    let (_dir, db_path) = test_db();
+    let output = cmd()
+        .args(["--strict-integrity", "thread", "create", "strict"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let thread_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    post_message(&db_path, &thread_id, "belongs to a thread");
+
+    let conn = rusqlite::Connection::open(format!("{}/aiboard.db", db_path)).unwrap();
+    let orphan = conn.execute("INSERT INTO messages (id, thread_id, content) VALUES ('m1', 'no-such-thread', 'x')", []);
+    assert!(orphan.is_err(), "a message without a thread is refused");
+    conn.execute("DELETE FROM threads WHERE id = ?1", [&thread_id]).unwrap();
+    let left: i64 = conn.query_row("SELECT COUNT(*) FROM messages", [], |row| row.get(0)).unwrap();
+    assert_eq!(left, 0, "messages go with their thread");
+    drop(conn);
+
+    cmd()
+        .args(["db", "doctor", "--orphans", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"strict_integrity\": true"));
+
+    // Existing boards are left as they are.
+    let (_other_dir, other_path) = test_db();
+    create_thread(&other_path, "lenient");
+    cmd()
+        .args(["--strict-integrity", "db", "doctor", "--orphans", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &other_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"strict_integrity\": false"));
+}
+
+/// Copies the board in `db_path` into a new data directory.
+fn copy_board(db_path: &str) -> (tempfile::TempDir, String) {
+    let (dir, copy_path) = test_db();
+    std::fs::create_dir_all(&copy_path).unwrap();
+    let conn = rusqlite::Connection::open(format!("{}/aiboard.db", db_path)).unwrap();
+    conn.execute("VACUUM INTO ?1", [format!("{}/aiboard.db", copy_path)]).unwrap();
+    (dir, copy_path)
+}
+
+#[test]
+fn db_merge_adds_missing_and_keeps_conflicts() {
+    let (_dir, db_path) = test_db();
+    let shared = create_thread(&db_path, "shared");
+    let msg_id = post_message(&db_path, &shared, "original");
+    let (_other_dir, other_path) = copy_board(&db_path);
+
+    let theirs = create_thread(&other_path, "only theirs");
+    post_message(&other_path, &theirs, "from the other board");
+    post_message(&other_path, &shared, "reply on the other board");
+    cmd()
+        .args(["message", "update", &msg_id, "--content", "edited there"])
+        .env("AIBOARD_DATA_DIR", &other_path)
+        .assert()
+        .success();
+
+    let other_db = format!("{}/aiboard.db", other_path);
+    let output = cmd()
+        .args(["db", "merge", &other_db, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["threads_added"], 1);
+    assert_eq!(report["messages_added"], 2);
+    assert_eq!(report["messages_updated"], 0);
+    assert_eq!(report["conflicts_kept"], 1);
+    assert!(String::from_utf8(output.stderr).unwrap().contains("--prefer-newer"));
+
+    cmd()
+        .args(["message", "read", "--thread", &theirs])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("from the other board"));
+    cmd()
+        .args(["message", "read", "--thread", &shared])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("original"))
+        .stdout(predicate::str::contains("reply on the other board"));
+
+    // Merging again adds nothing.
+    let output = cmd()
+        .args(["db", "merge", &other_db, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["threads_added"], 0);
+    assert_eq!(report["messages_added"], 0);
+}
+
+#[test]
+fn db_merge_prefer_newer_takes_later_edit() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "shared");
+    let msg_id = post_message(&db_path, &thread_id, "original");
+    let (_other_dir, other_path) = copy_board(&db_path);
+    cmd()
+        .args(["message", "update", &msg_id, "--content", "edited there"])
+        .env("AIBOARD_DATA_DIR", &other_path)
+        .assert()
+        .success();
+    let other_db = format!("{}/aiboard.db", other_path);
+    let conn = rusqlite::Connection::open(&other_db).unwrap();
+    conn.execute("UPDATE messages SET updated_at = '2999-01-01 00:00:00' WHERE id = ?1", [&msg_id])
+        .unwrap();
+    drop(conn);
+
+    cmd()
+        .args(["db", "merge", &other_db, "--prefer-newer"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("message: 0 件追加、1 件更新"));
+    cmd()
+        .args(["message", "read", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("edited there"));
+}
+
+#[test]
+fn db_merge_rejects_own_database() {
+    let (_dir, db_path) = test_db();
+    create_thread(&db_path, "self");
+    cmd()
+        .args(["db", "merge", &format!("{}/aiboard.db", db_path)])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Ephemeral mode tests ---
+
+#[test]
+fn ephemeral_does_not_touch_the_board() {
+    let (dir, db_path) = test_db();
+    let output = cmd()
+        .args(["--ephemeral", "thread", "create", "scratch"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let thread_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    assert!(!thread_id.is_empty());
+    assert!(!dir.path().join("aiboard.db").exists());
+
+    // Every invocation starts from an empty board.
+    cmd()
+        .args(["--ephemeral", "message", "read", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .code(4);
+}
+
+#[test]
+fn ephemeral_cleanup_skips_backup() {
+    let (dir, db_path) = test_db();
+    cmd()
+        .args(["--ephemeral", "cleanup", "age", "0"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("バックアップ").not());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn ephemeral_conflicts_with_db() {
+    cmd()
+        .args(["--ephemeral", "--db", "x.db", "thread", "list"])
+        .assert()
+        .code(2);
+}
+
+#[cfg(not(feature = "encryption"))]
+#[test]
+fn db_encrypt_needs_the_encryption_feature() {
+    let (_dir, db_path) = test_db();
+    cmd()
+        .args(["db", "encrypt"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .env("AIBOARD_DB_KEY", "secret")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--features encryption"));
+}
+
+#[test]
+fn opening_a_file_that_is_not_plain_sqlite_explains_encryption() {
+    let (dir, db_path) = test_db();
+    std::fs::write(dir.path().join("aiboard.db"), [0x5au8; 4096]).unwrap();
+    cmd()
+        .args(["thread", "list"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .env_remove("AIBOARD_DB_KEY")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("暗号化されて"));
+}
+
+// --- Board archive tests ---
+
+#[test]
+fn board_export_import_round_trips_threads_links_and_groups() {
+    let (dir, db_path) = test_db();
+    let first_thread = create_thread(&db_path, "first board thread");
+    let second_thread = create_thread(&db_path, "second board thread");
+    let question = post_message(&db_path, &first_thread, "question");
+    let answer = post_message(&db_path, &second_thread, "answer");
+    cmd()
+        .args(["message", "link", &question, &answer])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    cmd()
+        .args(["group", "add", "reviewers", "alice", "bob"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let archive = dir.path().join("board.tar.gz");
+    cmd()
+        .args(["export", "--output", archive.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("2 件の thread と 2 件の message"));
+
+    let (_other_dir, other_db) = test_db();
+    let output = cmd()
+        .args(["import", archive.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &other_db)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let imported: Vec<String> =
+        String::from_utf8(output.stdout).unwrap().lines().map(str::to_string).collect();
+    assert_eq!(imported, vec![first_thread.clone(), second_thread.clone()]);
+    assert_eq!(show_thread_json(&other_db, &second_thread)["title"], "second board thread");
+
+    let output = cmd()
+        .args(["message", "get", &question, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &other_db)
+        .output()
+        .unwrap();
+    let msg: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(msg["links"][0]["to_id"], answer.as_str());
+    cmd()
+        .args(["group", "list"])
+        .env("AIBOARD_DATA_DIR", &other_db)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("reviewers\talice, bob"));
+
+    // Importing again skips every thread instead of duplicating it.
+    cmd()
+        .args(["import", archive.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &other_db)
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(predicate::str::contains("既にある 2 件の thread はスキップしました"));
+}
+
+#[test]
+fn board_import_drops_a_name_already_taken_here() {
+    let (dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "exported");
+    cmd()
+        .args(["thread", "rename", &thread_id, "--name", "shared-name"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    let archive = dir.path().join("board.tar.gz");
+    cmd()
+        .args(["export", "--output", archive.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let (_other_dir, other_db) = test_db();
+    let local = create_thread(&other_db, "local");
+    cmd()
+        .args(["thread", "rename", &local, "--name", "shared-name"])
+        .env("AIBOARD_DATA_DIR", &other_db)
+        .assert()
+        .success();
+    cmd()
+        .args(["import", archive.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &other_db)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(thread_id.as_str()))
+        .stderr(predicate::str::contains("名前なしで取り込んだ thread: 1 件"));
+    assert_eq!(show_thread_json(&other_db, &thread_id)["title"], "exported");
+    assert!(show_thread_json(&other_db, &thread_id)["name"].is_null());
+    assert_eq!(show_thread_json(&other_db, &local)["name"], "shared-name");
+}
+
+#[test]
+fn board_import_rejects_other_files() {
+    let (dir, db_path) = test_db();
+    let bundle = dir.path().join("bundle.json");
+    std::fs::write(&bundle, "{}").unwrap();
+    cmd()
+        .args(["import", bundle.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .code(3);
+}
+
+// --- Sync tests ---
+
+fn sync(db_path: &str, action: &str, remote: &str) -> serde_json::Value {
+    let output = cmd()
+        .args(["sync", action, "--remote", remote, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn sync_push_and_pull_through_shared_file() {
+    let share = tempfile::tempdir().unwrap();
+    let remote = share.path().join("board.db");
+    let remote = remote.to_str().unwrap();
+    let (_a_dir, a) = test_db();
+    let (_b_dir, b) = test_db();
+    let thread_id = create_thread(&a, "shared");
+    post_message(&a, &thread_id, "from laptop");
+
+    let pushed = sync(&a, "push", remote);
+    assert_eq!(pushed["direction"], "push");
+    assert_eq!(pushed["threads_added"], 1);
+    assert_eq!(pushed["messages_added"], 1);
+
+    let pulled = sync(&b, "pull", remote);
+    assert_eq!(pulled["up_to_date"], false);
+    assert_eq!(pulled["messages_added"], 1);
+    cmd()
+        .args(["message", "read", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &b)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("from laptop"));
+
+    // Nothing new on either side.
+    assert_eq!(sync(&b, "pull", remote)["up_to_date"], true);
+    assert_eq!(sync(&a, "push", remote)["up_to_date"], true);
+
+    post_message(&b, &thread_id, "from ci");
+    assert_eq!(sync(&b, "push", remote)["messages_added"], 1);
+    assert_eq!(sync(&a, "pull", remote)["messages_added"], 1);
+
+    let output = cmd()
+        .args(["sync", "status", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &a)
+        .output()
+        .unwrap();
+    let states: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let directions: Vec<&str> = states.as_array().unwrap().iter().map(|s| s["direction"].as_str().unwrap()).collect();
+    assert_eq!(directions, ["pull", "push"]);
+    assert!(states[0]["remote"].as_str().unwrap().ends_with("board.db"));
+}
+
+#[test]
+fn sync_keeps_the_later_edit() {
+    let share = tempfile::tempdir().unwrap();
+    let remote = share.path().join("board.db");
+    let remote = remote.to_str().unwrap();
+    let (_a_dir, a) = test_db();
+    let (_b_dir, b) = test_db();
+    let thread_id = create_thread(&a, "shared");
+    let msg_id = post_message(&a, &thread_id, "original");
+    sync(&a, "push", remote);
+    sync(&b, "pull", remote);
+
+    cmd()
+        .args(["message", "update", &msg_id, "--content", "edited on b"])
+        .env("AIBOARD_DATA_DIR", &b)
+        .assert()
+        .success();
+    let conn = rusqlite::Connection::open(format!("{}/aiboard.db", b)).unwrap();
+    conn.execute("UPDATE messages SET updated_at = '2999-01-01 00:00:00' WHERE id = ?1", [&msg_id])
+        .unwrap();
+    drop(conn);
+    cmd()
+        .args(["message", "update", &msg_id, "--content", "edited on a"])
+        .env("AIBOARD_DATA_DIR", &a)
+        .assert()
+        .success();
+
+    assert_eq!(sync(&b, "push", remote)["messages_updated"], 1);
+    // The remote copy is newer, so a's own edit does not overwrite it.
+    let pushed = sync(&a, "push", remote);
+    assert_eq!(pushed["messages_updated"], 0);
+    assert_eq!(pushed["conflicts_kept"], 1);
+    assert_eq!(sync(&a, "pull", remote)["messages_updated"], 1);
+    cmd()
+        .args(["message", "read", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &a)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("edited on b"));
+}
+
+#[test]
+fn sync_push_waits_for_the_share_lock() {
+    let share = tempfile::tempdir().unwrap();
+    let remote = share.path().join("board.db");
+    let lock = share.path().join(".board.db.lock");
+    let (_dir, db_path) = test_db();
+    create_thread(&db_path, "shared");
+
+    std::fs::write(&lock, "").unwrap();
+    cmd()
+        .args(["sync", "push", "--remote", remote.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .code(12);
+    assert!(!remote.exists());
+
+    std::fs::remove_file(&lock).unwrap();
+    sync(&db_path, "push", remote.to_str().unwrap());
+    assert!(remote.exists());
+    assert!(!lock.exists());
+}
+
+#[test]
+fn sync_rejects_own_database_as_remote() {
+    let (_dir, db_path) = test_db();
+    create_thread(&db_path, "self");
+    cmd()
+        .args(["sync", "push", "--remote", &format!("{}/aiboard.db", db_path)])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("共有先がこの DB 自身です"));
+}
+
+// --- Audit tests ---
+
+fn audit_entries(db_path: &str, args: &[&str]) -> Vec<serde_json::Value> {
+    let output = cmd()
+        .args(["audit", "list", "--format", "json"])
+        .args(args)
+        .env("AIBOARD_DATA_DIR", db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap().as_array().unwrap().clone()
+}
+
+#[test]
+fn audit_records_post_update_and_delete() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "audited");
+    let msg_id = post_message_with_sender(&db_path, &thread_id, "original", "alice");
+    cmd()
+        .args(["message", "update", &msg_id, "--content", "edited"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    cmd()
+        .args(["thread", "delete", &thread_id[..8]])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let entries = audit_entries(&db_path, &[]);
+    let operations: Vec<&str> = entries.iter().map(|e| e["operation"].as_str().unwrap()).collect();
+    assert_eq!(operations, ["thread.delete", "message.update", "message.post"]);
+    assert_eq!(entries[0]["targets"], serde_json::json!([thread_id]));
+    assert_eq!(entries[2]["actor"], "alice");
+    assert_eq!(entries[2]["targets"], serde_json::json!([msg_id, thread_id]));
+
+    let by_alice = audit_entries(&db_path, &["--actor", "alice"]);
+    assert_eq!(by_alice.len(), 1);
+    let on_thread = audit_entries(&db_path, &["--target", &thread_id[..8]]);
+    assert_eq!(on_thread.len(), 2);
+    let updates = audit_entries(&db_path, &["--operation", "message.update"]);
+    assert_eq!(updates.len(), 1);
+}
+
+#[test]
+fn audit_records_cleanup_counts_and_skips_no_ops() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "cleaned");
+    post_message(&db_path, &thread_id, "old news");
+    let conn = rusqlite::Connection::open(format!("{}/aiboard.db", db_path)).unwrap();
+    conn.execute("UPDATE messages SET created_at = strftime('%Y-%m-%d %H:%M:%S', 'now', '-2 days')", [])
+        .unwrap();
+    drop(conn);
+    for _ in 0..2 {
+        cmd()
+            .args(["cleanup", "age", "0", "--no-backup"])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .assert()
+            .success();
+    }
+
+    let entries = audit_entries(&db_path, &["--operation", "cleanup.age"]);
+    assert_eq!(entries.len(), 1, "a cleanup that deleted nothing is not recorded");
+    assert_eq!(entries[0]["detail"]["days"], 0);
+    assert_eq!(entries[0]["detail"]["messages"], 1);
+}
+
+#[test]
+fn audit_log_cannot_be_changed() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "kept");
+    post_message(&db_path, &thread_id, "message");
+
+    let conn = rusqlite::Connection::open(format!("{}/aiboard.db", db_path)).unwrap();
+    assert!(conn.execute("DELETE FROM audit_log", []).is_err());
+    assert!(conn.execute("UPDATE audit_log SET actor = 'someone else'", []).is_err());
+    drop(conn);
+    assert_eq!(audit_entries(&db_path, &[]).len(), 1);
+
+    cmd()
+        .args(["audit", "list", "--operation", "message.delete"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .code(2);
+}
+
+// --- Error log tests ---
+
+#[test]
+fn error_log_can_be_written_as_json() {
+    let (_dir, db_path) = test_db();
+    let home = tempfile::tempdir().unwrap();
+    for format in ["json", "text"] {
+        cmd()
+            .args(["thread", "show", "deadbeef"])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .env("AIBOARD_LOG_FORMAT", format)
+            .env("HOME", home.path())
+            .env_remove("USERPROFILE")
+            .assert()
+            .code(4);
+    }
+
+    let log = std::fs::read_to_string(home.path().join(".aiboard").join("error.log")).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(lines.len(), 2);
+    let event: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+    assert_eq!(event["command"], "thread show");
+    assert_eq!(event["error_code"], "THREAD_NOT_FOUND");
+    assert_eq!(event["exit_code"], 4);
+    assert_eq!(event["args_hash"].as_str().unwrap().len(), 16);
+    assert!(event["duration_ms"].is_u64());
+    assert!(lines[1].starts_with('[') && lines[1].ends_with("deadbeef"));
+}
+
+#[test]
+fn timing_reports_phases_on_stderr() {
+    let (_dir, db_path) = test_db();
+    create_thread(&db_path, "timed");
+    cmd()
+        .args(["--timing", "thread", "list"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("timed"))
+        .stderr(
+            predicate::str::contains("DB オープン: ")
+                .and(predicate::str::contains("マイグレーション確認: "))
+                .and(predicate::str::contains("クエリ（"))
+                .and(predicate::str::contains("整形・出力: "))
+                .and(predicate::str::contains("合計: ")),
+        );
+    cmd()
+        .args(["thread", "list"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("合計").not());
+}
+
+// --- Batch tests ---
+
+#[test]
+fn batch_runs_lines_and_reports_each_result() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "batch");
+    let input = format!(
+        "# comment\n\
+         message post --thread {0} --sender a --content 'first post'\n\
+         [\"message\", \"post\", \"--thread\", \"{0}\", \"--sender\", \"b\", \"--content\", \"second\"]\n\
+         {{\"args\": [\"message\", \"post\", \"--thread\", \"{0}\", \"--sender\", \"c\"], \"stdin\": \"from stdin\"}}\n\
+         \n\
+         message read --thread {0} --format json\n",
+        thread_id
+    );
+    let output = cmd()
+        .arg("batch")
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .write_stdin(input)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let results: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(results.len(), 4);
+    assert!(results.iter().all(|r| r["ok"] == true));
+    assert_eq!(results[0]["line"], 2);
+    assert_eq!(results[3]["line"], 6);
+    let messages: serde_json::Value = serde_json::from_str(results[3]["output"].as_str().unwrap()).unwrap();
+    let contents: Vec<&str> = messages.as_array().unwrap().iter().map(|m| m["content"].as_str().unwrap()).collect();
+    assert_eq!(contents, ["first post", "second", "from stdin"]);
+}
+
+#[test]
+fn batch_undoes_only_failing_lines() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "batch");
+    let input = format!(
+        "message post --thread {0} --sender a --content kept\n\
+         message post --thread no-such-thread --sender a --content lost\n\
+         message post --thread {0} --sender a --content \"also kept\"\n",
+        thread_id
+    );
+    let output = cmd()
+        .arg("batch")
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .write_stdin(input)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let results: Vec<serde_json::Value> = stdout.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(results[1]["ok"], false);
+    assert_eq!(results[1]["error_code"], "THREAD_NOT_FOUND");
+    assert_eq!(results[1]["exit_code"], 4);
+    assert_eq!(results[2]["ok"], true);
+
+    cmd()
+        .args(["message", "read", "--thread", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("kept").and(predicate::str::contains("also kept")));
+}
+
+#[test]
+fn batch_atomic_rolls_back_everything() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "batch");
+    let input = format!(
+        "message post --thread {0} --sender a --content undone\n\
+         message post --thread no-such-thread --sender a --content x\n\
+         message post --thread {0} --sender a --content never-run\n",
+        thread_id
+    );
+    let output = cmd()
+        .args(["batch", "--atomic"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .write_stdin(input)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(String::from_utf8(output.stdout).unwrap().lines().count(), 2);
+
+    let output = cmd()
+        .args(["message", "read", "--thread", &thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let messages: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(messages.as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn batch_rejects_usage_errors_and_board_options_per_line() {
+    let (_dir, db_path) = test_db();
+    let output = cmd()
+        .arg("batch")
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .write_stdin("thread frobnicate\n--db other.db thread list\nbatch\nthread create ok\n")
+        .output()
+        .unwrap();
+    let results: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(results.len(), 4);
+    for r in &results[..3] {
+        assert_eq!(r["error_code"], "INVALID_INPUT");
+    }
+    assert_eq!(results[3]["ok"], true);
+}
+
+/// Test helper: run a one-line batch and return its result object.
+fn batch_single_line(db_path: &str, line: &str) -> serde_json::Value {
+    let output = cmd()
+        .arg("batch")
+        .env("AIBOARD_DATA_DIR", db_path)
+        .write_stdin(format!("{}\n", line))
+        .output()
+        .unwrap();
+    serde_json::from_str(String::from_utf8(output.stdout).unwrap().lines().next().unwrap()).unwrap()
+}
+
+#[test]
+fn batch_rejects_db_vacuum() {
+    let (_dir, db_path) = test_db();
+    let result = batch_single_line(&db_path, "db vacuum");
+    assert_eq!(result["ok"], false);
+    assert_eq!(result["error_code"], "INVALID_INPUT");
+}
+
+#[test]
+fn batch_rejects_backup_restore() {
+    let (_dir, db_path) = test_db();
+    let result = batch_single_line(&db_path, "backup restore latest");
+    assert_eq!(result["ok"], false);
+    assert_eq!(result["error_code"], "INVALID_INPUT");
+}
+
+#[test]
+fn batch_rejects_sync() {
+    let (dir, db_path) = test_db();
+    let remote = dir.path().join("shared.db");
+    let result = batch_single_line(&db_path, &format!("sync push --remote '{}'", remote.display()));
+    assert_eq!(result["ok"], false);
+    assert_eq!(result["error_code"], "INVALID_INPUT");
+    assert!(!remote.exists());
+
+    let result = batch_single_line(&db_path, &format!("sync pull --remote '{}'", remote.display()));
+    assert_eq!(result["error_code"], "INVALID_INPUT");
+
+    // Only reads the recorded sync state, so it may run in a batch.
+    let result = batch_single_line(&db_path, "sync status --format json");
+    assert_eq!(result["ok"], true);
+}
+
+#[test]
+fn ephemeral_batch_shares_one_in_memory_board() {
+    let (dir, db_path) = test_db();
+    let output = cmd()
+        .args(["--ephemeral", "batch"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .write_stdin("thread create scratch\nthread list --format json\n")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<serde_json::Value> = stdout.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    let threads: serde_json::Value = serde_json::from_str(lines[1]["output"].as_str().unwrap()).unwrap();
+    assert_eq!(threads[0]["title"], "scratch");
+    assert!(!dir.path().join("aiboard.db").exists());
+}
+
+// --- Thread pin tests ---
+
+#[test]
+fn thread_pin_sorts_first() {
+    let (_dir, db_path) = test_db();
+    let decisions = create_thread(&db_path, "決定事項");
+    let _other = create_thread(&db_path, "newer thread");
+
+    cmd()
+        .args(["thread", "pin", &decisions[..8]])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let output = cmd()
+        .args(["thread", "list", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    let arr = parsed.as_array().unwrap();
+    assert_eq!(arr[0]["id"], decisions);
+    assert_eq!(arr[0]["pinned"], true);
+    assert_eq!(arr[1]["pinned"], false);
+
+    let output = cmd()
+        .args(["thread", "list"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let first = stdout.lines().next().unwrap();
+    assert!(first.starts_with(&format!("* {}", &decisions[..8])));
+
+    cmd()
+        .args(["thread", "unpin", &decisions])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let output = cmd()
+        .args(["thread", "list", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert!(parsed.as_array().unwrap().iter().all(|t| t["pinned"] == false));
+}
+
+// --- Thread participant tests ---
+
+fn participants_json(db_path: &str, thread_id: &str) -> Vec<serde_json::Value> {
+    let output = cmd()
+        .args(["thread", "participants", thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    parsed.as_array().unwrap().clone()
+}
+
+#[test]
+fn thread_participants_tracks_posts() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "participants");
+    post_message_with_sender(&db_path, &thread_id, "one", "alice");
+    post_message_with_sender(&db_path, &thread_id, "two", "bob");
+    let moved = post_message_with_sender(&db_path, &thread_id, "three", "alice");
+
+    let participants = participants_json(&db_path, &thread_id);
+    assert_eq!(participants.len(), 2);
+    assert_eq!(participants[0]["sender"], "alice");
+    assert_eq!(participants[0]["message_count"], 2);
+    assert_eq!(participants[1]["sender"], "bob");
+    assert_eq!(participants[1]["message_count"], 1);
+
+    // Splitting moves participation along with the messages
+    let output = cmd()
+        .args(["thread", "split", &thread_id, "--from", &moved])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let new_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+    let participants = participants_json(&db_path, &thread_id);
+    assert_eq!(participants[0]["message_count"], 1);
+    let moved_participants = participants_json(&db_path, &new_id);
+    assert_eq!(moved_participants.len(), 1);
+    assert_eq!(moved_participants[0]["sender"], "alice");
+
+    cmd()
+        .args(["thread", "participants", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("alice\t1"))
+        .stdout(predicate::str::contains("bob\t1"));
+}
+
+#[test]
+fn thread_participants_tracks_hook_ingest() {
+    let (dir, db_path) = test_db();
+    let transcript = dir.path().join("transcript.jsonl");
+    std::fs::write(
+        &transcript,
+        r#"{"type":"assistant","message":{"role":"assistant","content":"all done"}}"#,
+    )
+    .unwrap();
+    let json = serde_json::json!({
+        "session_id": "sess-participants",
+        "hook_event_name": "Stop",
+        "transcript_path": transcript.to_str().unwrap(),
+    });
+    cmd()
+        .args(["hook", "ingest"])
+        .write_stdin(json.to_string())
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let participants = participants_json(&db_path, "sess-participants");
+    assert_eq!(participants.len(), 1);
+    assert_eq!(participants[0]["sender"], "claude");
+    assert_eq!(participants[0]["message_count"], 1);
+}
+
+// --- Thread autoclose tests ---
+
+/// Test helper: import a one-message transcript dated 2025-01-01 into a
+/// thread named after `session_id`.
+fn import_old_session(dir: &tempfile::TempDir, db_path: &str, session_id: &str) {
+    let transcript = dir.path().join(format!("{}.jsonl", session_id));
+    std::fs::write(
+        &transcript,
+        format!(
+            r#"{{"type":"user","sessionId":"{}","timestamp":"2025-01-01T10:00:00.000Z","message":{{"role":"user","content":"old work"}}}}"#,
+            session_id
+        ),
+    )
+    .unwrap();
+    cmd()
+        .args(["message", "import", "--transcript", transcript.to_str().unwrap()])
+        .env("AIBOARD_DATA_DIR", db_path)
+        .assert()
+        .success();
+}
+
+fn thread_status(db_path: &str, thread_id: &str) -> String {
+    show_thread_json(db_path, thread_id)["status"].as_str().unwrap().to_string()
+}
+
+#[test]
+fn thread_autoclose_closes_inactive_threads() {
+    let (dir, db_path) = test_db();
+    import_old_session(&dir, &db_path, "stale-session");
+    import_old_session(&dir, &db_path, "pinned-session");
+    let active = create_thread(&db_path, "active");
+    post_message(&db_path, &active, "fresh");
+
+    cmd()
+        .args(["thread", "pin", "pinned-session"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    // Dry run reports without closing
+    cmd()
+        .args(["thread", "autoclose", "--inactive-days", "30", "--dry-run"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("stale-se"))
+        .stderr(predicate::str::contains("1 件"));
+    assert_eq!(thread_status(&db_path, "stale-session"), "open");
+
+    cmd()
+        .args(["thread", "autoclose", "--inactive-days", "30"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\tclosed\t"))
+        .stderr(predicate::str::contains("1 件の thread をクローズしました"));
+
+    assert_eq!(thread_status(&db_path, "stale-session"), "closed");
+    assert_eq!(thread_status(&db_path, "pinned-session"), "open");
+    assert_eq!(thread_status(&db_path, &active), "open");
+}
+
+#[test]
+fn thread_autoclose_rejects_out_of_range_days() {
+    let (_dir, db_path) = test_db();
+    cmd()
+        .args(["thread", "autoclose", "--inactive-days", "9999999999999"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .code(2);
+}
+
+// --- Thread retention tests ---
+
+#[test]
+fn cleanup_apply_retention_enforces_per_thread_policies() {
+    let (dir, db_path) = test_db();
+
+    let log = create_thread(&db_path, "session log");
+    for i in 0..4 {
+        post_message(&db_path, &log, &format!("log {}", i));
+    }
+    cmd()
+        .args(["thread", "set-retention", &log, "--keep-last", "2"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    import_old_session(&dir, &db_path, "old-session");
+    post_message(&db_path, "old-session", "recent");
+    cmd()
+        .args(["thread", "set-retention", "old-session", "--days", "30"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    import_old_session(&dir, &db_path, "permanent");
+
+    cmd()
+        .args(["cleanup", "apply-retention", "--no-backup"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("合計 3 件"));
+
+    let contents = |tid: &str| -> Vec<String> {
+        let output = cmd()
+            .args(["message", "read", "--thread", tid, "--format", "json"])
+            .env("AIBOARD_DATA_DIR", &db_path)
+            .output()
+            .unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+        parsed.as_array().unwrap().iter().map(|m| m["content"].as_str().unwrap().to_string()).collect()
+    };
+    assert_eq!(contents(&log), vec!["log 2", "log 3"]);
+    assert_eq!(contents("old-session"), vec!["recent"]);
+    assert_eq!(contents("permanent"), vec!["old work"]);
+
+    assert_eq!(show_thread_json(&db_path, &log)["retention_keep_last"], 2);
+
+    cmd()
+        .args(["thread", "set-retention", &log, "--days", "1", "--keep-last", "1"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Thread stats tests ---
+
+#[test]
+fn thread_stats_counts_by_sender_and_role() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "stats-thread");
+    post_message_with_sender(&db_path, &thread_id, "one", "alice");
+    post_message_with_sender(&db_path, &thread_id, "two", "alice");
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--sender", "bob", "--role", "assistant", "--content", "three"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let output = cmd()
+        .args(["thread", "stats", &thread_id[..8], "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(parsed["message_count"], 3);
+    assert_eq!(parsed["by_sender"][0]["key"], "alice");
+    assert_eq!(parsed["by_sender"][0]["count"], 2);
+    assert_eq!(parsed["by_sender"][1]["key"], "bob");
+    assert_eq!(parsed["by_role"][0]["key"], "user");
+    assert_eq!(parsed["by_role"][1]["key"], "assistant");
+    assert!(parsed["first_post"].is_string());
+    assert!(parsed["average_interval_secs"].is_number());
+
+    cmd()
+        .args(["thread", "stats", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("messages:   3"))
+        .stdout(predicate::str::contains("  alice\t2"));
+}
+
+#[test]
+fn thread_stats_empty_thread() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "empty-stats");
+
+    let output = cmd()
+        .args(["thread", "stats", &thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(parsed["message_count"], 0);
+    assert!(parsed["first_post"].is_null());
+    assert!(parsed["average_interval_secs"].is_null());
+}
+
+// --- Thread subscription tests ---
+
+#[test]
+fn thread_watch_notifies_on_post_by_others() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "watched");
+
+    cmd()
+        .args(["thread", "watch", &thread_id[..8], "--sender", "bob"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--sender", "alice", "--content", "update"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!("thread {} の購読者: bob", &thread_id[..8])));
+
+    // Own posts do not notify the subscriber
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--sender", "bob", "--content", "ack"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("購読者").not());
+
+    cmd()
+        .args(["thread", "unwatch", &thread_id, "--sender", "bob"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--sender", "alice", "--content", "again"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("購読者").not());
+}
+
+#[test]
+fn thread_watch_notifies_on_hook_ingest() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "watched-hook");
+
+    cmd()
+        .args(["thread", "watch", &thread_id, "--sender", "reviewer"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let json = serde_json::json!({
+        "session_id": "sess-watch",
+        "hook_event_name": "UserPromptSubmit",
+        "prompt": "new prompt"
+    });
+    cmd()
+        .args(["hook", "ingest", "--thread", &thread_id])
+        .write_stdin(json.to_string())
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("の購読者: reviewer"));
+}
+
+// --- Thread rename tests ---
+
+fn show_thread_json(db_path: &str, thread_id: &str) -> serde_json::Value {
+    let output = cmd()
+        .args(["thread", "show", thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap()
+}
+
+#[test]
+fn thread_name_is_accepted_wherever_an_id_is() {
+    let (_dir, db_path) = test_db();
+    let output = cmd()
+        .args(["thread", "create", "shared board", "--name", "my-board"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let thread_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+    post_message(&db_path, "my-board", "posted by name");
+    let output = cmd()
+        .args(["message", "read", "--thread", "my-board", "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    let messages = parsed.as_array().unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0]["thread_id"], thread_id.as_str());
+
+    assert_eq!(show_thread_json(&db_path, "my-board")["id"], thread_id.as_str());
+
+    // Names are unique and must be slugs
+    cmd()
+        .args(["thread", "create", "another", "--name", "my-board"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already in use"));
+    cmd()
+        .args(["thread", "create", "bad", "--name", "has space"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn hook_ingest_thread_override_resolves_name() {
+    let (_dir, db_path) = test_db();
+    let output = cmd()
+        .args(["thread", "create", "worker log", "--name", "worker"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let thread_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+    cmd()
+        .args(["hook", "ingest", "--thread", "worker"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .write_stdin(r#"{"session_id":"sess-1","hook_event_name":"UserPromptSubmit","prompt":"hello"}"#)
+        .assert()
+        .success();
+
+    assert_eq!(show_thread_json(&db_path, &thread_id)["message_count"], 1);
+}
+
+#[test]
+fn thread_rename_title_and_name() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "Session 1234abcd");
+
+    cmd()
+        .args(["thread", "rename", &thread_id[..8], "認証リファクタリング", "--name", "auth-refactor"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    let thread = show_thread_json(&db_path, &thread_id);
+    assert_eq!(thread["title"], "認証リファクタリング");
+    assert_eq!(thread["name"], "auth-refactor");
+
+    // Name only keeps the title
+    cmd()
+        .args(["thread", "rename", &thread_id, "--name", "auth"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    let thread = show_thread_json(&db_path, &thread_id);
+    assert_eq!(thread["title"], "認証リファクタリング");
+    assert_eq!(thread["name"], "auth");
+}
+
+#[test]
+fn thread_rename_rejects_invalid_or_duplicate_name() {
+    let (_dir, db_path) = test_db();
+    let a = create_thread(&db_path, "a");
+    let b = create_thread(&db_path, "b");
+
+    cmd()
+        .args(["thread", "rename", &a, "--name", "shared"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+
+    cmd()
+        .args(["thread", "rename", &b, "--name", "shared"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already in use"));
+
+    cmd()
+        .args(["thread", "rename", &b, "--name", "has space"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+
+    cmd()
+        .args(["thread", "rename", &b])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .failure();
+}
+
+// --- Thread summary tests ---
+
+#[test]
+fn thread_summarize_stores_summary() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "summary-test");
+    for i in 1..=8 {
+        post_message(&db_path, &thread_id, &format!("message {}", i));
+    }
+    cmd()
+        .args(["message", "post", "--thread", &thread_id, "--content", "JWT を採用する", "--sender", "lead", "--type", "decision"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success();
+    post_message(&db_path, &thread_id, "context line\n@reviewer please check");
+    post_message(&db_path, &thread_id, "wrap up");
+
+    let output = cmd()
+        .args(["thread", "summarize", &thread_id, "--head", "2", "--tail", "1"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let summary = String::from_utf8(output.stdout).unwrap();
+    assert!(summary.contains("message 1"));
+    assert!(summary.contains("message 2"));
+    assert!(!summary.contains("message 5"));
+    assert!(summary.contains("## 決定事項"));
+    assert!(summary.contains("JWT を採用する"));
+    assert!(summary.contains("@reviewer please check"));
+    assert!(!summary.contains("context line"));
+    assert!(summary.contains("wrap up"));
+
+    // Stored summary is shown by thread show
+    cmd()
+        .args(["thread", "show", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("summary-test"))
+        .stdout(predicate::str::contains("JWT を採用する"));
+
+    let output = cmd()
+        .args(["thread", "show", &thread_id, "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert!(parsed["summary"].as_str().unwrap().contains("## 冒頭"));
+}
+
+#[test]
+fn thread_show_includes_activity() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "activity");
+    post_message_with_sender(&db_path, &thread_id, "first", "alice");
+    post_message_with_sender(&db_path, &thread_id, "second", "bob");
+    post_message_with_sender(&db_path, &thread_id, "third", "alice");
+
+    let thread = show_thread_json(&db_path, &thread_id);
+    assert_eq!(thread["title"], "activity");
+    assert_eq!(thread["status"], "open");
+    assert_eq!(thread["message_count"], 3);
+    assert_eq!(thread["participants"], serde_json::json!(["alice", "bob"]));
+    assert!(thread["last_activity"].is_string());
+
+    cmd()
+        .args(["thread", "show", &thread_id])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("messages:   3"))
+        .stdout(predicate::str::contains("participants: alice, bob"));
+}
+
+#[test]
+fn thread_show_without_summary() {
+    let (_dir, db_path) = test_db();
+    let thread_id = create_thread(&db_path, "plain-thread");
+
+    let output = cmd()
+        .args(["thread", "show", &thread_id[..8], "--format", "json"])
+        .env("AIBOARD_DATA_DIR", &db_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let parsed: serde_json::Value = serde_json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(parsed["id"], thread_id);
+    assert!(parsed["summary"].is_null());
+}
+
 // --- Message watch tests ---
 
 #[test]